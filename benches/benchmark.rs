@@ -338,18 +338,18 @@ fn bench_help_generation() {
     let bench = Benchmark::new("Generate help for simple command", 1_000);
     let duration = bench.run(|| {
         // Capture help output to avoid printing
-        let _ = std::panic::catch_unwind(|| {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
             simple.print_help();
-        });
+        }));
     });
     bench.report(duration);
 
     let bench = Benchmark::new("Generate help for complex command", 100);
     let duration = bench.run(|| {
         // Capture help output to avoid printing
-        let _ = std::panic::catch_unwind(|| {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
             complex.print_help();
-        });
+        }));
     });
     bench.report(duration);
 }
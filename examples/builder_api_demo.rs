@@ -177,6 +177,6 @@ fn main() {
 // Dummy function for example
 fn num_cpus() -> usize {
     std::thread::available_parallelism()
-        .map(std::num::NonZero::get)
+        .map(usize::from)
         .unwrap_or(4)
 }
@@ -362,9 +362,8 @@ fn build_completion_command() -> Command {
                 }
             };
 
-            // In a real app, you'd get the root command from a shared reference
-            // For this example, we'll recreate it
-            let root = build_kubectl();
+            // The running command tree, including everything registered at runtime
+            let root = ctx.root().expect("root is set while executing");
             println!("{}", root.generate_completion(shell));
 
             Ok(())
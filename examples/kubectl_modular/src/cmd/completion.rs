@@ -15,8 +15,8 @@ pub fn register(parent: &mut Command) {
                 _ => return Err(flag_rs::Error::ArgumentParsing(format!("unsupported shell: {}", shell_name))),
             };
 
-            // Get the root command
-            let root = get_root_command();
+            // The running command tree, so every registered command is included
+            let root = ctx.root().expect("root is set while executing");
             println!("{}", root.generate_completion(shell));
 
             Ok(())
@@ -25,22 +25,3 @@ pub fn register(parent: &mut Command) {
 
     parent.add_command(cmd);
 }
-
-// This is a bit of a hack - in a real implementation, you'd pass the root command
-// through the registration chain or store it in a context
-fn get_root_command() -> Command {
-    // Rebuild the command structure for completion generation
-    let mut app = CommandBuilder::new("kubectl")
-        .short("Kubernetes command-line tool")
-        .long("kubectl controls the Kubernetes cluster manager")
-        .build();
-
-    // We only need the structure, not the implementations
-    app.add_command(CommandBuilder::new("get").build());
-    app.add_command(CommandBuilder::new("describe").build());
-    app.add_command(CommandBuilder::new("delete").build());
-    app.add_command(CommandBuilder::new("apply").build());
-    app.add_command(CommandBuilder::new("completion").build());
-
-    app
-}
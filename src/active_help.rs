@@ -5,6 +5,7 @@
 //! conditional based on the current command context.
 
 use crate::context::Context;
+use crate::shell::Shell;
 
 /// Type alias for `ActiveHelp` condition functions
 pub type ConditionFn = dyn Fn(&Context) -> bool + Send + Sync;
@@ -15,6 +16,8 @@ pub struct ActiveHelp {
     pub message: String,
     /// Optional condition that must be true for this help to be shown
     pub condition: Option<std::sync::Arc<ConditionFn>>,
+    /// Shells this help is limited to; `None` shows it in every shell
+    pub shells: Option<Vec<Shell>>,
 }
 
 impl ActiveHelp {
@@ -32,6 +35,7 @@ impl ActiveHelp {
         Self {
             message: message.into(),
             condition: None,
+            shells: None,
         }
     }
 
@@ -58,9 +62,31 @@ impl ActiveHelp {
         Self {
             message: message.into(),
             condition: Some(std::sync::Arc::new(condition)),
+            shells: None,
         }
     }
 
+    /// Limits this help to the given shells
+    ///
+    /// Useful for hints that only make sense in one shell, such as
+    /// keybindings or install instructions. The help is hidden when the
+    /// shell can't be detected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::active_help::ActiveHelp;
+    /// use flag_rs::Shell;
+    ///
+    /// let help = ActiveHelp::new("Tip: press Alt-. to insert the last argument")
+    ///     .for_shells(&[Shell::Bash]);
+    /// ```
+    #[must_use]
+    pub fn for_shells(mut self, shells: &[Shell]) -> Self {
+        self.shells = Some(shells.to_vec());
+        self
+    }
+
     /// Checks if this help should be displayed given the current context
     #[must_use]
     pub fn should_display(&self, ctx: &Context) -> bool {
@@ -68,6 +94,19 @@ impl ActiveHelp {
             .as_ref()
             .map_or(true, |condition| condition(ctx))
     }
+
+    /// Checks if this help should be displayed in the given shell
+    ///
+    /// Like [`should_display`](Self::should_display), but also honours
+    /// any limit set with [`for_shells`](Self::for_shells).
+    #[must_use]
+    pub fn should_display_in(&self, ctx: &Context, shell: Option<Shell>) -> bool {
+        let shell_matches = self
+            .shells
+            .as_ref()
+            .map_or(true, |shells| shell.is_some_and(|s| shells.contains(&s)));
+        shell_matches && self.should_display(ctx)
+    }
 }
 
 impl Clone for ActiveHelp {
@@ -75,6 +114,7 @@ impl Clone for ActiveHelp {
         Self {
             message: self.message.clone(),
             condition: self.condition.clone(),
+            shells: self.shells.clone(),
         }
     }
 }
@@ -84,6 +124,7 @@ impl std::fmt::Debug for ActiveHelp {
         f.debug_struct("ActiveHelp")
             .field("message", &self.message)
             .field("condition", &self.condition.is_some())
+            .field("shells", &self.shells)
             .finish()
     }
 }
@@ -167,6 +208,20 @@ mod tests {
         assert!(!help.should_display(&ctx));
     }
 
+    #[test]
+    fn test_should_display_in_shell() {
+        let ctx = Context::new(vec![]);
+
+        let help = ActiveHelp::new("Bash only").for_shells(&[Shell::Bash]);
+        assert!(help.should_display_in(&ctx, Some(Shell::Bash)));
+        assert!(!help.should_display_in(&ctx, Some(Shell::Zsh)));
+        assert!(!help.should_display_in(&ctx, None));
+
+        let help = ActiveHelp::new("Everywhere");
+        assert!(help.should_display_in(&ctx, Some(Shell::Fish)));
+        assert!(help.should_display_in(&ctx, None));
+    }
+
     #[test]
     fn test_active_help_config_default() {
         let config = ActiveHelpConfig::default();
@@ -0,0 +1,64 @@
+//! Cooperative cancellation for run functions
+//!
+//! Rust can't stop a thread from the outside, so a command with
+//! [`CommandBuilder::execution_timeout`] can only ask its run function to
+//! stop. The run function finds a [`CancellationToken`] in its
+//! [`Context`](crate::Context) and should check it between steps of long
+//! work, returning early once it is cancelled.
+//!
+//! [`CommandBuilder::execution_timeout`]: crate::CommandBuilder::execution_timeout
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A flag shared between a run function and whoever may cancel it
+///
+/// Clones share the same state, so a token can be handed to worker
+/// threads the run function starts.
+///
+/// # Examples
+///
+/// ```
+/// use flag_rs::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let worker = token.clone();
+/// assert!(!worker.is_cancelled());
+///
+/// token.cancel();
+/// assert!(worker.is_cancelled());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    observed: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token that isn't cancelled
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks everything holding this token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether [`cancel`](Self::cancel) has been called
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        let cancelled = self.cancelled.load(Ordering::SeqCst);
+        if cancelled {
+            self.observed.store(true, Ordering::SeqCst);
+        }
+        cancelled
+    }
+
+    /// Returns whether anyone saw the cancellation through
+    /// [`is_cancelled`](Self::is_cancelled)
+    pub(crate) fn was_observed(&self) -> bool {
+        self.observed.load(Ordering::SeqCst)
+    }
+}
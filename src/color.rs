@@ -32,6 +32,7 @@ use std::io::{self, IsTerminal};
 /// ANSI style configuration
 ///
 /// Represents a text style with ANSI escape codes for coloring terminal output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Style {
     prefix: &'static str,
     suffix: &'static str,
@@ -3,15 +3,25 @@
 //! This module provides the core [`Command`] struct and [`CommandBuilder`] for creating
 //! CLI applications with subcommands, flags, and dynamic completions.
 
-use crate::completion::{CompletionFunc, CompletionResult};
+use crate::completion::{CompletionResult, SharedCompletionFunc, complete_path};
+use crate::completion_cache::CompletionCache;
 use crate::completion_format::CompletionFormat;
+use crate::completion_timeout::{TIMED_OUT_HELP, with_timeout};
 use crate::context::Context;
-use crate::error::{Error, Result};
-use crate::flag::{Flag, FlagConstraint, FlagType, FlagValue};
-use crate::suggestion::{DEFAULT_SUGGESTION_DISTANCE, find_suggestions};
-use crate::terminal::{format_help_entry, get_terminal_width, wrap_text_to_terminal};
+use crate::error::{BuildError, Error, Result};
+use crate::explain::{ExplainPlan, ExplainedFlag, FlagSource};
+use crate::flag::{Flag, FlagConstraint, FlagType, FlagValue, split_list};
+use crate::suggestion::{
+    DEFAULT_SUGGESTION_DISTANCE, DEFAULT_SUGGESTION_SIMILARITY, SuggestionAlgorithm,
+    find_similar_suggestions, find_suggestions,
+};
+use crate::terminal::{format_help_entry, get_terminal_width, wrap_text_to_terminal, write_paged};
 use crate::validator::ArgValidator;
 use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
 
 /// Type alias for the function that executes when a command runs
 pub type RunFunc = Box<dyn Fn(&mut Context) -> Result<()> + Send + Sync>;
@@ -19,6 +29,92 @@ pub type RunFunc = Box<dyn Fn(&mut Context) -> Result<()> + Send + Sync>;
 /// Type alias for lifecycle hook functions
 pub type HookFunc = Box<dyn Fn(&mut Context) -> Result<()> + Send + Sync>;
 
+/// Type alias for a check of the whole invocation, run before any hook
+pub type ValidateFunc = Box<dyn Fn(&Context) -> Result<()> + Send + Sync>;
+
+/// Type alias for a function naming the flags an invocation requires
+pub type RequiredFlagsFunc = Box<dyn Fn(&Context) -> Vec<String> + Send + Sync>;
+
+/// Type alias for the function that formats the error an execution ends in
+///
+/// Returning `None` keeps the default message.
+pub type ErrorHandler = Box<dyn Fn(&Error) -> Option<String> + Send + Sync>;
+
+/// Widest left column used when help is aligned across sections
+const MAX_ALIGNED_HELP_COLUMN: usize = 40;
+
+/// Parsed-flag key holding the `--explain` format, kept apart from any
+/// user flag named `explain`
+const EXPLAIN_KEY: &str = "__explain";
+
+/// Deprecation warnings already printed by this process
+static PRINTED_DEPRECATIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// How [`Command::merge`] resolves names that exist in both commands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Fail with `Error::Validation` and leave the target command unchanged
+    Error,
+    /// Replace the existing subcommand or flag with the incoming one
+    Overwrite,
+}
+
+/// Deprecation warnings raised during an invocation
+///
+/// Kept in the context, so hooks and run functions can check them with
+/// `ctx.get::<DeprecationWarnings>()`. Each invocation lists all of its
+/// own, even ones this process printed before and so doesn't print again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeprecationWarnings(pub Vec<String>);
+
+/// What a named positional argument holds, for completion and help
+///
+/// See [`CommandBuilder::positional_with_kind`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Positional {
+    /// Any value, completed by the command's arg completion if it has one
+    #[default]
+    Any,
+    /// One of these values, offered when completing
+    ValidArgs(Vec<String>),
+    /// A file path, completed from the filesystem
+    File,
+    /// A directory path; only directories are completed
+    Directory,
+}
+
+/// Which flag spellings a command accepts
+///
+/// See [`CommandBuilder::flag_prefix_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlagPrefixStyle {
+    /// `--name`, `--name=value` and `-x` (the default)
+    #[default]
+    Unix,
+    /// `/name`, `/name:value`, `/x` and `/?` for help
+    Windows,
+    /// Both spellings; help shows the Unix one
+    Both,
+}
+
+impl FlagPrefixStyle {
+    /// Returns the prefix shown before long flag names in help
+    const fn long_prefix(self) -> &'static str {
+        match self {
+            Self::Windows => "/",
+            Self::Unix | Self::Both => "--",
+        }
+    }
+
+    /// Returns the prefix shown before short flag names in help
+    const fn short_prefix(self) -> &'static str {
+        match self {
+            Self::Windows => "/",
+            Self::Unix | Self::Both => "-",
+        }
+    }
+}
+
 /// Represents a command in the CLI application
 ///
 /// Commands can have:
@@ -45,22 +141,62 @@ pub type HookFunc = Box<dyn Fn(&mut Context) -> Result<()> + Send + Sync>;
 /// // Direct construction
 /// let mut cmd = Command::new("serve");
 /// ```
+#[allow(clippy::struct_excessive_bools)]
 pub struct Command {
     name: String,
     aliases: Vec<String>,
     short: String,
     long: String,
     examples: Vec<String>,
+    example_descriptions: Vec<Option<String>>,
     group_id: Option<String>,
     subcommands: HashMap<String, Self>,
     flags: HashMap<String, Flag>,
+    default_overrides: HashMap<String, FlagValue>,
+    exclusive_flag_groups: Vec<Vec<String>>,
+    required_one_flag_groups: Vec<Vec<String>>,
+    together_flag_groups: Vec<Vec<String>>,
+    profiles: HashMap<String, Vec<(String, String)>>,
     run: Option<RunFunc>,
-    parent: Option<*mut Self>,
-    arg_completions: Option<CompletionFunc>,
-    flag_completions: HashMap<String, CompletionFunc>,
+    arg_completions: Option<SharedCompletionFunc>,
+    flag_completions: HashMap<String, SharedCompletionFunc>,
+    completers: HashMap<String, SharedCompletionFunc>,
+    own_flag_completions: HashMap<String, SharedCompletionFunc>,
+    arg_completer_ref: Option<String>,
+    flag_completer_refs: HashMap<String, String>,
+    completion_cache: Option<Arc<CompletionCache>>,
+    completion_timeout: Option<Duration>,
     arg_validator: Option<ArgValidator>,
+    positionals: Vec<(String, Positional)>,
     suggestions_enabled: bool,
     suggestion_distance: usize,
+    suggestion_algorithm: SuggestionAlgorithm,
+    suggestion_similarity: f64,
+    nested_suggestions: bool,
+    use_pager: bool,
+    aligned_help: bool,
+    explain: bool,
+    autocorrect: Option<usize>,
+    confirmation: Option<String>,
+    version: Option<String>,
+    flag_prefix_style: Option<FlagPrefixStyle>,
+    hidden: bool,
+    deprecated: Option<String>,
+    file_completion_fallback: bool,
+    allow_unknown_flags: bool,
+    passthrough_unknown: bool,
+    trailing_var_arg: bool,
+    deprecation_summary: bool,
+    prefix_matching: bool,
+    case_insensitive_matching: bool,
+    shows_help_topics: bool,
+    run_hooks_on_help: bool,
+    deny_extra_args: bool,
+    execution_timeout: Option<Duration>,
+    help_template: Option<String>,
+    error_handler: Option<ErrorHandler>,
+    validate: Option<ValidateFunc>,
+    required_flags_when: Option<RequiredFlagsFunc>,
     // Lifecycle hooks
     persistent_pre_run: Option<HookFunc>,
     pre_run: Option<HookFunc>,
@@ -68,28 +204,287 @@ pub struct Command {
     persistent_post_run: Option<HookFunc>,
 }
 
-unsafe impl Send for Command {}
-unsafe impl Sync for Command {}
+/// Lists flag names for an error, like `'--a', '--b' and '--c'`
+fn group_list<S: AsRef<str>>(names: &[S], conjunction: &str) -> String {
+    let names: Vec<String> = names
+        .iter()
+        .map(|name| format!("'--{}'", name.as_ref()))
+        .collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => {
+            format!("{} {conjunction} {last}", rest.join(", "))
+        }
+        _ => names.concat(),
+    }
+}
+
 /// Collects all available flags with their descriptions for completion
+///
+/// `ancestors` runs from the root to `current`'s parent; flags are listed
+/// nearest command first, except that required flags not yet given in
+/// `ctx` come before all others and are described as `(required)`.
 fn collect_all_flags_with_descriptions(
     current: &Command,
+    ancestors: &[&Command],
     result: &mut CompletionResult,
     prefix: &str,
+    ctx: Option<&Context>,
 ) {
-    // Add current command's flags
-    for (flag_name, flag) in &current.flags {
-        if flag_name.starts_with(prefix) {
-            let formatted_flag = format!("--{flag_name}");
-            result.values.push(formatted_flag);
-            result.descriptions.push(flag.usage.clone());
+    let start = result.values.len();
+    let mut required = CompletionResult::new();
+    for cmd in std::iter::once(current).chain(ancestors.iter().rev().copied()) {
+        for (flag_name, flag) in &cmd.flags {
+            if flag.hidden {
+                continue;
+            }
+            if flag_name.starts_with(prefix) {
+                let formatted_flag = format!("--{flag_name}");
+                if flag.required && ctx.map_or(true, |ctx| ctx.flag(flag_name).is_none()) {
+                    let description = format!("{} (required)", flag.usage);
+                    required.values.push(formatted_flag);
+                    required
+                        .descriptions
+                        .push(description.trim_start().to_string());
+                } else {
+                    result.values.push(formatted_flag);
+                    result.descriptions.push(flag.usage.clone());
+                }
+            }
+            // `--no-` forms only once the user starts typing one
+            if Command::is_negatable(flag)
+                && !prefix.is_empty()
+                && format!("no-{flag_name}").starts_with(prefix)
+            {
+                result.values.push(format!("--no-{flag_name}"));
+                result.descriptions.push(format!("Turn off --{flag_name}"));
+            }
+        }
+    }
+    result.values.splice(start..start, required.values);
+    result
+        .descriptions
+        .splice(start..start, required.descriptions);
+}
+
+/// Records a parsed flag value
+///
+/// List flags given more than once accumulate: their value is what was
+/// typed, comma-joined, and their items are kept apart in `items` so an
+/// item's own commas don't split it.
+fn insert_flag_value(parsed: &mut ParsedArgs, flag: &Flag, value: String) {
+    if !matches!(
+        flag.value_type,
+        FlagType::StringSlice | FlagType::StringArray | FlagType::KeyValue
+    ) {
+        parsed.flags.insert(flag.name.clone(), value);
+        return;
+    }
+
+    let items = if flag.comma_separated {
+        split_list(&value)
+    } else {
+        Some(value.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .into_iter()
+            .collect()
+    };
+    parsed
+        .items
+        .entry(flag.name.clone())
+        .or_default()
+        .extend(items);
+    match parsed.flags.get_mut(&flag.name) {
+        Some(existing) => {
+            existing.push(',');
+            existing.push_str(&value);
+        }
+        None => {
+            parsed.flags.insert(flag.name.clone(), value);
+        }
+    }
+}
+
+/// Returns the value a flag takes when given without one
+///
+/// Count flags go up by one from `current`; others use their optional
+/// value, or `true`.
+fn bare_flag_value(flag: &Flag, current: Option<&String>) -> String {
+    if flag.value_type == FlagType::Count {
+        let count = current
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap_or(0);
+        (count + 1).to_string()
+    } else {
+        flag.optional_value
+            .clone()
+            .unwrap_or_else(|| "true".to_string())
+    }
+}
+
+/// Records a deprecation warning in the context as [`DeprecationWarnings`]
+///
+/// Unless `deferred` for the summary, the warning is printed to stderr as
+/// well, the first time this process sees it.
+fn warn_deprecated(ctx: &mut Context, warning: String, deferred: bool) {
+    use crate::color;
+
+    if ctx
+        .get::<DeprecationWarnings>()
+        .is_some_and(|warnings| warnings.0.contains(&warning))
+    {
+        return;
+    }
+    if !deferred && first_notice(&warning) {
+        eprintln!("{} {warning}", color::yellow("warning:"));
+    }
+    if let Some(warnings) = ctx.get_mut::<DeprecationWarnings>() {
+        warnings.0.push(warning);
+    } else {
+        ctx.set(DeprecationWarnings(vec![warning]));
+    }
+}
+
+/// Returns whether this process hasn't printed `warning` yet, marking it
+/// printed
+fn first_notice(warning: &str) -> bool {
+    let mut printed = PRINTED_DEPRECATIONS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if printed.iter().any(|seen| seen == warning) {
+        false
+    } else {
+        printed.push(warning.to_string());
+        true
+    }
+}
+
+/// Prints the deprecation warnings of a finished execution that this
+/// process hasn't printed yet, as one summary on stderr
+fn print_deprecation_summary(ctx: &Context) {
+    let Some(warnings) = ctx.get::<DeprecationWarnings>() else {
+        return;
+    };
+    let fresh: Vec<&String> = warnings.0.iter().filter(|w| first_notice(w)).collect();
+    if let Some(summary) = deprecation_summary_text(&fresh) {
+        eprint!("{summary}");
+    }
+}
+
+/// Renders the summary [`CommandBuilder::deprecation_summary`] prints
+fn deprecation_summary_text(warnings: &[&String]) -> Option<String> {
+    use crate::color;
+    use std::fmt::Write;
+
+    if warnings.is_empty() {
+        return None;
+    }
+    let mut out = format!("{} deprecated usage:\n", color::yellow("warning:"));
+    for warning in warnings {
+        let _ = writeln!(out, "  - {warning}");
+    }
+    Some(out)
+}
+
+/// Returns the value part of a `--flag=value` argument as given
+///
+/// Only Unix can split an `OsStr` at a byte; elsewhere such values are
+/// only available lossily.
+#[cfg(unix)]
+fn raw_attached_value(raw: &OsStr) -> Option<OsString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = raw.as_bytes();
+    let pos = bytes.iter().position(|&b| b == b'=')?;
+    Some(OsStr::from_bytes(&bytes[pos + 1..]).to_os_string())
+}
+
+#[cfg(not(unix))]
+fn raw_attached_value(_raw: &OsStr) -> Option<OsString> {
+    None
+}
+
+/// Flags and remaining arguments of one command's share of the words
+///
+/// Words that weren't valid UTF-8 keep their original form: `raw_flags`
+/// for flag values taken from them, and `raw_remaining` alongside
+/// `remaining`, position by position. `items` holds the items of list
+/// flags, which `flags` only has comma-joined.
+#[derive(Default)]
+struct ParsedArgs {
+    flags: HashMap<String, String>,
+    raw_flags: HashMap<String, OsString>,
+    items: HashMap<String, Vec<String>>,
+    remaining: Vec<String>,
+    raw_remaining: Vec<Option<OsString>>,
+}
+
+impl ParsedArgs {
+    /// Remembers the original of the value just parsed for `name`, as long
+    /// as it is the whole value
+    fn set_raw_flag(&mut self, name: &str, raw: Option<OsString>) {
+        match raw {
+            Some(raw)
+                if self.flags.get(name).map(String::as_str)
+                    == Some(raw.to_string_lossy().as_ref()) =>
+            {
+                self.raw_flags.insert(name.to_string(), raw);
+            }
+            _ => {
+                self.raw_flags.remove(name);
+            }
         }
     }
+}
+
+/// Returns whether a command-line token is a flag rather than a value
+///
+/// A lone `-` is a positional argument (conventionally stdin/stdout).
+fn is_flag_token(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-')
+}
 
-    // Add parent flags
-    if let Some(parent) = current.parent {
-        unsafe {
-            collect_all_flags_with_descriptions(&*parent, result, prefix);
+/// Returns whether `word` is a negative number of the type `flag` takes
+///
+/// Only `Int`, `Range` and `Float` flags qualify, and the word must be a
+/// `-` followed by a number, so short flag clusters like `-inf` are still
+/// flags. A `Range` bound is checked later, when the value is validated.
+fn is_negative_number_for(flag: &Flag, word: &str) -> bool {
+    let numeric = word
+        .strip_prefix('-')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_ascii_digit() || c == '.');
+    numeric
+        && match flag.value_type {
+            FlagType::Int | FlagType::Range(_, _) => word.parse::<i64>().is_ok(),
+            FlagType::Float => word.parse::<f64>().is_ok(),
+            _ => false,
         }
+}
+
+/// Validates a flag value and returns the string stored in the context
+///
+/// Bool values are normalized to `true`/`false` so `Context::flag_bool`
+/// understands custom bool words.
+fn validated_flag_value(flag: &Flag, value: &str) -> Result<String> {
+    Ok(match flag.parse_value(value)? {
+        FlagValue::Bool(b) => b.to_string(),
+        _ => value.to_string(),
+    })
+}
+
+/// Returns the program name from an `argv[0]` value, without directories
+/// or the platform executable suffix
+fn program_basename(arg0: &str) -> String {
+    let name = std::path::Path::new(arg0)
+        .file_name()
+        .map_or_else(|| arg0.to_string(), |n| n.to_string_lossy().into_owned());
+
+    let suffix = std::env::consts::EXE_SUFFIX;
+    if suffix.is_empty() {
+        name
+    } else {
+        name.strip_suffix(suffix)
+            .map_or_else(|| name.clone(), String::from)
     }
 }
 
@@ -110,16 +505,55 @@ impl Command {
             short: String::new(),
             long: String::new(),
             examples: Vec::new(),
+            example_descriptions: Vec::new(),
             group_id: None,
             subcommands: HashMap::new(),
             flags: HashMap::new(),
+            default_overrides: HashMap::new(),
+            exclusive_flag_groups: Vec::new(),
+            required_one_flag_groups: Vec::new(),
+            together_flag_groups: Vec::new(),
+            profiles: HashMap::new(),
             run: None,
-            parent: None,
             arg_completions: None,
             flag_completions: HashMap::new(),
+            completers: HashMap::new(),
+            own_flag_completions: HashMap::new(),
+            arg_completer_ref: None,
+            flag_completer_refs: HashMap::new(),
+            completion_cache: None,
+            completion_timeout: None,
             arg_validator: None,
+            positionals: Vec::new(),
             suggestions_enabled: true,
             suggestion_distance: DEFAULT_SUGGESTION_DISTANCE,
+            suggestion_algorithm: SuggestionAlgorithm::default(),
+            suggestion_similarity: DEFAULT_SUGGESTION_SIMILARITY,
+            nested_suggestions: false,
+            use_pager: false,
+            aligned_help: false,
+            explain: false,
+            autocorrect: None,
+            confirmation: None,
+            version: None,
+            flag_prefix_style: None,
+            hidden: false,
+            deprecated: None,
+            file_completion_fallback: true,
+            allow_unknown_flags: false,
+            passthrough_unknown: false,
+            trailing_var_arg: false,
+            deprecation_summary: false,
+            prefix_matching: false,
+            case_insensitive_matching: false,
+            shows_help_topics: false,
+            run_hooks_on_help: false,
+            deny_extra_args: false,
+            execution_timeout: None,
+            help_template: None,
+            error_handler: None,
+            validate: None,
+            required_flags_when: None,
             persistent_pre_run: None,
             pre_run: None,
             post_run: None,
@@ -132,6 +566,21 @@ impl Command {
         &self.name
     }
 
+    /// Returns the program name completion scripts register and the
+    /// `<NAME>_COMPLETE` variable is derived from
+    ///
+    /// This is the command name without any leading directories, so a root
+    /// command named from `argv[0]` (`./myapp`, `/usr/local/bin/myapp`)
+    /// completes the same as one named `myapp`.
+    pub(crate) fn program_name(&self) -> &str {
+        self.name.rsplit(['/', '\\']).next().unwrap_or(&self.name)
+    }
+
+    /// Returns the environment variable that switches on completion mode
+    pub(crate) fn completion_env_var(&self) -> String {
+        format!("{}_COMPLETE", self.program_name().to_uppercase())
+    }
+
     /// Returns the short description
     pub fn short(&self) -> &str {
         &self.short
@@ -142,6 +591,32 @@ impl Command {
         &self.long
     }
 
+    /// Returns whether this command is left out of help and completion
+    pub const fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns the aliases this command can also be invoked by
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Returns the usage examples
+    pub fn examples(&self) -> &[String] {
+        &self.examples
+    }
+
+    /// Returns the usage examples, each with its description if it has one
+    pub fn described_examples(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.examples.iter().enumerate().map(|(index, example)| {
+            let description = self
+                .example_descriptions
+                .get(index)
+                .and_then(Option::as_deref);
+            (example.as_str(), description)
+        })
+    }
+
     /// Returns a reference to all subcommands
     pub fn subcommands(&self) -> &HashMap<String, Self> {
         &self.subcommands
@@ -152,8 +627,52 @@ impl Command {
         &self.flags
     }
 
+    /// Returns the default that applies to a flag in this command's scope
+    ///
+    /// `ancestors` are the commands from the root down to this command's
+    /// parent. Starting from this command and walking up to the root, the
+    /// first [`CommandBuilder::override_flag_default`] or flag declaring a
+    /// default wins; a flag redefined without one doesn't stop the search.
+    /// This is the default a run of the command sees.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag, FlagValue};
+    ///
+    /// let root = CommandBuilder::new("app")
+    ///     .flag(Flag::int("timeout").default_int(30))
+    ///     .subcommand(
+    ///         CommandBuilder::new("slow-op")
+    ///             .override_flag_default("timeout", FlagValue::Int(300))
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// let leaf = root.find_subcommand("slow-op").unwrap();
+    /// assert_eq!(root.effective_flag_default(&[], "timeout"), Some(&FlagValue::Int(30)));
+    /// assert_eq!(leaf.effective_flag_default(&[&root], "timeout"), Some(&FlagValue::Int(300)));
+    /// ```
+    pub fn effective_flag_default<'s>(
+        &'s self,
+        ancestors: &[&'s Self],
+        name: &str,
+    ) -> Option<&'s FlagValue> {
+        std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find_map(|cmd| {
+                cmd.default_overrides
+                    .get(name)
+                    .or_else(|| cmd.flags.get(name)?.default.as_ref())
+            })
+    }
+
     /// Finds a subcommand by name or alias
     ///
+    /// With [`CommandBuilder::match_case_insensitive`] set on this command,
+    /// a name or alias differing only in case matches too, when no exact
+    /// one does.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -169,10 +688,28 @@ impl Command {
     /// assert!(root.find_subcommand("s").is_some());
     /// ```
     pub fn find_subcommand(&self, name: &str) -> Option<&Self> {
-        self.subcommands.get(name).or_else(|| {
-            self.subcommands
-                .values()
-                .find(|cmd| cmd.aliases.contains(&name.to_string()))
+        self.subcommands
+            .get(name)
+            .or_else(|| {
+                self.subcommands
+                    .values()
+                    .find(|cmd| cmd.aliases.contains(&name.to_string()))
+            })
+            .or_else(|| {
+                self.case_insensitive_matching
+                    .then(|| self.find_subcommand_ignoring_case(name))
+                    .flatten()
+            })
+    }
+
+    /// Finds a subcommand whose name or alias equals `name` ignoring case
+    fn find_subcommand_ignoring_case(&self, name: &str) -> Option<&Self> {
+        let name = name.to_lowercase();
+        let mut subs: Vec<&Self> = self.subcommands.values().collect();
+        subs.sort_by(|a, b| a.name.cmp(&b.name));
+        subs.into_iter().find(|cmd| {
+            cmd.name.to_lowercase() == name
+                || cmd.aliases.iter().any(|alias| alias.to_lowercase() == name)
         })
     }
 
@@ -202,11 +739,305 @@ impl Command {
     ///
     /// root.add_command(serve);
     /// ```
-    pub fn add_command(&mut self, mut cmd: Self) {
-        cmd.parent = Some(self as *mut Self);
+    pub fn add_command(&mut self, cmd: Self) {
         self.subcommands.insert(cmd.name.clone(), cmd);
     }
 
+    /// Overlays another command's subcommands and flags onto this one
+    ///
+    /// This is meant for plugin systems that build a base command and layer
+    /// extensions on top. Only subcommands, flags and flag completions are
+    /// taken from `other`, along with the named completers it registers; its
+    /// name, descriptions, run function and hooks are discarded.
+    ///
+    /// A conflict is a subcommand or flag name present in both commands, or
+    /// an incoming flag whose short name is already used by a different flag.
+    /// With [`MergePolicy::Error`] the first conflict is reported and `self`
+    /// is left untouched. With [`MergePolicy::Overwrite`] incoming entries win;
+    /// an existing flag whose short name is taken keeps its long name but
+    /// loses the short one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Validation` describing the conflict when `policy` is
+    /// [`MergePolicy::Error`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{Command, CommandBuilder, Flag, MergePolicy};
+    ///
+    /// let mut base = CommandBuilder::new("app")
+    ///     .subcommand(CommandBuilder::new("init").build())
+    ///     .build();
+    /// let plugin = CommandBuilder::new("plugin")
+    ///     .subcommand(CommandBuilder::new("deploy").build())
+    ///     .flag(Flag::bool("dry-run"))
+    ///     .build();
+    ///
+    /// base.merge(plugin, MergePolicy::Error).unwrap();
+    /// assert!(base.find_subcommand("deploy").is_some());
+    /// assert!(base.flags().contains_key("dry-run"));
+    /// ```
+    pub fn merge(&mut self, other: Self, policy: MergePolicy) -> Result<()> {
+        if policy == MergePolicy::Error {
+            self.check_merge_conflicts(&other)?;
+        }
+
+        let Self {
+            subcommands,
+            flags,
+            flag_completions,
+            completers,
+            flag_completer_refs,
+            ..
+        } = other;
+
+        for flag in flags.into_values() {
+            if let Some(short) = flag.short {
+                for existing in self.flags.values_mut() {
+                    if existing.name != flag.name && existing.short == Some(short) {
+                        existing.short = None;
+                    }
+                }
+            }
+            self.flag_completions.remove(&flag.name);
+            self.flag_completer_refs.remove(&flag.name);
+            self.insert_flag(flag);
+        }
+        self.flag_completions.extend(flag_completions);
+        self.flag_completer_refs.extend(flag_completer_refs);
+        self.completers.extend(completers);
+
+        for sub in subcommands.into_values() {
+            self.add_command(sub);
+        }
+
+        Ok(())
+    }
+
+    /// Reports the first name clash that would occur when merging `other`
+    fn check_merge_conflicts(&self, other: &Self) -> Result<()> {
+        let mut names: Vec<_> = other.subcommands.keys().collect();
+        names.sort();
+        if let Some(name) = names
+            .into_iter()
+            .find(|n| self.subcommands.contains_key(*n))
+        {
+            return Err(Error::Validation(format!(
+                "cannot merge into '{}': subcommand '{name}' already exists",
+                self.name
+            )));
+        }
+
+        let mut incoming: Vec<_> = other.flags.values().collect();
+        incoming.sort_by_key(|f| &f.name);
+        for flag in incoming {
+            if self.flags.contains_key(&flag.name) {
+                return Err(Error::Validation(format!(
+                    "cannot merge into '{}': flag '--{}' already exists",
+                    self.name, flag.name
+                )));
+            }
+            if let Some(short) = flag.short {
+                if let Some(existing) = self.flags.values().find(|f| f.short == Some(short)) {
+                    return Err(Error::Validation(format!(
+                        "cannot merge into '{}': short flag '-{short}' of '--{}' is already used by '--{}'",
+                        self.name, flag.name, existing.name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the whole command tree for wiring mistakes
+    ///
+    /// Every command at any depth is checked for subcommands sharing a name
+    /// or alias, flags sharing a short name, constraints on flags that
+    /// don't exist in scope, ranges with `min > max`, required flags that
+    /// also have a default, references to named completers that aren't
+    /// registered on the command or a parent, profiles setting flags that
+    /// don't exist, and a `profile` flag of a command's own clashing with
+    /// its profiles. All problems are returned, each with the path of
+    /// the command it was found on. Handy in a test guarding a large CLI.
+    ///
+    /// # Errors
+    ///
+    /// Returns every problem found, in tree order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{BuildError, CommandBuilder, Flag};
+    ///
+    /// let app = CommandBuilder::new("app")
+    ///     .subcommand(
+    ///         CommandBuilder::new("serve")
+    ///             .flag(Flag::int("port").short('p'))
+    ///             .flag(Flag::string("profile").short('p'))
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     app.validate_tree(),
+    ///     Err(vec![BuildError::DuplicateShort {
+    ///         path: "app serve".to_string(),
+    ///         short: 'p',
+    ///         flags: vec!["port".to_string(), "profile".to_string()],
+    ///     }])
+    /// );
+    /// ```
+    pub fn validate_tree(&self) -> std::result::Result<(), Vec<BuildError>> {
+        let mut problems = Vec::new();
+        self.collect_build_errors(&mut Vec::new(), &mut problems);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Records this command's wiring problems, then its subcommands'
+    fn collect_build_errors<'a>(
+        &'a self,
+        ancestors: &mut Vec<&'a Self>,
+        problems: &mut Vec<BuildError>,
+    ) {
+        let path = ancestors
+            .iter()
+            .map(|cmd| cmd.name.as_str())
+            .chain(std::iter::once(self.name.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut subcommands: Vec<&Self> = self.subcommands.values().collect();
+        subcommands.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for name in subcommands
+            .iter()
+            .flat_map(|cmd| std::iter::once(&cmd.name).chain(&cmd.aliases))
+        {
+            if !seen.insert(name) && !duplicates.contains(&name) {
+                duplicates.push(name);
+            }
+        }
+        for name in duplicates {
+            problems.push(BuildError::DuplicateCommandName {
+                path: path.clone(),
+                name: name.clone(),
+            });
+        }
+
+        let mut flags: Vec<&Flag> = self.flags.values().collect();
+        flags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut shorts: Vec<char> = flags.iter().filter_map(|flag| flag.short).collect();
+        shorts.sort_unstable();
+        shorts.dedup();
+        for short in shorts {
+            let sharing: Vec<String> = flags
+                .iter()
+                .filter(|flag| flag.short == Some(short))
+                .map(|flag| flag.name.clone())
+                .collect();
+            if sharing.len() > 1 {
+                problems.push(BuildError::DuplicateShort {
+                    path: path.clone(),
+                    short,
+                    flags: sharing,
+                });
+            }
+        }
+
+        let in_scope = |name: &str| {
+            self.flags.contains_key(name)
+                || ancestors.iter().any(|cmd| cmd.flags.contains_key(name))
+        };
+        for flag in flags {
+            if let FlagType::Range(min, max) = flag.value_type {
+                if min > max {
+                    problems.push(BuildError::InvertedRange {
+                        path: path.clone(),
+                        flag: flag.name.clone(),
+                        min,
+                        max,
+                    });
+                }
+            }
+            if flag.required && flag.default.is_some() {
+                problems.push(BuildError::RequiredWithDefault {
+                    path: path.clone(),
+                    flag: flag.name.clone(),
+                });
+            }
+            for constraint in &flag.constraints {
+                let targets = match constraint {
+                    FlagConstraint::RequiredIf(target) => std::slice::from_ref(target),
+                    FlagConstraint::ConflictsWith(targets)
+                    | FlagConstraint::Requires(targets)
+                    | FlagConstraint::RequiresOneOf(targets) => targets.as_slice(),
+                    FlagConstraint::InvalidForCommands(_) => &[],
+                };
+                for target in targets.iter().filter(|target| !in_scope(target)) {
+                    problems.push(BuildError::DanglingConstraint {
+                        path: path.clone(),
+                        flag: flag.name.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut refs: Vec<&String> = self
+            .arg_completer_ref
+            .iter()
+            .chain(self.flag_completer_refs.values())
+            .collect();
+        refs.sort();
+        refs.dedup();
+        for name in refs {
+            if self.named_completer(ancestors, name).is_none() {
+                problems.push(BuildError::UnknownCompleter {
+                    path: path.clone(),
+                    name: name.clone(),
+                });
+            }
+        }
+
+        if !self.profiles.is_empty()
+            && !self
+                .flags
+                .get("profile")
+                .is_some_and(|flag| self.is_profile_flag(flag))
+        {
+            problems.push(BuildError::ProfileFlagTaken { path: path.clone() });
+        }
+        let mut profiles: Vec<(&String, &Vec<(String, String)>)> = self.profiles.iter().collect();
+        profiles.sort_by_key(|(name, _)| *name);
+        for (profile, settings) in profiles {
+            for (flag, _) in settings {
+                if !in_scope(flag) && !self.defines_flag_below(flag) {
+                    problems.push(BuildError::UnknownProfileFlag {
+                        path: path.clone(),
+                        profile: profile.clone(),
+                        flag: flag.clone(),
+                    });
+                }
+            }
+        }
+
+        ancestors.push(self);
+        for sub in subcommands {
+            sub.collect_build_errors(ancestors, problems);
+        }
+        ancestors.pop();
+    }
+
     /// Executes the command with the given arguments
     ///
     /// This is the main entry point for running your CLI application.
@@ -231,13 +1062,68 @@ impl Command {
     /// // In main():
     /// // let args: Vec<String> = std::env::args().skip(1).collect();
     /// // if let Err(e) = app.execute(args) {
-    /// //     eprintln!("Error: {}", e);
-    /// //     std::process::exit(1);
+    /// //     if !matches!(e, flag_rs::Error::Exit(_)) {
+    /// //         eprintln!("{e}");
+    /// //     }
+    /// //     std::process::exit(e.exit_code());
     /// // }
+    /// //
+    /// // or, doing the same: app.execute_or_exit(args);
     /// ```
     pub fn execute(&self, args: Vec<String>) -> Result<()> {
+        self.execute_with_raw_args(args, Vec::new())
+    }
+
+    /// Executes the command with arguments that may not be valid UTF-8
+    ///
+    /// Like [`execute`](Self::execute), for `std::env::args_os()`. Commands
+    /// see arguments converted lossily, with invalid sequences replaced by
+    /// `U+FFFD`, but a flag whose value was such an argument, either the
+    /// word after the flag or attached as `--flag=value`, also keeps the
+    /// original for [`Context::flag_os`] and [`Context::flag_path`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`execute`](Self::execute).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .flag(Flag::file("out"))
+    ///     .run(|ctx| {
+    ///         let _out = ctx.flag_path("out");
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// // In main():
+    /// // app.execute_os(std::env::args_os().skip(1))
+    /// ```
+    pub fn execute_os<I, S>(&self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        let (args, raw_args): (Vec<String>, Vec<Option<OsString>>) = args
+            .into_iter()
+            .map(|arg| match arg.into().into_string() {
+                Ok(arg) => (arg, None),
+                Err(raw) => (raw.to_string_lossy().into_owned(), Some(raw)),
+            })
+            .unzip();
+        self.execute_with_raw_args(args, raw_args)
+    }
+
+    fn execute_with_raw_args(
+        &self,
+        args: Vec<String>,
+        raw_args: Vec<Option<OsString>>,
+    ) -> Result<()> {
         // Check if we're in completion mode
-        if let Ok(_shell) = std::env::var(format!("{}_COMPLETE", self.name.to_uppercase())) {
+        if let Ok(_shell) = std::env::var(self.completion_env_var()) {
             // Disable colors during completion to avoid terminal rendering issues
             unsafe { std::env::set_var("NO_COLOR", "1") };
 
@@ -256,82 +1142,418 @@ impl Command {
         }
 
         let mut ctx = Context::new(args);
+        ctx.set_raw_args(raw_args);
         self.execute_with_context(&mut ctx)
     }
 
-    /// Executes the command with an existing context
+    /// Executes the command, exiting the process if it fails
     ///
-    /// This method is useful when you need to provide pre-configured context
-    /// or when implementing custom command routing.
-    pub fn execute_with_context(&self, ctx: &mut Context) -> Result<()> {
-        // Call the internal method with an empty hook chain
-        self.execute_with_context_and_hooks(ctx, &mut Vec::new())
+    /// On error the message from [`format_error`](Self::format_error) is
+    /// printed to stderr and the process exits with the error's
+    /// [`exit_code`](Error::exit_code). [`Error::Exit`] only sets the exit
+    /// code, and in completion mode nothing is printed either, so the
+    /// shell's prompt isn't disturbed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// app.execute_or_exit(std::env::args().skip(1).collect());
+    /// ```
+    pub fn execute_or_exit(&self, args: Vec<String>) {
+        if let Err(err) = self.execute(args) {
+            if !matches!(err, Error::Exit(_))
+                && std::env::var_os(self.completion_env_var()).is_none()
+            {
+                eprintln!("{}", self.format_error(&err));
+            }
+            std::process::exit(err.exit_code());
+        }
     }
 
-    /// Internal method that executes the command while collecting parent hooks
-    fn execute_with_context_and_hooks<'a>(
-        &'a self,
-        ctx: &mut Context,
-        parent_hooks: &mut Vec<(&'a Option<HookFunc>, &'a Option<HookFunc>)>,
-    ) -> Result<()> {
-        let args = ctx.args().to_vec();
-
-        // Parse flags first, before checking for empty args
-        let (flags, remaining_args) = self.parse_flags(&args)?;
+    /// Returns the message to show for an error this command ended in
+    ///
+    /// This is the error handler's message when one is set with
+    /// [`CommandBuilder::error_handler`] and it returns `Some`, and the
+    /// error's own `Display` otherwise.
+    pub fn format_error(&self, err: &Error) -> String {
+        self.error_handler
+            .as_ref()
+            .and_then(|handler| handler(err))
+            .unwrap_or_else(|| err.to_string())
+    }
+
+    /// Executes the subcommand at `path` with the given arguments
+    ///
+    /// Each name in `path` is looked up like on the command line, aliases
+    /// included, and the subcommand then runs with `args` in a fresh
+    /// context as if it had been invoked through this command: inherited
+    /// flags are accepted and the persistent hooks of every command on the
+    /// path run around it. An empty path executes this command.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CommandNotFound` if a name in `path` isn't a
+    /// subcommand, and otherwise whatever executing the subcommand returns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let app = CommandBuilder::new("app")
+    ///     .subcommand(
+    ///         CommandBuilder::new("db")
+    ///             .subcommand(
+    ///                 CommandBuilder::new("migrate")
+    ///                     .run(|ctx| {
+    ///                         assert_eq!(ctx.args(), &["latest".to_string()]);
+    ///                         Ok(())
+    ///                     })
+    ///                     .build(),
+    ///             )
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// app.execute_path(&["db", "migrate"], vec!["latest".to_string()]).unwrap();
+    /// ```
+    pub fn execute_path(&self, path: &[&str], args: Vec<String>) -> Result<()> {
+        let mut target = self;
+        let mut ancestors: Vec<&Self> = Vec::new();
+        let mut parent_hooks = Vec::new();
+
+        for name in path {
+            let Some(subcommand) = target.find_subcommand(name) else {
+                let suggestions = if target.suggestions_enabled {
+                    target.find_command_suggestions(name)
+                } else {
+                    Vec::new()
+                };
+                return Err(Error::CommandNotFound {
+                    command: (*name).to_string(),
+                    suggestions,
+                });
+            };
+            parent_hooks.push((&target.persistent_pre_run, &target.persistent_post_run));
+            ancestors.push(target);
+            target = subcommand;
+        }
+
+        let mut ctx = Context::new(args);
+        ctx.set_root(Some(self));
+        let result = target.execute_with_context_and_hooks(&mut ctx, &mut parent_hooks, &ancestors);
+        if self.deprecation_summary {
+            print_deprecation_summary(&ctx);
+        }
+        result
+    }
+
+    /// Executes the command with an existing context
+    ///
+    /// This method is useful when you need to provide pre-configured context
+    /// or when implementing custom command routing. While it runs, this
+    /// command is the context's [`root`](Context::root).
+    pub fn execute_with_context<'a>(&'a self, ctx: &mut Context<'a>) -> Result<()> {
+        let previous = ctx.set_root(Some(self));
+        // Call the internal method with an empty hook chain
+        let result = self.execute_with_context_and_hooks(ctx, &mut Vec::new(), &[]);
+        ctx.restore_root(previous);
+        if self.deprecation_summary {
+            print_deprecation_summary(ctx);
+        }
+        result
+    }
+
+    /// Dispatches a multi-call (busybox-style) invocation
+    ///
+    /// A single binary can behave as several tools depending on the name it
+    /// was invoked as. The basename of `argv[0]` is looked up in `applets`;
+    /// when it matches, that applet runs with the remaining arguments, so its
+    /// own help and completion apply. Otherwise every applet is registered as
+    /// a subcommand of this (umbrella) command and the invocation is routed
+    /// like a regular subcommand call, e.g. `toolbox ls -l`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{Command, CommandBuilder};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut applets = HashMap::new();
+    /// applets.insert(
+    ///     "hello".to_string(),
+    ///     CommandBuilder::new("hello")
+    ///         .run(|_| {
+    ///             println!("hello!");
+    ///             Ok(())
+    ///         })
+    ///         .build(),
+    /// );
+    ///
+    /// let umbrella = Command::new("toolbox");
+    ///
+    /// // In main():
+    /// // let argv: Vec<String> = std::env::args().collect();
+    /// let argv = vec!["/usr/local/bin/hello".to_string()];
+    /// umbrella.dispatch_multicall(applets, argv).unwrap();
+    /// ```
+    pub fn dispatch_multicall(
+        mut self,
+        mut applets: HashMap<String, Self>,
+        argv: Vec<String>,
+    ) -> Result<()> {
+        let mut argv = argv.into_iter();
+        let invoked_as = argv.next().map(|arg0| program_basename(&arg0));
+        let remaining: Vec<String> = argv.collect();
+
+        if let Some(applet) = invoked_as.and_then(|name| applets.remove(&name)) {
+            return applet.execute(remaining);
+        }
+
+        for (_, applet) in applets {
+            self.add_command(applet);
+        }
+        self.execute(remaining)
+    }
+
+    /// Internal method that executes the command while collecting parent hooks
+    ///
+    /// `ancestors` holds the commands from the root down to this command's
+    /// parent, so inherited flags can be resolved.
+    fn execute_with_context_and_hooks<'a>(
+        &'a self,
+        ctx: &mut Context,
+        parent_hooks: &mut Vec<(&'a Option<HookFunc>, &'a Option<HookFunc>)>,
+        ancestors: &[&'a Self],
+    ) -> Result<()> {
+        let args = ctx.args().to_vec();
+        // Originals of words that weren't valid UTF-8, while they still
+        // line up with the words
+        let raw_args = Some(ctx.take_raw_args())
+            .filter(|raw| raw.len() == args.len())
+            .unwrap_or_default();
+
+        // The command execution started from decides about the summary
+        let deferred = ancestors
+            .first()
+            .map_or(self, |root| root)
+            .deprecation_summary;
+        if let Some(message) = &self.deprecated {
+            warn_deprecated(
+                ctx,
+                format!("command {} is deprecated: {message}", self.name),
+                deferred,
+            );
+        }
+
+        // Parse flags first, before checking for empty args
+        let ParsedArgs {
+            flags,
+            mut raw_flags,
+            mut items,
+            remaining: remaining_args,
+            raw_remaining,
+        } = if self.trailing_var_arg {
+            // Every word is an argument, flag-like or not
+            ParsedArgs {
+                remaining: args,
+                raw_remaining: raw_args,
+                ..ParsedArgs::default()
+            }
+        } else {
+            self.parse_args(&args, &raw_args, ancestors)?
+        };
+
+        let mut used: Vec<&String> = flags.keys().collect();
+        used.sort();
+        for name in used {
+            if let Some(message) = self
+                .find_flag(ancestors, name)
+                .and_then(|flag| flag.deprecated.as_ref())
+            {
+                warn_deprecated(
+                    ctx,
+                    format!("flag --{name} is deprecated: {message}"),
+                    deferred,
+                );
+            }
+        }
 
         *ctx.args_mut() = remaining_args;
 
+        if let Some(version) = self.builtin_version(ancestors) {
+            if flags.contains_key("version") {
+                println!("{} {version}", self.name);
+                return Ok(());
+            }
+        }
+
         // Check if we have a subcommand first
         if let Some(subcommand_name) = ctx.args().first() {
-            if let Some(subcommand) = self.find_subcommand(subcommand_name) {
+            if let Some(subcommand) = self.resolve_subcommand(ancestors, subcommand_name) {
+                let mut chain = ancestors.to_vec();
+                chain.push(self);
+
                 // If help flag is present, show help for the subcommand
                 if flags.contains_key("help") {
-                    subcommand.print_help();
-                    return Ok(());
+                    // Flags after the subcommand name can gate its flag visibility
+                    let (mut provided, _) = subcommand
+                        .parse_flags(&ctx.args()[1..], &chain)
+                        .unwrap_or_default();
+                    provided.extend(flags);
+                    for (name, value) in &provided {
+                        ctx.set_flag(name.clone(), value.clone());
+                    }
+                    parent_hooks.push((&self.persistent_pre_run, &self.persistent_post_run));
+                    parent_hooks.push((
+                        &subcommand.persistent_pre_run,
+                        &subcommand.persistent_post_run,
+                    ));
+                    let help = subcommand.render_help(&chain, &provided);
+                    return subcommand.emit_help_with_hooks(ctx, &help, &chain, parent_hooks);
                 }
 
-                // Validate flags before setting them
-                self.validate_flags(&flags)?;
-
-                // Set flags and execute subcommand
-                for (name, value) in flags {
-                    ctx.set_flag(name, value);
-                }
+                // Set flags and execute subcommand; the command that finally
+                // runs validates the flags of the whole chain
+                Self::set_parsed_flags(ctx, flags, &mut raw_flags, &mut items);
 
                 // Add our persistent hooks to the chain for subcommands
                 parent_hooks.push((&self.persistent_pre_run, &self.persistent_post_run));
 
                 ctx.args_mut().remove(0);
-                return subcommand.execute_with_context_and_hooks(ctx, parent_hooks);
+                ctx.set_raw_args(raw_remaining.into_iter().skip(1).collect());
+                return subcommand.execute_with_context_and_hooks(ctx, parent_hooks, &chain);
+            }
+
+            // A command that runs takes an ambiguous prefix as an argument
+            let candidates = self.prefix_candidates(ancestors, subcommand_name);
+            if candidates.len() > 1 && self.run.is_none() {
+                return Err(Error::CommandNotFound {
+                    command: subcommand_name.clone(),
+                    suggestions: candidates.iter().map(|cmd| cmd.name.clone()).collect(),
+                });
             }
         }
 
         // No subcommand found, check for help at this level
         if flags.contains_key("help") {
-            self.print_help();
-            return Ok(());
+            let help = self.render_help(ancestors, &flags);
+            for (name, value) in flags {
+                ctx.set_flag(name, value);
+            }
+            parent_hooks.push((&self.persistent_pre_run, &self.persistent_post_run));
+            return self.emit_help_with_hooks(ctx, &help, ancestors, parent_hooks);
         }
 
-        // Validate flags before setting them
-        self.validate_flags(&flags)?;
+        if !self.allow_unknown_flags && !self.passthrough_unknown && !self.trailing_var_arg {
+            self.check_unknown_flags(ancestors, ctx.args())?;
+        }
 
         // Set flags
-        for (name, value) in flags {
-            ctx.set_flag(name, value);
+        Self::set_parsed_flags(ctx, flags, &mut raw_flags, &mut items);
+
+        // Flags not given are filled in from the chosen profile, then from
+        // their environment variables; both count as provided for validation
+        let from_profile = self.apply_profile(ctx, ancestors)?;
+        let from_env = self.apply_env_flags(ctx, ancestors)?;
+
+        // Validate our flags and every inherited one against all that were
+        // provided along the way; asking for help never needs them
+        let provided: HashSet<String> = ctx.flags().keys().cloned().collect();
+        if !self.shows_help_topics {
+            self.validate_flags(ancestors, &provided)?;
         }
 
+        // Arguments after `--` are passed through untouched; with trailing
+        // var args the `--` itself is one of the arguments
+        let passthrough = if self.trailing_var_arg {
+            Vec::new()
+        } else {
+            ctx.args()
+                .iter()
+                .position(|arg| arg == "--")
+                .map_or_else(Vec::new, |pos| ctx.args_mut().split_off(pos).split_off(1))
+        };
+
         // No subcommand found, try to run this command's function
         if let Some(ref run) = self.run {
+            let explaining = self.explain || ancestors.iter().any(|cmd| cmd.explain);
+            if let Some(format) = ctx.flag(EXPLAIN_KEY).filter(|_| explaining).cloned() {
+                let plan = self.explain_plan(ctx, ancestors, &from_profile, &from_env, passthrough);
+                if format == "json" {
+                    println!("{}", plan.to_json());
+                } else {
+                    print!("{}", plan.to_text());
+                }
+                ctx.set(plan);
+                return Ok(());
+            }
+
+            ctx.set_passthrough_args(passthrough.clone());
+            if self.passthrough_unknown {
+                let mut forwarded = std::mem::take(ctx.args_mut());
+                forwarded.extend(passthrough);
+                ctx.set_forwarded_args(forwarded);
+            } else {
+                ctx.args_mut().extend(passthrough);
+            }
+            self.apply_flag_defaults(ctx, ancestors);
+
             // Validate arguments before running
+            if self.deny_extra_args {
+                self.check_extra_args(ctx.args())?;
+            }
             if let Some(ref validator) = self.arg_validator {
                 validator.validate(ctx.args())?;
             }
+
+            // Checks of the whole invocation, root first
+            let path = ancestors.iter().chain(std::iter::once(&self));
+            ctx.set_command_path(path.clone().map(|cmd| cmd.name.clone()).collect());
+            for required in path
+                .clone()
+                .filter_map(|cmd| cmd.required_flags_when.as_ref())
+            {
+                Self::check_required_when(ctx, &provided, required)?;
+            }
+            for validate in path.filter_map(|cmd| cmd.validate.as_ref()) {
+                validate(ctx)?;
+            }
+            self.check_confirmation(ctx)?;
+            if self.shows_help_topics && !self.inherits(ancestors, |cmd| cmd.run_hooks_on_help) {
+                // The help command stays free of setup like any other help
+                return run(ctx);
+            }
             self.execute_with_parent_hooks(ctx, run, parent_hooks)
-        } else if ctx.args().is_empty() {
+        } else if ctx.args().is_empty() && passthrough.is_empty() {
             // No args and no run function - show help
             Err(Error::SubcommandRequired(self.name.clone()))
         } else {
-            let unknown_command = ctx.args().first().unwrap_or(&String::new()).clone();
+            if let Some(subcommand) = self.autocorrect_target(ctx) {
+                let mut chain = ancestors.to_vec();
+                chain.push(self);
+                parent_hooks.push((&self.persistent_pre_run, &self.persistent_post_run));
+
+                ctx.args_mut().remove(0);
+                if !passthrough.is_empty() {
+                    ctx.args_mut().push("--".to_string());
+                    ctx.args_mut().extend(passthrough);
+                }
+                return subcommand.execute_with_context_and_hooks(ctx, parent_hooks, &chain);
+            }
+
+            let unknown_command = ctx
+                .args()
+                .first()
+                .or_else(|| passthrough.first())
+                .cloned()
+                .unwrap_or_default();
             let suggestions = if self.suggestions_enabled {
                 self.find_command_suggestions(&unknown_command)
             } else {
@@ -345,79 +1567,499 @@ impl Command {
         }
     }
 
-    fn parse_flags(&self, args: &[String]) -> Result<(HashMap<String, String>, Vec<String>)> {
-        let mut flags = HashMap::new();
-        let mut remaining = Vec::new();
+    /// Rejects long flags that neither this command nor its ancestors define
+    ///
+    /// Parsing leaves unknown flags among the arguments, in case a
+    /// subcommand defines them. Once no subcommand is left to claim them
+    /// they are mistakes, reported with the closest flag names.
+    fn check_unknown_flags(&self, ancestors: &[&Self], args: &[String]) -> Result<()> {
+        if self.prefix_style(ancestors) == FlagPrefixStyle::Windows {
+            return Ok(());
+        }
+        let unknown = args
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .filter_map(|arg| arg.strip_prefix("--"))
+            .map(|flag| flag.split_once('=').map_or(flag, |(name, _)| name))
+            .find(|name| !name.is_empty());
+        let Some(name) = unknown else {
+            return Ok(());
+        };
+
+        let did_you_mean = if self.suggestions_enabled {
+            let mut candidates = vec!["help".to_string()];
+            for cmd in std::iter::once(self).chain(ancestors.iter().copied()) {
+                candidates.extend(
+                    cmd.flags
+                        .values()
+                        .filter(|flag| !flag.hidden)
+                        .map(|flag| flag.name.clone()),
+                );
+            }
+            self.suggest(name, &candidates)
+                .into_iter()
+                .map(|suggestion| format!("--{suggestion}"))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Err(Error::unknown_flag(name, did_you_mean))
+    }
+
+    fn parse_flags(
+        &self,
+        args: &[String],
+        ancestors: &[&Self],
+    ) -> Result<(HashMap<String, String>, Vec<String>)> {
+        let parsed = self.parse_args(args, &[], ancestors)?;
+        Ok((parsed.flags, parsed.remaining))
+    }
+
+    /// Parses flags like [`parse_flags`](Self::parse_flags), keeping the
+    /// originals of words that weren't valid UTF-8
+    ///
+    /// `raw` holds the original of each word of `args` that has one, by
+    /// position; it may be empty when none do.
+    fn parse_args(
+        &self,
+        args: &[String],
+        raw: &[Option<OsString>],
+        ancestors: &[&Self],
+    ) -> Result<ParsedArgs> {
+        let mut parsed = ParsedArgs::default();
+        let raw_at = |i: usize| raw.get(i).cloned().flatten();
         let mut i = 0;
+        let style = self.prefix_style(ancestors);
 
         while i < args.len() {
-            let arg = &args[i];
+            // Windows-style flags are rewritten to their Unix spelling;
+            // in Windows-only mode other dashed words are plain arguments
+            let translated = match style {
+                FlagPrefixStyle::Unix => None,
+                FlagPrefixStyle::Windows | FlagPrefixStyle::Both => {
+                    self.windows_flag_token(ancestors, &args[i])
+                }
+            };
+            let arg = translated.as_ref().unwrap_or(&args[i]);
+            let unix_spelling = style != FlagPrefixStyle::Windows || translated.is_some();
 
             if arg == "--" {
-                remaining.extend_from_slice(&args[i + 1..]);
+                // Keep the marker so the command that runs can tell
+                // passthrough arguments apart from its own
+                parsed.remaining.extend_from_slice(&args[i..]);
+                parsed.raw_remaining.extend((i..args.len()).map(raw_at));
                 break;
-            } else if arg.starts_with("--") {
+            } else if unix_spelling && arg.starts_with("--") {
                 let flag_name = arg.trim_start_matches("--");
 
                 // Special handling for help
                 if flag_name == "help" {
-                    flags.insert("help".to_string(), "true".to_string());
+                    parsed.flags.insert("help".to_string(), "true".to_string());
+                } else if flag_name == "version" && self.builtin_version(ancestors).is_some() {
+                    parsed
+                        .flags
+                        .insert("version".to_string(), "true".to_string());
+                } else if let Some(format) = self.explain_format(ancestors, flag_name)? {
+                    parsed
+                        .flags
+                        .insert(EXPLAIN_KEY.to_string(), format.to_string());
                 } else if let Some((name, value)) = flag_name.split_once('=') {
                     // Validate the flag value
-                    if let Some(flag) = self.find_flag(name) {
-                        flag.parse_value(value)?;
+                    match self.find_flag(ancestors, name) {
+                        Some(flag) => {
+                            let value = validated_flag_value(flag, value)?;
+                            insert_flag_value(&mut parsed, flag, value);
+                            let original = raw_at(i).and_then(|word| raw_attached_value(&word));
+                            parsed.set_raw_flag(&flag.name, original);
+                        }
+                        None => {
+                            // Unknown flag - might belong to a subcommand
+                            parsed.remaining.push(arg.clone());
+                            parsed.raw_remaining.push(raw_at(i));
+                        }
                     }
-                    flags.insert(name.to_string(), value.to_string());
-                } else if let Some(flag) = self.find_flag(flag_name) {
-                    if i + 1 < args.len() && !args[i + 1].starts_with('-') {
-                        let value = &args[i + 1];
+                } else if let Some(flag) = self.find_flag(ancestors, flag_name) {
+                    if i + 1 < args.len()
+                        && self.takes_next_word(style, ancestors, flag, &args[i + 1])
+                    {
                         // Validate the flag value
-                        flag.parse_value(value)?;
-                        flags.insert(flag_name.to_string(), value.clone());
+                        let value = validated_flag_value(flag, &args[i + 1])?;
+                        insert_flag_value(&mut parsed, flag, value);
+                        parsed.set_raw_flag(&flag.name, raw_at(i + 1));
                         i += 1;
                     } else {
-                        flags.insert(flag_name.to_string(), "true".to_string());
+                        let value = bare_flag_value(flag, parsed.flags.get(&flag.name));
+                        parsed.flags.insert(flag.name.clone(), value);
+                        parsed.set_raw_flag(&flag.name, None);
                     }
+                } else if let Some(flag) = self.find_negated_flag(ancestors, flag_name) {
+                    parsed.flags.insert(flag.name.clone(), "false".to_string());
+                    parsed.set_raw_flag(&flag.name, None);
                 } else {
                     // Unknown flag - might belong to a subcommand
-                    remaining.push(arg.clone());
+                    parsed.remaining.push(arg.clone());
+                    parsed.raw_remaining.push(raw_at(i));
                 }
-            } else if arg.starts_with('-') && arg.len() > 1 {
+            } else if unix_spelling && arg.starts_with('-') && arg.len() > 1 {
                 let short_flags = arg.trim_start_matches('-');
+                // `-v=false` attaches a value to the last flag in the group
+                let (short_flags, attached) = short_flags
+                    .split_once('=')
+                    .map_or((short_flags, None), |(flags, value)| (flags, Some(value)));
                 let chars: Vec<char> = short_flags.chars().collect();
 
                 for (idx, ch) in chars.iter().enumerate() {
+                    let last = idx == chars.len() - 1;
                     // Special handling for -h as help
                     if *ch == 'h' {
-                        flags.insert("help".to_string(), "true".to_string());
-                    } else if let Some(flag) = self.find_flag_by_short(*ch) {
-                        // If this is the last char and the flag takes a value
-                        if idx == chars.len() - 1
+                        parsed.flags.insert("help".to_string(), "true".to_string());
+                    } else if *ch == 'V'
+                        && self.find_flag_by_short(ancestors, 'V').is_none()
+                        && self.builtin_version(ancestors).is_some()
+                    {
+                        parsed
+                            .flags
+                            .insert("version".to_string(), "true".to_string());
+                    } else if let Some(flag) = self.find_flag_by_short(ancestors, *ch) {
+                        if let (true, Some(value)) = (last, attached) {
+                            let value = validated_flag_value(flag, value)?;
+                            insert_flag_value(&mut parsed, flag, value);
+                            let original = raw_at(i).and_then(|word| raw_attached_value(&word));
+                            parsed.set_raw_flag(&flag.name, original);
+                        } else if last
                             && i + 1 < args.len()
-                            && !args[i + 1].starts_with('-')
+                            && self.takes_next_word(style, ancestors, flag, &args[i + 1])
                         {
-                            let value = &args[i + 1];
-                            // Validate the flag value
-                            flag.parse_value(value)?;
-                            flags.insert(flag.name.clone(), value.clone());
+                            // The last flag in the group takes the next argument
+                            let value = validated_flag_value(flag, &args[i + 1])?;
+                            insert_flag_value(&mut parsed, flag, value);
+                            parsed.set_raw_flag(&flag.name, raw_at(i + 1));
                             i += 1;
                         } else {
-                            flags.insert(flag.name.clone(), "true".to_string());
+                            let value = bare_flag_value(flag, parsed.flags.get(&flag.name));
+                            parsed.flags.insert(flag.name.clone(), value);
+                            parsed.set_raw_flag(&flag.name, None);
                         }
                     } else {
                         // Unknown short flag - might belong to a subcommand
-                        remaining.push(format!("-{}", chars[idx..].iter().collect::<String>()));
+                        let rest: String = chars[idx..].iter().collect();
+                        parsed.remaining.push(attached.map_or_else(
+                            || format!("-{rest}"),
+                            |value| format!("-{rest}={value}"),
+                        ));
+                        // Only a whole word keeps its original
+                        parsed.raw_remaining.push(raw_at(i).filter(|_| idx == 0));
                         break;
                     }
                 }
+            } else if parsed.remaining.is_empty()
+                && self
+                    .resolve_subcommand(ancestors, arg)
+                    .is_some_and(|cmd| cmd.trailing_var_arg)
+            {
+                // That subcommand takes every word after it verbatim
+                parsed.remaining.extend_from_slice(&args[i..]);
+                parsed.raw_remaining.extend((i..args.len()).map(raw_at));
+                break;
             } else {
-                remaining.push(arg.clone());
+                parsed.remaining.push(arg.clone());
+                parsed.raw_remaining.push(raw_at(i));
             }
 
             i += 1;
         }
 
-        Ok((flags, remaining))
+        Ok(parsed)
+    }
+
+    /// Sets parsed flags, with their original values where they have one
+    fn set_parsed_flags(
+        ctx: &mut Context,
+        flags: HashMap<String, String>,
+        raw_flags: &mut HashMap<String, OsString>,
+        items: &mut HashMap<String, Vec<String>>,
+    ) {
+        for (name, value) in flags {
+            let list = items.remove(&name);
+            match raw_flags.remove(&name) {
+                Some(raw) => ctx.set_flag_os(name.clone(), raw),
+                None => ctx.set_flag(name.clone(), value),
+            }
+            if let Some(list) = list {
+                ctx.set_flag_items(name, list);
+            }
+        }
+    }
+
+    /// Returns whether the word after `flag` is consumed as its value
+    ///
+    /// A numeric flag takes a negative number, so `--offset -5` works;
+    /// any other flag-like word is left to be parsed as a flag.
+    fn takes_next_word(
+        &self,
+        style: FlagPrefixStyle,
+        ancestors: &[&Self],
+        flag: &Flag,
+        next: &str,
+    ) -> bool {
+        if is_negative_number_for(flag, next) {
+            return true;
+        }
+        !self.looks_like_flag(style, ancestors, next) && self.takes_next_as_value(flag, next)
+    }
+
+    /// Returns whether the word `next` can be the value of `flag`
+    ///
+    /// Bool flags only take a word they accept, so `--verbose false` works
+    /// while `--verbose file.txt` leaves the file as an argument. Flags with
+    /// an optional value leave subcommand names alone, so `app --color
+    /// deploy` runs `deploy` with the flag's optional value. Callers rule
+    /// out flag-like words.
+    fn takes_next_as_value(&self, flag: &Flag, next: &str) -> bool {
+        match flag.value_type {
+            FlagType::Count => false,
+            FlagType::Bool => flag.parse_value(next).is_ok(),
+            _ => flag.optional_value.is_none() || self.find_subcommand(next).is_none(),
+        }
+    }
+
+    /// Returns the flag prefix style in effect, set here or inherited
+    fn prefix_style(&self, ancestors: &[&Self]) -> FlagPrefixStyle {
+        std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find_map(|cmd| cmd.flag_prefix_style)
+            .unwrap_or_default()
+    }
+
+    /// Spells a Windows-style flag (`/name`, `/name:value`, `/x`) the Unix way
+    ///
+    /// Only known flags are translated, so other words starting with `/`,
+    /// such as absolute paths, stay arguments. `/?` asks for help.
+    fn windows_flag_token(&self, ancestors: &[&Self], arg: &str) -> Option<String> {
+        let body = arg.strip_prefix('/')?;
+        if body == "?" {
+            return Some("--help".to_string());
+        }
+        let (name, value) = body
+            .split_once(':')
+            .map_or((body, None), |(name, value)| (name, Some(value)));
+
+        let long = name == "help"
+            || (name == "version" && self.builtin_version(ancestors).is_some())
+            || self.find_flag(ancestors, name).is_some();
+        let spelled = if long {
+            format!("--{name}")
+        } else {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(short), None)
+                    if short == 'h' || self.find_flag_by_short(ancestors, short).is_some() =>
+                {
+                    format!("-{short}")
+                }
+                _ => return None,
+            }
+        };
+        Some(value.map_or_else(|| spelled.clone(), |value| format!("{spelled}={value}")))
+    }
+
+    /// Returns whether `word` is a flag in this prefix style, so it can't be
+    /// the value of the flag before it
+    fn looks_like_flag(&self, style: FlagPrefixStyle, ancestors: &[&Self], word: &str) -> bool {
+        let windows = || self.windows_flag_token(ancestors, word).is_some();
+        match style {
+            FlagPrefixStyle::Unix => word.starts_with('-'),
+            FlagPrefixStyle::Windows => windows(),
+            FlagPrefixStyle::Both => word.starts_with('-') || windows(),
+        }
+    }
+
+    /// Returns the version to report for `--version`, if it is built in here
+    ///
+    /// Only the root handles it, and only when a version is set and no flag
+    /// named `version` is defined.
+    pub(crate) fn builtin_version(&self, ancestors: &[&Self]) -> Option<&str> {
+        if !ancestors.is_empty() || self.flags.contains_key("version") {
+            return None;
+        }
+        self.version.as_deref()
+    }
+
+    /// Recognizes `--explain` and `--explain=<format>` when explain is enabled
+    fn explain_format<'f>(
+        &self,
+        ancestors: &[&Self],
+        flag_name: &'f str,
+    ) -> Result<Option<&'f str>> {
+        if !self.explain && !ancestors.iter().any(|cmd| cmd.explain) {
+            return Ok(None);
+        }
+        let format = match flag_name.split_once('=') {
+            Some(("explain", format)) => format,
+            None if flag_name == "explain" => "text",
+            _ => return Ok(None),
+        };
+        if matches!(format, "text" | "json") {
+            Ok(Some(format))
+        } else {
+            Err(Error::invalid_choice(
+                format,
+                "explain",
+                vec!["text".to_string(), "json".to_string()],
+            ))
+        }
+    }
+
+    /// Sets flags that weren't given from their environment variables
+    ///
+    /// Returns the names of the flags that were set.
+    fn apply_env_flags(&self, ctx: &mut Context, ancestors: &[&Self]) -> Result<Vec<String>> {
+        let mut names: Vec<&String> = std::iter::once(self)
+            .chain(ancestors.iter().copied())
+            .flat_map(|cmd| cmd.flags.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let mut from_env = Vec::new();
+        for name in names {
+            if ctx.flag(name).is_some() {
+                continue;
+            }
+            let Some(flag) = self.find_flag(ancestors, name) else {
+                continue;
+            };
+            let Some(value) = flag
+                .env
+                .as_ref()
+                .and_then(|var| std::env::var(var).ok())
+                .filter(|value| !value.is_empty())
+            else {
+                continue;
+            };
+            ctx.set_flag(name.clone(), validated_flag_value(flag, &value)?);
+            from_env.push(name.clone());
+        }
+        Ok(from_env)
+    }
+
+    /// Returns the names of this command's profiles, sorted
+    fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns whether `flag` is the `--profile` flag
+    /// [`CommandBuilder::profile`] adds, choosing one of the profiles
+    fn is_profile_flag(&self, flag: &Flag) -> bool {
+        flag.value_type == FlagType::Choice(self.profile_names())
+    }
+
+    /// Returns whether this command or one below it defines `name`
+    fn defines_flag_below(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+            || self
+                .subcommands
+                .values()
+                .any(|sub| sub.defines_flag_below(name))
+    }
+
+    /// Sets the flags of the `--profile` preset that weren't given
+    ///
+    /// The profile is looked up from this command towards the root. Flags
+    /// it names that aren't in this command's scope are skipped, so one
+    /// profile can serve several subcommands. Returns the names of the
+    /// flags it set.
+    fn apply_profile(&self, ctx: &mut Context, ancestors: &[&Self]) -> Result<Vec<String>> {
+        let Some(name) = ctx.flag("profile") else {
+            return Ok(Vec::new());
+        };
+        let Some(settings) = std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find_map(|cmd| cmd.profiles.get(name))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut from_profile = Vec::new();
+        for (flag_name, value) in settings {
+            if ctx.flag(flag_name).is_some() {
+                continue;
+            }
+            let Some(flag) = self.find_flag(ancestors, flag_name) else {
+                continue;
+            };
+            ctx.set_flag(flag_name.clone(), validated_flag_value(flag, value)?);
+            from_profile.push(flag_name.clone());
+        }
+        Ok(from_profile)
+    }
+
+    /// Stores the default of every flag in scope that wasn't provided
+    ///
+    /// Inherited flags get their defaults too, so handlers can read
+    /// `ctx.flag("port")` without repeating the declared default.
+    fn apply_flag_defaults(&self, ctx: &mut Context, ancestors: &[&Self]) {
+        let names: HashSet<&String> = std::iter::once(self)
+            .chain(ancestors.iter().copied())
+            .flat_map(|cmd| cmd.flags.keys())
+            .collect();
+
+        for name in names {
+            if ctx.flag(name).is_none() {
+                if let Some(default) = self.effective_flag_default(ancestors, name) {
+                    ctx.set_default_flag(name.clone(), default.to_string());
+                }
+            }
+        }
+    }
+
+    /// Builds the explain plan for running this command with `ctx`
+    fn explain_plan(
+        &self,
+        ctx: &Context,
+        ancestors: &[&Self],
+        from_profile: &[String],
+        from_env: &[String],
+        passthrough: Vec<String>,
+    ) -> ExplainPlan {
+        let chain = || ancestors.iter().copied().chain(std::iter::once(self));
+        let command_path = chain().map(|cmd| cmd.name.clone()).collect();
+        let mut names: Vec<&String> = chain().flat_map(|cmd| cmd.flags.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        let flags = names
+            .into_iter()
+            .filter_map(|name| {
+                let (value, source) = match ctx.flag(name) {
+                    Some(value) if from_env.contains(name) => {
+                        (value.clone(), FlagSource::Environment)
+                    }
+                    Some(value) if from_profile.contains(name) => {
+                        (value.clone(), FlagSource::Profile)
+                    }
+                    Some(value) => (value.clone(), FlagSource::CommandLine),
+                    None => (
+                        self.effective_flag_default(ancestors, name)?.to_string(),
+                        FlagSource::Default,
+                    ),
+                };
+                Some(ExplainedFlag {
+                    name: name.clone(),
+                    value,
+                    source,
+                })
+            })
+            .collect();
+
+        ExplainPlan {
+            command_path,
+            flags,
+            args: ctx.args().to_vec(),
+            passthrough,
+        }
     }
 
     /// Sets the argument completion function for this command
@@ -444,7 +2086,7 @@ impl Command {
     where
         F: Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync + 'static,
     {
-        self.arg_completions = Some(Box::new(f));
+        self.arg_completions = Some(Arc::new(f));
     }
 
     /// Sets the completion function for a specific flag
@@ -470,7 +2112,104 @@ impl Command {
     where
         F: Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync + 'static,
     {
-        self.flag_completions.insert(flag_name.into(), Box::new(f));
+        self.flag_completions.insert(flag_name.into(), Arc::new(f));
+    }
+
+    /// Registers a completion function under a name
+    ///
+    /// Arguments and flags of this command and of any command below it can
+    /// then use it by name, through [`CommandBuilder::arg_completer_ref`]
+    /// and [`CommandBuilder::flag_completer_ref`], instead of each carrying
+    /// a copy. References are resolved when completing, to the nearest
+    /// command that registers the name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, CompletionResult};
+    ///
+    /// let mut app = CommandBuilder::new("app")
+    ///     .subcommand(CommandBuilder::new("get").arg_completer_ref("resource").build())
+    ///     .subcommand(CommandBuilder::new("delete").arg_completer_ref("resource").build())
+    ///     .build();
+    /// app.register_completer("resource", |_ctx, prefix| {
+    ///     Ok(CompletionResult::new().extend(
+    ///         ["pods", "services"].into_iter().filter(|r| r.starts_with(prefix)).map(String::from),
+    ///     ))
+    /// });
+    /// assert!(app.validate_tree().is_ok());
+    /// ```
+    pub fn register_completer<F>(&mut self, name: impl Into<String>, f: F)
+    where
+        F: Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync + 'static,
+    {
+        self.completers.insert(name.into(), Arc::new(f));
+    }
+
+    /// Adds a flag, keeping its completion function shared so a completion
+    /// timeout can run it on a worker thread
+    fn insert_flag(&mut self, mut flag: Flag) {
+        match flag.completion.take() {
+            Some(completion) => {
+                let shared: SharedCompletionFunc = Arc::from(completion);
+                let delegate = Arc::clone(&shared);
+                flag.completion = Some(Box::new(move |ctx, prefix| delegate(ctx, prefix)));
+                self.own_flag_completions.insert(flag.name.clone(), shared);
+            }
+            None => {
+                self.own_flag_completions.remove(&flag.name);
+            }
+        }
+        self.flags.insert(flag.name.clone(), flag);
+    }
+
+    /// Returns the completion function of the flag itself, from the
+    /// nearest command declaring it
+    fn own_flag_completer<'s>(
+        &'s self,
+        ancestors: &[&'s Self],
+        flag_name: &str,
+    ) -> Option<&'s SharedCompletionFunc> {
+        std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find(|cmd| cmd.flags.contains_key(flag_name))?
+            .own_flag_completions
+            .get(flag_name)
+    }
+
+    /// Finds the completer registered as `name` here or on the nearest parent
+    fn named_completer<'s>(
+        &'s self,
+        ancestors: &[&'s Self],
+        name: &str,
+    ) -> Option<&'s SharedCompletionFunc> {
+        std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find_map(|cmd| cmd.completers.get(name))
+    }
+
+    /// Returns the function completing this command's arguments, either its
+    /// own or the named completer it refers to
+    fn arg_completer<'s>(&'s self, ancestors: &[&'s Self]) -> Option<&'s SharedCompletionFunc> {
+        self.arg_completions.as_ref().or_else(|| {
+            self.arg_completer_ref
+                .as_ref()
+                .and_then(|name| self.named_completer(ancestors, name))
+        })
+    }
+
+    /// Returns the function registered to complete a flag's values, either
+    /// directly or as a reference to a named completer
+    fn flag_completer<'s>(
+        &'s self,
+        ancestors: &[&'s Self],
+        flag_name: &str,
+    ) -> Option<&'s SharedCompletionFunc> {
+        self.flag_completions.get(flag_name).or_else(|| {
+            self.flag_completer_refs
+                .get(flag_name)
+                .and_then(|name| self.named_completer(ancestors, name))
+        })
     }
 
     /// Gets completion suggestions for the current context
@@ -483,82 +2222,185 @@ impl Command {
         completing_flag: Option<&str>,
     ) -> Result<CompletionResult> {
         if let Some(flag_name) = completing_flag {
-            if let Some(completion_func) = self.flag_completions.get(flag_name) {
-                return completion_func(ctx, to_complete);
+            if let Some(completion_func) = self.flag_completer(&[], flag_name) {
+                return self.run_completion(&[], completion_func, flag_name, ctx, to_complete);
             }
-        } else if let Some(ref completion_func) = self.arg_completions {
-            return completion_func(ctx, to_complete);
+        } else if let Some(completion_func) = self.arg_completer(&[]) {
+            return self.run_completion(&[], completion_func, "", ctx, to_complete);
         }
 
         Ok(CompletionResult::new())
     }
 
-    fn find_flag(&self, name: &str) -> Option<&Flag> {
-        self.flags.get(name).or_else(|| {
-            self.parent
-                .and_then(|parent| unsafe { (*parent).find_flag(name) })
-        })
+    /// Finds a flag on this command or the nearest ancestor defining it
+    fn find_flag<'s>(&'s self, ancestors: &[&'s Self], name: &str) -> Option<&'s Flag> {
+        self.flags
+            .get(name)
+            .or_else(|| ancestors.iter().rev().find_map(|cmd| cmd.flags.get(name)))
     }
 
-    fn find_flag_by_short(&self, short: char) -> Option<&Flag> {
-        self.flags
-            .values()
-            .find(|f| f.short == Some(short))
-            .or_else(|| {
-                self.parent
-                    .and_then(|parent| unsafe { (*parent).find_flag_by_short(short) })
-            })
+    /// Finds the negatable flag that `name`, like `no-color`, turns off
+    fn find_negated_flag<'s>(&'s self, ancestors: &[&'s Self], name: &str) -> Option<&'s Flag> {
+        let flag = self.find_flag(ancestors, name.strip_prefix("no-")?)?;
+        Self::is_negatable(flag).then_some(flag)
     }
 
-    /// Validates all flags including required flags and constraints
-    fn validate_flags(&self, provided_flags: &HashMap<String, String>) -> Result<()> {
-        let provided_flag_names: HashSet<String> = provided_flags.keys().cloned().collect();
+    /// Returns whether `--no-<name>` turns the flag off
+    fn is_negatable(flag: &Flag) -> bool {
+        flag.negatable && flag.value_type == FlagType::Bool
+    }
 
-        // Check required flags
-        for (flag_name, flag) in &self.flags {
-            if flag.required && !provided_flag_names.contains(flag_name) {
-                return Err(Error::flag_parsing_with_suggestions(
-                    format!("Required flag '--{flag_name}' not provided"),
-                    flag_name.to_string(),
-                    vec![format!("add --{flag_name} <value>")],
-                ));
+    /// Finds a flag by short name on this command or the nearest ancestor
+    fn find_flag_by_short<'s>(&'s self, ancestors: &[&'s Self], short: char) -> Option<&'s Flag> {
+        let by_short = |cmd: &'s Self| cmd.flags.values().find(|f| f.short == Some(short));
+        by_short(self).or_else(|| ancestors.iter().rev().find_map(|cmd| by_short(cmd)))
+    }
+
+    /// Checks the flags a [`CommandBuilder::require_flag_when`] function
+    /// asks for against those that were provided
+    fn check_required_when(
+        ctx: &Context,
+        provided: &HashSet<String>,
+        required: &RequiredFlagsFunc,
+    ) -> Result<()> {
+        let mut missing: Vec<String> = required(ctx)
+            .into_iter()
+            .filter(|name| !provided.contains(name))
+            .collect();
+        missing.sort();
+        missing.dedup();
+        let Some(first) = missing.first().cloned() else {
+            return Ok(());
+        };
+        let invocation: Vec<&str> = ctx
+            .command_path()
+            .iter()
+            .chain(ctx.args())
+            .map(String::as_str)
+            .collect();
+        Err(Error::flag_parsing_with_suggestions(
+            format!(
+                "Required flag{} {} not provided, needed for '{}'",
+                if missing.len() == 1 { "" } else { "s" },
+                group_list(&missing, "and"),
+                invocation.join(" ")
+            ),
+            first,
+            vec![format!("add {}", group_list(&missing, "and"))],
+        ))
+    }
+
+    /// Validates all flags including required flags and constraints
+    ///
+    /// Flags inherited from `ancestors` are checked too, so a required
+    /// global flag is enforced however deeply nested the running command is.
+    fn validate_flags(&self, ancestors: &[&Self], provided: &HashSet<String>) -> Result<()> {
+        let chain = || ancestors.iter().copied().chain(std::iter::once(self));
+
+        // Check required flags, from the root down
+        for cmd in chain() {
+            let mut required: Vec<&String> = cmd
+                .flags
+                .iter()
+                .filter(|(name, flag)| flag.required && !provided.contains(*name))
+                .map(|(name, _)| name)
+                .collect();
+            required.sort();
+            if let Some(flag_name) = required.first() {
+                return Err(Error::flag_parsing_with_suggestions(
+                    format!("Required flag '--{flag_name}' not provided"),
+                    (*flag_name).clone(),
+                    vec![format!("add --{flag_name} <value>")],
+                ));
             }
         }
 
-        // TODO: Fix unsafe parent flag validation
-        // Check parent flags if any
-        // if let Some(parent) = self.parent {
-        //     unsafe {
-        //         for (flag_name, flag) in &(*parent).flags {
-        //             if flag.required && !provided_flag_names.contains(flag_name) {
-        //                 return Err(Error::flag_parsing_with_suggestions(
-        //                     format!("Required flag '--{flag_name}' not provided"),
-        //                     flag_name.to_string(),
-        //                     vec![format!("add --{flag_name} <value>")],
-        //                 ));
-        //             }
-        //         }
-        //     }
-        // }
+        // Check flag groups, from the root down
+        for cmd in chain() {
+            for group in &cmd.exclusive_flag_groups {
+                let set: Vec<&String> = group
+                    .iter()
+                    .filter(|name| provided.contains(*name))
+                    .collect();
+                if set.len() > 1 {
+                    return Err(Error::flag_parsing_with_suggestions(
+                        format!(
+                            "Flags {} are mutually exclusive, but {} were given",
+                            group_list(group, "and"),
+                            group_list(&set, "and")
+                        ),
+                        set[0].clone(),
+                        vec![format!("use only one of {}", group_list(group, "or"))],
+                    ));
+                }
+            }
+            for group in &cmd.required_one_flag_groups {
+                if !group.is_empty() && !group.iter().any(|name| provided.contains(name)) {
+                    return Err(Error::flag_parsing_with_suggestions(
+                        format!("One of {} is required", group_list(group, "or")),
+                        group[0].clone(),
+                        vec![format!("add {}", group_list(group, "or"))],
+                    ));
+                }
+            }
+            for group in &cmd.together_flag_groups {
+                let (set, missing): (Vec<&String>, Vec<&String>) =
+                    group.iter().partition(|name| provided.contains(*name));
+                if !set.is_empty() && !missing.is_empty() {
+                    return Err(Error::flag_parsing_with_suggestions(
+                        format!(
+                            "Flags {} must be used together, but {} {} not given",
+                            group_list(group, "and"),
+                            group_list(&missing, "and"),
+                            if missing.len() == 1 { "was" } else { "were" }
+                        ),
+                        missing[0].clone(),
+                        vec![format!("add {}", group_list(&missing, "and"))],
+                    ));
+                }
+            }
+        }
 
         // Validate constraints for all flags
-        for (flag_name, flag) in &self.flags {
-            flag.validate_constraints(flag_name, &provided_flag_names)?;
-        }
-
-        // TODO: Fix unsafe parent flag constraint validation
-        // The current approach with raw pointers can lead to undefined behavior
-        // when the parent Command is moved or when accessing heap-allocated data
-        // through the pointer (like Vec<FlagConstraint>).
-        //
-        // Validate parent flag constraints
-        // if let Some(parent) = self.parent {
-        //     unsafe {
-        //         for (flag_name, flag) in &(*parent).flags {
-        //             flag.validate_constraints(flag_name, &provided_flag_names)?;
-        //         }
-        //     }
-        // }
+        for cmd in chain() {
+            for (flag_name, flag) in &cmd.flags {
+                flag.validate_constraints(flag_name, provided)?;
+            }
+        }
+
+        // Flags that don't apply to the command being run, by its path
+        // below the root
+        let path: Vec<&str> = chain().skip(1).map(|cmd| cmd.name.as_str()).collect();
+        for cmd in chain() {
+            let mut flags: Vec<&Flag> = cmd
+                .flags
+                .values()
+                .filter(|flag| provided.contains(&flag.name))
+                .collect();
+            flags.sort_by(|a, b| a.name.cmp(&b.name));
+            for flag in flags {
+                for constraint in &flag.constraints {
+                    let FlagConstraint::InvalidForCommands(commands) = constraint else {
+                        continue;
+                    };
+                    let matches = |command: &String| {
+                        let words: Vec<&str> = command.split_whitespace().collect();
+                        !words.is_empty() && path.starts_with(&words)
+                    };
+                    if let Some(command) = commands.iter().find(|command| matches(command)) {
+                        let root = chain().next().map_or("", |cmd| cmd.name.as_str());
+                        return Err(Error::flag_parsing_with_suggestions(
+                            format!(
+                                "Flag '--{}' can't be used with '{root} {command}'",
+                                flag.name
+                            ),
+                            flag.name.clone(),
+                            vec![format!("remove --{}", flag.name)],
+                        ));
+                    }
+                }
+            }
+        }
 
         Ok(())
     }
@@ -588,7 +2430,7 @@ impl Command {
         }
 
         // Execute the main run function
-        let result = run(ctx);
+        let result = self.run_with_timeout(ctx, run);
 
         // Execute post-run hook if present, but preserve the original error
         let post_run_result = if let Some(ref post_run) = self.post_run {
@@ -645,212 +2487,553 @@ impl Command {
     /// - Available subcommands
     /// - Local and global flags
     ///
-    /// Help text is automatically colored when outputting to a TTY.
-    #[allow(clippy::cognitive_complexity)]
+    /// Help text is automatically colored when outputting to a TTY. When the
+    /// pager is enabled (see [`CommandBuilder::use_pager`]), help that doesn't
+    /// fit the terminal is shown through `$PAGER`.
     pub fn print_help(&self) {
-        use crate::color;
+        self.emit_help(&self.help_text());
+    }
+
+    /// Writes the help message for this command to `out`
+    ///
+    /// The pager is never used, since `out` is not known to be a terminal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp").short("My application").build();
+    /// let mut out = Vec::new();
+    /// cmd.write_help(&mut out).unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), cmd.help_text());
+    /// ```
+    pub fn write_help<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        self.write_help_text(&self.help_text(), out, false)
+    }
+
+    /// Shows help, running the persistent hooks of `hooks` around it only
+    /// when [`CommandBuilder::run_hooks_on_help`] is set here or above
+    ///
+    /// `hooks` runs from the root down to this command. `text` is already
+    /// rendered, so the hooks can't change it.
+    fn emit_help_with_hooks(
+        &self,
+        ctx: &mut Context,
+        text: &str,
+        ancestors: &[&Self],
+        hooks: &[(&Option<HookFunc>, &Option<HookFunc>)],
+    ) -> Result<()> {
+        if !self.inherits(ancestors, |cmd| cmd.run_hooks_on_help) {
+            self.emit_help(text);
+            return Ok(());
+        }
+        for hook in hooks.iter().filter_map(|(pre, _)| pre.as_ref()) {
+            hook(ctx)?;
+        }
+        self.emit_help(text);
+        for hook in hooks.iter().rev().filter_map(|(_, post)| post.as_ref()) {
+            hook(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Prints rendered help to stdout, paging it if enabled
+    fn emit_help(&self, text: &str) {
+        use std::io::IsTerminal;
+
+        let stdout = std::io::stdout();
+        let is_tty = stdout.is_terminal();
+        let _ = self.write_help_text(text, &mut stdout.lock(), is_tty);
+    }
+
+    fn write_help_text<W: std::io::Write>(
+        &self,
+        text: &str,
+        out: &mut W,
+        is_tty: bool,
+    ) -> std::io::Result<()> {
+        if self.use_pager {
+            write_paged(text, out, is_tty)
+        } else {
+            out.write_all(text.as_bytes())
+        }
+    }
+
+    /// Renders the help message for this command without printing it
+    ///
+    /// This is the text [`print_help`](Self::print_help) writes to stdout.
+    /// The command is rendered on its own, so a subcommand's help has no
+    /// global flags section. Help shown while executing through the root
+    /// includes them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp").short("My application").build();
+    /// assert!(cmd.help_text().starts_with("My application"));
+    /// ```
+    #[must_use]
+    pub fn help_text(&self) -> String {
+        self.render_help(&[], &HashMap::new())
+    }
+
+    /// Renders help, revealing flags whose `visible_when` gate is in `provided`
+    ///
+    /// `ancestors` are the commands from the root down to the parent, and
+    /// inherited flags come from them; when empty, none are shown.
+    fn render_help(&self, ancestors: &[&Self], provided: &HashMap<String, String>) -> String {
+        let (command_column, flag_column) = if self.aligned_help {
+            let column = self.aligned_help_column(ancestors, provided);
+            (column, column)
+        } else {
+            (26, 30)
+        };
+
+        if let Some(template) = &self.help_template {
+            return self.render_help_template(
+                template,
+                ancestors,
+                provided,
+                command_column,
+                flag_column,
+            );
+        }
+
+        let mut out = self.help_description();
+        out.push_str(&self.help_usage());
+        out.push_str(&self.help_commands(command_column));
+        out.push_str(&self.help_flags(ancestors, provided, flag_column));
+        out.push_str(&self.help_global_flags(ancestors, provided, flag_column));
+        out.push_str(&self.help_examples());
+        out.push_str(&self.help_footer(ancestors));
+        out
+    }
+
+    /// Renders a [`CommandBuilder::help_template`], replacing each known
+    /// placeholder with its section and keeping any other text as written
+    fn render_help_template(
+        &self,
+        template: &str,
+        ancestors: &[&Self],
+        provided: &HashMap<String, String>,
+        command_column: usize,
+        flag_column: usize,
+    ) -> String {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let section = after.find('}').and_then(|end| {
+                let text = match &after[..end] {
+                    "name" => self.name.clone(),
+                    "short" => self.short.clone(),
+                    "long" => self.long.clone(),
+                    "description" => self.help_description(),
+                    "usage" => self.help_usage(),
+                    "commands" => self.help_commands(command_column),
+                    "flags" => self.help_flags(ancestors, provided, flag_column),
+                    "global_flags" => self.help_global_flags(ancestors, provided, flag_column),
+                    "examples" => self.help_examples(),
+                    "footer" => self.help_footer(ancestors),
+                    _ => return None,
+                };
+                Some((text, end))
+            });
+            if let Some((text, end)) = section {
+                // Sections bring no blank lines of their own; the template
+                // decides the spacing
+                out.push_str(text.trim_matches('\n'));
+                rest = &after[end + 1..];
+            } else {
+                out.push('{');
+                rest = after;
+            }
+        }
+        out.push_str(rest);
+        out
+    }
 
-        // Print description with text wrapping
-        if !self.long.is_empty() {
-            println!("{}", wrap_text_to_terminal(&self.long, None));
-            println!();
-        } else if !self.short.is_empty() {
-            println!("{}", wrap_text_to_terminal(&self.short, None));
-            println!();
+    /// Returns the long description, or the short one, followed by a blank line
+    fn help_description(&self) -> String {
+        let description = if self.long.is_empty() {
+            &self.short
+        } else {
+            &self.long
+        };
+        if description.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n\n", wrap_text_to_terminal(description, None))
         }
+    }
 
-        // Print usage line
-        print!("{}:\n  {}", color::bold("Usage"), self.name);
+    /// Returns the usage section, followed by a blank line
+    fn help_usage(&self) -> String {
+        use crate::color;
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let _ = write!(out, "{}:\n  {}", color::bold("Usage"), self.name);
         if !self.flags.is_empty() {
-            print!(" {}", color::yellow("[flags]"));
+            let _ = write!(out, " {}", color::yellow("[flags]"));
         }
-        if !self.subcommands.is_empty() {
-            print!(" {}", color::yellow("[command]"));
+        if self.subcommands.values().any(|cmd| !cmd.hidden) {
+            let _ = write!(out, " {}", color::yellow("[command]"));
         }
 
-        // Show if command requires args
-        if let Some(validator) = &self.arg_validator {
+        // Named positionals replace the generic placeholder
+        if !self.positionals.is_empty() {
+            let required = match self.arg_validator {
+                Some(
+                    ArgValidator::MinimumArgs(n)
+                    | ArgValidator::ExactArgs(n)
+                    | ArgValidator::RangeArgs(n, _),
+                ) => n,
+                _ => self.positionals.len(),
+            };
+            for (index, (name, kind)) in self.positionals.iter().enumerate() {
+                let name = match kind {
+                    Positional::File => format!("{name}:file"),
+                    Positional::Directory => format!("{name}:dir"),
+                    Positional::Any | Positional::ValidArgs(_) => name.clone(),
+                };
+                let placeholder = if index < required {
+                    format!("<{name}>")
+                } else {
+                    format!("[{name}]")
+                };
+                let _ = write!(out, " {}", color::yellow(&placeholder));
+            }
+        } else if let Some(validator) = &self.arg_validator {
             match validator {
                 ArgValidator::MinimumArgs(n) if n > &0 => {
-                    print!(" {}", color::yellow("<args>"));
+                    let _ = write!(out, " {}", color::yellow("<args>"));
                 }
                 ArgValidator::ExactArgs(n) if n > &0 => {
                     let arg_str = if n == &1 { "<arg>" } else { "<args>" };
-                    print!(" {}", color::yellow(arg_str));
+                    let _ = write!(out, " {}", color::yellow(arg_str));
                 }
                 ArgValidator::RangeArgs(min, _) if min > &0 => {
-                    print!(" {}", color::yellow("<args>"));
+                    let _ = write!(out, " {}", color::yellow("<args>"));
                 }
                 _ => {}
             }
         }
-        println!("\n");
-
-        // Print available commands
-        if !self.subcommands.is_empty() {
-            let mut commands: Vec<_> = self.subcommands.values().collect();
-            commands.sort_by_key(|cmd| &cmd.name);
+        let _ = writeln!(out, "\n");
+        out
+    }
 
-            // Group commands by their group_id
-            let mut grouped: std::collections::BTreeMap<Option<String>, Vec<&Self>> =
-                std::collections::BTreeMap::new();
-            for cmd in commands {
-                grouped.entry(cmd.group_id.clone()).or_default().push(cmd);
-            }
+    /// Returns the visible subcommands, ungrouped ones first and then one
+    /// section per group, each followed by a blank line
+    fn help_commands(&self, command_column: usize) -> String {
+        use crate::color;
+        use std::fmt::Write;
 
-            let terminal_width = get_terminal_width();
-            let left_column_width = 24;
+        let mut out = String::new();
+        if self.subcommands.is_empty() {
+            return out;
+        }
 
-            // Print commands without groups first
-            if let Some(ungrouped) = grouped.get(&None) {
-                println!("{}:", color::bold("Available Commands"));
-                for cmd in ungrouped {
-                    // Build command name with aliases
-                    let mut name_with_aliases = color::green(&cmd.name);
-                    if !cmd.aliases.is_empty() {
-                        let aliases = cmd.aliases.join(", ");
-                        name_with_aliases = format!(
-                            "{} {}",
-                            name_with_aliases,
-                            color::dim(&format!("({aliases})"))
-                        );
-                    }
+        let mut commands: Vec<_> = self
+            .subcommands
+            .values()
+            .filter(|cmd| !cmd.hidden)
+            .collect();
+        commands.sort_by_key(|cmd| &cmd.name);
+
+        // Group commands by their group_id
+        let mut grouped: std::collections::BTreeMap<Option<String>, Vec<&Self>> =
+            std::collections::BTreeMap::new();
+        for cmd in commands {
+            grouped.entry(cmd.group_id.clone()).or_default().push(cmd);
+        }
 
-                    let formatted = format_help_entry(
-                        &format!("  {name_with_aliases}"),
-                        &cmd.short,
-                        left_column_width + 2, // account for the "  " prefix
-                        terminal_width,
+        let terminal_width = get_terminal_width();
+        let write_commands = |out: &mut String, title: &str, cmds: &[&Self]| {
+            let _ = writeln!(out, "{}:", color::bold(title));
+            for cmd in cmds {
+                // Build command name with aliases
+                let mut name_with_aliases = color::green(&cmd.name);
+                if !cmd.aliases.is_empty() {
+                    let aliases = cmd.aliases.join(", ");
+                    name_with_aliases = format!(
+                        "{} {}",
+                        name_with_aliases,
+                        color::dim(&format!("({aliases})"))
                     );
-                    println!("{formatted}");
                 }
-                println!();
-            }
-
-            // Print grouped commands
-            for (group_id, cmds) in grouped {
-                if let Some(group) = group_id {
-                    println!("{}:", color::bold(&group));
-                    for cmd in cmds {
-                        // Build command name with aliases
-                        let mut name_with_aliases = color::green(&cmd.name);
-                        if !cmd.aliases.is_empty() {
-                            let aliases = cmd.aliases.join(", ");
-                            name_with_aliases = format!(
-                                "{} {}",
-                                name_with_aliases,
-                                color::dim(&format!("({aliases})"))
-                            );
-                        }
 
-                        let formatted = format_help_entry(
-                            &format!("  {name_with_aliases}"),
-                            &cmd.short,
-                            left_column_width + 2, // account for the "  " prefix
-                            terminal_width,
-                        );
-                        println!("{formatted}");
-                    }
-                    println!();
-                }
+                let formatted = format_help_entry(
+                    &format!("  {name_with_aliases}"),
+                    &cmd.short,
+                    command_column,
+                    terminal_width,
+                );
+                let _ = writeln!(out, "{formatted}");
             }
+            let _ = writeln!(out);
+        };
+
+        // Print commands without groups first
+        if let Some(ungrouped) = grouped.get(&None) {
+            write_commands(&mut out, "Available Commands", ungrouped);
         }
 
-        // Print flags
-        if !self.flags.is_empty() || self.parent.is_some() {
-            // Separate required and optional flags
-            let mut required_flags: Vec<_> = self.flags.values().filter(|f| f.required).collect();
-            let mut optional_flags: Vec<_> = self.flags.values().filter(|f| !f.required).collect();
+        // Print grouped commands
+        for (group_id, cmds) in &grouped {
+            if let Some(group) = group_id {
+                write_commands(&mut out, group, cmds);
+            }
+        }
+        out
+    }
 
-            required_flags.sort_by_key(|f| &f.name);
-            optional_flags.sort_by_key(|f| &f.name);
+    /// Returns this command's visible flags, required ones first
+    fn help_flags(
+        &self,
+        ancestors: &[&Self],
+        provided: &HashMap<String, String>,
+        flag_column: usize,
+    ) -> String {
+        use crate::color;
+        use std::fmt::Write;
 
-            // Print required flags first
-            if !required_flags.is_empty() {
-                println!("{} {}:", color::bold("Required Flags"), color::red("*"));
-                for flag in required_flags {
-                    Self::print_flag(flag);
-                }
-                if !optional_flags.is_empty() {
-                    println!();
-                }
-            }
+        let mut out = String::new();
+        let style = self.prefix_style(ancestors);
+
+        // Separate required and optional flags
+        let visible_flags = self.flags.values().filter(|f| f.is_visible(provided));
+        let (mut required_flags, mut optional_flags): (Vec<_>, Vec<_>) =
+            visible_flags.partition(|f| f.required);
 
-            // Print optional flags
+        required_flags.sort_by_key(|f| &f.name);
+        optional_flags.sort_by_key(|f| &f.name);
+
+        // Print required flags first
+        if !required_flags.is_empty() {
+            let _ = writeln!(
+                out,
+                "{} {}:",
+                color::bold("Required Flags"),
+                color::red("*")
+            );
+            for flag in required_flags {
+                Self::write_flag(&mut out, flag, flag_column, style);
+            }
             if !optional_flags.is_empty() {
-                println!("{}:", color::bold("Flags"));
-                for flag in optional_flags {
-                    Self::print_flag(flag);
-                }
+                let _ = writeln!(out);
+            }
+        }
+
+        // Group optional flags by their group, ungrouped ones first
+        let mut grouped: std::collections::BTreeMap<Option<&str>, Vec<&Flag>> =
+            std::collections::BTreeMap::new();
+        for flag in optional_flags {
+            grouped.entry(flag.group.as_deref()).or_default().push(flag);
+        }
+
+        // Print optional flags
+        for (index, (group, flags)) in grouped.into_iter().enumerate() {
+            if index > 0 {
+                let _ = writeln!(out);
+            }
+            let _ = writeln!(out, "{}:", color::bold(group.unwrap_or("Flags")));
+            for flag in flags {
+                Self::write_flag(&mut out, flag, flag_column, style);
             }
         }
+        out
+    }
+
+    /// Returns the visible flags of the parent, with this command's
+    /// defaults, after a blank line
+    fn help_global_flags(
+        &self,
+        ancestors: &[&Self],
+        provided: &HashMap<String, String>,
+        flag_column: usize,
+    ) -> String {
+        use crate::color;
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let style = self.prefix_style(ancestors);
 
         // Print global flags from parent
-        if let Some(parent) = self.parent {
-            unsafe {
-                let parent_flags = &(*parent).flags;
-                if !parent_flags.is_empty() {
-                    println!("\n{}:", color::bold("Global Flags"));
-                    let mut global_flags: Vec<_> = parent_flags.values().collect();
-                    global_flags.sort_by_key(|f| &f.name);
-
-                    for flag in global_flags {
-                        Self::print_flag(flag);
+        if let Some(parent) = ancestors.last() {
+            let parent_flags = &parent.flags;
+            if !parent_flags.is_empty() {
+                let _ = writeln!(out, "\n{}:", color::bold("Global Flags"));
+                let mut global_flags: Vec<_> = parent_flags
+                    .values()
+                    .filter(|f| f.is_visible(provided))
+                    .collect();
+                global_flags.sort_by_key(|f| &f.name);
+
+                for flag in global_flags {
+                    let default = self.effective_flag_default(ancestors, &flag.name);
+                    if default == flag.default.as_ref() {
+                        Self::write_flag(&mut out, flag, flag_column, style);
+                    } else {
+                        let mut flag = flag.clone();
+                        flag.default = default.cloned();
+                        Self::write_flag(&mut out, &flag, flag_column, style);
                     }
                 }
             }
         }
+        out
+    }
 
-        // Print examples if available
+    /// Returns the examples section, followed by a blank line
+    fn help_examples(&self) -> String {
+        use crate::color;
+        use std::fmt::Write;
+
+        let mut out = String::new();
         if !self.examples.is_empty() {
-            println!("{}:", color::bold("Examples"));
-            for example in &self.examples {
-                println!("  {}", color::dim(example));
+            let _ = writeln!(out, "{}:", color::bold("Examples"));
+            for (example, description) in self.described_examples() {
+                if let Some(description) = description {
+                    let _ = writeln!(out, "  {}", color::dim(&format!("# {description}")));
+                }
+                let _ = writeln!(out, "  {}", color::dim(example));
             }
-            println!();
+            let _ = writeln!(out);
         }
+        out
+    }
+
+    /// Returns the closing line about getting help on subcommands
+    fn help_footer(&self, ancestors: &[&Self]) -> String {
+        use crate::color;
 
-        // Print help about help
-        println!(
-            "Use \"{} {} --help\" for more information about a command.",
+        format!(
+            "Use \"{} {} {}help\" for more information about a command.\n",
             self.name,
-            color::yellow("[command]")
+            color::yellow("[command]"),
+            self.prefix_style(ancestors).long_prefix()
+        )
+    }
+
+    /// Computes one left-column width shared by commands and flags
+    ///
+    /// The widest visible entry sets the column, capped so a single long
+    /// name doesn't squeeze every description.
+    fn aligned_help_column(
+        &self,
+        ancestors: &[&Self],
+        provided: &HashMap<String, String>,
+    ) -> usize {
+        let commands = self
+            .subcommands
+            .values()
+            .filter(|cmd| !cmd.hidden)
+            .map(|cmd| {
+                let aliases = if cmd.aliases.is_empty() {
+                    0
+                } else {
+                    cmd.aliases.join(", ").len() + 3
+                };
+                2 + cmd.name.len() + aliases
+            });
+
+        let parent_flags = ancestors
+            .last()
+            .map(|parent| parent.flags.values().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let flags = self
+            .flags
+            .values()
+            .chain(parent_flags)
+            .filter(|f| f.is_visible(provided))
+            .map(|f| 12 + Self::flag_label(f).len());
+
+        commands
+            .chain(flags)
+            .max()
+            .unwrap_or(0)
+            .min(MAX_ALIGNED_HELP_COLUMN)
+    }
+
+    /// Renders the `-v, --verbose string` column of a flag's help line
+    ///
+    /// Flags without a short name get plain padding in its place, so long
+    /// names line up whether or not color is on.
+    fn flag_left_column(flag: &Flag, style: FlagPrefixStyle) -> String {
+        use crate::color;
+
+        let short = flag.short.map_or_else(
+            || "    ".to_string(),
+            |s| color::cyan(&format!("{}{s}, ", style.short_prefix())),
         );
+        format!(
+            "      {short}{}{}",
+            style.long_prefix(),
+            color::cyan(&Self::flag_label(flag))
+        )
     }
 
-    fn print_flag(flag: &Flag) {
+    fn write_flag(out: &mut String, flag: &Flag, left_column_width: usize, style: FlagPrefixStyle) {
         use crate::color;
         use std::fmt::Write;
 
-        let short = flag
-            .short
-            .map_or_else(|| "    ".to_string(), |s| format!("-{s}, "));
-
         // Build constraint indicators
+        let long = style.long_prefix();
         let mut constraint_info = String::new();
+        if let Some(var) = &flag.env {
+            let _ = write!(
+                &mut constraint_info,
+                " {}",
+                color::dim(&format!("[env: {var}]"))
+            );
+        }
         for constraint in &flag.constraints {
             match constraint {
                 FlagConstraint::RequiredIf(other) => {
                     let _ = write!(
                         &mut constraint_info,
                         " {}",
-                        color::yellow(&format!("[required if --{other}]"))
+                        color::yellow(&format!("[required if {long}{other}]"))
                     );
                 }
                 FlagConstraint::ConflictsWith(others) => {
-                    let conflicts = others.join(", --");
+                    let conflicts = others.join(&format!(", {long}"));
                     let _ = write!(
                         &mut constraint_info,
                         " {}",
-                        color::yellow(&format!("[conflicts with --{conflicts}]"))
+                        color::yellow(&format!("[conflicts with {long}{conflicts}]"))
                     );
                 }
                 FlagConstraint::Requires(others) => {
-                    let requires = others.join(", --");
+                    let requires = others.join(&format!(", {long}"));
+                    let _ = write!(
+                        &mut constraint_info,
+                        " {}",
+                        color::yellow(&format!("[requires {long}{requires}]"))
+                    );
+                }
+                FlagConstraint::RequiresOneOf(others) => {
+                    let requires = others.join(&format!(" | {long}"));
                     let _ = write!(
                         &mut constraint_info,
                         " {}",
-                        color::yellow(&format!("[requires --{requires}]"))
+                        color::yellow(&format!("[requires one of {long}{requires}]"))
+                    );
+                }
+                FlagConstraint::InvalidForCommands(commands) => {
+                    let _ = write!(
+                        &mut constraint_info,
+                        " {}",
+                        color::yellow(&format!("[not valid with {}]", commands.join(", ")))
                     );
                 }
             }
@@ -858,8 +3041,7 @@ impl Command {
 
         // Handle special formatting for Choice and Range types
         match &flag.value_type {
-            FlagType::Choice(choices) => {
-                let choices_str = choices.join("|");
+            FlagType::Choice(_) => {
                 let default = flag
                     .default
                     .as_ref()
@@ -869,24 +3051,18 @@ impl Command {
                     })
                     .unwrap_or_default();
 
-                let flag_name_formatted = format!("{} {{{}}}", flag.name, choices_str);
-                let left_part = format!(
-                    "      {}--{}",
-                    color::cyan(&short),
-                    color::cyan(&flag_name_formatted)
-                );
+                let left_part = Self::flag_left_column(flag, style);
 
                 let description =
                     format!("{}{}{}", flag.usage, color::dim(&default), constraint_info);
                 let terminal_width = get_terminal_width();
-                let left_column_width = 30;
 
                 let formatted =
                     format_help_entry(&left_part, &description, left_column_width, terminal_width);
-                println!("{formatted}");
+                let _ = writeln!(out, "{formatted}");
                 return;
             }
-            FlagType::Range(min, max) => {
+            FlagType::Range(_, _) => {
                 let default = flag
                     .default
                     .as_ref()
@@ -896,37 +3072,20 @@ impl Command {
                     })
                     .unwrap_or_default();
 
-                let flag_name_formatted = format!("{} int[{}-{}]", flag.name, min, max);
-                let left_part = format!(
-                    "      {}--{}",
-                    color::cyan(&short),
-                    color::cyan(&flag_name_formatted)
-                );
+                let left_part = Self::flag_left_column(flag, style);
 
                 let description =
                     format!("{}{}{}", flag.usage, color::dim(&default), constraint_info);
                 let terminal_width = get_terminal_width();
-                let left_column_width = 30;
 
                 let formatted =
                     format_help_entry(&left_part, &description, left_column_width, terminal_width);
-                println!("{formatted}");
+                let _ = writeln!(out, "{formatted}");
                 return;
             }
             _ => {}
         }
 
-        let flag_type = match &flag.value_type {
-            FlagType::String => " string",
-            FlagType::Int => " int",
-            FlagType::Float => " float",
-            FlagType::Bool => "",
-            FlagType::StringSlice | FlagType::StringArray => " strings",
-            FlagType::File => " file",
-            FlagType::Directory => " dir",
-            FlagType::Choice(_) | FlagType::Range(_, _) => unreachable!(),
-        };
-
         let default = flag
             .default
             .as_ref()
@@ -936,149 +3095,562 @@ impl Command {
                 FlagValue::Int(i) => format!(" (default {i})"),
                 FlagValue::Float(f) => format!(" (default {f})"),
                 FlagValue::StringSlice(v) => format!(" (default {v:?})"),
+                FlagValue::Timestamp(t) => format!(" (default {t})"),
+                FlagValue::Duration(_) | FlagValue::KeyValue(_) => format!(" (default {d})"),
             })
             .unwrap_or_default();
 
-        let flag_name_formatted = format!("{}{flag_type}", flag.name);
-        let left_part = format!(
-            "      {}--{}",
-            color::cyan(&short),
-            color::cyan(&flag_name_formatted)
-        );
+        let left_part = Self::flag_left_column(flag, style);
 
         let description = format!("{}{}{}", flag.usage, color::dim(&default), constraint_info);
         let terminal_width = get_terminal_width();
-        let left_column_width = 30; // Adjust based on typical flag length
 
         let formatted =
             format_help_entry(&left_part, &description, left_column_width, terminal_width);
-        println!("{formatted}");
+        let _ = writeln!(out, "{formatted}");
     }
 
-    /// Finds command suggestions based on similarity
-    fn find_command_suggestions(&self, input: &str) -> Vec<String> {
-        let candidates: Vec<String> = self.subcommands.keys().cloned().collect();
-        find_suggestions(input, &candidates, self.suggestion_distance)
+    /// Returns a flag's name with its value hint, e.g. `port int`, or
+    /// `[no-]color` for a negatable flag
+    pub(crate) fn flag_label(flag: &Flag) -> String {
+        let hint = Self::flag_value_hint(flag);
+        if Self::is_negatable(flag) {
+            format!("[no-]{}", flag.name)
+        } else if hint.is_empty() {
+            flag.name.clone()
+        } else {
+            format!("{} {hint}", flag.name)
+        }
     }
 
-    /// Handles shell completion requests
+    /// Returns the value placeholder shown in help, e.g. `int` or `{a|b}`
     ///
-    /// This method is called when the shell requests completions via the
-    /// environment variable (e.g., `MYAPP_COMPLETE=bash`).
-    pub fn handle_completion_request(&self, args: &[String]) -> Result<Vec<String>> {
-        // Detect shell type from environment variable
-        let shell_type = self.detect_completion_shell();
-
-        // args format: ["__complete", ...previous_args, current_word]
-        if args.is_empty() || args[0] != "__complete" {
-            return Err(Error::Completion("Invalid completion request".to_string()));
+    /// Switches take no value, so theirs is empty.
+    pub(crate) fn flag_value_hint(flag: &Flag) -> String {
+        match &flag.value_type {
+            FlagType::Choice(choices) => format!("{{{}}}", choices.join("|")),
+            FlagType::Range(min, max) => format!("int[{min}-{max}]"),
+            FlagType::String => "string".to_string(),
+            FlagType::Int => "int".to_string(),
+            FlagType::Float => "float".to_string(),
+            FlagType::Bool | FlagType::Count => String::new(),
+            FlagType::StringSlice | FlagType::StringArray => "strings".to_string(),
+            FlagType::KeyValue => "key=value".to_string(),
+            FlagType::File => "file".to_string(),
+            FlagType::Directory => "dir".to_string(),
+            FlagType::Timestamp => "time".to_string(),
+            FlagType::Duration => "duration".to_string(),
+            FlagType::Bytes => "bytes".to_string(),
+            FlagType::IpAddr => "ip".to_string(),
+            FlagType::Cidr => "cidr".to_string(),
+            FlagType::Email => "email".to_string(),
         }
+    }
 
-        let args = &args[1..];
-        if args.is_empty() {
-            // Complete root level
-            return Ok(self.get_completion_suggestions("", None, shell_type.as_deref()));
+    /// Asks the user to confirm a command set up with `require_confirmation`
+    ///
+    /// Typing `yes`, or the first argument (usually the resource being
+    /// deleted), proceeds; `--yes` skips the prompt.
+    fn check_confirmation(&self, ctx: &mut Context) -> Result<()> {
+        let Some(prompt) = &self.confirmation else {
+            return Ok(());
+        };
+        if ctx.flag_bool("yes") == Some(true) {
+            return Ok(());
         }
 
-        let current_word = args.last().unwrap_or(&String::new()).clone();
-        let previous_args = &args[..args.len().saturating_sub(1)];
+        let resource = ctx.args().first().cloned();
+        let question = resource.as_ref().map_or_else(
+            || format!("{prompt} Type 'yes' to continue:"),
+            |resource| format!("{prompt} Type 'yes' or '{resource}' to continue:"),
+        );
+        match ctx.prompt(&question) {
+            Some(answer) if answer == "yes" || Some(&answer) == resource.as_ref() => Ok(()),
+            Some(_) => Err(Error::Validation(format!(
+                "'{}' was not confirmed; nothing was done",
+                self.name
+            ))),
+            None => Err(Error::Validation(format!(
+                "'{}' needs confirmation; pass --yes to run it non-interactively",
+                self.name
+            ))),
+        }
+    }
 
-        // Parse through the command hierarchy
-        let mut current_cmd = self;
-        let mut ctx = Context::new(vec![]);
-        let mut i = 0;
+    /// Runs the run function, cancelling it once the deadline passes
+    ///
+    /// The function runs on a worker thread. At the deadline the context's
+    /// cancellation token is cancelled and the worker is waited for, since
+    /// it borrows the context. A function that saw the cancellation fails
+    /// with a timed out error saying how long it really ran; one that never
+    /// checked keeps its own result.
+    fn run_with_timeout(&self, ctx: &mut Context, run: &RunFunc) -> Result<()> {
+        let Some(timeout) = self.execution_timeout else {
+            return run(ctx);
+        };
+        let started = std::time::Instant::now();
+        let token = ctx.cancellation().clone();
+        let (done, finished) = mpsc::channel();
+        thread::scope(|scope| {
+            let worker = scope.spawn(move || {
+                let result = run(ctx);
+                let _ = done.send(());
+                result
+            });
+            let timed_out = matches!(
+                finished.recv_timeout(timeout),
+                Err(mpsc::RecvTimeoutError::Timeout)
+            );
+            if timed_out {
+                token.cancel();
+            }
+            let result = worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            if timed_out && token.was_observed() {
+                Err(Error::custom(format!(
+                    "timed out after {timeout:?}, stopped after {:?}",
+                    started.elapsed()
+                )))
+            } else {
+                result
+            }
+        })
+    }
 
-        while i < previous_args.len() {
-            let arg = &previous_args[i];
+    /// Rejects arguments past the most this command accepts
+    ///
+    /// The limit comes from the argument validator when it has one, and
+    /// otherwise from the named positionals; validators without an upper
+    /// bound, like `MinimumArgs`, leave the arguments alone.
+    fn check_extra_args(&self, args: &[String]) -> Result<()> {
+        let limit = match &self.arg_validator {
+            Some(
+                ArgValidator::ExactArgs(n)
+                | ArgValidator::MaximumArgs(n)
+                | ArgValidator::RangeArgs(_, n),
+            ) => *n,
+            Some(_) => return Ok(()),
+            None => self.positionals.len(),
+        };
+        if args.len() <= limit {
+            return Ok(());
+        }
+        Err(Error::ArgumentValidation {
+            message: format!("unexpected argument(s): {}", args[limit..].join(", ")),
+            expected: format!("at most {limit}"),
+            received: args.len(),
+        })
+    }
 
-            if arg.starts_with("--") {
-                // Long flag
-                let flag_name = arg.trim_start_matches("--");
-                if let Some((name, _)) = flag_name.split_once('=') {
-                    // Flag with value
-                    ctx.set_flag(name.to_string(), String::new());
-                } else if let Some(_flag) = current_cmd.find_flag(flag_name) {
-                    // Flag that might need a value
-                    if i + 1 < previous_args.len() && !previous_args[i + 1].starts_with('-') {
-                        ctx.set_flag(flag_name.to_string(), previous_args[i + 1].clone());
-                        i += 1;
-                    }
-                }
-            } else if arg.starts_with('-') && arg.len() > 1 {
-                // Short flags
-                let chars = arg.chars().skip(1).collect::<Vec<_>>();
-                for ch in chars {
-                    if let Some(flag) = current_cmd.find_flag_by_short(ch) {
-                        ctx.set_flag(flag.name.clone(), String::new());
-                    }
-                }
-            } else {
-                // Potential subcommand
-                if let Some(subcmd) = current_cmd.find_subcommand(arg) {
-                    current_cmd = subcmd;
-                } else {
-                    ctx.args_mut().push(arg.clone());
-                }
+    /// Finds the subcommand a typed word names
+    ///
+    /// Exact names and aliases always match. With
+    /// [`CommandBuilder::match_case_insensitive`] set on this command or a
+    /// parent, so do names differing only in case, and with
+    /// [`CommandBuilder::match_prefix`] the start of a single visible
+    /// subcommand's name or alias.
+    fn resolve_subcommand(&self, ancestors: &[&Self], name: &str) -> Option<&Self> {
+        if let Some(subcommand) = self.find_subcommand(name) {
+            return Some(subcommand);
+        }
+        if self.inherits(ancestors, |cmd| cmd.case_insensitive_matching) {
+            if let Some(subcommand) = self.find_subcommand_ignoring_case(name) {
+                return Some(subcommand);
             }
-            i += 1;
         }
+        match self.prefix_candidates(ancestors, name).as_slice() {
+            [only] => Some(only),
+            _ => None,
+        }
+    }
 
-        // Now determine what to complete
-        if current_word.starts_with("--") {
-            // Complete long flags only (when user explicitly started typing --)
-            let prefix = current_word.trim_start_matches("--");
+    /// Returns whether `setting` is on for this command or a parent
+    fn inherits(&self, ancestors: &[&Self], setting: impl Fn(&Self) -> bool) -> bool {
+        setting(self) || ancestors.iter().any(|cmd| setting(cmd))
+    }
+
+    /// Returns the visible subcommands whose name or alias starts with
+    /// `name`, sorted by name, when prefix matching applies
+    fn prefix_candidates<'s>(&'s self, ancestors: &[&Self], name: &str) -> Vec<&'s Self> {
+        if name.is_empty() || !self.inherits(ancestors, |cmd| cmd.prefix_matching) {
+            return Vec::new();
+        }
+        let fold = self.inherits(ancestors, |cmd| cmd.case_insensitive_matching);
+        let normalize = |text: &str| {
+            if fold {
+                text.to_lowercase()
+            } else {
+                text.to_string()
+            }
+        };
+        let name = normalize(name);
+        let mut matches: Vec<&Self> = self
+            .subcommands
+            .values()
+            .filter(|cmd| {
+                !cmd.hidden
+                    && std::iter::once(&cmd.name)
+                        .chain(&cmd.aliases)
+                        .any(|candidate| normalize(candidate).starts_with(&name))
+            })
+            .collect();
+        matches.sort_by(|a, b| a.name.cmp(&b.name));
+        matches
+    }
+
+    /// Builds the subcommand added by [`CommandBuilder::with_help_command`]
+    fn help_command() -> Self {
+        let mut help = CommandBuilder::new("help")
+            .short("Help about any command")
+            .long(
+                "Help provides help for any command in the application.\n\
+                 Type help followed by the command name for full details.",
+            )
+            .run(|ctx| {
+                let root = ctx
+                    .root()
+                    .ok_or_else(|| Error::custom("help can only run from execute"))?;
+                let (topic, ancestors) = Self::help_topic(root, ctx.command_path(), ctx.args())?;
+                topic.emit_help(&topic.render_help(&ancestors, &HashMap::new()));
+                Ok(())
+            })
+            .build();
+        help.shows_help_topics = true;
+        help
+    }
+
+    /// Finds the command `help` was asked about, and the commands above it
+    ///
+    /// `path` is the running command path from `root`, ending in the help
+    /// command itself, and `topic` the words after it, looked up like on
+    /// the command line starting from the help command's parent.
+    fn help_topic<'a>(
+        root: &'a Self,
+        path: &[String],
+        topic: &[String],
+    ) -> Result<(&'a Self, Vec<&'a Self>)> {
+        let parents = path
+            .get(1..path.len().saturating_sub(1))
+            .unwrap_or_default();
+        let mut ancestors = Vec::new();
+        let mut target = root;
+        for name in parents.iter().chain(topic) {
+            let Some(subcommand) = target.resolve_subcommand(&ancestors, name) else {
+                let suggestions = if target.suggestions_enabled {
+                    target.find_command_suggestions(name)
+                } else {
+                    Vec::new()
+                };
+                return Err(Error::CommandNotFound {
+                    command: name.clone(),
+                    suggestions,
+                });
+            };
+            ancestors.push(target);
+            target = subcommand;
+        }
+        Ok((target, ancestors))
+    }
+
+    /// Completes the words after `help`: this command's visible subcommands
+    fn help_topic_completions(&self, prefix: &str) -> CompletionResult {
+        let mut subs: Vec<&Self> = self
+            .subcommands
+            .values()
+            .filter(|cmd| !cmd.hidden && !cmd.shows_help_topics && cmd.name.starts_with(prefix))
+            .collect();
+        subs.sort_by(|a, b| a.name.cmp(&b.name));
+        subs.into_iter()
+            .fold(CompletionResult::new(), |result, cmd| {
+                result.add_with_description(cmd.name.clone(), cmd.short.clone())
+            })
+    }
+
+    /// Offers to run the single subcommand close to a mistyped one
+    ///
+    /// Only applies with [`CommandBuilder::autocorrect`] set, and only when
+    /// the user confirms interactively.
+    fn autocorrect_target(&self, ctx: &mut Context) -> Option<&Self> {
+        let threshold = self.autocorrect?;
+        let typed = ctx.args().first()?;
+        let candidates = self.visible_subcommand_names();
+        let suggestions = find_suggestions(typed, &candidates, threshold);
+        let [only] = suggestions.as_slice() else {
+            return None;
+        };
+        let subcommand = self.subcommands.get(only.as_str())?;
+        ctx.confirm(&format!("Did you mean '{only}'?"), true)?
+            .then_some(subcommand)
+    }
+
+    /// Finds command suggestions based on similarity
+    ///
+    /// With [`CommandBuilder::nested_suggestions`] the deeper commands are
+    /// searched too, after the direct subcommands, and suggested by their
+    /// path from here, like `get pods`.
+    fn find_command_suggestions(&self, input: &str) -> Vec<String> {
+        let candidates = self.visible_subcommand_names();
+        let mut suggestions = self.suggest(input, &candidates);
+        if !self.nested_suggestions {
+            return suggestions;
+        }
+
+        let mut nested = Vec::new();
+        for cmd in self.subcommands.values().filter(|cmd| !cmd.hidden) {
+            cmd.collect_nested_paths(&cmd.name, &mut nested);
+        }
+        let mut names: Vec<String> = nested.iter().map(|(_, name)| name.clone()).collect();
+        names.sort();
+        names.dedup();
+        for name in self.suggest(input, &names) {
+            let mut paths: Vec<&String> = nested
+                .iter()
+                .filter(|(_, nested_name)| *nested_name == name)
+                .map(|(path, _)| path)
+                .collect();
+            paths.sort();
+            suggestions.extend(paths.into_iter().cloned());
+        }
+        suggestions
+    }
+
+    /// Collects `(path, name)` for every visible command below this one,
+    /// where `path` starts with `prefix`, this command's own path
+    fn collect_nested_paths(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        for cmd in self.subcommands.values().filter(|cmd| !cmd.hidden) {
+            let path = format!("{prefix} {}", cmd.name);
+            cmd.collect_nested_paths(&path, out);
+            out.push((path, cmd.name.clone()));
+        }
+    }
+
+    /// Finds the candidates close to `input` with this command's
+    /// suggestion algorithm and threshold
+    fn suggest(&self, input: &str, candidates: &[String]) -> Vec<String> {
+        match self.suggestion_algorithm {
+            SuggestionAlgorithm::Levenshtein => {
+                find_suggestions(input, candidates, self.suggestion_distance)
+            }
+            SuggestionAlgorithm::JaroWinkler => {
+                find_similar_suggestions(input, candidates, self.suggestion_similarity)
+            }
+        }
+    }
+
+    /// Names of the subcommands that aren't hidden, which are the only ones
+    /// offered as suggestions
+    fn visible_subcommand_names(&self) -> Vec<String> {
+        self.subcommands
+            .values()
+            .filter(|cmd| !cmd.hidden)
+            .map(|cmd| cmd.name.clone())
+            .collect()
+    }
+
+    /// Handles shell completion requests
+    ///
+    /// This method is called when the shell requests completions via the
+    /// environment variable (e.g., `MYAPP_COMPLETE=bash`).
+    pub fn handle_completion_request(&self, args: &[String]) -> Result<Vec<String>> {
+        // Detect shell type from environment variable
+        let shell_type = self.detect_completion_shell();
+
+        // args format: ["__complete", ...previous_args, current_word]
+        if args.is_empty() || args[0] != "__complete" {
+            return Err(Error::Completion("Invalid completion request".to_string()));
+        }
+
+        let args = &args[1..];
+        if args.is_empty() {
+            // Complete root level
+            return Ok(self.get_completion_suggestions(&[], "", None, shell_type.as_deref()));
+        }
+
+        let current_word = args.last().unwrap_or(&String::new()).clone();
+        let previous_args = &args[..args.len().saturating_sub(1)];
+
+        // Parse through the command hierarchy
+        let mut current_cmd = self;
+        let mut ancestors: Vec<&Self> = Vec::new();
+        let mut ctx = Context::new(vec![]);
+        let mut after_terminator = false;
+        let mut help_topics = false;
+        let mut i = 0;
+
+        while i < previous_args.len() {
+            let arg = &previous_args[i];
+
+            if after_terminator {
+                // Everything after `--` is a positional argument
+                ctx.args_mut().push(arg.clone());
+            } else if arg == "--" {
+                after_terminator = true;
+            } else if arg.starts_with("--") {
+                // Long flag
+                let flag_name = arg.trim_start_matches("--");
+                if let Some((name, value)) = flag_name.split_once('=') {
+                    // Flag with value
+                    ctx.set_flag(name.to_string(), value.to_string());
+                } else if let Some(flag) = current_cmd.find_flag(&ancestors, flag_name) {
+                    // Flag that might need a value
+                    if i + 1 < previous_args.len()
+                        && current_cmd.completion_takes_value(flag, &previous_args[i + 1])
+                    {
+                        ctx.set_flag(flag.name.clone(), previous_args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        let value = bare_flag_value(flag, ctx.flag(&flag.name));
+                        ctx.set_flag(flag.name.clone(), value);
+                    }
+                }
+            } else if is_flag_token(arg) {
+                // Short flags; only the last one in a group can take a value,
+                // either attached with `=` or as the next word
+                let (group, attached) = arg[1..]
+                    .split_once('=')
+                    .map_or((&arg[1..], None), |(group, value)| (group, Some(value)));
+                let chars = group.chars().collect::<Vec<_>>();
+                for (idx, ch) in chars.iter().enumerate() {
+                    if let Some(flag) = current_cmd.find_flag_by_short(&ancestors, *ch) {
+                        if let (true, Some(value)) = (idx == chars.len() - 1, attached) {
+                            ctx.set_flag(flag.name.clone(), value.to_string());
+                        } else if idx == chars.len() - 1
+                            && i + 1 < previous_args.len()
+                            && current_cmd.completion_takes_value(flag, &previous_args[i + 1])
+                        {
+                            ctx.set_flag(flag.name.clone(), previous_args[i + 1].clone());
+                            i += 1;
+                        } else {
+                            let value = bare_flag_value(flag, ctx.flag(&flag.name));
+                            ctx.set_flag(flag.name.clone(), value);
+                        }
+                    }
+                }
+            } else {
+                // Potential subcommand
+                if let Some(subcmd) = current_cmd.resolve_subcommand(&ancestors, arg) {
+                    if subcmd.shows_help_topics && !help_topics {
+                        // The words after `help` name a command from here
+                        help_topics = true;
+                    } else {
+                        ancestors.push(current_cmd);
+                        current_cmd = subcmd;
+                    }
+                } else {
+                    ctx.args_mut().push(arg.clone());
+                }
+            }
+            i += 1;
+        }
+
+        // Now determine what to complete
+        if help_topics && !current_word.starts_with('-') {
+            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
+            let result = current_cmd.help_topic_completions(&current_word);
+            return Ok(format.format(&result, Some(&ctx)));
+        }
+
+        if after_terminator {
+            // Past `--` only positional arguments remain
+            let result = match current_cmd.arg_completer(&ancestors) {
+                Some(completion_func) => current_cmd.run_completion(
+                    &ancestors,
+                    completion_func,
+                    "",
+                    &ctx,
+                    &current_word,
+                )?,
+                None => CompletionResult::new(),
+            };
+            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
+            return Ok(format.format(&result, Some(&ctx)));
+        }
+
+        if let Some((flag_name, value_prefix)) = current_word
+            .strip_prefix("--")
+            .and_then(|word| word.split_once('='))
+        {
+            // Complete the value of --flag=prefix. Results keep the --flag= prefix
+            // so every shell sees a candidate for the whole word; the generated
+            // scripts strip it again where the shell splits on '='.
+            let mut result = current_cmd
+                .complete_flag_value(&ancestors, flag_name, &ctx, value_prefix)?
+                .unwrap_or_default();
+            result.values = result
+                .values
+                .into_iter()
+                .map(|value| format!("--{flag_name}={value}"))
+                .collect();
+            result.default = result.default.map(|value| format!("--{flag_name}={value}"));
+            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
+            Ok(format.format(&result, Some(&ctx)))
+        } else if current_word.starts_with("--") {
+            // Complete long flags only (when user explicitly started typing --)
+            let prefix = current_word.trim_start_matches("--");
             let mut flag_completions = CompletionResult::new();
 
             // Collect flags with descriptions from current command and parents
-            collect_all_flags_with_descriptions(current_cmd, &mut flag_completions, prefix);
+            collect_all_flags_with_descriptions(
+                current_cmd,
+                &ancestors,
+                &mut flag_completions,
+                prefix,
+                Some(&ctx),
+            );
 
             let format = CompletionFormat::from_shell_type(shell_type.as_deref());
             Ok(format.format(&flag_completions, Some(&ctx)))
-        } else if current_word.starts_with('-') && current_word.len() > 1 {
-            // For short flags, we don't complete (too complex)
+        } else if current_word == "-" {
+            // A bare dash lists the short flags, described by their long names
+            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
+            let result = current_cmd.short_flag_completions(&ancestors);
+            Ok(format.format(&result, Some(&ctx)))
+        } else if is_flag_token(&current_word) {
+            // Short flag groups are complete words already
             Ok(vec![])
         } else {
-            // Check if previous arg was a flag that needs a value
+            // Check if previous arg was a flag awaiting its value
+            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
+            let mut value_suggestions = Vec::new();
             if let Some(prev) = previous_args.last() {
-                if prev.starts_with("--") {
-                    let flag_name = prev.trim_start_matches("--");
-
-                    // First check if the flag itself has a completion function
-                    if let Some(flag) = current_cmd.flags.get(flag_name) {
-                        if let Some(ref completion_func) = flag.completion {
-                            let result = completion_func(&ctx, &current_word)?;
-                            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
-                            return Ok(format.format(&result, Some(&ctx)));
-                        }
-                    }
+                let pending = prev.strip_prefix("--").map_or_else(
+                    || current_cmd.pending_short_flag(&ancestors, prev),
+                    |flag_name| current_cmd.find_flag(&ancestors, flag_name),
+                );
 
-                    // Fall back to flag_completions HashMap
-                    if let Some(completion_func) = current_cmd.flag_completions.get(flag_name) {
-                        let result = completion_func(&ctx, &current_word)?;
-                        let format = CompletionFormat::from_shell_type(shell_type.as_deref());
-                        return Ok(format.format(&result, Some(&ctx)));
-                    }
-                } else if prev.starts_with('-') && prev.len() == 2 {
-                    // Handle short flag completions
-                    let Some(short_flag) = prev.chars().nth(1) else {
-                        // This should not happen given the length check, but handle gracefully
-                        return Ok(vec![]);
-                    };
-                    if let Some(flag) = current_cmd.find_flag_by_short(short_flag) {
-                        if let Some(ref completion_func) = flag.completion {
-                            let result = completion_func(&ctx, &current_word)?;
-                            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
-                            return Ok(format.format(&result, Some(&ctx)));
+                if let Some(flag) = pending {
+                    // A word after a plain bool flag is almost always an
+                    // argument, so `true`/`false` are only offered after `=`
+                    let plain_bool = flag.value_type == FlagType::Bool
+                        && flag.bool_words.is_none()
+                        && flag.completion.is_none()
+                        && current_cmd.flag_completer(&ancestors, &flag.name).is_none();
+                    if !plain_bool {
+                        let values = current_cmd.complete_flag_value(
+                            &ancestors,
+                            &flag.name,
+                            &ctx,
+                            &current_word,
+                        )?;
+                        if flag.requires_value() {
+                            // The next word can only be the value, never a subcommand
+                            return Ok(format.format(&values.unwrap_or_default(), Some(&ctx)));
                         }
-
-                        // Also check flag_completions HashMap by flag name
-                        if let Some(completion_func) = current_cmd.flag_completions.get(&flag.name)
-                        {
-                            let result = completion_func(&ctx, &current_word)?;
-                            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
-                            return Ok(format.format(&result, Some(&ctx)));
+                        if let Some(values) = values {
+                            value_suggestions = format.format(&values, Some(&ctx));
                         }
                     }
+                } else if let Some(flag_name) = prev.strip_prefix("--") {
+                    // Completions registered for flags this command doesn't declare
+                    if let Some(result) = current_cmd.complete_flag_value(
+                        &ancestors,
+                        flag_name,
+                        &ctx,
+                        &current_word,
+                    )? {
+                        return Ok(format.format(&result, Some(&ctx)));
+                    }
                 }
             }
 
@@ -1087,6 +3659,7 @@ impl Command {
 
             // Get subcommand/argument completions
             let subcommand_suggestions = current_cmd.get_completion_suggestions(
+                &ancestors,
                 &current_word,
                 Some(&ctx),
                 shell_type.as_deref(),
@@ -1094,18 +3667,27 @@ impl Command {
 
             // Add flags that don't start with current_word (so user can discover them)
             // Only add flags if current_word is empty or doesn't look like it's trying to complete a specific subcommand
-            if current_word.is_empty()
-                || !current_cmd
-                    .subcommands
-                    .keys()
-                    .any(|name| name.starts_with(&current_word))
+            // (leaf commands without an arg completer already include their flags)
+            if current_cmd.has_positional_completions()
+                && (current_word.is_empty()
+                    || !current_cmd
+                        .subcommands
+                        .values()
+                        .any(|cmd| !cmd.hidden && cmd.name.starts_with(&current_word)))
             {
-                collect_all_flags_with_descriptions(current_cmd, &mut combined_completions, "");
+                collect_all_flags_with_descriptions(
+                    current_cmd,
+                    &ancestors,
+                    &mut combined_completions,
+                    "",
+                    Some(&ctx),
+                );
             }
 
-            // Convert subcommand suggestions to CompletionResult format and combine
-            let format = CompletionFormat::from_shell_type(shell_type.as_deref());
-            let mut final_suggestions = subcommand_suggestions;
+            // Convert subcommand suggestions to CompletionResult format and combine;
+            // values for an optional-value flag come first
+            let mut final_suggestions = value_suggestions;
+            final_suggestions.extend(subcommand_suggestions);
             let flag_suggestions = format.format(&combined_completions, Some(&ctx));
             final_suggestions.extend(flag_suggestions);
 
@@ -1113,17 +3695,210 @@ impl Command {
         }
     }
 
+    /// Runs the value completion registered for a flag, if any
+    ///
+    /// The flag's own completion function takes precedence over one
+    /// registered through `flag_completion`. When neither is set,
+    /// `--profile` offers the profiles in scope, described by the flags
+    /// they set, and other flags the values their type declares, such as
+    /// their choices.
+    fn complete_flag_value(
+        &self,
+        ancestors: &[&Self],
+        flag_name: &str,
+        ctx: &Context,
+        prefix: &str,
+    ) -> Result<Option<CompletionResult>> {
+        let flag = self.find_flag(ancestors, flag_name);
+        if let Some(completion_func) = self.own_flag_completer(ancestors, flag_name) {
+            return self
+                .run_completion(ancestors, completion_func, flag_name, ctx, prefix)
+                .map(Some);
+        }
+
+        if let Some(completion_func) = self.flag_completer(ancestors, flag_name) {
+            return self
+                .run_completion(ancestors, completion_func, flag_name, ctx, prefix)
+                .map(Some);
+        }
+
+        if flag_name == "profile" {
+            if let Some(result) = self.complete_profiles(ancestors, prefix) {
+                return Ok(Some(result));
+            }
+        }
+
+        Ok(flag.and_then(|flag| flag.complete_declared_values(prefix)))
+    }
+
+    /// Offers the names of the nearest profiles, each described by its
+    /// settings like `workers=16, cache=true`
+    fn complete_profiles(&self, ancestors: &[&Self], prefix: &str) -> Option<CompletionResult> {
+        let profiles = &std::iter::once(self)
+            .chain(ancestors.iter().rev().copied())
+            .find(|cmd| !cmd.profiles.is_empty())?
+            .profiles;
+        let mut names: Vec<&String> = profiles
+            .keys()
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        Some(
+            names
+                .into_iter()
+                .fold(CompletionResult::new(), |result, name| {
+                    let settings: Vec<String> = profiles[name]
+                        .iter()
+                        .map(|(flag, value)| format!("{flag}={value}"))
+                        .collect();
+                    result.add_with_description(name.clone(), settings.join(", "))
+                }),
+        )
+    }
+
+    /// Runs a completion function, through the completion cache in scope
+    ///
+    /// Results are cached by command path, the flag being completed (empty
+    /// for arguments), the prefix and the flags given so far. A miss runs
+    /// the function, on a worker thread when a
+    /// [`completion_timeout`](CommandBuilder::completion_timeout) is in
+    /// scope; results that timed out aren't cached.
+    fn run_completion(
+        &self,
+        ancestors: &[&Self],
+        completion_func: &SharedCompletionFunc,
+        flag_name: &str,
+        ctx: &Context,
+        prefix: &str,
+    ) -> Result<CompletionResult> {
+        let scope = || std::iter::once(self).chain(ancestors.iter().rev().copied());
+        let complete = || match scope().find_map(|cmd| cmd.completion_timeout) {
+            Some(timeout) => {
+                let completion_func = Arc::clone(completion_func);
+                with_timeout(
+                    move |ctx, prefix| completion_func(ctx, prefix),
+                    timeout,
+                    ctx,
+                    prefix,
+                )
+            }
+            None => completion_func(ctx, prefix),
+        };
+        let Some(cache) = scope().find_map(|cmd| cmd.completion_cache.as_ref()) else {
+            return complete();
+        };
+
+        let path: Vec<&str> = ancestors
+            .iter()
+            .map(|cmd| cmd.name.as_str())
+            .chain(std::iter::once(self.name.as_str()))
+            .collect();
+        let flag = (!flag_name.is_empty()).then_some(flag_name);
+        let key = CompletionCache::completion_key(&path, flag, prefix, ctx.flags());
+        if let Some(result) = cache.get(&key) {
+            return Ok(result);
+        }
+
+        let result = complete()?;
+        if !result
+            .active_help
+            .iter()
+            .any(|help| help.message == TIMED_OUT_HELP)
+        {
+            cache.put(key, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// Returns the flag a short flag group like `-vn` leaves waiting for a
+    /// value, which is its last flag
+    ///
+    /// Groups with a value attached by `=`, or with a letter that isn't a
+    /// flag in scope, leave nothing pending, the same as when parsing.
+    fn pending_short_flag<'s>(&'s self, ancestors: &[&'s Self], word: &str) -> Option<&'s Flag> {
+        if !is_flag_token(word) || word.contains('=') {
+            return None;
+        }
+        let mut pending = None;
+        for ch in word.chars().skip(1) {
+            pending = Some(self.find_flag_by_short(ancestors, ch)?);
+        }
+        pending
+    }
+
+    /// Returns whether completion should treat `next` as the value of `flag`
+    ///
+    /// Same rules as parsing, except that a lone `-` can be a value.
+    fn completion_takes_value(&self, flag: &Flag, next: &str) -> bool {
+        !is_flag_token(next) && self.takes_next_as_value(flag, next)
+    }
+
+    /// Lists the short flags in scope as `-x` entries, nearest definition first
+    fn short_flag_completions(&self, ancestors: &[&Self]) -> CompletionResult {
+        let mut flags: Vec<(char, &Flag)> = Vec::new();
+        for cmd in std::iter::once(self).chain(ancestors.iter().rev().copied()) {
+            for flag in cmd.flags.values().filter(|flag| !flag.hidden) {
+                if let Some(short) = flag.short {
+                    if !flags.iter().any(|(c, _)| *c == short) {
+                        flags.push((short, flag));
+                    }
+                }
+            }
+        }
+        flags.sort_by_key(|(short, _)| *short);
+
+        let mut result = CompletionResult::new();
+        for (short, flag) in flags {
+            let description = if flag.usage.is_empty() {
+                format!("--{}", flag.name)
+            } else {
+                format!("--{}: {}", flag.name, flag.usage)
+            };
+            result = result.add_with_description(format!("-{short}"), description);
+        }
+        result
+    }
+
+    /// Returns whether shells may complete filenames when nothing matches
+    pub(crate) const fn file_completion_fallback(&self) -> bool {
+        self.file_completion_fallback
+    }
+
+    /// Returns whether this command completes its arguments at runtime
+    pub(crate) const fn has_arg_completions(&self) -> bool {
+        self.arg_completions.is_some() || self.arg_completer_ref.is_some()
+    }
+
+    /// Returns whether the values of `flag` are completed at runtime
+    pub(crate) fn has_dynamic_flag_values(&self, flag: &Flag) -> bool {
+        flag.completion.is_some()
+            || self.flag_completions.contains_key(&flag.name)
+            || self.flag_completer_refs.contains_key(&flag.name)
+    }
+
+    /// Returns whether TAB on a bare word can offer subcommands or arguments
+    fn has_positional_completions(&self) -> bool {
+        !self.subcommands.is_empty()
+            || self.has_arg_completions()
+            || self
+                .positionals
+                .iter()
+                .any(|(_, kind)| *kind != Positional::Any)
+            || matches!(self.arg_validator, Some(ArgValidator::OnlyValidArgs(_)))
+    }
+
     /// Detects the shell type from the environment variable
     fn detect_completion_shell(&self) -> Option<String> {
         use std::env;
 
         // Look for shell-specific completion environment variables
-        let env_var = format!("{}_COMPLETE", self.name.to_uppercase());
+        let env_var = self.completion_env_var();
         env::var(&env_var).ok()
     }
 
     fn get_completion_suggestions(
         &self,
+        ancestors: &[&Self],
         prefix: &str,
         ctx: Option<&Context>,
         shell_type: Option<&str>,
@@ -1132,7 +3907,7 @@ impl Command {
         let mut has_suggestions = false;
 
         // Add subcommands with their descriptions
-        for (name, cmd) in &self.subcommands {
+        for (name, cmd) in self.subcommands.iter().filter(|(_, cmd)| !cmd.hidden) {
             if name.starts_with(prefix) {
                 completion_result =
                     completion_result.add_with_description(name.clone(), cmd.short.clone());
@@ -1148,18 +3923,76 @@ impl Command {
             }
         }
 
+        // A named positional's kind takes precedence for its slot
+        if !has_suggestions {
+            let position = ctx.map_or(0, |ctx| ctx.args().len());
+            let result = match self.positionals.get(position).map(|(_, kind)| kind) {
+                Some(Positional::ValidArgs(valid_args)) => Some(
+                    CompletionResult::new().extend(
+                        valid_args
+                            .iter()
+                            .filter(|value| value.starts_with(prefix))
+                            .cloned(),
+                    ),
+                ),
+                Some(Positional::File) => complete_path(prefix, false, false).ok(),
+                Some(Positional::Directory) => complete_path(prefix, false, true).ok(),
+                Some(Positional::Any) | None => match &self.arg_validator {
+                    // The values the validator accepts, unless the command
+                    // completes its arguments itself
+                    Some(ArgValidator::OnlyValidArgs(valid_args))
+                        if self.arg_completer(ancestors).is_none() =>
+                    {
+                        Some(
+                            CompletionResult::new().extend(
+                                valid_args
+                                    .iter()
+                                    .filter(|value| value.starts_with(prefix))
+                                    .cloned(),
+                            ),
+                        )
+                    }
+                    _ => None,
+                },
+            };
+            if let Some(result) = result {
+                let default_ctx = Context::new(vec![]);
+                let format = CompletionFormat::from_shell_type(shell_type);
+                return format.format(&result, Some(ctx.unwrap_or(&default_ctx)));
+            }
+        }
+
         // If we have arg completions and no subcommands match, try those
         if !has_suggestions {
-            if let Some(ref completion_func) = self.arg_completions {
+            if let Some(completion_func) = self.arg_completer(ancestors) {
                 let default_ctx = Context::new(vec![]);
                 let ctx = ctx.unwrap_or(&default_ctx);
-                if let Ok(result) = completion_func(ctx, prefix) {
+                if let Ok(result) = self.run_completion(ancestors, completion_func, "", ctx, prefix)
+                {
                     let format = CompletionFormat::from_shell_type(shell_type);
                     return format.format(&result, Some(ctx));
                 }
             }
         }
 
+        // A leaf without an arg completer still offers its flags, with a hint
+        // so an otherwise empty TAB isn't mistaken for a broken completion
+        if !self.has_positional_completions() {
+            if prefix.is_empty() {
+                collect_all_flags_with_descriptions(
+                    self,
+                    ancestors,
+                    &mut completion_result,
+                    "",
+                    ctx,
+                );
+            }
+            completion_result = completion_result.add_help_text(format!(
+                "'{}' has no argument completions; use --help for usage",
+                self.name
+            ));
+        }
+
         // Format the results
         let format = CompletionFormat::from_shell_type(shell_type);
         let default_ctx = Context::new(vec![]);
@@ -1301,6 +4134,39 @@ impl CommandBuilder {
     #[must_use]
     pub fn example(mut self, example: impl Into<String>) -> Self {
         self.command.examples.push(example.into());
+        self.command.example_descriptions.push(None);
+        self
+    }
+
+    /// Adds an example with a line saying what it does
+    ///
+    /// Help shows the description as a comment above the example, like
+    ///
+    /// ```text
+    ///   # Deploy to production
+    ///   myapp deploy --env prod
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("deploy")
+    ///     .example_with_description("myapp deploy --env prod", "Deploy to production")
+    ///     .example("myapp deploy --env staging")
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn example_with_description(
+        mut self,
+        example: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.command.examples.push(example.into());
+        self.command
+            .example_descriptions
+            .push(Some(description.into()));
         self
     }
 
@@ -1413,7 +4279,7 @@ impl CommandBuilder {
     /// ```
     #[must_use]
     pub fn flag(mut self, flag: Flag) -> Self {
-        self.command.flags.insert(flag.name.clone(), flag);
+        self.command.insert_flag(flag);
         self
     }
 
@@ -1435,58 +4301,235 @@ impl CommandBuilder {
     #[must_use]
     pub fn flags(mut self, flags: Vec<Flag>) -> Self {
         for flag in flags {
-            self.command.flags.insert(flag.name.clone(), flag);
+            self.command.insert_flag(flag);
         }
         self
     }
 
-    /// Sets the function to run when this command is executed
+    /// Adds a named preset of flag values, chosen with `--profile`
     ///
-    /// The run function receives a mutable reference to the [`Context`]
-    /// which provides access to parsed flags and arguments.
+    /// The first profile adds a `--profile` choice flag to this command,
+    /// inherited by its subcommands, and each later one extends its
+    /// choices. Giving `--profile <name>` sets the profile's flags as if
+    /// they had been passed; flags given explicitly still win, and
+    /// profile values are checked like any other flag value. Completing
+    /// `--profile` offers the names, described by the flags they set.
+    ///
+    /// A `profile` flag of the command's own is kept rather than replaced,
+    /// and [`Command::validate_tree`] reports it, along with profiles
+    /// setting flags that don't exist.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use flag_rs::CommandBuilder;
+    /// use flag_rs::{CommandBuilder, Flag};
     ///
-    /// let cmd = CommandBuilder::new("greet")
+    /// let cmd = CommandBuilder::new("build")
+    ///     .flag(Flag::int("workers").default_int(2))
+    ///     .flag(Flag::bool("cache"))
+    ///     .profile("fast", &[("workers", "16"), ("cache", "true")])
     ///     .run(|ctx| {
-    ///         let name = ctx.args().first()
-    ///             .map(|s| s.as_str())
-    ///             .unwrap_or("World");
-    ///         println!("Hello, {}!", name);
+    ///         assert_eq!(ctx.flag_int("workers"), Some(16));
+    ///         assert_eq!(ctx.flag_bool("cache"), Some(true));
     ///         Ok(())
     ///     })
     ///     .build();
+    ///
+    /// cmd.execute(vec!["--profile".to_string(), "fast".to_string()]).unwrap();
     /// ```
     #[must_use]
-    pub fn run<F>(mut self, f: F) -> Self
-    where
-        F: Fn(&mut Context) -> Result<()> + Send + Sync + 'static,
-    {
-        self.command.run = Some(Box::new(f));
+    pub fn profile(mut self, name: impl Into<String>, settings: &[(&str, &str)]) -> Self {
+        let generated = self
+            .command
+            .flags
+            .get("profile")
+            .map_or(true, |flag| self.command.is_profile_flag(flag));
+        self.command.profiles.insert(
+            name.into(),
+            settings
+                .iter()
+                .map(|(flag, value)| ((*flag).to_string(), (*value).to_string()))
+                .collect(),
+        );
+
+        if generated {
+            let names = self.command.profile_names();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            let flag = Flag::choice("profile", &names).usage("Preset of flag values to use");
+            self.command.flags.insert(flag.name.clone(), flag);
+        }
         self
     }
 
-    /// Sets the argument validator for this command
+    /// Declares flags of which at most one may be given
     ///
-    /// The validator will be called before the run function to ensure
-    /// arguments meet the specified constraints.
+    /// Giving two or more of them fails with an error naming the whole
+    /// group, which is easier to keep right than pairwise
+    /// [`FlagConstraint::ConflictsWith`]. Members may be defined on this
+    /// command or its parents, and the group applies to subcommands too.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use flag_rs::{CommandBuilder, ArgValidator};
+    /// use flag_rs::{CommandBuilder, Flag};
     ///
-    /// let cmd = CommandBuilder::new("delete")
-    ///     .args(ArgValidator::MinimumArgs(1))
-    ///     .run(|ctx| {
-    ///         for file in ctx.args() {
-    ///             println!("Deleting: {}", file);
-    ///         }
-    ///         Ok(())
-    ///     })
+    /// let cmd = CommandBuilder::new("export")
+    ///     .flag(Flag::bool("json"))
+    ///     .flag(Flag::bool("yaml"))
+    ///     .flag(Flag::bool("xml"))
+    ///     .flag_group_exclusive(vec!["json", "yaml", "xml"])
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// assert!(cmd.execute(vec!["--json".to_string()]).is_ok());
+    /// assert!(cmd.execute(vec!["--json".to_string(), "--xml".to_string()]).is_err());
+    /// ```
+    ///
+    /// [`FlagConstraint::ConflictsWith`]: crate::FlagConstraint::ConflictsWith
+    #[must_use]
+    pub fn flag_group_exclusive<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.command
+            .exclusive_flag_groups
+            .push(flags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Declares flags of which at least one must be given
+    ///
+    /// Combine with [`flag_group_exclusive`](Self::flag_group_exclusive) on
+    /// the same flags to require exactly one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("login")
+    ///     .flag(Flag::string("token"))
+    ///     .flag(Flag::string("password"))
+    ///     .flag_group_required_one(vec!["token", "password"])
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// assert!(cmd.execute(vec![]).is_err());
+    /// assert!(cmd.execute(vec!["--token".to_string(), "abc".to_string()]).is_ok());
+    /// ```
+    #[must_use]
+    pub fn flag_group_required_one<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.command
+            .required_one_flag_groups
+            .push(flags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Declares flags that must be given together or not at all
+    ///
+    /// Giving some of them but not all fails with an error naming the
+    /// missing ones. For one flag that needs any of several others, see
+    /// [`Flag::requires_one_of`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("login")
+    ///     .flag(Flag::string("user"))
+    ///     .flag(Flag::string("password"))
+    ///     .flag_group_together(vec!["user", "password"])
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// assert!(cmd.execute(vec![]).is_ok());
+    /// assert!(cmd.execute(vec!["--user".to_string(), "me".to_string()]).is_err());
+    /// ```
+    #[must_use]
+    pub fn flag_group_together<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.command
+            .together_flag_groups
+            .push(flags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Changes the default of an inherited flag for this command's scope
+    ///
+    /// The flag is still defined once on an ancestor; this command and its
+    /// subcommands see `value` as its default instead of the declared one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, FlagValue};
+    ///
+    /// let cmd = CommandBuilder::new("slow-op")
+    ///     .override_flag_default("timeout", FlagValue::Int(300))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn override_flag_default(mut self, name: impl Into<String>, value: FlagValue) -> Self {
+        self.command.default_overrides.insert(name.into(), value);
+        self
+    }
+
+    /// Sets the function to run when this command is executed
+    ///
+    /// The run function receives a mutable reference to the [`Context`]
+    /// which provides access to parsed flags and arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("greet")
+    ///     .run(|ctx| {
+    ///         let name = ctx.args().first()
+    ///             .map(|s| s.as_str())
+    ///             .unwrap_or("World");
+    ///         println!("Hello, {}!", name);
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn run<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Context) -> Result<()> + Send + Sync + 'static,
+    {
+        self.command.run = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the argument validator for this command
+    ///
+    /// The validator will be called before the run function to ensure
+    /// arguments meet the specified constraints.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, ArgValidator};
+    ///
+    /// let cmd = CommandBuilder::new("delete")
+    ///     .args(ArgValidator::MinimumArgs(1))
+    ///     .run(|ctx| {
+    ///         for file in ctx.args() {
+    ///             println!("Deleting: {}", file);
+    ///         }
+    ///         Ok(())
+    ///     })
     ///     .build();
     /// ```
     #[must_use]
@@ -1495,6 +4538,93 @@ impl CommandBuilder {
         self
     }
 
+    /// Names the next positional argument
+    ///
+    /// Named positionals are shown in the usage line, like
+    /// `get <resource> <name>`, instead of the generic `<args>`. When an
+    /// argument validator sets a minimum, positionals past it are shown as
+    /// optional, like `[name]`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("get")
+    ///     .positional("resource")
+    ///     .positional("name")
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn positional(mut self, name: impl Into<String>) -> Self {
+        self.command
+            .positionals
+            .push((name.into(), Positional::Any));
+        self
+    }
+
+    /// Names the next positional argument and lists the values it accepts
+    ///
+    /// Completing this position offers the valid args that match what was
+    /// typed, ahead of any [`arg_completion`](Self::arg_completion)
+    /// function. They are not enforced when the command runs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("get")
+    ///     .positional_with_valid_args("resource", ["pods", "services"])
+    ///     .positional("name")
+    ///     .build();
+    ///
+    /// let completions = cmd
+    ///     .handle_completion_request(&["__complete".to_string(), "p".to_string()])
+    ///     .unwrap();
+    /// assert_eq!(completions, vec!["pods"]);
+    /// ```
+    #[must_use]
+    pub fn positional_with_valid_args<I, S>(
+        mut self,
+        name: impl Into<String>,
+        valid_args: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.command.positionals.push((
+            name.into(),
+            Positional::ValidArgs(valid_args.into_iter().map(Into::into).collect()),
+        ));
+        self
+    }
+
+    /// Names the next positional argument and says what it holds
+    ///
+    /// [`Positional::File`] and [`Positional::Directory`] complete from the
+    /// filesystem without an [`arg_completion`](Self::arg_completion)
+    /// function, the latter offering only directories, and show the kind
+    /// in the usage line, like `open <path:file>`. Directories complete
+    /// with a trailing `/` and no space, so the next TAB lists inside them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Positional};
+    ///
+    /// let cmd = CommandBuilder::new("copy")
+    ///     .positional_with_kind("source", Positional::File)
+    ///     .positional_with_kind("dest", Positional::Directory)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn positional_with_kind(mut self, name: impl Into<String>, kind: Positional) -> Self {
+        self.command.positionals.push((name.into(), kind));
+        self
+    }
+
     /// Sets the persistent pre-run hook for this command
     ///
     /// This hook runs before the command and all its subcommands.
@@ -1607,6 +4737,38 @@ impl CommandBuilder {
         self
     }
 
+    /// Runs the persistent hooks when help is shown
+    ///
+    /// By default `--help`, `-h` and the `help` command skip every hook,
+    /// so asking for help is fast and free of setup like authentication.
+    /// With this on, the persistent pre- and post-run hooks of the command
+    /// shown and those above it run around the help, seeing the flags
+    /// given. It applies to subcommands too. Completion requests never
+    /// run hooks.
+    ///
+    /// The help text is rendered before the hooks run, so they can't
+    /// change what it says; use this for side effects such as logging or
+    /// usage metrics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .persistent_pre_run(|_ctx| {
+    ///         // Record that help was asked for
+    ///         Ok(())
+    ///     })
+    ///     .run_hooks_on_help(true)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn run_hooks_on_help(mut self, enabled: bool) -> Self {
+        self.command.run_hooks_on_help = enabled;
+        self
+    }
+
     /// Sets the argument completion function
     ///
     /// This function is called when the user presses TAB to complete arguments.
@@ -1671,6 +4833,126 @@ impl CommandBuilder {
         self
     }
 
+    /// Stops waiting for slow completion functions after `timeout`
+    ///
+    /// Every argument and flag value completion function of this command
+    /// and the commands below it then runs on a worker thread. One that
+    /// hasn't returned in time, like one stuck on a hung API call, gives an
+    /// empty result with an `ActiveHelp` note instead of blocking the shell;
+    /// its thread is left to finish in the background. Subcommands can set
+    /// their own timeout. Without one, completions run with no limit.
+    ///
+    /// The timeout applies when completing, so it also covers functions
+    /// added to the built [`Command`], such as with
+    /// [`Command::set_arg_completion`] or [`Command::merge`]. On the worker
+    /// thread the context has the arguments, flags and command path, but
+    /// not values stored with [`Context::set`] or [`Context::root`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, CompletionResult};
+    /// use std::time::Duration;
+    ///
+    /// let cmd = CommandBuilder::new("kubectl")
+    ///     .completion_timeout(Duration::from_millis(500))
+    ///     .arg_completion(|_ctx, _prefix| {
+    ///         // A call to a cluster that may not answer
+    ///         Ok(CompletionResult::new())
+    ///     })
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn completion_timeout(mut self, timeout: Duration) -> Self {
+        self.command.completion_timeout = Some(timeout);
+        self
+    }
+
+    /// Caches the results of this command's completion functions
+    ///
+    /// Argument and flag value completions of this command and the commands
+    /// below it are looked up in `cache` before their function runs, and
+    /// stored there after, so repeated TABs within the cache's TTL don't
+    /// redo expensive work. Results are keyed by command path, flag, the
+    /// prefix being completed and the flags given so far. Give the cache a
+    /// session (see [`CompletionCache::with_session_from_env`]) to share
+    /// results between the separate processes shells start for each TAB.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, CompletionCache, CompletionResult};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let cmd = CommandBuilder::new("kubectl")
+    ///     .completion_cache(Arc::new(CompletionCache::new(Duration::from_secs(5))))
+    ///     .arg_completion(|_ctx, _prefix| {
+    ///         // An expensive lookup, run at most once per TTL and prefix
+    ///         Ok(CompletionResult::new().extend(["pods".to_string(), "services".to_string()]))
+    ///     })
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn completion_cache(mut self, cache: Arc<CompletionCache>) -> Self {
+        self.command.completion_cache = Some(cache);
+        self
+    }
+
+    /// Registers a completion function under a name for this command and
+    /// the commands below it
+    ///
+    /// See [`Command::register_completer`].
+    #[must_use]
+    pub fn register_completer<F>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync + 'static,
+    {
+        self.command.register_completer(name, f);
+        self
+    }
+
+    /// Completes arguments with the completer registered under `name`
+    ///
+    /// The name is looked up on this command, then its parents, each time
+    /// completion runs. A function set with
+    /// [`arg_completion`](Self::arg_completion) takes precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, CompletionResult};
+    ///
+    /// let app = CommandBuilder::new("app")
+    ///     .register_completer("resource", |_ctx, _prefix| {
+    ///         Ok(CompletionResult::new().extend(["pods".to_string(), "services".to_string()]))
+    ///     })
+    ///     .subcommand(CommandBuilder::new("get").arg_completer_ref("resource").build())
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn arg_completer_ref(mut self, name: impl Into<String>) -> Self {
+        self.command.arg_completer_ref = Some(name.into());
+        self
+    }
+
+    /// Completes a flag's values with the completer registered under `name`
+    ///
+    /// Resolved like [`arg_completer_ref`](Self::arg_completer_ref). A
+    /// function set with [`flag_completion`](Self::flag_completion) or on
+    /// the flag itself takes precedence.
+    #[must_use]
+    pub fn flag_completer_ref(
+        mut self,
+        flag_name: impl Into<String>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.command
+            .flag_completer_refs
+            .insert(flag_name.into(), name.into());
+        self
+    }
+
     /// Enables or disables command suggestions
     ///
     /// When enabled, the framework will suggest similar commands when
@@ -1711,265 +4993,3949 @@ impl CommandBuilder {
         self
     }
 
-    /// Builds and returns the completed [`Command`]
+    /// Suggests commands from the whole tree below, not just subcommands
+    ///
+    /// For an unknown command, deeper commands with a close name are
+    /// suggested by their path, after the close direct subcommands, so
+    /// `myapp pods` or `myapp podz` suggests `get pods`. Names are compared
+    /// with the same algorithm and threshold as other suggestions. Off by
+    /// default, since it visits every command in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Error};
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .nested_suggestions(true)
+    ///     .subcommand(
+    ///         CommandBuilder::new("get")
+    ///             .subcommand(CommandBuilder::new("pods").run(|_| Ok(())).build())
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// match app.execute(vec!["podz".to_string()]) {
+    ///     Err(Error::CommandNotFound { suggestions, .. }) => {
+    ///         assert_eq!(suggestions, vec!["get pods"]);
+    ///     }
+    ///     other => panic!("unexpected result: {other:?}"),
+    /// }
+    /// ```
     #[must_use]
-    pub fn build(self) -> Command {
-        self.command
+    pub const fn nested_suggestions(mut self, enabled: bool) -> Self {
+        self.command.nested_suggestions = enabled;
+        self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::flag::FlagType;
-    use std::sync::{Arc, Mutex};
-
-    #[test]
-    fn test_simple_command_execution() {
-        let executed = Arc::new(Mutex::new(false));
-        let executed_clone = executed.clone();
+
+    /// Sets how mistyped commands and flags are compared with real ones
+    ///
+    /// [`SuggestionAlgorithm::Levenshtein`], the default, suggests names
+    /// within [`suggestion_distance`](Self::suggestion_distance) edits.
+    /// [`SuggestionAlgorithm::JaroWinkler`] suggests names scoring at least
+    /// [`suggestion_similarity`](Self::suggestion_similarity), and catches
+    /// swapped letters like `pdos` for `pods` that cost two edits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    /// use flag_rs::suggestion::SuggestionAlgorithm;
+    ///
+    /// let cmd = CommandBuilder::new("kubectl")
+    ///     .suggestion_algorithm(SuggestionAlgorithm::JaroWinkler)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn suggestion_algorithm(mut self, algorithm: SuggestionAlgorithm) -> Self {
+        self.command.suggestion_algorithm = algorithm;
+        self
+    }
+
+    /// Sets the minimum Jaro-Winkler similarity for suggestions
+    ///
+    /// Scores run from 0.0 to 1.0; names scoring at least this are
+    /// suggested when the algorithm is
+    /// [`SuggestionAlgorithm::JaroWinkler`]. Default is 0.85.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    /// use flag_rs::suggestion::SuggestionAlgorithm;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .suggestion_algorithm(SuggestionAlgorithm::JaroWinkler)
+    ///     .suggestion_similarity(0.9)  // Only very close names
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn suggestion_similarity(mut self, similarity: f64) -> Self {
+        self.command.suggestion_similarity = similarity;
+        self
+    }
+
+    /// Shows help through a pager when it doesn't fit the terminal
+    ///
+    /// When enabled, `--help` output longer than the terminal height is piped
+    /// through `$PAGER` (or `less -R`). Output that is not a TTY is printed
+    /// directly, as is everything when the pager can't be started.
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .use_pager(true)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn use_pager(mut self, enabled: bool) -> Self {
+        self.command.use_pager = enabled;
+        self
+    }
+
+    /// Aligns commands and flags in help output to a single column
+    ///
+    /// By default each section uses its own fixed width. When enabled,
+    /// the widest visible entry (capped) sets one column for all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .aligned_help(true)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn aligned_help(mut self, enabled: bool) -> Self {
+        self.command.aligned_help = enabled;
+        self
+    }
+
+    /// Marks this command as deprecated
+    ///
+    /// The command keeps working, but running it prints
+    /// `warning: command <name> is deprecated: <message>` to stderr.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .subcommand(
+    ///         CommandBuilder::new("rm")
+    ///             .deprecated("use 'myapp delete'")
+    ///             .run(|_ctx| Ok(()))
+    ///             .build(),
+    ///     )
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn deprecated(mut self, message: impl Into<String>) -> Self {
+        self.command.deprecated = Some(message.into());
+        self
+    }
+
+    /// Sets whether deprecation warnings are printed together at the end
+    ///
+    /// Each distinct deprecation warning is printed at most once per
+    /// process anyway. With this on, the warnings of an invocation aren't
+    /// printed as they come up but as one list after it has run, whether
+    /// it succeeded or not. Set it on the command you call `execute` on.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, DeprecationWarnings, Flag, Context};
+    ///
+    /// let cmd = CommandBuilder::new("app")
+    ///     .deprecation_summary(true)
+    ///     .flag(Flag::string("out").deprecated("use --output"))
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// let mut ctx = Context::new(vec!["--out".to_string(), "a".to_string()]);
+    /// cmd.execute_with_context(&mut ctx).unwrap();
+    /// assert_eq!(
+    ///     ctx.get::<DeprecationWarnings>().unwrap().0,
+    ///     vec!["flag --out is deprecated: use --output"]
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn deprecation_summary(mut self, enabled: bool) -> Self {
+        self.command.deprecation_summary = enabled;
+        self
+    }
+
+    /// Sets a check of the whole invocation
+    ///
+    /// Runs once the command to run is known, after its flags and
+    /// arguments are validated and before confirmation, hooks or the run
+    /// function. The context has every flag and argument, and
+    /// [`Context::command_path`] names the command. Set on the root, it
+    /// covers every command, which suits rules that cut across them;
+    /// checks on several commands of the path run root first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Error, Flag};
+    ///
+    /// let app = CommandBuilder::new("app")
+    ///     .flag(Flag::bool("dry-run"))
+    ///     .validate(|ctx| {
+    ///         if ctx.flag_bool("dry-run") == Some(true) && ctx.command_path().ends_with(&["login".to_string()]) {
+    ///             return Err(Error::Validation("login has nothing to dry-run".to_string()));
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .subcommand(CommandBuilder::new("login").run(|_| Ok(())).build())
+    ///     .build();
+    ///
+    /// assert!(app.execute(vec!["login".to_string()]).is_ok());
+    /// assert!(app.execute(vec!["--dry-run".to_string(), "login".to_string()]).is_err());
+    /// ```
+    #[must_use]
+    pub fn validate<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&Context) -> Result<()> + Send + Sync + 'static,
+    {
+        self.command.validate = Some(Box::new(check));
+        self
+    }
+
+    /// Sets a function naming the flags required by this invocation
+    ///
+    /// For flags whose need depends on the arguments or on other flags,
+    /// where [`Flag::required`] and the flag constraints fall short. The
+    /// function runs once the command to run is known and its arguments
+    /// are validated, and returns the names of the flags that must have
+    /// been given. The error names every missing flag and the command and
+    /// arguments that required them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let app = CommandBuilder::new("create")
+    ///     .flag(Flag::string("image"))
+    ///     .flag(Flag::string("port"))
+    ///     .require_flag_when(|ctx| match ctx.args().first().map(String::as_str) {
+    ///         Some("deployment") => vec!["image".to_string()],
+    ///         Some("service") => vec!["port".to_string()],
+    ///         _ => Vec::new(),
+    ///     })
+    ///     .run(|_| Ok(()))
+    ///     .build();
+    ///
+    /// assert!(app.execute(vec!["service".to_string(), "--port".to_string(), "80".to_string()]).is_ok());
+    /// assert!(app.execute(vec!["deployment".to_string()]).is_err());
+    /// ```
+    #[must_use]
+    pub fn require_flag_when<F>(mut self, required: F) -> Self
+    where
+        F: Fn(&Context) -> Vec<String> + Send + Sync + 'static,
+    {
+        self.command.required_flags_when = Some(Box::new(required));
+        self
+    }
+
+    /// Sets a function that formats the error an execution ends in
+    ///
+    /// The handler is consulted by [`Command::execute_or_exit`] and
+    /// [`Command::format_error`], and only on the command they are called
+    /// on, usually the root. It can add a support link or reword specific
+    /// errors; returning `None` keeps the default message.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Error};
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .error_handler(|err| match err {
+    ///         Error::Io(_) => Some(format!("{err}\n\nSee https://example.com/help")),
+    ///         _ => None,
+    ///     })
+    ///     .build();
+    ///
+    /// let err = Error::Validation("bad input".to_string());
+    /// assert_eq!(app.format_error(&err), err.to_string());
+    /// ```
+    #[must_use]
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Error) -> Option<String> + Send + Sync + 'static,
+    {
+        self.command.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Lays out this command's help from a template
+    ///
+    /// Placeholders in braces are replaced with a section of the default
+    /// help: `{description}`, `{usage}`, `{commands}`, `{flags}`,
+    /// `{global_flags}`, `{examples}` and `{footer}`, or `{name}`, `{short}`
+    /// and `{long}` for the plain text. Sections are rendered as usual,
+    /// colors and wrapping included, but without their surrounding blank
+    /// lines, so the template decides the spacing. A section that isn't in
+    /// the template isn't shown, and other text is kept as written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("deploy")
+    ///     .short("Deploy the app")
+    ///     .flag(Flag::bool("force").usage("Skip checks"))
+    ///     .help_template("{name} - {short}\n\n{flags}\n")
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn help_template(mut self, template: impl Into<String>) -> Self {
+        self.command.help_template = Some(template.into());
+        self
+    }
+
+    /// Stops shells from completing filenames when nothing else matches
+    ///
+    /// Useful for programs whose arguments are never paths. The generated
+    /// Bash script then turns off readline's default completion when there
+    /// are no results, and the Zsh script reports success so no fallback
+    /// completer runs. Set this on the root command, which the scripts are
+    /// generated from; the Fish script never completes files anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Shell};
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .no_file_completion_fallback()
+    ///     .build();
+    ///
+    /// let script = cmd.generate_completion(Shell::Bash);
+    /// assert!(script.contains("compopt +o default"));
+    /// ```
+    #[must_use]
+    pub const fn no_file_completion_fallback(mut self) -> Self {
+        self.command.file_completion_fallback = false;
+        self
+    }
+
+    /// Lets this command receive flags it doesn't define as arguments
+    ///
+    /// By default a long flag that neither the command that runs nor its
+    /// parents define is an error, with suggestions for flags of a similar
+    /// name. Commands that hand their arguments to another program, like a
+    /// wrapper around a compiler, can opt out; their run function then
+    /// finds unknown flags in [`Context::args`] as typed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("wrap")
+    ///     .allow_unknown_flags()
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.args(), &["--release"]);
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(vec!["--release".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub const fn allow_unknown_flags(mut self) -> Self {
+        self.command.allow_unknown_flags = true;
+        self
+    }
+
+    /// Collects everything this command doesn't recognize for forwarding
+    ///
+    /// Meant for wrappers that run another program: the flags this command
+    /// and its parents define are parsed as usual, and every other word,
+    /// unknown flags and arguments alike, ends up in
+    /// [`Context::forwarded_args`] in the order typed, leaving
+    /// [`Context::args`] empty. Words after `--` are forwarded too, without
+    /// the `--` itself. Unlike the default, unknown flags are no error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("wrap")
+    ///     .flag(Flag::bool("dry-run"))
+    ///     .passthrough_unknown()
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.flag_bool("dry-run"), Some(true));
+    ///         assert_eq!(ctx.forwarded_args(), &["build", "--release"]);
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(["build", "--dry-run", "--release"].map(String::from).to_vec())
+    ///     .unwrap();
+    /// ```
+    #[must_use]
+    pub const fn passthrough_unknown(mut self) -> Self {
+        self.command.passthrough_unknown = true;
+        self
+    }
+
+    /// Turns off flag parsing for this command
+    ///
+    /// Every word after the command's name reaches [`Context::args`]
+    /// verbatim, `-x`, `--help` and `--` included, and unknown flags are no
+    /// error. Flags given to parent commands before this one are parsed
+    /// as usual. Meant for commands like `exec` that hand their arguments
+    /// to another program.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("mytool")
+    ///     .flag(Flag::bool("verbose"))
+    ///     .subcommand(
+    ///         CommandBuilder::new("exec")
+    ///             .trailing_var_arg()
+    ///             .run(|ctx| {
+    ///                 assert_eq!(ctx.flag_bool("verbose"), Some(true));
+    ///                 assert_eq!(ctx.args(), &["ls", "-la", "--verbose"]);
+    ///                 Ok(())
+    ///             })
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// cmd.execute(["--verbose", "exec", "ls", "-la", "--verbose"].map(String::from).to_vec())
+    ///     .unwrap();
+    /// ```
+    #[must_use]
+    pub const fn trailing_var_arg(mut self) -> Self {
+        self.command.trailing_var_arg = true;
+        self
+    }
+
+    /// Asks the run function to stop once `timeout` passes
+    ///
+    /// The timeout is cooperative: it signals the run function rather than
+    /// stopping it. The run function runs on a worker thread; if it is
+    /// still running when `timeout` passes, the context's
+    /// [`cancellation`](Context::cancellation) token is cancelled. The
+    /// command only returns once the run function does. If the function
+    /// saw the cancellation through [`Context::is_cancelled`], the command
+    /// fails with an `Error::Custom` saying it timed out, with the deadline
+    /// and how long the function really ran; otherwise the function's own
+    /// result is kept.
+    ///
+    /// A thread can't be stopped from outside, so a run function that
+    /// ignores the token, for instance one blocked in a network call, keeps
+    /// [`execute`](Command::execute) waiting. Long-running work should check
+    /// [`Context::is_cancelled`] between steps, and give the clients it
+    /// calls a timeout of their own. Hooks are not covered.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let cmd = CommandBuilder::new("sync")
+    ///     .execution_timeout(Duration::from_millis(50))
+    ///     .run(|ctx| {
+    ///         while !ctx.is_cancelled() {
+    ///             std::thread::sleep(Duration::from_millis(5));
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// let err = cmd.execute(vec![]).unwrap_err();
+    /// assert!(err.to_string().contains("timed out after 50ms"));
+    /// ```
+    #[must_use]
+    pub const fn execution_timeout(mut self, timeout: Duration) -> Self {
+        self.command.execution_timeout = Some(timeout);
+        self
+    }
+
+    /// Rejects arguments beyond the ones this command declares
+    ///
+    /// The command accepts as many arguments as its argument validator
+    /// allows at most, or, without a validator, as many as its named
+    /// [`positional`](Self::positional)s. Anything past that is an
+    /// `Error::ArgumentValidation` listing the unexpected arguments,
+    /// instead of being handed to the run function.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Error};
+    ///
+    /// let cmd = CommandBuilder::new("get")
+    ///     .positional("resource")
+    ///     .deny_extra_args()
+    ///     .run(|_| Ok(()))
+    ///     .build();
+    ///
+    /// assert!(cmd.execute(vec!["pods".to_string()]).is_ok());
+    /// let err = cmd.execute(vec!["pods".to_string(), "web".to_string()]).unwrap_err();
+    /// assert!(matches!(err, Error::ArgumentValidation { .. }));
+    /// ```
+    #[must_use]
+    pub const fn deny_extra_args(mut self) -> Self {
+        self.command.deny_extra_args = true;
+        self
+    }
+
+    /// Sets whether the start of a subcommand's name stands for the whole
+    /// name
+    ///
+    /// With this on, `app ser` runs `app server` as long as no other
+    /// visible subcommand or alias starts with `ser`. A prefix of several
+    /// is an error listing them, rather than a guess, unless the command
+    /// has a run function of its own: then the word is left to it as a
+    /// positional argument, as any word naming no subcommand is. Applies to this
+    /// command's subcommands and to theirs, in both execution and
+    /// completion, so set on the root it covers the whole tree. Completion
+    /// still offers the full names.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Error};
+    ///
+    /// let app = CommandBuilder::new("kubectl")
+    ///     .match_prefix(true)
+    ///     .subcommand(CommandBuilder::new("deployments").run(|_| Ok(())).build())
+    ///     .subcommand(CommandBuilder::new("describe").run(|_| Ok(())).build())
+    ///     .build();
+    ///
+    /// app.execute(vec!["dep".to_string()]).unwrap();
+    /// match app.execute(vec!["de".to_string()]) {
+    ///     Err(Error::CommandNotFound { suggestions, .. }) => {
+    ///         assert_eq!(suggestions, vec!["deployments", "describe"]);
+    ///     }
+    ///     other => panic!("expected an ambiguous prefix, got {other:?}"),
+    /// }
+    /// ```
+    #[must_use]
+    pub const fn match_prefix(mut self, enabled: bool) -> Self {
+        self.command.prefix_matching = enabled;
+        self
+    }
+
+    /// Sets whether subcommand names match regardless of case
+    ///
+    /// With this on, `app Deploy` runs `app deploy` when no subcommand is
+    /// named `Deploy` exactly, and [`match_prefix`](Self::match_prefix)
+    /// compares prefixes ignoring case too. Like prefix matching it applies
+    /// to the whole subtree.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let app = CommandBuilder::new("app")
+    ///     .match_case_insensitive(true)
+    ///     .subcommand(CommandBuilder::new("deploy").run(|_| Ok(())).build())
+    ///     .build();
+    ///
+    /// app.execute(vec!["DEPLOY".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub const fn match_case_insensitive(mut self, enabled: bool) -> Self {
+        self.command.case_insensitive_matching = enabled;
+        self
+    }
+
+    /// Adds a `help` subcommand showing help for the command named after it
+    ///
+    /// `myapp help get pods` prints the same help as `myapp get pods
+    /// --help`, and `myapp help` prints this command's help. Completion
+    /// after `help` offers this command's subcommands, then theirs. A
+    /// `help` subcommand added later replaces this one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .with_help_command()
+    ///     .subcommand(CommandBuilder::new("get").short("Display resources").build())
+    ///     .build();
+    ///
+    /// assert!(app.find_subcommand("help").is_some());
+    /// app.execute(vec!["help".to_string(), "get".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub fn with_help_command(mut self) -> Self {
+        if !self.command.subcommands.contains_key("help") {
+            self.command.add_command(Command::help_command());
+        }
+        self
+    }
+
+    /// Hides this command from help, completion and suggestions
+    ///
+    /// Meant for internal or debugging commands. A hidden command still
+    /// runs when invoked by name, and its own help still works.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .subcommand(CommandBuilder::new("debug-dump").hidden().build())
+    ///     .build();
+    /// assert!(!cmd.help_text().contains("debug-dump"));
+    /// ```
+    #[must_use]
+    pub const fn hidden(mut self) -> Self {
+        self.command.hidden = true;
+        self
+    }
+
+    /// Sets which flag spellings this command and its subcommands accept
+    ///
+    /// With [`FlagPrefixStyle::Windows`], `/verbose`, `/port 8080`,
+    /// `/port:8080` and `/p` are recognized instead of their Unix spellings,
+    /// and help shows that style. [`FlagPrefixStyle::Both`] accepts either.
+    /// Only names of known flags are treated as flags, so paths like
+    /// `/tmp/out` remain arguments. `--` still ends flag parsing. A
+    /// subcommand can set its own style.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Flag, FlagPrefixStyle};
+    ///
+    /// let cmd = CommandBuilder::new("mytool")
+    ///     .flag_prefix_style(FlagPrefixStyle::Windows)
+    ///     .flag(Flag::int("port"))
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.flag_int("port"), Some(8080));
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(vec!["/port:8080".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub const fn flag_prefix_style(mut self, style: FlagPrefixStyle) -> Self {
+        self.command.flag_prefix_style = Some(style);
+        self
+    }
+
+    /// Offers to run a subcommand when the typed one is a near miss
+    ///
+    /// If exactly one subcommand is within `threshold` edits of an unknown
+    /// subcommand and stdin is interactive, the user is asked
+    /// `Did you mean 'start'? [Y/n]` and it runs on confirmation. Otherwise
+    /// the usual unknown-command error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .autocorrect(1)
+    ///     .subcommand(CommandBuilder::new("start").build())
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn autocorrect(mut self, threshold: usize) -> Self {
+        self.command.autocorrect = Some(threshold);
+        self
+    }
+
+    /// Sets the version reported by `--version`
+    ///
+    /// On the root command, `--version` and `-V` print `"{name} {version}"`
+    /// and exit without running anything. Flags you define named `version`
+    /// or with short `-V` take precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .version(env!("CARGO_PKG_VERSION"))
+    ///     .build();
+    ///
+    /// // Prints "myapp 0.1.0"
+    /// cmd.execute(vec!["--version".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.command.version = Some(version.into());
+        self
+    }
+
+    /// Asks for confirmation before running this command
+    ///
+    /// Meant for destructive commands like `delete`. Before the run
+    /// function, `prompt` is shown and the user must type `yes` or the first
+    /// argument to go ahead. A `--yes`/`-y` flag is added to skip the
+    /// prompt; without it, running non-interactively is an error. A `yes`
+    /// flag defined on the command is kept as is, and `-y` is left off
+    /// when another flag already uses it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("delete")
+    ///     .require_confirmation("This permanently deletes the cluster.")
+    ///     .run(|_ctx| Ok(()))
+    ///     .build();
+    ///
+    /// // Scripts skip the prompt with --yes
+    /// assert!(cmd.execute(vec!["prod".to_string(), "--yes".to_string()]).is_ok());
+    /// ```
+    #[must_use]
+    pub fn require_confirmation(mut self, prompt: impl Into<String>) -> Self {
+        self.command.confirmation = Some(prompt.into());
+        if self.command.flags.contains_key("yes") {
+            return self;
+        }
+        let mut yes = Flag::bool("yes").usage("Skip the confirmation prompt");
+        if !self
+            .command
+            .flags
+            .values()
+            .any(|flag| flag.short == Some('y'))
+        {
+            yes = yes.short('y');
+        }
+        self.flag(yes)
+    }
+
+    /// Enables `--explain` for this command and its subcommands
+    ///
+    /// `--explain` prints how the invocation resolves — the command path,
+    /// each flag's value and source, and the arguments — instead of running
+    /// it. `--explain=json` prints the same plan as JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .explain(true)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub const fn explain(mut self, enabled: bool) -> Self {
+        self.command.explain = enabled;
+        self
+    }
+
+    /// Builds and returns the completed [`Command`]
+    #[must_use]
+    pub fn build(self) -> Command {
+        self.command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flag::FlagType;
+    use crate::shell::Shell;
+    use crate::test_support::{complete, no_color};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_simple_command_execution() {
+        let executed = Arc::new(Mutex::new(false));
+        let executed_clone = executed.clone();
+
+        let cmd = CommandBuilder::new("test")
+            .run(move |_ctx| {
+                *executed_clone.lock().unwrap() = true;
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec![]).unwrap();
+        assert!(*executed.lock().unwrap());
+    }
+
+    #[test]
+    fn test_command_with_args() {
+        let received_args = Arc::new(Mutex::new(Vec::new()));
+        let args_clone = received_args.clone();
 
         let cmd = CommandBuilder::new("test")
+            .run(move |ctx| {
+                *args_clone.lock().unwrap() = ctx.args().to_vec();
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec!["arg1".to_string(), "arg2".to_string()])
+            .unwrap();
+        assert_eq!(*received_args.lock().unwrap(), vec!["arg1", "arg2"]);
+    }
+
+    #[test]
+    fn test_subcommand_execution() {
+        let main_executed = Arc::new(Mutex::new(false));
+        let sub_executed = Arc::new(Mutex::new(false));
+        let sub_clone = sub_executed.clone();
+
+        let subcmd = CommandBuilder::new("sub")
+            .run(move |_ctx| {
+                *sub_clone.lock().unwrap() = true;
+                Ok(())
+            })
+            .build();
+
+        let main_clone = main_executed.clone();
+        let cmd = CommandBuilder::new("main")
+            .run(move |_ctx| {
+                *main_clone.lock().unwrap() = true;
+                Ok(())
+            })
+            .subcommand(subcmd)
+            .build();
+
+        // Execute subcommand
+        cmd.execute(vec!["sub".to_string()]).unwrap();
+        assert!(*sub_executed.lock().unwrap());
+        assert!(!*main_executed.lock().unwrap());
+    }
+
+    #[test]
+    fn test_flag_parsing() {
+        let cmd = CommandBuilder::new("test")
+            .flag(Flag::new("verbose").short('v').value_type(FlagType::Bool))
+            .flag(Flag::new("output").short('o').value_type(FlagType::String))
+            .flag(Flag::new("count").value_type(FlagType::Int))
+            .run(|ctx| {
+                assert_eq!(ctx.flag("verbose"), Some(&"true".to_string()));
+                assert_eq!(ctx.flag("output"), Some(&"file.txt".to_string()));
+                assert_eq!(ctx.flag("count"), Some(&"42".to_string()));
+                assert_eq!(ctx.args(), &["remaining"]);
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec![
+            "-v".to_string(),
+            "--output".to_string(),
+            "file.txt".to_string(),
+            "--count=42".to_string(),
+            "remaining".to_string(),
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn test_flag_inheritance() {
+        let sub_executed = Arc::new(Mutex::new(false));
+        let sub_clone = sub_executed.clone();
+
+        let subcmd = CommandBuilder::new("sub")
+            .run(move |ctx| {
+                assert_eq!(ctx.flag("global"), Some(&"value".to_string()));
+                *sub_clone.lock().unwrap() = true;
+                Ok(())
+            })
+            .build();
+
+        let cmd = CommandBuilder::new("main")
+            .flag(Flag::new("global").value_type(FlagType::String))
+            .subcommand(subcmd)
+            .build();
+
+        cmd.execute(vec![
+            "--global".to_string(),
+            "value".to_string(),
+            "sub".to_string(),
+        ])
+        .unwrap();
+
+        assert!(*sub_executed.lock().unwrap());
+    }
+
+    #[test]
+    fn test_command_aliases() {
+        let executed = Arc::new(Mutex::new(String::new()));
+        let exec_clone = executed.clone();
+
+        let subcmd = CommandBuilder::new("subcommand")
+            .aliases(vec!["sub", "s"])
             .run(move |_ctx| {
-                *executed_clone.lock().unwrap() = true;
+                *exec_clone.lock().unwrap() = "subcommand".to_string();
+                Ok(())
+            })
+            .build();
+
+        let cmd = CommandBuilder::new("main").subcommand(subcmd).build();
+
+        // Test main name
+        cmd.execute(vec!["subcommand".to_string()]).unwrap();
+        assert_eq!(*executed.lock().unwrap(), "subcommand");
+
+        // Test alias
+        cmd.execute(vec!["sub".to_string()]).unwrap();
+        assert_eq!(*executed.lock().unwrap(), "subcommand");
+
+        // Test short alias
+        cmd.execute(vec!["s".to_string()]).unwrap();
+        assert_eq!(*executed.lock().unwrap(), "subcommand");
+    }
+
+    #[test]
+    fn test_error_cases() {
+        let cmd = CommandBuilder::new("main").build();
+
+        // No subcommand when required
+        let result = cmd.execute(vec![]);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::SubcommandRequired(_)));
+
+        // Unknown subcommand
+        let result = cmd.execute(vec!["unknown".to_string()]);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::CommandNotFound { .. }));
+
+        // Unknown flag
+        let result = cmd.execute(vec!["--unknown".to_string()]);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::FlagParsing { .. }));
+    }
+
+    #[test]
+    fn test_completion() {
+        let cmd = CommandBuilder::new("test")
+            .arg_completion(|_ctx, prefix| {
+                Ok(CompletionResult::new().extend(
+                    vec!["file1.txt", "file2.txt", "folder/"]
+                        .into_iter()
+                        .filter(|f| f.starts_with(prefix))
+                        .map(String::from),
+                ))
+            })
+            .flag_completion("type", |_ctx, prefix| {
+                Ok(CompletionResult::new().extend(
+                    vec!["json", "yaml", "xml"]
+                        .into_iter()
+                        .filter(|t| t.starts_with(prefix))
+                        .map(String::from),
+                ))
+            })
+            .build();
+
+        let ctx = Context::new(vec![]);
+
+        // Test arg completion
+        let result = cmd.get_completions(&ctx, "fi", None).unwrap();
+        assert_eq!(result.values, vec!["file1.txt", "file2.txt"]);
+
+        // Test flag completion
+        let result = cmd.get_completions(&ctx, "j", Some("type")).unwrap();
+        assert_eq!(result.values, vec!["json"]);
+    }
+
+    #[test]
+    fn test_flag_with_equals() {
+        let cmd = CommandBuilder::new("test")
+            .flag(Flag::new("output").value_type(FlagType::String))
+            .run(|ctx| {
+                assert_eq!(
+                    ctx.flag("output"),
+                    Some(&"/path/with=equals.txt".to_string())
+                );
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec!["--output=/path/with=equals.txt".to_string()])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_help_flag() {
+        let cmd = CommandBuilder::new("test")
+            .short("Test command")
+            .long("This is a test command")
+            .flag(
+                Flag::new("verbose")
+                    .short('v')
+                    .usage("Enable verbose output"),
+            )
+            .build();
+
+        // Test --help
+        let result = cmd.execute(vec!["--help".to_string()]);
+        assert!(result.is_ok());
+
+        // Test -h
+        let result = cmd.execute(vec!["-h".to_string()]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_subcommand_help() {
+        let subcmd = CommandBuilder::new("sub")
+            .short("Subcommand")
+            .flag(Flag::new("subflag").usage("A flag for the subcommand"))
+            .build();
+
+        let cmd = CommandBuilder::new("main")
+            .flag(Flag::new("global").usage("A global flag"))
+            .subcommand(subcmd)
+            .build();
+
+        // Test help on subcommand
+        let result = cmd.execute(vec!["sub".to_string(), "--help".to_string()]);
+        assert!(result.is_ok());
+    }
+
+    fn plugin_command() -> Command {
+        CommandBuilder::new("plugin")
+            .subcommand(
+                CommandBuilder::new("deploy")
+                    .subcommand(CommandBuilder::new("status").build())
+                    .build(),
+            )
+            .flag(Flag::bool("dry-run").short('n'))
+            .build()
+    }
+
+    #[test]
+    fn test_merge_commands() {
+        let mut base = CommandBuilder::new("app")
+            .subcommand(CommandBuilder::new("init").build())
+            .flag(Flag::bool("verbose").short('v'))
+            .build();
+
+        base.merge(plugin_command(), MergePolicy::Error).unwrap();
+
+        let mut names: Vec<_> = base.subcommands().keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["deploy", "init"]);
+        assert!(base.flags().contains_key("verbose"));
+        assert!(base.flags().contains_key("dry-run"));
+        let deploy = base.find_subcommand("deploy").unwrap();
+        assert!(deploy.find_subcommand("status").is_some());
+    }
+
+    #[test]
+    fn test_command_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Command>();
+    }
+
+    #[test]
+    fn test_merge_conflict_error() {
+        let mut base = CommandBuilder::new("app")
+            .subcommand(CommandBuilder::new("deploy").short("Base deploy").build())
+            .build();
+
+        let err = base
+            .merge(plugin_command(), MergePolicy::Error)
+            .unwrap_err();
+        assert!(matches!(&err, Error::Validation(msg) if msg.contains("subcommand 'deploy'")));
+        // Nothing was merged
+        assert!(!base.flags().contains_key("dry-run"));
+        assert_eq!(
+            base.find_subcommand("deploy").unwrap().short(),
+            "Base deploy"
+        );
+
+        let mut base = CommandBuilder::new("app")
+            .flag(Flag::bool("no-op").short('n'))
+            .build();
+        let err = base
+            .merge(plugin_command(), MergePolicy::Error)
+            .unwrap_err();
+        assert!(matches!(&err, Error::Validation(msg) if msg.contains("'-n'")));
+        assert!(base.subcommands().is_empty());
+    }
+
+    #[test]
+    fn test_merge_conflict_overwrite() {
+        let mut base = CommandBuilder::new("app")
+            .subcommand(CommandBuilder::new("deploy").short("Base deploy").build())
+            .flag(Flag::bool("no-op").short('n'))
+            .build();
+
+        base.merge(plugin_command(), MergePolicy::Overwrite)
+            .unwrap();
+
+        let deploy = base.find_subcommand("deploy").unwrap();
+        assert_eq!(deploy.short(), "");
+        assert!(deploy.find_subcommand("status").is_some());
+        assert_eq!(base.flags()["dry-run"].short, Some('n'));
+        assert_eq!(base.flags()["no-op"].short, None);
+    }
+
+    #[test]
+    fn test_write_help_to_sink() {
+        let mut builder = CommandBuilder::new("test").short("Test command");
+        for i in 0..60 {
+            builder = builder.subcommand(
+                CommandBuilder::new(format!("sub{i:02}"))
+                    .short(format!("Subcommand {i}"))
+                    .build(),
+            );
+        }
+        let cmd = builder.build();
+
+        // Pager disabled: the sink receives the full help
+        let mut out = Vec::new();
+        cmd.write_help(&mut out).unwrap();
+        let help = String::from_utf8(out).unwrap();
+        assert_eq!(help, cmd.help_text());
+        assert!(help.contains("sub00") && help.contains("sub59"));
+
+        // Pager enabled but output is not a TTY: the pager is skipped
+        let paged = CommandBuilder::new("test").use_pager(true).build();
+        let text = "line\n".repeat(200);
+        let mut out = Vec::new();
+        paged.write_help_text(&text, &mut out, false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), text);
+    }
+
+    #[test]
+    fn test_custom_bool_words() {
+        let seen = Arc::new(Mutex::new(None));
+        let seen_clone = seen.clone();
+
+        let cmd = CommandBuilder::new("boolwords")
+            .flag(Flag::bool("cache").bool_words(&["on"], &["off"]))
+            .run(move |ctx| {
+                *seen_clone.lock().unwrap() = ctx.flag_bool("cache");
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec!["--cache=off".to_string()]).unwrap();
+        assert_eq!(*seen.lock().unwrap(), Some(false));
+
+        cmd.execute(vec!["--cache".to_string(), "on".to_string()])
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), Some(true));
+
+        assert!(cmd.execute(vec!["--cache=yes".to_string()]).is_err());
+
+        let completions = complete(&cmd, &["--cache=o"]);
+        assert_eq!(completions, vec!["--cache=on", "--cache=off"]);
+    }
+
+    #[test]
+    fn test_flag_visible_when() {
+        let seen = Arc::new(Mutex::new(None));
+        let seen_clone = seen.clone();
+
+        let cmd = CommandBuilder::new("test")
+            .flag(Flag::bool("advanced").usage("Show advanced options"))
+            .flag(
+                Flag::int("gc-interval")
+                    .usage("Garbage collection interval")
+                    .visible_when("advanced"),
+            )
+            .run(move |ctx| {
+                *seen_clone.lock().unwrap() = ctx.flag("gc-interval").cloned();
+                Ok(())
+            })
+            .build();
+
+        let plain = cmd.help_text();
+        assert!(plain.contains("advanced"));
+        assert!(!plain.contains("gc-interval"));
+
+        let mut provided = HashMap::new();
+        provided.insert("advanced".to_string(), "true".to_string());
+        let revealed = cmd.render_help(&[], &provided);
+        assert!(revealed.contains("gc-interval"));
+        assert!(revealed.contains("Garbage collection interval"));
+
+        provided.insert("advanced".to_string(), "false".to_string());
+        assert!(!cmd.render_help(&[], &provided).contains("gc-interval"));
+
+        // Hidden flags still parse
+        cmd.execute(vec!["--gc-interval".to_string(), "30".to_string()])
+            .unwrap();
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("30"));
+    }
+
+    #[test]
+    fn test_override_flag_default() {
+        let root = CommandBuilder::new("app")
+            .flag(
+                Flag::int("timeout")
+                    .usage("Timeout in seconds")
+                    .default_int(30),
+            )
+            .subcommand(
+                CommandBuilder::new("slow-op")
+                    .override_flag_default("timeout", FlagValue::Int(300))
+                    .subcommand(CommandBuilder::new("step").build())
+                    .build(),
+            )
+            .subcommand(CommandBuilder::new("fast-op").build())
+            .build();
+
+        let slow = root.find_subcommand("slow-op").unwrap();
+        let step = slow.find_subcommand("step").unwrap();
+        let fast = root.find_subcommand("fast-op").unwrap();
+
+        assert_eq!(
+            root.effective_flag_default(&[], "timeout"),
+            Some(&FlagValue::Int(30))
+        );
+        assert_eq!(
+            slow.effective_flag_default(&[&root], "timeout"),
+            Some(&FlagValue::Int(300))
+        );
+        assert_eq!(
+            step.effective_flag_default(&[&root, slow], "timeout"),
+            Some(&FlagValue::Int(300))
+        );
+        assert_eq!(
+            fast.effective_flag_default(&[&root], "timeout"),
+            Some(&FlagValue::Int(30))
+        );
+        assert_eq!(slow.effective_flag_default(&[&root], "missing"), None);
+
+        // A redefinition without a default leaves the inherited one
+        let plain = CommandBuilder::new("plain")
+            .flag(Flag::int("timeout"))
+            .build();
+        assert_eq!(
+            plain.effective_flag_default(&[&root], "timeout"),
+            Some(&FlagValue::Int(30))
+        );
+
+        assert!(
+            slow.render_help(&[&root], &HashMap::new())
+                .contains("(default 300)")
+        );
+        assert!(
+            fast.render_help(&[&root], &HashMap::new())
+                .contains("(default 30)")
+        );
+    }
+
+    #[test]
+    fn test_aligned_help_columns() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("app")
+            .aligned_help(true)
+            .subcommand(
+                CommandBuilder::new("init")
+                    .short("Create a project")
+                    .build(),
+            )
+            .flag(Flag::bool("verbose").short('v').usage("Verbose output"))
+            .flag(Flag::string("output").usage("Output file"))
+            .build();
+
+        let help = cmd.help_text();
+        let column = |needle: &str| {
+            let line = help.lines().find(|l| l.contains(needle)).unwrap();
+            line.find(needle).unwrap()
+        };
+        let command_column = column("Create a project");
+        assert_eq!(column("Verbose output"), command_column);
+        assert_eq!(column("Output file"), command_column);
+    }
+
+    #[test]
+    fn test_flags_grouped_in_help() {
+        let _no_color = no_color();
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose").usage("Verbose output"))
+            .flag(Flag::string("output").group("Output").usage("Output file"))
+            .flag(Flag::bool("json").group("Output").usage("Print JSON"))
+            .flag(Flag::int("port").group("Networking").usage("Port"))
+            .flag(Flag::string("name").required().group("Output"))
+            .build();
+
+        let help = cmd.render_help(&[], &HashMap::new());
+        let section = |title: &str| {
+            let start = help.find(&format!("\n{title}:\n")).unwrap() + title.len() + 3;
+            let end = help[start..].find("\n\n").map_or(help.len(), |i| start + i);
+            help[start..end].to_string()
+        };
+
+        let flags = section("Flags");
+        assert!(flags.contains("--verbose"));
+        assert!(!flags.contains("--output"));
+        let output = section("Output");
+        assert!(output.contains("--json") && output.contains("--output"));
+        assert!(section("Networking").contains("--port"));
+        // Required flags stay in their own section
+        assert!(!output.contains("--name"));
+        assert!(help.find("\nFlags:").unwrap() < help.find("\nNetworking:").unwrap());
+        assert!(help.find("\nNetworking:").unwrap() < help.find("\nOutput:").unwrap());
+    }
+
+    #[test]
+    fn test_explain() {
+        let ran = Arc::new(Mutex::new(false));
+        let ran_clone = ran.clone();
+        let cli = CommandBuilder::new("app")
+            .explain(true)
+            .flag(Flag::int("timeout").default_int(30))
+            .flag(Flag::bool("verbose").short('v'))
+            .subcommand(
+                CommandBuilder::new("deploy")
+                    .flag(Flag::string("env"))
+                    .override_flag_default("timeout", FlagValue::Int(300))
+                    .run(move |_| {
+                        *ran_clone.lock().unwrap() = true;
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        let mut ctx = Context::new(
+            [
+                "deploy",
+                "--env",
+                "prod",
+                "web",
+                "-v",
+                "--explain",
+                "--",
+                "--dry",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        cli.execute_with_context(&mut ctx).unwrap();
+        assert!(!*ran.lock().unwrap(), "explain must not run the command");
+
+        let plan = ctx.get::<ExplainPlan>().unwrap();
+        assert_eq!(plan.command_path, vec!["app", "deploy"]);
+        assert_eq!(plan.args, vec!["web"]);
+        assert_eq!(plan.passthrough, vec!["--dry"]);
+        assert_eq!(
+            plan.to_text(),
+            "Command: app deploy\n\
+             Flags:\n\
+             \x20 --env = prod (command-line)\n\
+             \x20 --timeout = 300 (default)\n\
+             \x20 --verbose = true (command-line)\n\
+             Args: web\n\
+             Passthrough: --dry\n"
+        );
+
+        let mut ctx = Context::new(
+            ["deploy", "--explain=json", "--timeout", "5"]
+                .map(String::from)
+                .to_vec(),
+        );
+        cli.execute_with_context(&mut ctx).unwrap();
+        assert!(!*ran.lock().unwrap());
+
+        let plan = ctx.get::<ExplainPlan>().unwrap();
+        assert_eq!(
+            plan.to_json(),
+            r#"{"command":["app","deploy"],"flags":[{"name":"timeout","value":"5","source":"command-line"}],"args":[],"passthrough":[]}"#
+        );
+
+        // Unknown formats are rejected; without explain mode the flag is unknown
+        let result = cli.execute(["deploy", "--explain=yaml"].map(String::from).to_vec());
+        assert!(matches!(result, Err(Error::FlagParsing { .. })));
+
+        cli.execute(["deploy", "web"].map(String::from).to_vec())
+            .unwrap();
+        assert!(*ran.lock().unwrap());
+
+        // Without explain mode, a user's own --explain flag is left alone
+        let seen = Arc::new(Mutex::new(None));
+        let seen_run = Arc::clone(&seen);
+        let cli = CommandBuilder::new("app")
+            .flag(Flag::string("explain"))
+            .run(move |ctx| {
+                *seen_run.lock().unwrap() = ctx.flag("explain").cloned();
+                Ok(())
+            })
+            .build();
+        cli.execute(["--explain", "why"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("why"));
+    }
+
+    #[test]
+    fn test_flag_defaults_applied() {
+        let seen = Arc::new(Mutex::new(HashMap::new()));
+        let seen_clone = seen.clone();
+
+        let cli = CommandBuilder::new("app")
+            .flag(Flag::int("port").default_int(8080))
+            .flag(Flag::string("region"))
+            .subcommand(
+                CommandBuilder::new("serve")
+                    .flag(Flag::bool("tls").default(FlagValue::Bool(false)))
+                    .flag(Flag::string("host").default(FlagValue::String("localhost".into())))
+                    .run(move |ctx| {
+                        *seen_clone.lock().unwrap() = ctx.flags().clone();
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        cli.execute(vec!["serve".to_string()]).unwrap();
+        {
+            let seen = seen.lock().unwrap();
+            assert_eq!(seen.get("port").map(String::as_str), Some("8080"));
+            assert_eq!(seen.get("tls").map(String::as_str), Some("false"));
+            assert_eq!(seen.get("host").map(String::as_str), Some("localhost"));
+            assert!(!seen.contains_key("region"));
+        }
+
+        // Provided values win over defaults
+        cli.execute(
+            ["serve", "--port", "9090", "--tls", "--host", "0.0.0.0"]
+                .map(String::from)
+                .to_vec(),
+        )
+        .unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.get("port").map(String::as_str), Some("9090"));
+        assert_eq!(seen.get("tls").map(String::as_str), Some("true"));
+        assert_eq!(seen.get("host").map(String::as_str), Some("0.0.0.0"));
+    }
+
+    #[test]
+    fn test_autocorrect() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let mut cli = CommandBuilder::new("app").autocorrect(1).build();
+        for name in ["start", "stop"] {
+            let ran = ran.clone();
+            cli.add_command(
+                CommandBuilder::new(name)
+                    .run(move |ctx| {
+                        ran.lock()
+                            .unwrap()
+                            .push(format!("{name} {}", ctx.args().join(" ")));
+                        Ok(())
+                    })
+                    .build(),
+            );
+        }
+
+        // Declined
+        let mut ctx = Context::new(vec!["stat".to_string()]);
+        ctx.set_input(std::io::Cursor::new("n\n"));
+        match cli.execute_with_context(&mut ctx) {
+            Err(Error::CommandNotFound {
+                command,
+                suggestions,
+            }) => {
+                assert_eq!(command, "stat");
+                assert_eq!(suggestions, vec!["start", "stop"]);
+            }
+            other => panic!("expected CommandNotFound, got {other:?}"),
+        }
+
+        // Too far from any subcommand for autocorrect
+        let mut ctx = Context::new(vec!["xyz".to_string()]);
+        ctx.set_input(std::io::Cursor::new("y\n"));
+        assert!(cli.execute_with_context(&mut ctx).is_err());
+        assert!(ran.lock().unwrap().is_empty());
+
+        // Confirmed
+        let mut ctx = Context::new(vec!["stat".to_string(), "web".to_string()]);
+        ctx.set_input(std::io::Cursor::new("y\n"));
+        cli.execute_with_context(&mut ctx).unwrap();
+
+        // An empty answer takes the default
+        let mut ctx = Context::new(vec!["stat".to_string()]);
+        ctx.set_input(std::io::Cursor::new("\n"));
+        cli.execute_with_context(&mut ctx).unwrap();
+
+        assert_eq!(*ran.lock().unwrap(), vec!["start web", "start "]);
+    }
+
+    #[test]
+    fn test_flag_changed() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+
+        let cli = CommandBuilder::new("app")
+            .flag(Flag::int("port").default_int(8080))
+            .flag(Flag::string("host").default(FlagValue::String("localhost".into())))
+            .run(move |ctx| {
+                *seen_clone.lock().unwrap() = ["port", "host", "unknown"]
+                    .iter()
+                    .map(|name| (ctx.flag(name).cloned(), ctx.flag_changed(name)))
+                    .collect();
+                Ok(())
+            })
+            .build();
+
+        cli.execute(vec!["--port".to_string(), "8080".to_string()])
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                // Provided, even though it matches the default
+                (Some("8080".to_string()), true),
+                // Defaulted
+                (Some("localhost".to_string()), false),
+                // Not a flag at all
+                (None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_required_global_flag_at_nested_command() {
+        let cli = CommandBuilder::new("kubectl")
+            .flag(Flag::string("kubeconfig").required())
+            .flag(Flag::string("namespace"))
+            .subcommand(
+                CommandBuilder::new("config")
+                    .subcommand(
+                        CommandBuilder::new("view")
+                            .flag(Flag::bool("raw").constraint(FlagConstraint::Requires(vec![
+                                "namespace".to_string(),
+                            ])))
+                            .run(|_| Ok(()))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        match cli.execute(vec!["config".to_string(), "view".to_string()]) {
+            Err(Error::FlagParsing { message, flag, .. }) => {
+                assert_eq!(message, "Required flag '--kubeconfig' not provided");
+                assert_eq!(flag.as_deref(), Some("kubeconfig"));
+            }
+            other => panic!("expected missing --kubeconfig, got {other:?}"),
+        }
+
+        // Provided before or after the subcommand path
+        cli.execute(
+            ["--kubeconfig", "k.yaml", "config", "view"]
+                .map(String::from)
+                .to_vec(),
+        )
+        .unwrap();
+        cli.execute(
+            ["config", "view", "--kubeconfig", "k.yaml"]
+                .map(String::from)
+                .to_vec(),
+        )
+        .unwrap();
+
+        // A constraint on an inherited flag
+        let result = cli.execute(
+            ["config", "view", "--kubeconfig", "k.yaml", "--raw"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert!(matches!(result, Err(Error::FlagParsing { .. })));
+
+        // The leaf's constraint is satisfied by a flag owned by the root
+        cli.execute(
+            [
+                "--namespace",
+                "dev",
+                "config",
+                "view",
+                "--kubeconfig",
+                "k.yaml",
+                "--raw",
+            ]
+            .map(String::from)
+            .to_vec(),
+        )
+        .unwrap();
+    }
+
+    fn multicall_applets(log: &Arc<Mutex<Vec<String>>>) -> HashMap<String, Command> {
+        let mut applets = HashMap::new();
+        for name in ["ls", "cat"] {
+            let log = log.clone();
+            applets.insert(
+                name.to_string(),
+                CommandBuilder::new(name)
+                    .flag(Flag::bool("all").short('a'))
+                    .run(move |ctx| {
+                        log.lock().unwrap().push(format!(
+                            "{name} all={} args={:?}",
+                            ctx.flag("all").is_some(),
+                            ctx.args()
+                        ));
+                        Ok(())
+                    })
+                    .build(),
+            );
+        }
+        applets
+    }
+
+    #[test]
+    fn test_multicall_dispatch_by_basename() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        Command::new("toolbox")
+            .dispatch_multicall(
+                multicall_applets(&log),
+                vec![
+                    "/usr/bin/ls".to_string(),
+                    "dir".to_string(),
+                    "-a".to_string(),
+                ],
+            )
+            .unwrap();
+        Command::new("toolbox")
+            .dispatch_multicall(
+                multicall_applets(&log),
+                vec!["cat".to_string(), "file.txt".to_string()],
+            )
+            .unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "ls all=true args=[\"dir\"]".to_string(),
+                "cat all=false args=[\"file.txt\"]".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multicall_umbrella_fallback() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        Command::new("toolbox")
+            .dispatch_multicall(
+                multicall_applets(&log),
+                vec!["./toolbox".to_string(), "ls".to_string(), "-a".to_string()],
+            )
+            .unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["ls all=true args=[]".to_string()]
+        );
+
+        // Unknown applet under the umbrella name reports a missing command
+        let result = Command::new("toolbox").dispatch_multicall(
+            multicall_applets(&log),
+            vec!["toolbox".to_string(), "rm".to_string()],
+        );
+        assert!(matches!(result, Err(Error::CommandNotFound { .. })));
+    }
+
+    #[test]
+    fn test_repeated_slice_flags_accumulate() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("deploy")
+            .flag(Flag::string_slice("label").short('l'))
+            .flag(Flag::string_slice("port"))
+            .flag(Flag::new("env"))
+            .run(move |ctx| {
+                let mut seen = seen_run.lock().unwrap();
+                seen.push(format!("{:?}", ctx.flag_pairs_checked("label")?));
+                seen.push(format!("{:?}", ctx.flag_int_slice_checked("port")?));
+                seen.push(ctx.flag("env").unwrap().clone());
+                Ok(())
+            })
+            .build();
+
+        let args = [
+            "--label",
+            "env=prod",
+            "-l",
+            "tier=web",
+            "--label=a=b",
+            "--port",
+            "80",
+            "--port",
+            "443",
+            "--env",
+            "dev",
+            "--env",
+            "prod",
+        ];
+        cmd.execute(args.map(String::from).to_vec()).unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                r#"[("env", "prod"), ("tier", "web"), ("a", "b")]"#.to_string(),
+                "[80, 443]".to_string(),
+                // Non-slice flags keep the last value
+                "prod".to_string(),
+            ]
+        );
+
+        let result = cmd.execute(
+            ["--port", "80", "--port", "https"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert!(matches!(result, Err(Error::FlagParsing { .. })));
+    }
+
+    #[test]
+    fn test_count_flag() {
+        let counts = Arc::new(Mutex::new(Vec::new()));
+        let counts_run = Arc::clone(&counts);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::count("verbose").short('v').usage("More output"))
+            .flag(Flag::bool("quiet").short('q'))
+            .run(move |ctx| {
+                counts_run
+                    .lock()
+                    .unwrap()
+                    .push((ctx.flag_count("verbose"), ctx.args().to_vec()));
+                Ok(())
+            })
+            .build();
+
+        for args in [
+            vec!["-vvv"],
+            vec!["-v", "-v", "-v"],
+            vec!["-vv", "--verbose", "-qv", "file"],
+            vec!["--verbose=5"],
+            vec!["file"],
+        ] {
+            cmd.execute(args.into_iter().map(String::from).collect())
+                .unwrap();
+        }
+        let file = vec!["file".to_string()];
+        assert_eq!(
+            *counts.lock().unwrap(),
+            vec![
+                (Some(3), vec![]),
+                (Some(3), vec![]),
+                (Some(4), file.clone()),
+                (Some(5), vec![]),
+                (None, file),
+            ]
+        );
+
+        assert!(cmd.execute(vec!["--verbose=lots".to_string()]).is_err());
+
+        // No value placeholder in help
+        let _no_color = no_color();
+        let help = cmd.help_text();
+        let line = help.lines().find(|l| l.contains("More output")).unwrap();
+        assert!(line.trim_start().starts_with("-v, --verbose  "));
+    }
+
+    #[test]
+    fn test_require_confirmation() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let ran_clone = Arc::clone(&ran);
+        let cmd = CommandBuilder::new("delete")
+            .require_confirmation("This deletes the cluster.")
+            .run(move |ctx| {
+                ran_clone.lock().unwrap().push(ctx.args().join(" "));
+                Ok(())
+            })
+            .build();
+
+        // Anything but yes or the first argument declines
+        for answer in ["y\n", "staging\n", ""] {
+            let mut ctx = Context::new(vec!["prod".to_string()]);
+            ctx.set_input(std::io::Cursor::new(answer));
+            assert!(matches!(
+                cmd.execute_with_context(&mut ctx),
+                Err(Error::Validation(_))
+            ));
+        }
+        assert!(ran.lock().unwrap().is_empty());
+
+        for answer in ["yes\n", "prod\n"] {
+            let mut ctx = Context::new(vec!["prod".to_string()]);
+            ctx.set_input(std::io::Cursor::new(answer));
+            cmd.execute_with_context(&mut ctx).unwrap();
+        }
+        assert_eq!(*ran.lock().unwrap(), vec!["prod", "prod"]);
+
+        // No input is read when --yes is given
+        ran.lock().unwrap().clear();
+        cmd.execute(["prod", "--yes"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["staging", "-y"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["prod", "staging"]);
+
+        // A user's --yes and -y are kept
+        let cmd = CommandBuilder::new("delete")
+            .flag(Flag::bool("yes").usage("Assume yes everywhere"))
+            .flag(Flag::bool("yaml").short('y'))
+            .require_confirmation("This deletes the cluster.")
+            .build();
+        assert_eq!(cmd.flags()["yes"].usage, "Assume yes everywhere");
+        assert_eq!(cmd.flags()["yaml"].short, Some('y'));
+        let cmd = CommandBuilder::new("delete")
+            .flag(Flag::bool("yaml").short('y'))
+            .require_confirmation("This deletes the cluster.")
+            .build();
+        assert_eq!(cmd.flags()["yes"].short, None);
+        assert_eq!(cmd.flags()["yaml"].short, Some('y'));
+    }
+
+    #[test]
+    fn test_bool_flag_explicit_values() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose").short('v').default_bool(true))
+            .flag(Flag::bool("color").short('c'))
+            .run(move |ctx| {
+                seen_run.lock().unwrap().push(format!(
+                    "{:?} {:?} {:?}",
+                    ctx.flag_bool("verbose"),
+                    ctx.flag_bool("color"),
+                    ctx.args()
+                ));
+                Ok(())
+            })
+            .build();
+
+        for args in [
+            vec!["--verbose=false"],
+            vec!["--verbose", "false"],
+            vec!["-v=false"],
+            vec!["-v", "no"],
+            vec!["-cv=0"],
+            vec!["-v", "file.txt"],
+        ] {
+            cmd.execute(args.into_iter().map(String::from).collect())
+                .unwrap();
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "Some(false) None []",
+                "Some(false) None []",
+                "Some(false) None []",
+                "Some(false) None []",
+                "Some(false) Some(true) []",
+                // Words that aren't booleans stay arguments
+                r#"Some(true) None ["file.txt"]"#,
+            ]
+        );
+
+        assert!(matches!(
+            cmd.execute(vec!["-v=maybe".to_string()]),
+            Err(Error::FlagParsing { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_tree_reports_nested_problems() {
+        let app = CommandBuilder::new("app")
+            .flag(Flag::string("namespace").short('n'))
+            .subcommand(
+                CommandBuilder::new("db")
+                    .subcommand(CommandBuilder::new("migrate").aliases(vec!["m"]).build())
+                    .subcommand(CommandBuilder::new("m").build())
+                    .subcommand(
+                        CommandBuilder::new("seed")
+                            .flag(Flag::int("count").short('c'))
+                            .flag(Flag::bool("clean").short('c'))
+                            .flag(Flag::range("batch", 100, 1))
+                            .flag(Flag::string("file").required().default_str("seed.sql"))
+                            .flag(
+                                Flag::bool("force")
+                                    .constraint(FlagConstraint::Requires(vec![
+                                        "namespace".to_string(),
+                                        "confirm".to_string(),
+                                    ]))
+                                    .constraint(FlagConstraint::RequiredIf("dry".to_string())),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let problems = app.validate_tree().unwrap_err();
+        assert_eq!(
+            problems,
+            vec![
+                BuildError::DuplicateCommandName {
+                    path: "app db".to_string(),
+                    name: "m".to_string(),
+                },
+                BuildError::DuplicateShort {
+                    path: "app db seed".to_string(),
+                    short: 'c',
+                    flags: vec!["clean".to_string(), "count".to_string()],
+                },
+                BuildError::InvertedRange {
+                    path: "app db seed".to_string(),
+                    flag: "batch".to_string(),
+                    min: 100,
+                    max: 1,
+                },
+                BuildError::RequiredWithDefault {
+                    path: "app db seed".to_string(),
+                    flag: "file".to_string(),
+                },
+                // --namespace is inherited from the root, so it's fine
+                BuildError::DanglingConstraint {
+                    path: "app db seed".to_string(),
+                    flag: "force".to_string(),
+                    target: "confirm".to_string(),
+                },
+                BuildError::DanglingConstraint {
+                    path: "app db seed".to_string(),
+                    flag: "force".to_string(),
+                    target: "dry".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            problems[1].to_string(),
+            "app db seed: short flag '-c' is used by --clean, --count"
+        );
+
+        assert!(CommandBuilder::new("ok").build().validate_tree().is_ok());
+    }
+
+    #[test]
+    fn test_flag_env_precedence() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("kubectl")
+            .explain(true)
+            .flag(
+                Flag::string("namespace")
+                    .env("FLAG_RS_TEST_NAMESPACE")
+                    .default_str("default")
+                    .usage("Namespace to use"),
+            )
+            .flag(Flag::int("replicas").env("FLAG_RS_TEST_REPLICAS"))
+            .run(move |ctx| {
+                seen_run
+                    .lock()
+                    .unwrap()
+                    .push(ctx.flag("namespace").unwrap().clone());
+                Ok(())
+            })
+            .build();
+
+        // Default when neither is given
+        cmd.execute(vec![]).unwrap();
+
+        unsafe { std::env::set_var("FLAG_RS_TEST_NAMESPACE", "from-env") };
+        cmd.execute(vec![]).unwrap();
+        // The command line beats the environment
+        cmd.execute(["--namespace", "from-flag"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["default", "from-env", "from-flag"]
+        );
+
+        let mut ctx = Context::new(vec!["--explain".to_string()]);
+        cmd.execute_with_context(&mut ctx).unwrap();
+        let plan = ctx.get::<ExplainPlan>().unwrap();
+        assert_eq!(plan.flags[0].source, FlagSource::Environment);
+        unsafe { std::env::remove_var("FLAG_RS_TEST_NAMESPACE") };
+
+        // Environment values are validated
+        unsafe { std::env::set_var("FLAG_RS_TEST_REPLICAS", "many") };
+        let result = cmd.execute(vec![]);
+        unsafe { std::env::remove_var("FLAG_RS_TEST_REPLICAS") };
+        assert!(matches!(result, Err(Error::FlagParsing { .. })));
+
+        let _no_color = no_color();
+        let help = cmd.help_text();
+        let line = help.lines().find(|l| l.contains("--replicas")).unwrap();
+        assert!(line.ends_with("int        [env: FLAG_RS_TEST_REPLICAS]"));
+    }
+
+    #[test]
+    fn test_version_flag() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let ran_clone = Arc::clone(&ran);
+        let mut cmd = CommandBuilder::new("app")
+            .version("1.2.3")
+            .run(move |ctx| {
+                ran_clone.lock().unwrap().push(ctx.args().join(" "));
+                Ok(())
+            })
+            .build();
+        cmd.add_command(CommandBuilder::new("sub").run(|_| Ok(())).build());
+
+        cmd.execute(vec!["--version".to_string()]).unwrap();
+        cmd.execute(vec!["-V".to_string()]).unwrap();
+        assert!(ran.lock().unwrap().is_empty());
+
+        // Only the root reports a version
+        let result = cmd.execute(["sub", "--version"].map(String::from).to_vec());
+        assert!(result.is_ok());
+        assert!(ran.lock().unwrap().is_empty());
+
+        // Without a version it's an unknown flag like any other
+        let cmd = CommandBuilder::new("app").run(|_| Ok(())).build();
+        let err = cmd.execute(vec!["--version".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("unknown flag --version"));
+    }
+
+    #[test]
+    fn test_version_short_defers_to_user_flag() {
+        let verbose = Arc::new(Mutex::new(false));
+        let verbose_run = Arc::clone(&verbose);
+        let cmd = CommandBuilder::new("app")
+            .version("1.2.3")
+            .flag(Flag::bool("verbose").short('V'))
+            .run(move |ctx| {
+                *verbose_run.lock().unwrap() = ctx.flag_bool("verbose").unwrap_or(false);
+                Ok(())
+            })
+            .build();
+
+        cmd.execute(vec!["-V".to_string()]).unwrap();
+        assert!(*verbose.lock().unwrap());
+
+        // --version is still built in
+        *verbose.lock().unwrap() = false;
+        cmd.execute(vec!["--version".to_string()]).unwrap();
+        assert!(!*verbose.lock().unwrap());
+    }
+
+    #[test]
+    fn test_long_flags_align_with_and_without_shorts() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose").short('v').usage("Verbose output"))
+            .flag(Flag::string("output").usage("Output file"))
+            .flag(Flag::choice("format", &["json", "yaml"]).short('f'))
+            .flag(Flag::range("level", 1, 3).usage("Level"))
+            .build();
+
+        let help = cmd.help_text();
+        let columns: Vec<usize> = ["--verbose", "--output", "--format", "--level"]
+            .iter()
+            .map(|name| {
+                let line = help.lines().find(|l| l.contains(name)).unwrap();
+                crate::terminal::visible_width(&line[..line.find(name).unwrap()])
+            })
+            .collect();
+        assert_eq!(columns, vec![10, 10, 10, 10]);
+    }
+
+    #[test]
+    fn test_flag_prefix_styles() {
+        for (style, runs, expected) in [
+            (
+                FlagPrefixStyle::Windows,
+                vec![
+                    vec!["/verbose"],
+                    vec!["/port:8080"],
+                    vec!["/v", "/p", "9090", "/tmp/out.txt"],
+                    vec!["--verbose", "-p"],
+                ],
+                vec![
+                    "true None []",
+                    "false Some(8080) []",
+                    r#"true Some(9090) ["/tmp/out.txt"]"#,
+                    r#"false None ["--verbose", "-p"]"#,
+                ],
+            ),
+            (
+                FlagPrefixStyle::Both,
+                vec![
+                    vec!["/verbose", "--port", "8080", "/etc/hosts"],
+                    vec!["-v", "/port:80"],
+                ],
+                vec![r#"true Some(8080) ["/etc/hosts"]"#, "true Some(80) []"],
+            ),
+            // Unix stays the default and leaves slashes alone
+            (
+                FlagPrefixStyle::Unix,
+                vec![vec!["/verbose", "--port=1"]],
+                vec![r#"false Some(1) ["/verbose"]"#],
+            ),
+        ] {
+            let seen = Arc::new(Mutex::new(Vec::new()));
+            let seen_run = Arc::clone(&seen);
+            let cmd = CommandBuilder::new("tool")
+                .flag_prefix_style(style)
+                .flag(Flag::bool("verbose").short('v').usage("Verbose output"))
+                .flag(Flag::int("port").short('p').usage("Port to use"))
+                .run(move |ctx| {
+                    seen_run.lock().unwrap().push(format!(
+                        "{} {:?} {:?}",
+                        ctx.flag_bool("verbose").unwrap_or(false),
+                        ctx.flag_int("port"),
+                        ctx.args()
+                    ));
+                    Ok(())
+                })
+                .build();
+
+            for args in runs {
+                cmd.execute(args.into_iter().map(String::from).collect())
+                    .unwrap();
+            }
+            assert_eq!(*seen.lock().unwrap(), expected, "{style:?}");
+
+            if style == FlagPrefixStyle::Windows {
+                assert!(cmd.execute(vec!["/port:many".to_string()]).is_err());
+
+                let _no_color = no_color();
+                let help = cmd.help_text();
+                assert!(help.contains("/v, /verbose"));
+                assert!(help.contains("/p, /port int"));
+                assert!(help.contains("[command] /help"));
+                assert!(!help.contains("--port"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_hidden_commands_and_flags() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let ran_clone = Arc::clone(&ran);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose").usage("Verbose output"))
+            .flag(Flag::bool("trace-io").short('T').usage("Trace IO").hidden())
+            .subcommand(CommandBuilder::new("status").short("Show status").build())
+            .subcommand(
+                CommandBuilder::new("debug-dump")
+                    .short("Dump internal state")
+                    .hidden()
+                    .run(move |ctx| {
+                        ran_clone
+                            .lock()
+                            .unwrap()
+                            .push(ctx.flag_bool("trace-io").unwrap_or(false));
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        // Hidden items still run and parse
+        cmd.execute(["-T", "debug-dump"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["debug-dump", "--trace-io"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec![true, true]);
+
+        let help = cmd.help_text();
+        assert!(help.contains("status"));
+        assert!(!help.contains("debug-dump"));
+        assert!(!help.contains("trace-io"));
+
+        assert!(!complete(&cmd, &["d"]).contains(&"debug-dump".to_string()));
+        assert!(complete(&cmd, &[""]).contains(&"status".to_string()));
+        assert!(!complete(&cmd, &[""]).contains(&"debug-dump".to_string()));
+        assert_eq!(complete(&cmd, &["--"]), vec!["--verbose"]);
+        assert!(complete(&cmd, &["-"]).is_empty());
+
+        // Mistyped names aren't matched against hidden commands
+        match cmd.execute(vec!["debug-dum".to_string()]) {
+            Err(Error::CommandNotFound { suggestions, .. }) => assert!(suggestions.is_empty()),
+            other => panic!("expected CommandNotFound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deprecation_warnings() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let warnings_clone = Arc::clone(&warnings);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::string("out").deprecated("use --output"))
+            .flag(Flag::string("output"))
+            .subcommand(
+                CommandBuilder::new("rm")
+                    .deprecated("use 'app delete'")
+                    .arg_completion(|_ctx, prefix| {
+                        Ok(CompletionResult::new().extend(
+                            ["alpha", "beta"]
+                                .iter()
+                                .filter(|name| name.starts_with(prefix))
+                                .map(|name| (*name).to_string()),
+                        ))
+                    })
+                    .run(move |ctx| {
+                        warnings_clone.lock().unwrap().push(
+                            ctx.get::<DeprecationWarnings>()
+                                .cloned()
+                                .unwrap_or_default(),
+                        );
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        // The flag is given twice, at two levels, but warned about once
+        cmd.execute(["--out", "a", "rm", "--out=b"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["rm", "--output", "c"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![
+                DeprecationWarnings(vec![
+                    "flag --out is deprecated: use --output".to_string(),
+                    "command rm is deprecated: use 'app delete'".to_string(),
+                ]),
+                DeprecationWarnings(vec![
+                    "command rm is deprecated: use 'app delete'".to_string()
+                ]),
+            ]
+        );
+
+        // Completion doesn't run the command, so nothing is warned about
+        let completions = complete(&cmd, &["--out", "a", "rm", "a"]);
+        assert_eq!(completions[0], "alpha");
+        assert!(!completions.iter().any(|line| line.contains("deprecated")));
+        assert_eq!(warnings.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_run_handler_custom_errors() {
+        #[derive(Debug)]
+        struct QuotaExceeded(u32);
+
+        impl std::fmt::Display for QuotaExceeded {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "quota of {} exceeded", self.0)
+            }
+        }
+
+        impl std::error::Error for QuotaExceeded {}
+
+        fn reserve(
+            count: &str,
+        ) -> std::result::Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+            let count: u32 = count.parse()?;
+            if count > 10 {
+                return Err(Box::new(QuotaExceeded(10)));
+            }
+            Ok(count)
+        }
+
+        let cmd = CommandBuilder::new("app")
+            .subcommand(
+                CommandBuilder::new("reserve")
+                    .run(|ctx| {
+                        reserve(ctx.args().first().map_or("", String::as_str))?;
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .subcommand(
+                CommandBuilder::new("check")
+                    .run(|_ctx| Err(Error::custom(QuotaExceeded(3))))
+                    .build(),
+            )
+            .build();
+
+        assert!(
+            cmd.execute(["reserve", "4"].map(String::from).to_vec())
+                .is_ok()
+        );
+
+        let err = cmd
+            .execute(["reserve", "40"].map(String::from).to_vec())
+            .unwrap_err();
+        match err {
+            Error::Custom(inner) => {
+                assert_eq!(inner.to_string(), "quota of 10 exceeded");
+                assert!(inner.is::<QuotaExceeded>());
+            }
+            other => panic!("expected a custom error, got {other:?}"),
+        }
+
+        let err = cmd
+            .execute(["reserve", "lots"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(matches!(err, Error::Custom(inner) if inner.is::<std::num::ParseIntError>()));
+
+        let err = cmd
+            .execute(["check"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(matches!(err, Error::Custom(inner) if inner.to_string() == "quota of 3 exceeded"));
+    }
+
+    #[test]
+    fn test_error_handler_rewrites_message() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("app")
+            .subcommand(CommandBuilder::new("start").run(|_ctx| Ok(())).build())
+            .error_handler(|err| match err {
+                Error::CommandNotFound { command, .. } => Some(format!(
+                    "app has no '{command}' command\nReport problems at https://example.com/issues"
+                )),
+                _ => None,
+            })
+            .build();
+
+        let err = cmd
+            .execute(["launch"].map(String::from).to_vec())
+            .unwrap_err();
+        assert_eq!(
+            cmd.format_error(&err),
+            "app has no 'launch' command\nReport problems at https://example.com/issues"
+        );
+
+        // Errors the handler passes on keep their default message
+        let err = Error::Validation("bad input".to_string());
+        assert_eq!(cmd.format_error(&err), err.to_string());
+
+        let plain = CommandBuilder::new("app").build();
+        let err = plain
+            .execute(["launch"].map(String::from).to_vec())
+            .unwrap_err();
+        assert_eq!(plain.format_error(&err), err.to_string());
+    }
+
+    #[test]
+    fn test_exclusive_flag_group() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("export")
+            .flag(Flag::bool("json"))
+            .flag(Flag::bool("yaml"))
+            .flag(Flag::bool("xml"))
+            .flag_group_exclusive(vec!["json", "yaml", "xml"])
+            .subcommand(CommandBuilder::new("all").run(|_ctx| Ok(())).build())
+            .run(|_ctx| Ok(()))
+            .build();
+
+        assert!(cmd.execute(vec![]).is_ok());
+        assert!(cmd.execute(["--yaml"].map(String::from).to_vec()).is_ok());
+
+        let err = cmd
+            .execute(["--xml", "--json"].map(String::from).to_vec())
+            .unwrap_err();
+        match err {
+            Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            } => {
+                assert_eq!(
+                    message,
+                    "Flags '--json', '--yaml' and '--xml' are mutually exclusive, \
+                     but '--json' and '--xml' were given"
+                );
+                assert_eq!(flag.as_deref(), Some("json"));
+                assert_eq!(
+                    suggestions,
+                    vec!["use only one of '--json', '--yaml' or '--xml'"]
+                );
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        // The group applies to subcommands too
+        assert!(
+            cmd.execute(["--json", "all", "--yaml"].map(String::from).to_vec())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_required_one_flag_group() {
+        let cmd = CommandBuilder::new("login")
+            .flag(Flag::string("token"))
+            .flag(Flag::string("password"))
+            .flag_group_required_one(vec!["token", "password"])
+            .run(|_ctx| Ok(()))
+            .build();
+
+        let err = cmd.execute(vec![]).unwrap_err();
+        match err {
+            Error::FlagParsing {
+                message,
+                suggestions,
+                ..
+            } => {
+                assert_eq!(message, "One of '--token' or '--password' is required");
+                assert_eq!(suggestions, vec!["add '--token' or '--password'"]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        assert!(
+            cmd.execute(["--password", "hunter2"].map(String::from).to_vec())
+                .is_ok()
+        );
+        assert!(
+            cmd.execute(
+                ["--token=abc", "--password", "x"]
+                    .map(String::from)
+                    .to_vec()
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_negative_numbers_as_flag_values() {
+        let cmd = CommandBuilder::new("seek")
+            .flag(Flag::int("offset").short('o'))
+            .flag(Flag::float("ratio"))
+            .flag(Flag::new("level").value_type(FlagType::Range(-3, 3)))
+            .flag(Flag::bool("verbose"))
+            .build();
+        let parse = |words: &[&str]| {
+            let args: Vec<String> = words.iter().map(ToString::to_string).collect();
+            cmd.parse_flags(&args, &[]).unwrap()
+        };
+
+        let (flags, remaining) = parse(&["--offset", "-5"]);
+        assert_eq!(flags.get("offset").map(String::as_str), Some("-5"));
+        assert!(remaining.is_empty());
+
+        let (flags, _) = parse(&["-o", "-12"]);
+        assert_eq!(flags.get("offset").map(String::as_str), Some("-12"));
+
+        let (flags, remaining) = parse(&["--ratio", "-0.5"]);
+        assert_eq!(flags.get("ratio").map(String::as_str), Some("-0.5"));
+        assert!(remaining.is_empty());
+
+        let (flags, _) = parse(&["--level", "-2"]);
+        assert_eq!(flags.get("level").map(String::as_str), Some("-2"));
+
+        // A bool flag doesn't take a number, so it stays a separate word
+        let (flags, remaining) = parse(&["--verbose", "-5"]);
+        assert_eq!(flags.get("verbose").map(String::as_str), Some("true"));
+        assert_eq!(remaining, vec!["-5"]);
+
+        // Words that aren't numbers are still flags
+        let (flags, _) = parse(&["--offset", "--verbose"]);
+        assert_eq!(flags.get("verbose").map(String::as_str), Some("true"));
+    }
+
+    #[test]
+    fn test_together_flag_group() {
+        let cmd = CommandBuilder::new("connect")
+            .flag(Flag::string("user"))
+            .flag(Flag::string("password"))
+            .flag(Flag::string("host"))
+            .flag(Flag::bool("tls").requires_one_of(["cert-file", "cert-inline"]))
+            .flag(Flag::string("cert-file"))
+            .flag(Flag::string("cert-inline"))
+            .flag_group_together(vec!["user", "password", "host"])
+            .run(|_ctx| Ok(()))
+            .build();
+
+        let args = |words: &[&str]| words.iter().map(ToString::to_string).collect();
+        assert!(cmd.execute(vec![]).is_ok());
+        assert!(
+            cmd.execute(args(&["--user", "me", "--password", "x", "--host", "h"]))
+                .is_ok()
+        );
+
+        match cmd.execute(args(&["--password", "x"])).unwrap_err() {
+            Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            } => {
+                assert_eq!(
+                    message,
+                    "Flags '--user', '--password' and '--host' must be used together, \
+                     but '--user' and '--host' were not given"
+                );
+                assert_eq!(flag.as_deref(), Some("user"));
+                assert_eq!(suggestions, vec!["add '--user' and '--host'"]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        assert!(cmd.execute(args(&["--tls"])).is_err());
+        assert!(
+            cmd.execute(args(&["--tls", "--cert-inline", "PEM"]))
+                .is_ok()
+        );
+        assert!(cmd.execute(args(&["--cert-file", "a.pem"])).is_ok());
+    }
+
+    #[test]
+    fn test_require_flag_when_depends_on_positional() {
+        let cmd = CommandBuilder::new("kubectl")
+            .subcommand(
+                CommandBuilder::new("create")
+                    .flag(Flag::string("image"))
+                    .flag(Flag::string("replicas"))
+                    .flag(Flag::string("port"))
+                    .require_flag_when(|ctx| match ctx.args().first().map(String::as_str) {
+                        Some("deployment") => vec!["replicas".to_string(), "image".to_string()],
+                        Some("service") => vec!["port".to_string()],
+                        _ => Vec::new(),
+                    })
+                    .run(|_ctx| Ok(()))
+                    .build(),
+            )
+            .build();
+
+        let args = |words: &[&str]| words.iter().map(ToString::to_string).collect();
+        assert!(cmd.execute(args(&["create", "namespace"])).is_ok());
+        assert!(
+            cmd.execute(args(&["create", "service", "--port", "80"]))
+                .is_ok()
+        );
+        assert!(
+            cmd.execute(args(&[
+                "create",
+                "deployment",
+                "--image",
+                "nginx",
+                "--replicas",
+                "2"
+            ]))
+            .is_ok()
+        );
+
+        match cmd
+            .execute(args(&["create", "deployment", "web", "--port", "80"]))
+            .unwrap_err()
+        {
+            Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            } => {
+                assert_eq!(
+                    message,
+                    "Required flags '--image' and '--replicas' not provided, \
+                     needed for 'kubectl create deployment web'"
+                );
+                assert_eq!(flag.as_deref(), Some("image"));
+                assert_eq!(suggestions, vec!["add '--image' and '--replicas'"]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        match cmd.execute(args(&["create", "service"])).unwrap_err() {
+            Error::FlagParsing { message, .. } => assert_eq!(
+                message,
+                "Required flag '--port' not provided, needed for 'kubectl create service'"
+            ),
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_negatable_flag_completion() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("color").default_bool(true).negatable())
+            .flag(Flag::bool("cache"))
+            .flag(Flag::bool("no-op"))
+            .run(|_ctx| Ok(()))
+            .build();
+
+        assert_eq!(complete(&cmd, &["--no-c"]), vec!["--no-color"]);
+        let names = complete(&cmd, &["--n"]);
+        assert!(names.iter().any(|line| line.starts_with("--no-op")));
+        assert!(names.iter().any(|line| line.starts_with("--no-color")));
+        // Only flags made negatable get a `--no-` form
+        assert!(!names.iter().any(|line| line.starts_with("--no-cache")));
+        // The `--no-` forms stay out of the way until asked for
+        assert!(
+            !complete(&cmd, &["--"])
+                .iter()
+                .any(|line| line.starts_with("--no-color"))
+        );
+    }
+
+    #[test]
+    fn test_negatable_flag_parsing() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("color").default_bool(true).negatable())
+            .flag(Flag::bool("no-op"))
+            .subcommand(
+                CommandBuilder::new("show")
+                    .run(move |ctx| {
+                        seen_clone
+                            .lock()
+                            .unwrap()
+                            .push((ctx.flag_bool("color"), ctx.flag_bool("no-op")));
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        cmd.execute(["show"].map(String::from).to_vec()).unwrap();
+        cmd.execute(["show", "--no-color"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["--no-color", "show", "--color"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["show", "--no-op"].map(String::from).to_vec())
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (Some(true), None),
+                (Some(false), None),
+                (Some(true), None),
+                (Some(true), Some(true)),
+            ]
+        );
+
+        let _no_color = no_color();
+        assert!(
+            cmd.render_help(&[], &HashMap::new())
+                .contains("--[no-]color")
+        );
+    }
+
+    #[test]
+    fn test_profile_sets_flags() {
+        type Seen = Arc<Mutex<Vec<(Option<i64>, Option<bool>, Option<String>)>>>;
+
+        let seen: Seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::int("workers").default_int(2))
+            .flag(Flag::bool("cache"))
+            .profile("fast", &[("workers", "16"), ("cache", "true")])
+            .profile("safe", &[("workers", "1"), ("mode", "strict")])
+            .subcommand(
+                CommandBuilder::new("build")
+                    .flag(Flag::choice("mode", &["strict", "lax"]))
+                    .run(move |ctx| {
+                        seen_clone.lock().unwrap().push((
+                            ctx.flag_int("workers"),
+                            ctx.flag_bool("cache"),
+                            ctx.flag("mode").cloned(),
+                        ));
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        cmd.execute(["--profile", "fast", "build"].map(String::from).to_vec())
+            .unwrap();
+        // Explicit flags win over the profile, wherever they are given
+        cmd.execute(
+            ["--profile", "fast", "build", "--workers", "4"]
+                .map(String::from)
+                .to_vec(),
+        )
+        .unwrap();
+        cmd.execute(["build", "--profile=safe"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["build"].map(String::from).to_vec()).unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (Some(16), Some(true), None),
+                (Some(4), Some(true), None),
+                (Some(1), None, Some("strict".to_string())),
+                (Some(2), None, None),
+            ]
+        );
+
+        assert!(
+            cmd.execute(["--profile", "slow", "build"].map(String::from).to_vec())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_profile_completion_describes_settings() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::int("workers"))
+            .flag(Flag::bool("cache"))
+            .profile("fast", &[("workers", "16"), ("cache", "true")])
+            .profile("safe", &[("workers", "1")])
+            .subcommand(CommandBuilder::new("build").run(|_ctx| Ok(())).build())
+            .build();
+        let build = cmd.find_subcommand("build").unwrap();
+        let ctx = Context::new(vec![]);
+
+        let result = build
+            .complete_flag_value(&[&cmd], "profile", &ctx, "")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.values, vec!["fast", "safe"]);
+        assert_eq!(
+            result.descriptions,
+            vec!["workers=16, cache=true", "workers=1"]
+        );
+
+        let result = cmd
+            .complete_flag_value(&[], "profile", &ctx, "s")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.values, vec!["safe"]);
+
+        // Through the completion request too
+        let values = complete(&cmd, &["build", "--profile", "f"]);
+        assert_eq!(values, vec!["fast"]);
+    }
+
+    #[test]
+    fn test_profile_values_are_validated() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::int("workers"))
+            .profile("broken", &[("workers", "many")])
+            .run(|_ctx| Ok(()))
+            .build();
+
+        let err = cmd
+            .execute(["--profile", "broken"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(matches!(err, Error::FlagParsing { .. }), "{err:?}");
+
+        // An explicit value means the profile's is never used
+        assert!(
+            cmd.execute(
+                ["--profile", "broken", "--workers", "3"]
+                    .map(String::from)
+                    .to_vec()
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_tree_checks_profiles() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::int("workers"))
+            .profile("fast", &[("workers", "16"), ("cache", "true")])
+            .subcommand(
+                CommandBuilder::new("build")
+                    .flag(Flag::bool("release"))
+                    .build(),
+            )
+            .profile("ship", &[("release", "true")])
+            .build();
+        assert_eq!(
+            cmd.validate_tree(),
+            Err(vec![BuildError::UnknownProfileFlag {
+                path: "app".to_string(),
+                profile: "fast".to_string(),
+                flag: "cache".to_string(),
+            }])
+        );
+
+        // A profile flag of the command's own is kept, and reported
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::string("profile").usage("AWS profile"))
+            .flag(Flag::int("workers"))
+            .profile("fast", &[("workers", "16")])
+            .build();
+        assert_eq!(cmd.flags()["profile"].usage, "AWS profile");
+        assert_eq!(
+            cmd.validate_tree(),
+            Err(vec![BuildError::ProfileFlagTaken {
+                path: "app".to_string(),
+            }])
+        );
+
+        // Likewise when it's added after the profiles
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::int("workers"))
+            .profile("fast", &[("workers", "16")])
+            .flag(Flag::string("profile"))
+            .build();
+        assert!(cmd.validate_tree().is_err());
+    }
+
+    #[test]
+    fn test_named_positionals_in_usage() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("get")
+            .positional("resource")
+            .positional("name")
+            .build();
+        assert!(
+            cmd.render_help(&[], &HashMap::new())
+                .contains("Usage:\n  get <resource> <name>\n")
+        );
+
+        let cmd = CommandBuilder::new("get")
+            .positional("resource")
+            .positional("name")
+            .args(ArgValidator::RangeArgs(1, 2))
+            .build();
+        let help = cmd.render_help(&[], &HashMap::new());
+        assert!(help.contains("Usage:\n  get <resource> [name]\n"));
+        assert!(!help.contains("<args>"));
+    }
+
+    #[test]
+    fn test_valid_args_complete_by_position() {
+        let cmd = CommandBuilder::new("kubectl")
+            .subcommand(
+                CommandBuilder::new("get")
+                    .positional_with_valid_args("resource", ["pods", "services"])
+                    .positional_with_valid_args("output", ["json", "jsonpath", "yaml"])
+                    .arg_completion(|_ctx, _prefix| {
+                        Ok(CompletionResult::new().add("from-arg-completion"))
+                    })
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(complete(&cmd, &["get", ""])[..2], ["pods", "services"]);
+        assert_eq!(
+            complete(&cmd, &["get", "pods", "js"]),
+            vec!["json", "jsonpath"]
+        );
+        // Flags in between don't count as positions
+        assert_eq!(
+            complete(&cmd, &["get", "--help", "pods", "y"]),
+            vec!["yaml"]
+        );
+        // Past the named positionals the arg completion takes over
+        assert_eq!(
+            complete(&cmd, &["get", "pods", "json", ""])[0],
+            "from-arg-completion"
+        );
+    }
+
+    #[test]
+    fn test_path_positionals_complete_from_filesystem() {
+        let _no_color = no_color();
+
+        let root = std::env::temp_dir().join(format!("flag-rs-positional-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        std::fs::write(root.join("load.txt"), "").unwrap();
+        let root = format!("{}/", root.to_string_lossy());
+
+        let cmd = CommandBuilder::new("myapp")
+            .subcommand(
+                CommandBuilder::new("copy")
+                    .positional_with_kind("source", Positional::File)
+                    .positional_with_kind("dest", Positional::Directory)
+                    .build(),
+            )
+            .build();
+        let prefix = format!("{root}lo");
+        assert_eq!(
+            complete(&cmd, &["copy", &prefix]),
+            vec![format!("{root}load.txt"), format!("{root}logs/")]
+        );
+        // Only directories for the directory positional
+        assert_eq!(
+            complete(&cmd, &["copy", "a.txt", &prefix]),
+            vec![format!("{root}logs/")]
+        );
+
+        let copy = cmd.find_subcommand("copy").unwrap();
+        assert!(
+            copy.render_help(&[&cmd], &HashMap::new())
+                .contains("copy <source:file> <dest:dir>")
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_required_flags_complete_first() {
+        let cmd = CommandBuilder::new("deploy")
+            .flag(Flag::string("env").usage("Target environment").required())
+            .flag(Flag::string("tag").required())
+            .flag(Flag::bool("dry-run").usage("Only print the plan"))
+            .flag(Flag::bool("verbose"))
+            .run(|_ctx| Ok(()))
+            .build();
+
+        let mut result = CompletionResult::new();
+        collect_all_flags_with_descriptions(&cmd, &[], &mut result, "", None);
+        let mut required: Vec<(&str, &str)> = result.values[..2]
+            .iter()
+            .map(String::as_str)
+            .zip(result.descriptions.iter().map(String::as_str))
+            .collect();
+        required.sort_unstable();
+        assert_eq!(
+            required,
+            vec![
+                ("--env", "Target environment (required)"),
+                ("--tag", "(required)")
+            ]
+        );
+        let position = result.values.iter().position(|v| v == "--dry-run").unwrap();
+        assert_eq!(result.descriptions[position], "Only print the plan");
+
+        // Once given, a required flag is listed like any other
+        let mut ctx = Context::new(vec![]);
+        ctx.set_flag("env".to_string(), "prod".to_string());
+        let mut result = CompletionResult::new();
+        collect_all_flags_with_descriptions(&cmd, &[], &mut result, "", Some(&ctx));
+        assert_eq!(result.values[0], "--tag");
+        let position = result.values.iter().position(|v| v == "--env").unwrap();
+        assert_eq!(result.descriptions[position], "Target environment");
+
+        let completions = complete(&cmd, &["--env", "prod", "--"]);
+        assert_eq!(completions[0], "--tag");
+    }
+
+    #[test]
+    fn test_execute_path_runs_inherited_hooks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let record = |events: &Arc<Mutex<Vec<String>>>, event: &'static str| {
+            let events = Arc::clone(events);
+            move |ctx: &mut Context| {
+                let level = ctx.flag("level").cloned().unwrap_or_default();
+                events.lock().unwrap().push(format!("{event} {level}"));
+                Ok(())
+            }
+        };
+
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::string("level"))
+            .persistent_pre_run(record(&events, "app-pre"))
+            .persistent_post_run(record(&events, "app-post"))
+            .subcommand(
+                CommandBuilder::new("db")
+                    .alias("database")
+                    .persistent_pre_run(record(&events, "db-pre"))
+                    .subcommand(
+                        CommandBuilder::new("migrate")
+                            .pre_run(record(&events, "migrate-pre"))
+                            .run(record(&events, "migrate"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        cmd.execute_path(
+            &["database", "migrate"],
+            ["--level", "debug"].map(String::from).to_vec(),
+        )
+        .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "app-pre debug",
+                "db-pre debug",
+                "migrate-pre debug",
+                "migrate debug",
+                "app-post debug",
+            ]
+        );
+
+        let err = cmd.execute_path(&["db", "migrat"], vec![]).unwrap_err();
+        match err {
+            Error::CommandNotFound {
+                command,
+                suggestions,
+            } => {
+                assert_eq!(command, "migrat");
+                assert_eq!(suggestions, vec!["migrate"]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_declared_flag_values_complete() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::choice("env", &["dev", "staging", "prod", "preview"]))
+            .flag(Flag::range("level", 1, 5))
+            .flag(Flag::bool("force"))
+            .flag(Flag::choice("region", &["us", "eu"]))
+            .flag_completion("region", |_ctx, _prefix| {
+                Ok(CompletionResult::new().add("from-completion"))
+            })
+            .run(|_ctx| Ok(()))
+            .build();
+
+        assert_eq!(complete(&cmd, &["--env", "p"]), vec!["prod", "preview"]);
+        assert_eq!(
+            complete(&cmd, &["--env", ""]),
+            vec!["dev", "staging", "prod", "preview"]
+        );
+        assert_eq!(complete(&cmd, &["--env=s"]), vec!["--env=staging"]);
+        assert_eq!(
+            complete(&cmd, &["--level", ""]),
+            vec!["1", "2", "3", "4", "5"]
+        );
+        assert_eq!(complete(&cmd, &["--force=f"]), vec!["--force=false"]);
+        // The word after a bare bool flag is an argument, not its value
+        let after_force = complete(&cmd, &["--force", ""]);
+        assert!(
+            !after_force
+                .iter()
+                .any(|word| word == "true" || word == "false")
+        );
+        // An explicit completion function wins over the declared values
+        assert_eq!(complete(&cmd, &["--region", ""]), vec!["from-completion"]);
+    }
+
+    #[test]
+    fn test_flag_invalid_for_commands() {
+        let _no_color = no_color();
+
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("watch").usage("Watch for changes").constraint(
+                FlagConstraint::InvalidForCommands(vec![
+                    "config".to_string(),
+                    "cluster delete".to_string(),
+                ]),
+            ))
+            .subcommand(CommandBuilder::new("get").run(|_ctx| Ok(())).build())
+            .subcommand(
+                CommandBuilder::new("config")
+                    .subcommand(CommandBuilder::new("view").run(|_ctx| Ok(())).build())
+                    .run(|_ctx| Ok(()))
+                    .build(),
+            )
+            .subcommand(
+                CommandBuilder::new("cluster")
+                    .subcommand(CommandBuilder::new("delete").run(|_ctx| Ok(())).build())
+                    .subcommand(CommandBuilder::new("list").run(|_ctx| Ok(())).build())
+                    .build(),
+            )
+            .build();
+
+        let run = |args: &[&str]| cmd.execute(args.iter().map(|arg| (*arg).to_string()).collect());
+
+        assert!(run(&["--watch", "get"]).is_ok());
+        assert!(run(&["config"]).is_ok());
+        assert!(run(&["--watch", "cluster", "list"]).is_ok());
+
+        let err = run(&["--watch", "config"]).unwrap_err();
+        match err {
+            Error::FlagParsing { message, flag, .. } => {
+                assert_eq!(message, "Flag '--watch' can't be used with 'app config'");
+                assert_eq!(flag.as_deref(), Some("watch"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        // Subcommands of a listed command are covered too
+        assert!(run(&["config", "view", "--watch"]).is_err());
+        assert!(run(&["cluster", "delete", "--watch"]).is_err());
+
+        assert!(
+            cmd.render_help(&[], &HashMap::new())
+                .contains("[not valid with config,")
+        );
+    }
+
+    #[test]
+    fn test_help_template_orders_sections() {
+        let _no_color = no_color();
+        let cmd = CommandBuilder::new("deploy")
+            .short("Deploy the app")
+            .example("deploy --force")
+            .flag(Flag::bool("force").usage("Skip checks"))
+            .subcommand(CommandBuilder::new("status").short("Show status").build())
+            .help_template("{name}: {short}\n\n{examples}\n\n{flags}\n{unknown}\n")
+            .build();
+
+        let help = cmd.render_help(&[], &HashMap::new());
+        assert!(
+            help.starts_with("deploy: Deploy the app\n\nExamples:\n  deploy --force\n\nFlags:\n")
+        );
+        assert!(help.contains("Skip checks"));
+        assert!(help.ends_with("\n{unknown}\n"));
+
+        // Sections left out of the template aren't shown
+        assert!(!help.contains("Usage:"));
+        assert!(!help.contains("Available Commands:"));
+        assert!(!help.contains("status"));
+    }
+
+    #[test]
+    fn test_nested_command_suggestions() {
+        let build = |nested: bool| {
+            CommandBuilder::new("myapp")
+                .nested_suggestions(nested)
+                .subcommand(
+                    CommandBuilder::new("get")
+                        .subcommand(
+                            CommandBuilder::new("pods")
+                                .subcommand(CommandBuilder::new("logs").run(|_| Ok(())).build())
+                                .run(|_| Ok(()))
+                                .build(),
+                        )
+                        .subcommand(CommandBuilder::new("services").run(|_| Ok(())).build())
+                        .build(),
+                )
+                .subcommand(CommandBuilder::new("pod").run(|_| Ok(())).build())
+                .build()
+        };
+        let suggestions =
+            |cmd: &Command, word: &str| match cmd.execute(vec![word.to_string()]).unwrap_err() {
+                Error::CommandNotFound { suggestions, .. } => suggestions,
+                other => panic!("unexpected error: {other:?}"),
+            };
+
+        let flat = build(false);
+        assert!(suggestions(&flat, "logz").is_empty());
+        assert_eq!(suggestions(&flat, "podz"), vec!["pod"]);
+
+        let nested = build(true);
+        // A grandchild, by its path
+        assert_eq!(suggestions(&nested, "logz"), vec!["get pods logs"]);
+        // Direct subcommands come first
+        assert_eq!(suggestions(&nested, "podz"), vec!["pod", "get pods"]);
+        assert_eq!(suggestions(&nested, "servics"), vec!["get services"]);
+        assert!(suggestions(&nested, "xyz").is_empty());
+
+        let err = nested.execute(vec!["logz".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("get pods logs"));
+    }
+
+    #[test]
+    fn test_jaro_winkler_suggestions() {
+        let build = |algorithm| {
+            CommandBuilder::new("kubectl")
+                .suggestion_distance(1)
+                .suggestion_algorithm(algorithm)
+                .flag(Flag::string("namespace"))
+                .subcommand(CommandBuilder::new("pods").run(|_| Ok(())).build())
+                .subcommand(CommandBuilder::new("services").run(|_| Ok(())).build())
+                .build()
+        };
+        let suggestions = |cmd: &Command, words: &[&str]| match cmd
+            .execute(words.iter().map(ToString::to_string).collect())
+            .unwrap_err()
+        {
+            Error::CommandNotFound { suggestions, .. } => suggestions,
+            Error::FlagParsing { did_you_mean, .. } => did_you_mean,
+            other => panic!("unexpected error: {other:?}"),
+        };
+
+        // A transposition is two edits, beyond the distance set
+        let levenshtein = build(SuggestionAlgorithm::Levenshtein);
+        assert!(suggestions(&levenshtein, &["pdos"]).is_empty());
+        assert!(suggestions(&levenshtein, &["--nmaespace", "x"]).is_empty());
+
+        let jaro_winkler = build(SuggestionAlgorithm::JaroWinkler);
+        assert_eq!(suggestions(&jaro_winkler, &["pdos"]), vec!["pods"]);
+        assert_eq!(
+            suggestions(&jaro_winkler, &["--nmaespace", "x"]),
+            vec!["--namespace"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_flag_suggestions() {
+        let _no_color = no_color();
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose"))
+            .subcommand(
+                CommandBuilder::new("deploy")
+                    .flag(Flag::string("region"))
+                    .run(|_| Ok(()))
+                    .build(),
+            )
+            .build();
+
+        let err = cmd
+            .execute(["deploy", "--verbsoe"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::FlagParsing { ref message, ref did_you_mean, .. }
+                if message == "unknown flag --verbsoe" && did_you_mean == &["--verbose"]
+        ));
+        assert!(err.to_string().contains("Did you mean '--verbose'?"));
+
+        // Given before the subcommand, or with a value attached
+        let err = cmd
+            .execute(["--regoin", "eu", "deploy"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown flag --regoin"));
+        let err = cmd
+            .execute(["deploy", "--regoin=eu"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(err.to_string().contains("Did you mean '--region'?"));
+
+        // Arguments after `--` are left alone
+        assert!(
+            cmd.execute(["deploy", "--", "--verbsoe"].map(String::from).to_vec())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_allow_unknown_flags_keeps_args() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::bool("verbose"))
+            .subcommand(
+                CommandBuilder::new("cargo")
+                    .allow_unknown_flags()
+                    .run(move |ctx| {
+                        seen_run.lock().unwrap().extend(ctx.args().to_vec());
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+
+        cmd.execute(
+            ["cargo", "build", "--verbose", "--relase"]
+                .map(String::from)
+                .to_vec(),
+        )
+        .unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["build", "--relase"]);
+    }
+
+    #[test]
+    fn test_trailing_var_arg_skips_flag_parsing() {
+        let build = |trailing: bool| {
+            let seen = Arc::new(Mutex::new(Vec::new()));
+            let seen_run = Arc::clone(&seen);
+            let mut exec = CommandBuilder::new("exec")
+                .flag(Flag::bool("x").short('x'))
+                .flag(Flag::string("foo"))
+                .run(move |ctx| {
+                    *seen_run.lock().unwrap() = ctx.args().to_vec();
+                    Ok(())
+                });
+            if trailing {
+                exec = exec.trailing_var_arg();
+            }
+            let cmd = CommandBuilder::new("mytool")
+                .subcommand(exec.build())
+                .build();
+            (cmd, seen)
+        };
+        let args = |words: &[&str]| words.iter().map(ToString::to_string).collect();
+
+        let (cmd, seen) = build(true);
+        cmd.execute(args(&["exec", "-x", "--foo", "bar"])).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["-x", "--foo", "bar"]);
+        cmd.execute(args(&["exec", "cmd", "--help", "--", "--its-own"]))
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["cmd", "--help", "--", "--its-own"]
+        );
+
+        // Without the mode the same words are flags
+        let (cmd, seen) = build(false);
+        cmd.execute(args(&["exec", "-x", "--foo", "bar"])).unwrap();
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(cmd.execute(args(&["exec", "--its-own"])).is_err());
+    }
+
+    #[test]
+    fn test_double_dash_separates_own_flags_from_passthrough() {
+        let seen = Arc::new(Mutex::new(None));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("myapp")
+            .subcommand(
+                CommandBuilder::new("run")
+                    .flag(Flag::bool("verbose").short('v'))
+                    .flag(Flag::string("env"))
+                    .run(move |ctx| {
+                        *seen_run.lock().unwrap() = Some((
+                            ctx.flag_bool("verbose"),
+                            ctx.flag("env").cloned(),
+                            ctx.args().to_vec(),
+                            ctx.passthrough_args().to_vec(),
+                        ));
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+        let args = |words: &[&str]| words.iter().map(ToString::to_string).collect();
+
+        cmd.execute(args(&[
+            "run",
+            "--verbose",
+            "--env",
+            "dev",
+            "--",
+            "prog",
+            "--verbose",
+            "--",
+            "-v",
+        ]))
+        .unwrap();
+        let (verbose, env, all, passthrough) = seen.lock().unwrap().take().unwrap();
+        assert_eq!(verbose, Some(true));
+        assert_eq!(env.as_deref(), Some("dev"));
+        assert_eq!(passthrough, vec!["prog", "--verbose", "--", "-v"]);
+        assert_eq!(all, passthrough);
+
+        // Forwarded words never set the command's flags
+        cmd.execute(args(&["run", "--", "--verbose"])).unwrap();
+        let (verbose, _, _, passthrough) = seen.lock().unwrap().take().unwrap();
+        assert_ne!(verbose, Some(true));
+        assert_eq!(passthrough, vec!["--verbose"]);
+
+        cmd.execute(args(&["run", "-v", "prog"])).unwrap();
+        let (_, _, all, passthrough) = seen.lock().unwrap().take().unwrap();
+        assert_eq!(all, vec!["prog"]);
+        assert!(passthrough.is_empty());
+    }
+
+    #[test]
+    fn test_passthrough_unknown_partitions_args() {
+        let seen = Arc::new(Mutex::new(None));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("dockerwrap")
+            .flag(Flag::bool("dry-run"))
+            .flag(Flag::string("image").short('i'))
+            .passthrough_unknown()
+            .run(move |ctx| {
+                *seen_run.lock().unwrap() = Some((
+                    ctx.flag_bool("dry-run"),
+                    ctx.flag("image").cloned(),
+                    ctx.args().to_vec(),
+                    ctx.forwarded_args().to_vec(),
+                ));
                 Ok(())
             })
             .build();
 
-        cmd.execute(vec![]).unwrap();
-        assert!(*executed.lock().unwrap());
+        cmd.execute(
+            [
+                "--dry-run",
+                "run",
+                "--rm",
+                "-p",
+                "8080:80",
+                "-i",
+                "nginx",
+                "--name=web",
+                "--",
+                "--dry-run",
+            ]
+            .map(String::from)
+            .to_vec(),
+        )
+        .unwrap();
+        let (dry_run, image, args, forwarded) = seen.lock().unwrap().take().unwrap();
+        assert_eq!(dry_run, Some(true));
+        assert_eq!(image.as_deref(), Some("nginx"));
+        assert!(args.is_empty());
+        assert_eq!(
+            forwarded,
+            vec!["run", "--rm", "-p", "8080:80", "--name=web", "--dry-run"]
+        );
+
+        // Without it, the same unknown flag is an error
+        let strict = CommandBuilder::new("dockerwrap")
+            .flag(Flag::bool("dry-run"))
+            .run(|_| Ok(()))
+            .build();
+        assert!(
+            strict
+                .execute(["--dry-run", "--rm"].map(String::from).to_vec())
+                .is_err()
+        );
     }
 
     #[test]
-    fn test_command_with_args() {
-        let received_args = Arc::new(Mutex::new(Vec::new()));
-        let args_clone = received_args.clone();
-
-        let cmd = CommandBuilder::new("test")
-            .run(move |ctx| {
-                *args_clone.lock().unwrap() = ctx.args().to_vec();
+    fn test_validate_rejects_flag_and_command() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let sub = |name: &str| {
+            let ran = Arc::clone(&ran);
+            let name = name.to_string();
+            CommandBuilder::new(name.clone())
+                .run(move |_| {
+                    ran.lock().unwrap().push(name.clone());
+                    Ok(())
+                })
+                .build()
+        };
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        let app = CommandBuilder::new("app")
+            .flag(Flag::bool("dry-run"))
+            .validate(move |ctx| {
+                seen_clone.lock().unwrap().push(ctx.command_path().to_vec());
+                if ctx.flag_bool("dry-run") == Some(true)
+                    && ctx.command_path().last().map(String::as_str) == Some("write")
+                {
+                    return Err(Error::Validation(
+                        "--dry-run can't be used with write".to_string(),
+                    ));
+                }
                 Ok(())
             })
+            .subcommand(sub("read"))
+            .subcommand(sub("write"))
             .build();
 
-        cmd.execute(vec!["arg1".to_string(), "arg2".to_string()])
+        let result = app.execute(["write", "--dry-run"].map(String::from).to_vec());
+        assert!(matches!(result, Err(Error::Validation(msg)) if msg.contains("write")));
+        assert!(ran.lock().unwrap().is_empty());
+
+        app.execute(["read", "--dry-run"].map(String::from).to_vec())
             .unwrap();
-        assert_eq!(*received_args.lock().unwrap(), vec!["arg1", "arg2"]);
+        app.execute(["write"].map(String::from).to_vec()).unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["read", "write"]);
+        assert_eq!(
+            seen.lock().unwrap()[0],
+            ["app", "write"].map(String::from).to_vec()
+        );
     }
 
     #[test]
-    fn test_subcommand_execution() {
-        let main_executed = Arc::new(Mutex::new(false));
-        let sub_executed = Arc::new(Mutex::new(false));
-        let sub_clone = sub_executed.clone();
-
-        let subcmd = CommandBuilder::new("sub")
-            .run(move |_ctx| {
-                *sub_clone.lock().unwrap() = true;
+    fn test_comma_separated_flag_mixes_with_repeats() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("deploy")
+            .flag(Flag::string_slice("tag").short('t').comma_separated())
+            .flag(Flag::string_slice("note"))
+            .run(move |ctx| {
+                let mut seen = seen_run.lock().unwrap();
+                seen.push(ctx.flag_string_slice("tag").unwrap_or_default());
+                seen.push(ctx.flag_string_slice("note").unwrap_or_default());
+                // The value itself is what was typed
+                seen.push(vec![ctx.flag("note").cloned().unwrap_or_default()]);
                 Ok(())
             })
             .build();
 
-        let main_clone = main_executed.clone();
-        let cmd = CommandBuilder::new("main")
-            .run(move |_ctx| {
-                *main_clone.lock().unwrap() = true;
+        let args = [
+            "--tag",
+            "a,b",
+            "-t",
+            "c",
+            r"--tag=x\,y,z",
+            "--note",
+            "fast, cheap",
+            "--note",
+            "done",
+        ];
+        cmd.execute(args.map(String::from).to_vec()).unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                vec!["a", "b", "c", "x,y", "z"],
+                // Not comma-separated: commas stay inside each item
+                vec!["fast, cheap", "done"],
+                vec!["fast, cheap,done"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_value_flag_accumulates() {
+        let seen = Arc::new(Mutex::new(None));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("deploy")
+            .flag(Flag::key_value("label").short('l'))
+            .run(move |ctx| {
+                *seen_run.lock().unwrap() = ctx.flag_key_value("label");
                 Ok(())
             })
-            .subcommand(subcmd)
             .build();
 
-        // Execute subcommand
-        cmd.execute(vec!["sub".to_string()]).unwrap();
-        assert!(*sub_executed.lock().unwrap());
-        assert!(!*main_executed.lock().unwrap());
+        let args = [
+            "--label",
+            "app=web",
+            "-l",
+            "tier=front",
+            "--label=query=a=b",
+            "--label",
+            "tags=a,b",
+        ];
+        cmd.execute(args.map(String::from).to_vec()).unwrap();
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            Some(vec![
+                pair("app", "web"),
+                pair("tier", "front"),
+                pair("query", "a=b"),
+                pair("tags", "a,b")
+            ])
+        );
+
+        let result = cmd.execute(
+            ["--label", "app=web", "--label", "tier"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert!(
+            matches!(result, Err(Error::FlagParsing { message, .. }) if message.contains("'tier'"))
+        );
     }
 
     #[test]
-    fn test_flag_parsing() {
-        let cmd = CommandBuilder::new("test")
-            .flag(Flag::new("verbose").short('v').value_type(FlagType::Bool))
-            .flag(Flag::new("output").short('o').value_type(FlagType::String))
-            .flag(Flag::new("count").value_type(FlagType::Int))
-            .run(|ctx| {
-                assert_eq!(ctx.flag("verbose"), Some(&"true".to_string()));
-                assert_eq!(ctx.flag("output"), Some(&"file.txt".to_string()));
-                assert_eq!(ctx.flag("count"), Some(&"42".to_string()));
-                assert_eq!(ctx.args(), &["remaining"]);
+    fn test_deny_extra_args_names_them() {
+        let ran = Arc::new(Mutex::new(0));
+        let ran_clone = Arc::clone(&ran);
+        let cmd = CommandBuilder::new("get")
+            .positional("resource")
+            .positional("name")
+            .deny_extra_args()
+            .run(move |_| {
+                *ran_clone.lock().unwrap() += 1;
                 Ok(())
             })
             .build();
 
-        cmd.execute(vec![
-            "-v".to_string(),
-            "--output".to_string(),
-            "file.txt".to_string(),
-            "--count=42".to_string(),
-            "remaining".to_string(),
-        ])
-        .unwrap();
+        cmd.execute(["pods", "web"].map(String::from).to_vec())
+            .unwrap();
+        cmd.execute(["pods"].map(String::from).to_vec()).unwrap();
+        match cmd.execute(["pods", "web", "extra"].map(String::from).to_vec()) {
+            Err(Error::ArgumentValidation {
+                message,
+                expected,
+                received,
+            }) => {
+                assert_eq!(message, "unexpected argument(s): extra");
+                assert_eq!(expected, "at most 2");
+                assert_eq!(received, 3);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(*ran.lock().unwrap(), 2);
+
+        // The validator's arity wins over the named positionals
+        let cmd = CommandBuilder::new("cp")
+            .positional("source")
+            .args(ArgValidator::ExactArgs(2))
+            .deny_extra_args()
+            .run(|_| Ok(()))
+            .build();
+        cmd.execute(["a", "b"].map(String::from).to_vec()).unwrap();
+        let err = cmd
+            .execute(["a", "b", "c", "d"].map(String::from).to_vec())
+            .unwrap_err();
+        assert!(err.to_string().contains("unexpected argument(s): c, d"));
     }
 
     #[test]
-    fn test_flag_inheritance() {
-        let sub_executed = Arc::new(Mutex::new(false));
-        let sub_clone = sub_executed.clone();
-
-        let subcmd = CommandBuilder::new("sub")
+    fn test_execution_timeout_cancels_run() {
+        let steps = Arc::new(Mutex::new(0));
+        let steps_clone = Arc::clone(&steps);
+        let cmd = CommandBuilder::new("sync")
+            .execution_timeout(Duration::from_millis(50))
             .run(move |ctx| {
-                assert_eq!(ctx.flag("global"), Some(&"value".to_string()));
-                *sub_clone.lock().unwrap() = true;
+                while !ctx.is_cancelled() {
+                    *steps_clone.lock().unwrap() += 1;
+                    std::thread::sleep(Duration::from_millis(5));
+                }
                 Ok(())
             })
             .build();
 
-        let cmd = CommandBuilder::new("main")
-            .flag(Flag::new("global").value_type(FlagType::String))
-            .subcommand(subcmd)
+        let start = std::time::Instant::now();
+        match cmd.execute(vec![]) {
+            Err(Error::Custom(err)) => {
+                assert!(
+                    err.to_string()
+                        .starts_with("timed out after 50ms, stopped after ")
+                );
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(*steps.lock().unwrap() > 0);
+
+        // A run that finishes in time keeps its own result
+        let cmd = CommandBuilder::new("quick")
+            .execution_timeout(Duration::from_secs(5))
+            .run(|ctx| {
+                assert!(!ctx.is_cancelled());
+                Err(Error::Validation("done early".to_string()))
+            })
             .build();
+        assert!(matches!(cmd.execute(vec![]), Err(Error::Validation(_))));
 
-        cmd.execute(vec![
-            "--global".to_string(),
-            "value".to_string(),
-            "sub".to_string(),
-        ])
-        .unwrap();
+        // A run that ignores the token is waited for and keeps its result
+        let cmd = CommandBuilder::new("stuck")
+            .execution_timeout(Duration::from_millis(20))
+            .run(|_ctx| {
+                std::thread::sleep(Duration::from_millis(200));
+                Ok(())
+            })
+            .build();
+        let start = std::time::Instant::now();
+        cmd.execute(vec![]).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
 
-        assert!(*sub_executed.lock().unwrap());
+        // One that sees the cancellation late still reports how long it took
+        let cmd = CommandBuilder::new("slow")
+            .execution_timeout(Duration::from_millis(20))
+            .run(|ctx| {
+                std::thread::sleep(Duration::from_millis(200));
+                if ctx.is_cancelled() {
+                    return Ok(());
+                }
+                Err(Error::Validation("not cancelled".to_string()))
+            })
+            .build();
+        let err = cmd.execute(vec![]).unwrap_err().to_string();
+        let took = err
+            .rsplit("stopped after ")
+            .next()
+            .and_then(|took| took.strip_suffix("ms"))
+            .and_then(|ms| ms.parse::<f64>().ok())
+            .unwrap();
+        assert!(took >= 200.0, "{err}");
     }
 
     #[test]
-    fn test_command_aliases() {
-        let executed = Arc::new(Mutex::new(String::new()));
-        let exec_clone = executed.clone();
+    fn test_described_examples_in_help() {
+        let _no_color = no_color();
+        let cmd = CommandBuilder::new("deploy")
+            .example_with_description("myapp deploy --env prod", "Deploy to prod")
+            .example("myapp deploy --env staging")
+            .build();
 
-        let subcmd = CommandBuilder::new("subcommand")
-            .aliases(vec!["sub", "s"])
-            .run(move |_ctx| {
-                *exec_clone.lock().unwrap() = "subcommand".to_string();
+        let help = cmd.render_help(&[], &HashMap::new());
+        assert!(help.contains(
+            "Examples:\n  # Deploy to prod\n  myapp deploy --env prod\n  myapp deploy --env staging\n"
+        ));
+        let described: Vec<_> = cmd.described_examples().collect();
+        assert_eq!(
+            described,
+            vec![
+                ("myapp deploy --env prod", Some("Deploy to prod")),
+                ("myapp deploy --env staging", None)
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_os_keeps_raw_flag_values() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_run = Arc::clone(&seen);
+        let cmd = CommandBuilder::new("copy")
+            .flag(Flag::string("from"))
+            .flag(Flag::string("to"))
+            .flag(Flag::string("label"))
+            .run(move |ctx| {
+                let mut seen = seen_run.lock().unwrap();
+                for name in ["from", "to", "label"] {
+                    seen.push((
+                        ctx.flag(name).unwrap().clone(),
+                        ctx.flag_os(name).unwrap().to_os_string(),
+                    ));
+                }
                 Ok(())
             })
             .build();
 
-        let cmd = CommandBuilder::new("main").subcommand(subcmd).build();
+        let raw = |bytes: &[u8]| OsString::from_vec(bytes.to_vec());
+        cmd.execute_os(vec![
+            raw(b"--from"),
+            raw(b"caf\xe9"),
+            raw(b"--to=na\xefve"),
+            raw("--label=caf\u{fffd}".as_bytes()),
+        ])
+        .unwrap();
+        assert_eq!(
+            seen.lock().unwrap().split_off(0),
+            vec![
+                ("caf\u{fffd}".to_string(), raw(b"caf\xe9")),
+                ("na\u{fffd}ve".to_string(), raw(b"na\xefve")),
+                ("caf\u{fffd}".to_string(), raw("caf\u{fffd}".as_bytes())),
+            ]
+        );
 
-        // Test main name
-        cmd.execute(vec!["subcommand".to_string()]).unwrap();
-        assert_eq!(*executed.lock().unwrap(), "subcommand");
+        // Values with the same lossy form keep their own originals
+        cmd.execute_os(vec![
+            raw(b"--from"),
+            raw(b"caf\xe9"),
+            raw(b"--to"),
+            raw(b"caf\xe8"),
+            raw(b"--label"),
+            raw(b"x"),
+        ])
+        .unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].1, raw(b"caf\xe9"));
+        assert_eq!(seen[1].1, raw(b"caf\xe8"));
+    }
 
-        // Test alias
-        cmd.execute(vec!["sub".to_string()]).unwrap();
-        assert_eq!(*executed.lock().unwrap(), "subcommand");
+    #[test]
+    fn test_completion_command_uses_running_tree() {
+        let script = Arc::new(Mutex::new((String::new(), false)));
+        let script_run = Arc::clone(&script);
+        let mut app = CommandBuilder::new("myapp")
+            .subcommand(
+                CommandBuilder::new("completion")
+                    .run(move |ctx| {
+                        let root = ctx.root().expect("root is set while executing");
+                        *script_run.lock().unwrap() = (
+                            root.generate_completion(Shell::Bash),
+                            root.subcommands().contains_key("plugin-cmd"),
+                        );
+                        Ok(())
+                    })
+                    .build(),
+            )
+            .build();
+        // Only the running tree has this one; rebuilding wouldn't
+        app.add_command(CommandBuilder::new("plugin-cmd").run(|_| Ok(())).build());
 
-        // Test short alias
-        cmd.execute(vec!["s".to_string()]).unwrap();
-        assert_eq!(*executed.lock().unwrap(), "subcommand");
+        app.execute(["completion"].map(String::from).to_vec())
+            .unwrap();
+        let (script, saw_plugin) = &*script.lock().unwrap();
+        assert!(script.contains("_myapp_complete()"));
+        assert_eq!(*script, app.generate_completion(Shell::Bash));
+        assert!(saw_plugin);
+
+        let mut ctx = Context::new(vec!["plugin-cmd".to_string()]);
+        app.execute_with_context(&mut ctx).unwrap();
+        assert!(ctx.root().is_none());
     }
 
     #[test]
-    fn test_error_cases() {
-        let cmd = CommandBuilder::new("main").build();
-
-        // No subcommand when required
-        let result = cmd.execute(vec![]);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::SubcommandRequired(_)));
+    fn test_help_command() {
+        let app = CommandBuilder::new("myapp")
+            .with_help_command()
+            .flag(Flag::string("token").required())
+            .subcommand(
+                CommandBuilder::new("get")
+                    .short("Display resources")
+                    .subcommand(CommandBuilder::new("pods").short("List pods").build())
+                    .build(),
+            )
+            .subcommand(
+                CommandBuilder::new("delete")
+                    .short("Delete resources")
+                    .build(),
+            )
+            .build();
+        let path = ["myapp", "help"].map(String::from);
+        let topic = |words: &[&str]| {
+            let words: Vec<String> = words.iter().map(|word| (*word).to_string()).collect();
+            Command::help_topic(&app, &path, &words).map(|(topic, _)| topic.name())
+        };
 
-        // Unknown subcommand
-        let result = cmd.execute(vec!["unknown".to_string()]);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::CommandNotFound { .. }));
+        assert_eq!(topic(&[]).unwrap(), "myapp");
+        assert_eq!(topic(&["get"]).unwrap(), "get");
+        assert_eq!(topic(&["get", "pods"]).unwrap(), "pods");
+        assert!(matches!(
+            topic(&["gte"]),
+            Err(Error::CommandNotFound { command, .. }) if command == "gte"
+        ));
+
+        // Required flags don't stand in the way of asking for help
+        for args in [
+            vec!["help"],
+            vec!["help", "get"],
+            vec!["help", "get", "pods"],
+        ] {
+            app.execute(args.into_iter().map(String::from).collect())
+                .unwrap();
+        }
+        assert!(
+            app.execute(["help", "nope"].map(String::from).to_vec())
+                .is_err()
+        );
 
-        // Unknown flag (now treated as argument, so it becomes unknown command)
-        let result = cmd.execute(vec!["--unknown".to_string()]);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::CommandNotFound { .. }));
+        // Completion offers the commands help can tell about
+        assert_eq!(complete(&app, &["help", ""]), vec!["delete", "get"]);
+        assert_eq!(complete(&app, &["help", "g"]), vec!["get"]);
+        assert_eq!(complete(&app, &["help", "get", ""]), vec!["pods"]);
+        assert!(
+            complete(&app, &[""])
+                .iter()
+                .any(|value| value.starts_with("help"))
+        );
     }
 
     #[test]
-    fn test_completion() {
-        let cmd = CommandBuilder::new("test")
-            .arg_completion(|_ctx, prefix| {
-                Ok(CompletionResult::new().extend(
-                    vec!["file1.txt", "file2.txt", "folder/"]
-                        .into_iter()
-                        .filter(|f| f.starts_with(prefix))
-                        .map(String::from),
-                ))
-            })
-            .flag_completion("type", |_ctx, prefix| {
-                Ok(CompletionResult::new().extend(
-                    vec!["json", "yaml", "xml"]
-                        .into_iter()
-                        .filter(|t| t.starts_with(prefix))
-                        .map(String::from),
-                ))
-            })
-            .build();
+    fn test_subcommand_matching() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let app = |prefix: bool, ignore_case: bool| {
+            let sub = |name: &str| {
+                let ran = Arc::clone(&ran);
+                let name = name.to_string();
+                CommandBuilder::new(name.clone())
+                    .run(move |_| {
+                        ran.lock().unwrap().push(name.clone());
+                        Ok(())
+                    })
+                    .build()
+            };
+            CommandBuilder::new("kubectl")
+                .match_prefix(prefix)
+                .match_case_insensitive(ignore_case)
+                .subcommand(sub("deployments"))
+                .subcommand(sub("describe"))
+                .subcommand(sub("get"))
+                .build()
+        };
 
-        let ctx = Context::new(vec![]);
+        // Prefixes are off by default
+        assert!(app(false, false).execute(vec!["dep".to_string()]).is_err());
 
-        // Test arg completion
-        let result = cmd.get_completions(&ctx, "fi", None).unwrap();
-        assert_eq!(result.values, vec!["file1.txt", "file2.txt"]);
+        let prefixed = app(true, false);
+        prefixed.execute(vec!["dep".to_string()]).unwrap();
+        prefixed.execute(vec!["g".to_string()]).unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["deployments", "get"]);
 
-        // Test flag completion
-        let result = cmd.get_completions(&ctx, "j", Some("type")).unwrap();
-        assert_eq!(result.values, vec!["json"]);
+        // An ambiguous prefix lists the candidates
+        ran.lock().unwrap().clear();
+        match prefixed.execute(vec!["de".to_string()]) {
+            Err(Error::CommandNotFound {
+                command,
+                suggestions,
+            }) => {
+                assert_eq!(command, "de");
+                assert_eq!(suggestions, vec!["deployments", "describe"]);
+            }
+            other => panic!("expected an ambiguous prefix, got {other:?}"),
+        }
+        assert!(ran.lock().unwrap().is_empty());
+
+        // Completion still offers the full names
+        let mut values = complete(&prefixed, &["de"]);
+        values.sort();
+        assert_eq!(values, vec!["deployments", "describe"]);
+
+        let folded = app(false, true);
+        folded.execute(vec!["GET".to_string()]).unwrap();
+        folded.execute(vec!["Describe".to_string()]).unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["get", "describe"]);
+        assert_eq!(
+            folded.find_subcommand("Get").map(Command::name),
+            Some("get")
+        );
+
+        // Prefixes only with match_prefix, then ignoring case as well
+        assert!(folded.execute(vec!["DEP".to_string()]).is_err());
+        ran.lock().unwrap().clear();
+        app(true, true).execute(vec!["DEP".to_string()]).unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["deployments"]);
     }
 
     #[test]
-    fn test_flag_with_equals() {
-        let cmd = CommandBuilder::new("test")
-            .flag(Flag::new("output").value_type(FlagType::String))
-            .run(|ctx| {
-                assert_eq!(
-                    ctx.flag("output"),
-                    Some(&"/path/with=equals.txt".to_string())
-                );
+    fn test_match_prefix_ambiguous_is_argument_of_runnable_command() {
+        let args = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&args);
+        let app = CommandBuilder::new("kubectl")
+            .match_prefix(true)
+            .subcommand(CommandBuilder::new("deployments").build())
+            .subcommand(CommandBuilder::new("describe").build())
+            .run(move |ctx| {
+                seen.lock().unwrap().extend(ctx.args().iter().cloned());
                 Ok(())
             })
             .build();
 
-        cmd.execute(vec!["--output=/path/with=equals.txt".to_string()])
-            .unwrap();
+        app.execute(vec!["de".to_string()]).unwrap();
+        assert_eq!(*args.lock().unwrap(), vec!["de"]);
+    }
+
+    fn printed_count(warning: &str) -> usize {
+        PRINTED_DEPRECATIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|printed| *printed == warning)
+            .count()
     }
 
     #[test]
-    fn test_help_flag() {
-        let cmd = CommandBuilder::new("test")
-            .short("Test command")
-            .long("This is a test command")
-            .flag(
-                Flag::new("verbose")
-                    .short('v')
-                    .usage("Enable verbose output"),
-            )
+    fn test_deprecation_warned_once_per_process() {
+        let cmd = CommandBuilder::new("app")
+            .flag(Flag::string("once-old").deprecated("use --once-new"))
+            .flag(Flag::string("once-new"))
+            .subcommand(CommandBuilder::new("run").run(|_ctx| Ok(())).build())
             .build();
+        let warning = "flag --once-old is deprecated: use --once-new";
 
-        // Test --help
-        let result = cmd.execute(vec!["--help".to_string()]);
-        assert!(result.is_ok());
-
-        // Test -h
-        let result = cmd.execute(vec!["-h".to_string()]);
-        assert!(result.is_ok());
+        for _ in 0..2 {
+            let mut ctx = Context::new(
+                ["--once-old", "a", "run", "--once-old=b"]
+                    .map(String::from)
+                    .to_vec(),
+            );
+            cmd.execute_with_context(&mut ctx).unwrap();
+            // Every invocation still knows what it used
+            assert_eq!(
+                ctx.get::<DeprecationWarnings>().unwrap().0,
+                vec![warning.to_string()]
+            );
+        }
+        assert_eq!(printed_count(warning), 1);
     }
 
     #[test]
-    fn test_subcommand_help() {
-        let subcmd = CommandBuilder::new("sub")
-            .short("Subcommand")
-            .flag(Flag::new("subflag").usage("A flag for the subcommand"))
+    fn test_deprecation_summary_lists_distinct_warnings() {
+        let cmd = CommandBuilder::new("app")
+            .deprecation_summary(true)
+            .flag(Flag::string("sum-old").deprecated("use --sum-new"))
+            .subcommand(
+                CommandBuilder::new("sum-rm")
+                    .deprecated("use 'app delete'")
+                    .run(|ctx| {
+                        // Nothing is printed until the run is over
+                        let warnings = &ctx.get::<DeprecationWarnings>().unwrap().0;
+                        assert!(warnings.iter().all(|warning| printed_count(warning) == 0));
+                        Ok(())
+                    })
+                    .build(),
+            )
             .build();
 
-        let cmd = CommandBuilder::new("main")
-            .flag(Flag::new("global").usage("A global flag"))
-            .subcommand(subcmd)
-            .build();
+        let mut ctx = Context::new(["--sum-old", "a", "sum-rm"].map(String::from).to_vec());
+        cmd.execute_with_context(&mut ctx).unwrap();
+        let warnings = &ctx.get::<DeprecationWarnings>().unwrap().0;
+        assert_eq!(
+            *warnings,
+            vec![
+                "flag --sum-old is deprecated: use --sum-new".to_string(),
+                "command sum-rm is deprecated: use 'app delete'".to_string(),
+            ]
+        );
+        assert!(warnings.iter().all(|warning| printed_count(warning) == 1));
+
+        let summary = deprecation_summary_text(&warnings.iter().collect::<Vec<_>>()).unwrap();
+        assert!(summary.ends_with(
+            " deprecated usage:\n  \
+             - flag --sum-old is deprecated: use --sum-new\n  \
+             - command sum-rm is deprecated: use 'app delete'\n"
+        ));
+        assert_eq!(deprecation_summary_text(&[]), None);
+    }
 
-        // Test help on subcommand
-        let result = cmd.execute(vec!["sub".to_string(), "--help".to_string()]);
-        assert!(result.is_ok());
+    #[test]
+    fn test_program_basename() {
+        assert_eq!(program_basename("/usr/local/bin/ls"), "ls");
+        assert_eq!(program_basename("./toolbox"), "toolbox");
+        assert_eq!(program_basename("cat"), "cat");
     }
 }
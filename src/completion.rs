@@ -7,6 +7,7 @@
 use crate::active_help::ActiveHelp;
 use crate::context::Context;
 use crate::error::Result;
+use crate::shell::Shell;
 
 /// Result returned by completion functions
 ///
@@ -37,6 +38,8 @@ pub struct CompletionResult {
     pub descriptions: Vec<String>,
     /// `ActiveHelp` messages to display
     pub active_help: Vec<ActiveHelp>,
+    /// The value to pre-select, see [`default_value`](Self::default_value)
+    pub(crate) default: Option<String>,
 }
 
 impl CompletionResult {
@@ -47,6 +50,7 @@ impl CompletionResult {
             values: Vec::new(),
             descriptions: Vec::new(),
             active_help: Vec::new(),
+            default: None,
         }
     }
 
@@ -100,6 +104,37 @@ impl CompletionResult {
         self
     }
 
+    /// Adds a completion value marked as the recommended choice
+    ///
+    /// Shell formats list the default first and append `(default)` to its
+    /// description. There is only one default; adding another moves the
+    /// marker.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion::CompletionResult;
+    ///
+    /// let result = CompletionResult::new()
+    ///     .add_with_description("us-west-1", "N. California")
+    ///     .add_default("us-east-1", "N. Virginia");
+    /// assert_eq!(result.default_value(), Some("us-east-1"));
+    /// ```
+    #[must_use]
+    pub fn add_default(self, value: impl Into<String>, desc: impl Into<String>) -> Self {
+        let value = value.into();
+        let mut result = self.add_with_description(value.clone(), desc);
+        result.default = Some(value);
+        result
+    }
+
+    /// Returns the value to pre-select, if one was added with
+    /// [`add_default`](Self::add_default)
+    #[must_use]
+    pub fn default_value(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
     /// Adds multiple completion values without descriptions
     ///
     /// # Arguments
@@ -192,6 +227,86 @@ impl CompletionResult {
             .push(ActiveHelp::with_condition(message, condition));
         self
     }
+
+    /// Adds an `ActiveHelp` message shown only in the given shells
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The help message text
+    /// * `shells` - The shells in which the help should be shown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion::CompletionResult;
+    /// use flag_rs::Shell;
+    ///
+    /// let result = CompletionResult::new()
+    ///     .add_shell_help("Tip: run 'bind -p' to list keybindings", &[Shell::Bash]);
+    /// ```
+    #[must_use]
+    pub fn add_shell_help<S: Into<String>>(mut self, message: S, shells: &[Shell]) -> Self {
+        self.active_help
+            .push(ActiveHelp::new(message).for_shells(shells));
+        self
+    }
+
+    /// Builds a completion function for a `key=value` flag
+    ///
+    /// While the word being completed has no `=`, `keys` is called with it
+    /// and each key it returns is offered as `key=`. Once it does, `values`
+    /// is called with the key and the text after the `=`, and each value is
+    /// offered as `key=value`. For a comma-separated list only the last
+    /// item is completed; the items before it are kept in every suggestion.
+    /// Descriptions, `ActiveHelp` and the default carry over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion::CompletionResult;
+    /// use flag_rs::context::Context;
+    ///
+    /// let complete = CompletionResult::key_value(
+    ///     |_ctx, prefix| {
+    ///         Ok(CompletionResult::new().extend(
+    ///             ["app", "env"].into_iter().filter(|key| key.starts_with(prefix)).map(String::from),
+    ///         ))
+    ///     },
+    ///     |_ctx, key, _prefix| match key {
+    ///         "env" => Ok(CompletionResult::new().add("prod").add("dev")),
+    ///         _ => Ok(CompletionResult::new()),
+    ///     },
+    /// );
+    ///
+    /// let ctx = Context::new(vec![]);
+    /// assert_eq!(complete(&ctx, "a").unwrap().values, vec!["app="]);
+    /// assert_eq!(complete(&ctx, "env=").unwrap().values, vec!["env=prod", "env=dev"]);
+    /// ```
+    pub fn key_value<K, V>(
+        keys: K,
+        values: V,
+    ) -> impl Fn(&Context, &str) -> Result<Self> + Send + Sync + 'static
+    where
+        K: Fn(&Context, &str) -> Result<Self> + Send + Sync + 'static,
+        V: Fn(&Context, &str, &str) -> Result<Self> + Send + Sync + 'static,
+    {
+        move |ctx, prefix| {
+            let (done, item) = prefix
+                .rfind(',')
+                .map_or(("", prefix), |pos| prefix.split_at(pos + 1));
+            let (result, lead) = match item.split_once('=') {
+                Some((key, value)) => (values(ctx, key, value)?, format!("{done}{key}=")),
+                None => (keys(ctx, item)?, done.to_string()),
+            };
+            let suffix = if item.contains('=') { "" } else { "=" };
+            let complete = |value: &str| format!("{lead}{value}{suffix}");
+            Ok(Self {
+                values: result.values.iter().map(|value| complete(value)).collect(),
+                default: result.default.as_deref().map(complete),
+                ..result
+            })
+        }
+    }
 }
 
 impl Default for CompletionResult {
@@ -242,10 +357,150 @@ impl Default for CompletionResult {
 /// ```
 pub type CompletionFunc = Box<dyn Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync>;
 
+/// A completion function a command keeps, shareable with the worker
+/// thread a completion timeout runs it on
+pub(crate) type SharedCompletionFunc =
+    std::sync::Arc<dyn Fn(&Context, &str) -> Result<CompletionResult> + Send + Sync>;
+
+/// Completes a filesystem path
+///
+/// The directory part of `prefix` (everything up to the last `/`) is read
+/// and its entries starting with the rest are offered, keeping the
+/// directory part so each value replaces the whole word. Directories end
+/// in `/`, so the next TAB lists inside them. An empty prefix lists the
+/// current directory, and dotfiles are only offered once the name being
+/// completed starts with `.`.
+///
+/// `files_only` leaves out directories and `dirs_only` leaves out
+/// everything else. A directory that can't be read, because it doesn't
+/// exist or isn't accessible, completes to nothing rather than an error.
+///
+/// # Examples
+///
+/// ```
+/// use flag_rs::CommandBuilder;
+/// use flag_rs::completion::complete_path;
+///
+/// let cmd = CommandBuilder::new("cat")
+///     .arg_completion(|_ctx, prefix| complete_path(prefix, true, false))
+///     .build();
+/// ```
+// Returns a `Result` so it can be returned from a completion function as is
+#[allow(clippy::unnecessary_wraps)]
+pub fn complete_path(prefix: &str, files_only: bool, dirs_only: bool) -> Result<CompletionResult> {
+    let (dir, base) = prefix
+        .rfind('/')
+        .map_or(("", prefix), |pos| prefix.split_at(pos + 1));
+    let read_from = if dir.is_empty() { "." } else { dir };
+
+    let Ok(entries) = std::fs::read_dir(read_from) else {
+        return Ok(CompletionResult::new());
+    };
+
+    let mut values: Vec<String> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(base) || (name.starts_with('.') && !base.starts_with('.')) {
+                return None;
+            }
+            // Follow symlinks, so a link to a directory completes like one
+            let is_dir = std::fs::metadata(entry.path()).is_ok_and(|meta| meta.is_dir());
+            if (is_dir && files_only) || (!is_dir && dirs_only) {
+                return None;
+            }
+            Some(if is_dir {
+                format!("{dir}{name}/")
+            } else {
+                format!("{dir}{name}")
+            })
+        })
+        .collect();
+    values.sort();
+
+    Ok(CompletionResult::new().extend(values))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Creates a fresh directory tree for path completion tests
+    fn path_fixture(name: &str) -> String {
+        let root = std::env::temp_dir().join(format!("flag-rs-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src/bin")).unwrap();
+        std::fs::create_dir_all(root.join("scripts")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        for file in ["setup.cfg", "src/main.rs", "src/lib.rs", ".env"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        format!("{}/", root.to_string_lossy())
+    }
+
+    fn values(result: &CompletionResult, root: &str) -> Vec<String> {
+        result
+            .values
+            .iter()
+            .map(|value| value.strip_prefix(root).unwrap_or(value).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_path_filters_by_basename() {
+        let root = path_fixture("path-basename");
+
+        let result = complete_path(&format!("{root}s"), false, false).unwrap();
+        assert_eq!(
+            values(&result, &root),
+            vec!["scripts/", "setup.cfg", "src/"]
+        );
+
+        let result = complete_path(&format!("{root}s"), true, false).unwrap();
+        assert_eq!(values(&result, &root), vec!["setup.cfg"]);
+
+        let result = complete_path(&format!("{root}s"), false, true).unwrap();
+        assert_eq!(values(&result, &root), vec!["scripts/", "src/"]);
+
+        // Dotfiles only once asked for
+        let result = complete_path(&root, false, false).unwrap();
+        assert_eq!(
+            values(&result, &root),
+            vec!["scripts/", "setup.cfg", "src/"]
+        );
+        let result = complete_path(&format!("{root}."), false, false).unwrap();
+        assert_eq!(values(&result, &root), vec![".env", ".git/"]);
+    }
+
+    #[test]
+    fn test_complete_path_inside_directory() {
+        let root = path_fixture("path-inside");
+
+        let result = complete_path(&format!("{root}src/"), false, false).unwrap();
+        assert_eq!(
+            values(&result, &root),
+            vec!["src/bin/", "src/lib.rs", "src/main.rs"]
+        );
+
+        let result = complete_path(&format!("{root}src/m"), true, false).unwrap();
+        assert_eq!(values(&result, &root), vec!["src/main.rs"]);
+    }
+
+    #[test]
+    fn test_complete_path_edge_cases() {
+        // An empty prefix lists the current directory, without a `./`
+        let result = complete_path("", false, false).unwrap();
+        assert!(result.values.contains(&"src/".to_string()));
+        assert!(result.values.contains(&"Cargo.toml".to_string()));
+
+        // Unreadable directories complete to nothing
+        let root = path_fixture("path-errors");
+        let result = complete_path(&format!("{root}missing/"), false, false).unwrap();
+        assert!(result.values.is_empty());
+        let result = complete_path(&format!("{root}setup.cfg/"), false, false).unwrap();
+        assert!(result.values.is_empty());
+    }
+
     #[test]
     fn test_completion_result() {
         let result = CompletionResult::new()
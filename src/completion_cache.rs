@@ -2,11 +2,21 @@
 //!
 //! This module provides a time-based cache for completion results to improve
 //! performance when users repeatedly request completions for the same context.
+//!
+//! Shells run the binary once per completion request, so an in-memory cache
+//! alone does not survive between requests. When a session token is set
+//! (see [`CompletionCache::with_session_from_env`]), results are also written
+//! to short-lived files in the user's own cache directory, so later
+//! invocations in the same session can reuse them.
 
+use crate::active_help::ActiveHelp;
 use crate::completion::CompletionResult;
+use crate::error::Result;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// A cached completion entry with timestamp
 #[derive(Clone)]
@@ -22,6 +32,8 @@ struct CacheEntry {
 pub struct CompletionCache {
     cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
     ttl: Duration,
+    session: Option<String>,
+    dir: Option<PathBuf>,
 }
 
 impl CompletionCache {
@@ -34,9 +46,76 @@ impl CompletionCache {
         Self {
             cache: Arc::new(Mutex::new(HashMap::new())),
             ttl,
+            session: None,
+            dir: user_cache_dir(),
+        }
+    }
+
+    /// Shares cached results across invocations within a completion session
+    ///
+    /// Entries are persisted per session token and expire after the TTL, so
+    /// results are reused only by invocations that present the same token.
+    /// An empty token disables sharing. They are kept in
+    /// `$XDG_CACHE_HOME/flag-rs/completion` (or `~/.cache/...`), a
+    /// directory only the user can access; without a home directory, or
+    /// off Unix, the cache stays in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion_cache::CompletionCache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = CompletionCache::new(Duration::from_secs(2)).with_session("tab-41");
+    /// assert_eq!(cache.session(), Some("tab-41"));
+    /// ```
+    #[must_use]
+    pub fn with_session(mut self, token: impl Into<String>) -> Self {
+        let token = token.into();
+        self.session = (!token.is_empty()).then_some(token);
+        self
+    }
+
+    /// Reads the completion session token from an environment variable
+    ///
+    /// The generated completion scripts set `<PROGRAM>_COMPLETE_SESSION`,
+    /// e.g. `MYAPP_COMPLETE_SESSION`, to the process id of the shell unless
+    /// the user set it already, so TABs in one shell share cached results
+    /// for the TTL. Without the variable the cache is in-memory only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion_cache::CompletionCache;
+    /// use std::time::Duration;
+    ///
+    /// let cache = CompletionCache::new(Duration::from_secs(2))
+    ///     .with_session_from_env("MYAPP_COMPLETE_SESSION");
+    /// ```
+    #[must_use]
+    pub fn with_session_from_env(self, env_var: &str) -> Self {
+        match std::env::var(env_var) {
+            Ok(token) => self.with_session(token),
+            Err(_) => self,
         }
     }
 
+    /// Returns the completion session token, if any
+    pub fn session(&self) -> Option<&str> {
+        self.session.as_deref()
+    }
+
+    /// Keeps the entries of sessions in `dir` instead of the user's cache
+    /// directory
+    ///
+    /// The directory is created, accessible only to the user, when the
+    /// first entry is written.
+    #[must_use]
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
     /// Creates a new completion cache with a default TTL of 5 seconds
     pub fn with_default_ttl() -> Self {
         Self::new(Duration::from_secs(5))
@@ -67,10 +146,52 @@ impl CompletionCache {
         parts.join(":")
     }
 
+    /// Generates the key a command's completions are cached under
+    ///
+    /// `command_path` runs from the root, e.g. `["kubectl", "get", "pods"]`.
+    /// `flag` names the flag whose value is completed, or is `None` for
+    /// arguments. `flags` are those given before the word being completed,
+    /// so keys for [`warm`](Self::warm) usually pass none; `flag` itself
+    /// is left out of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::completion_cache::CompletionCache;
+    /// use std::collections::HashMap;
+    ///
+    /// let key = CompletionCache::completion_key(&["kubectl", "get", "pods"], None, "", &HashMap::new());
+    /// let namespace_key =
+    ///     CompletionCache::completion_key(&["kubectl"], Some("namespace"), "", &HashMap::new());
+    /// assert_ne!(key, namespace_key);
+    /// ```
+    pub fn completion_key<S: AsRef<str>>(
+        command_path: &[S],
+        flag: Option<&str>,
+        prefix: &str,
+        flags: &HashMap<String, String>,
+    ) -> String {
+        let mut path: Vec<String> = command_path
+            .iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+        path.push(format!("__flag:{}", flag.unwrap_or_default()));
+
+        // The flag being completed has no value yet, whatever the parse made of it
+        let given: HashMap<String, String> = flags
+            .iter()
+            .filter(|(name, _)| Some(name.as_str()) != flag)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Self::make_key(&path, prefix, &given)
+    }
+
     /// Attempts to get a cached completion result
     ///
     /// Returns `Some(CompletionResult)` if a valid cached entry exists,
-    /// or `None` if the entry doesn't exist or has expired.
+    /// or `None` if the entry doesn't exist or has expired. With a session
+    /// token, entries stored by earlier invocations in the session are
+    /// found as well.
     pub fn get(&self, key: &str) -> Option<CompletionResult> {
         let mut cache = self.cache.lock().ok()?;
 
@@ -82,7 +203,16 @@ impl CompletionCache {
             cache.remove(key);
         }
 
-        None
+        let (result, age) = self.read_session_entry(key)?;
+        let timestamp = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+        cache.insert(
+            key.to_string(),
+            CacheEntry {
+                result: result.clone(),
+                timestamp,
+            },
+        );
+        Some(result)
     }
 
     /// Stores a completion result in the cache
@@ -92,6 +222,8 @@ impl CompletionCache {
     /// * `key` - The cache key
     /// * `result` - The completion result to cache
     pub fn put(&self, key: String, result: CompletionResult) {
+        self.write_session_entry(&key, &result);
+
         if let Ok(mut cache) = self.cache.lock() {
             cache.insert(
                 key,
@@ -106,17 +238,92 @@ impl CompletionCache {
         }
     }
 
+    /// Pre-populates the cache, so the next completion for `key` is instant
+    ///
+    /// For applications that already hold what a completion would fetch,
+    /// e.g. a long-running session that just listed resources. The entry
+    /// expires after the TTL like any other; build the key with
+    /// [`completion_key`](Self::completion_key).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::CompletionResult;
+    /// use flag_rs::completion_cache::CompletionCache;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    ///
+    /// let cache = CompletionCache::new(Duration::from_secs(30));
+    /// let key = CompletionCache::completion_key(&["kubectl", "get", "pods"], None, "", &HashMap::new());
+    /// cache.warm(key.clone(), CompletionResult::new().add("nginx-abc123"));
+    /// assert_eq!(cache.get(&key).unwrap().values, vec!["nginx-abc123"]);
+    /// ```
+    pub fn warm(&self, key: String, result: CompletionResult) {
+        self.put(key, result);
+    }
+
+    /// Keeps `key` warm by running `generate` now and then every `interval`
+    ///
+    /// The work happens on a background thread until the returned handle
+    /// is stopped or dropped. A failed refresh keeps the previous entry, so
+    /// an `interval` shorter than the TTL avoids gaps where it expired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::CompletionResult;
+    /// use flag_rs::completion_cache::CompletionCache;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Arc::new(CompletionCache::new(Duration::from_secs(30)));
+    /// let refresher = cache.refresh_in_background("pods".to_string(), Duration::from_secs(10), || {
+    ///     Ok(CompletionResult::new().add("nginx-abc123"))
+    /// });
+    /// refresher.stop();
+    /// assert!(cache.get("pods").is_some());
+    /// ```
+    pub fn refresh_in_background<F>(
+        self: &Arc<Self>,
+        key: String,
+        interval: Duration,
+        generate: F,
+    ) -> RefreshHandle
+    where
+        F: Fn() -> Result<CompletionResult> + Send + 'static,
+    {
+        let cache = Arc::clone(self);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            loop {
+                if let Ok(result) = generate() {
+                    cache.warm(key.clone(), result);
+                }
+                if stopped.recv_timeout(interval) != Err(mpsc::RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+        });
+        RefreshHandle {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
     /// Removes expired entries from the cache
     fn cleanup_expired(&self, cache: &mut HashMap<String, CacheEntry>) {
         let now = Instant::now();
         cache.retain(|_, entry| now.duration_since(entry.timestamp) < self.ttl);
     }
 
-    /// Clears all cached entries
+    /// Clears all cached entries, including this session's persisted ones
     pub fn clear(&self) {
         if let Ok(mut cache) = self.cache.lock() {
             cache.clear();
         }
+        if let Some(dir) = self.session_dir() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
     }
 
     /// Returns the number of cached entries
@@ -125,6 +332,234 @@ impl CompletionCache {
     }
 }
 
+/// Stops a [`CompletionCache::refresh_in_background`] refresher
+///
+/// Dropping the handle stops the refresher too, without waiting for it.
+pub struct RefreshHandle {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RefreshHandle {
+    /// Stops refreshing and waits for a refresh in progress to finish
+    pub fn stop(mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RefreshHandle {
+    fn drop(&mut self) {
+        // Closing the channel wakes the refresher, which then exits
+        self.stop.take();
+    }
+}
+
+impl CompletionCache {
+    fn session_dir(&self) -> Option<PathBuf> {
+        let session = self.session.as_ref()?;
+        Some(
+            self.dir
+                .as_ref()?
+                .join(format!("{:016x}", hash_str(session))),
+        )
+    }
+
+    fn session_file(&self, key: &str) -> Option<PathBuf> {
+        Some(self.session_dir()?.join(format!("{:016x}", hash_str(key))))
+    }
+
+    /// Loads a persisted entry and its age, dropping it if expired
+    fn read_session_entry(&self, key: &str) -> Option<(CompletionResult, Duration)> {
+        let path = self.session_file(key)?;
+        let contents = std::fs::read_to_string(&path).ok()?;
+        let mut lines = contents.lines();
+
+        // The key is stored too, so a hash collision is a miss
+        let stored_at: u128 = lines.next()?.parse().ok()?;
+        if lines.next().map(unescape).as_deref() != Some(key) {
+            return None;
+        }
+        let age = Duration::from_millis(
+            u64::try_from(unix_millis().saturating_sub(stored_at)).unwrap_or(u64::MAX),
+        );
+        if age >= self.ttl {
+            let _ = std::fs::remove_file(path);
+            return None;
+        }
+
+        let mut result = CompletionResult::new();
+        for line in lines {
+            match line.split('\t').collect::<Vec<_>>().as_slice() {
+                ["v", value, description] => {
+                    result.values.push(unescape(value));
+                    result.descriptions.push(unescape(description));
+                }
+                ["h", message] => result.active_help.push(ActiveHelp::new(unescape(message))),
+                ["d", value] => result.default = Some(unescape(value)),
+                _ => return None,
+            }
+        }
+        Some((result, age))
+    }
+
+    /// Persists an entry for later invocations in the same session
+    ///
+    /// Results with conditional or shell-specific `ActiveHelp` can't be
+    /// written out, so they stay in memory only.
+    fn write_session_entry(&self, key: &str, result: &CompletionResult) {
+        use std::fmt::Write;
+
+        let (Some(dir), Some(path)) = (self.session_dir(), self.session_file(key)) else {
+            return;
+        };
+        if result
+            .active_help
+            .iter()
+            .any(|help| help.condition.is_some() || help.shells.is_some())
+        {
+            return;
+        }
+
+        self.remove_expired_sessions();
+
+        let mut contents = format!("{}\n{}\n", unix_millis(), escape(key));
+        for (value, description) in result.values.iter().zip(&result.descriptions) {
+            let _ = writeln!(contents, "v\t{}\t{}", escape(value), escape(description));
+        }
+        for help in &result.active_help {
+            let _ = writeln!(contents, "h\t{}", escape(&help.message));
+        }
+        if let Some(default) = &result.default {
+            let _ = writeln!(contents, "d\t{}", escape(default));
+        }
+
+        // Write then rename so concurrent readers never see a partial entry
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let _ = std::fs::remove_file(&tmp);
+        if create_private_dir(&dir).is_ok() && write_private_file(&tmp, &contents).is_ok() {
+            let _ = std::fs::rename(&tmp, &path);
+        }
+    }
+
+    /// Removes session directories untouched for longer than the TTL
+    fn remove_expired_sessions(&self) {
+        let Some(Ok(sessions)) = self.dir.as_ref().map(std::fs::read_dir) else {
+            return;
+        };
+        for dir in sessions.flatten() {
+            let expired = dir
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > self.ttl);
+            if expired {
+                let _ = std::fs::remove_dir_all(dir.path());
+            }
+        }
+    }
+}
+
+/// Returns the directory session entries are kept in by default
+///
+/// It lives under the user's own cache directory, never a shared one like
+/// the temp directory, where other users could plant or remove entries.
+#[cfg(unix)]
+fn user_cache_dir() -> Option<PathBuf> {
+    let non_empty = |var: &str| {
+        std::env::var_os(var)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let cache = non_empty("XDG_CACHE_HOME")
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))?;
+    Some(cache.join("flag-rs").join("completion"))
+}
+
+#[cfg(not(unix))]
+fn user_cache_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Creates `dir` and its missing parents, accessible only to the user
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        builder.mode(0o700).create(dir)?;
+        // An existing directory keeps its mode, so tighten it
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    {
+        builder.create(dir)
+    }
+}
+
+/// Writes a new file only the user can read
+fn write_private_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Hashes a key or session token for a file name
+///
+/// FNV-1a, so the names stay the same across Rust releases, unlike those
+/// from `DefaultHasher`.
+fn hash_str(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn unix_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
+
+/// Escapes tabs, newlines and backslashes for the line-based entry format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
 impl Default for CompletionCache {
     fn default() -> Self {
         Self::with_default_ttl()
@@ -179,6 +614,19 @@ mod tests {
         assert_ne!(key1, key4);
     }
 
+    #[test]
+    fn test_hash_str_is_stable() {
+        assert_eq!(hash_str(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash_str("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_session_entries_escape_round_trip() {
+        let original = "tab\there\nnew line \\ backslash";
+        assert_eq!(unescape(&escape(original)), original);
+        assert!(!escape(original).contains(['\t', '\n']));
+    }
+
     #[test]
     fn test_cache_cleanup() {
         let cache = CompletionCache::new(Duration::from_millis(100));
@@ -201,4 +649,55 @@ mod tests {
         // Only the new entry should remain
         assert_eq!(cache.size(), 1);
     }
+
+    #[test]
+    fn test_refresh_in_background() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = Arc::new(CompletionCache::new(Duration::from_secs(5)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let refresher =
+            cache.refresh_in_background("pods".to_string(), Duration::from_millis(20), move || {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                Ok(CompletionResult::new().add(format!("pod-{n}")))
+            });
+
+        std::thread::sleep(Duration::from_millis(100));
+        refresher.stop();
+        let refreshes = calls.load(Ordering::SeqCst);
+        assert!(refreshes > 1);
+        assert_eq!(
+            cache.get("pods").unwrap().values,
+            vec![format!("pod-{}", refreshes - 1)]
+        );
+
+        // Nothing runs once stopped
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(calls.load(Ordering::SeqCst), refreshes);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_session_entries_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("flag-rs-private-{}", std::process::id()));
+        let cache = CompletionCache::new(Duration::from_secs(5))
+            .with_cache_dir(&dir)
+            .with_session("private");
+        cache.put("key".to_string(), CompletionResult::new().add("value"));
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let session_dir = cache.session_dir().unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(&session_dir), 0o700);
+        assert_eq!(mode(&cache.session_file("key").unwrap()), 0o600);
+
+        let reader = CompletionCache::new(Duration::from_secs(5))
+            .with_cache_dir(&dir)
+            .with_session("private");
+        assert_eq!(reader.get("key").unwrap().values, vec!["value"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,168 @@
+//! Completing values from an external command's output
+//!
+//! Many completions list things another program already knows about, like
+//! git branches or kubectl contexts. [`CompletionResult::from_command`] runs
+//! the program and offers each line of its output, so completion functions
+//! don't have to deal with processes, pipes and timeouts themselves.
+
+use crate::completion::CompletionResult;
+use crate::completion_timeout::DEFAULT_COMPLETION_TIMEOUT;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+impl CompletionResult {
+    /// Completes with the lines a program prints, waiting at most two seconds
+    ///
+    /// See [`from_command_with_timeout`](Self::from_command_with_timeout).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use flag_rs::{CompletionResult, Flag};
+    ///
+    /// let flag = Flag::string("branch").completion(|_ctx, prefix| {
+    ///     let branches =
+    ///         CompletionResult::from_command("git", &["branch", "--format=%(refname:short)"]);
+    ///     Ok(CompletionResult::new()
+    ///         .extend(branches.values.into_iter().filter(|b| b.starts_with(prefix))))
+    /// });
+    /// ```
+    #[must_use]
+    pub fn from_command(program: &str, args: &[&str]) -> Self {
+        Self::from_command_with_timeout(program, args, DEFAULT_COMPLETION_TIMEOUT)
+    }
+
+    /// Completes with the lines a program prints to stdout
+    ///
+    /// Each non-empty line becomes a value; stderr is ignored. Completion
+    /// shouldn't fail loudly, so problems become `ActiveHelp` hints instead
+    /// of errors: the program can't be started, exits unsuccessfully, or is
+    /// still running after `timeout` (it is then killed). Values are not
+    /// filtered by the prefix being completed.
+    #[must_use]
+    pub fn from_command_with_timeout(program: &str, args: &[&str], timeout: Duration) -> Self {
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) => {
+                return Self::new().add_help_text(format!("Could not run '{program}': {err}"));
+            }
+        };
+
+        // Drain stdout on its own thread so a chatty program can't block
+        // on a full pipe while we wait for it
+        let reader = child.stdout.take().map(|mut stdout| {
+            thread::spawn(move || {
+                let mut output = String::new();
+                let _ = stdout.read_to_string(&mut output);
+                output
+            })
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() >= timeout => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Self::new().add_help_text(format!(
+                        "'{program}' took longer than {}ms; completions skipped",
+                        timeout.as_millis()
+                    ));
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                Err(err) => {
+                    return Self::new().add_help_text(format!("Could not run '{program}': {err}"));
+                }
+            }
+        };
+
+        if !status.success() {
+            return Self::new().add_help_text(format!("'{program}' failed ({status})"));
+        }
+
+        let output = reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        Self::new().extend(
+            output
+                .lines()
+                .map(str::trim_end)
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        )
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Writes a shell script into a fresh temp directory
+    ///
+    /// Scripts are run through `sh` rather than executed directly, which
+    /// avoids "text file busy" races with other tests spawning processes.
+    fn stub_script(name: &str, body: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("flag-rs-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, format!("{body}\n")).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn help_messages(result: &CompletionResult) -> Vec<&str> {
+        result
+            .active_help
+            .iter()
+            .map(|help| help.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_command_lines() {
+        let script = stub_script("lines", "printf 'main\\n\\nfeature/login  \\nfix\\r\\n'");
+        let result = CompletionResult::from_command("sh", &[&script]);
+        assert_eq!(result.values, vec!["main", "feature/login", "fix"]);
+        assert!(result.active_help.is_empty());
+
+        let script = stub_script("args", "for arg in \"$@\"; do echo \"$arg\"; done");
+        let result = CompletionResult::from_command("sh", &[&script, "a", "b c"]);
+        assert_eq!(result.values, vec!["a", "b c"]);
+    }
+
+    #[test]
+    fn test_from_command_timeout() {
+        let script = stub_script("slow", "echo early\nsleep 5");
+        let start = Instant::now();
+        let result = CompletionResult::from_command_with_timeout(
+            "sh",
+            &[&script],
+            Duration::from_millis(100),
+        );
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert!(result.values.is_empty());
+        assert_eq!(help_messages(&result).len(), 1);
+        assert!(help_messages(&result)[0].contains("took longer than 100ms"));
+    }
+
+    #[test]
+    fn test_from_command_failure_becomes_hint() {
+        let script = stub_script("fails", "echo partial\nexit 3");
+        let result = CompletionResult::from_command("sh", &[&script]);
+        assert!(result.values.is_empty());
+        assert_eq!(help_messages(&result).len(), 1);
+        assert!(help_messages(&result)[0].contains("failed (exit status: 3)"));
+
+        let result = CompletionResult::from_command("/nonexistent/flag-rs-stub", &[]);
+        assert!(result.values.is_empty());
+        assert!(help_messages(&result)[0].starts_with("Could not run '/nonexistent/flag-rs-stub'"));
+    }
+}
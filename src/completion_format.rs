@@ -6,6 +6,8 @@
 use crate::active_help::ActiveHelp;
 use crate::completion::CompletionResult;
 use crate::context::Context;
+use crate::shell::Shell;
+use std::borrow::Cow;
 
 /// Represents the format in which completions should be returned
 #[derive(Debug, Clone, Copy)]
@@ -18,8 +20,10 @@ pub enum CompletionFormat {
     Zsh,
     /// Fish format with descriptions
     Fish,
-    /// Bash format (requires special handling)
+    /// Bash format: `value<TAB>description` lines the script lays out itself
     Bash,
+    /// Nushell format: one JSON record per line
+    Nushell,
 }
 
 impl CompletionFormat {
@@ -29,18 +33,35 @@ impl CompletionFormat {
             Some("zsh") => Self::Zsh,
             Some("fish") => Self::Fish,
             Some("bash") => Self::Bash,
+            Some("nushell") => Self::Nushell,
             Some("display") => Self::Display,
             _ => Self::Simple,
         }
     }
 
+    /// Returns the shell this format targets, if any
+    pub const fn shell(self) -> Option<Shell> {
+        match self {
+            Self::Bash => Some(Shell::Bash),
+            Self::Zsh => Some(Shell::Zsh),
+            Self::Fish => Some(Shell::Fish),
+            Self::Nushell => Some(Shell::Nushell),
+            Self::Simple | Self::Display => None,
+        }
+    }
+
     /// Formats a completion result according to this format
     pub fn format(self, result: &CompletionResult, ctx: Option<&Context>) -> Vec<String> {
+        let result = &Self::default_first(result);
         let mut output = match self {
-            Self::Simple | Self::Bash => {
-                // For bash and simple format, return just the values
+            Self::Simple => {
+                // For simple format, return just the values
                 result.values.clone()
             }
+            Self::Bash => {
+                // The bash script splits descriptions off at the tab
+                Self::format_bash(result)
+            }
             Self::Display => {
                 // For display, show formatted with descriptions
                 Self::format_display(result)
@@ -53,6 +74,10 @@ impl CompletionFormat {
                 // Fish uses tab-separated format
                 Self::format_fish(result)
             }
+            Self::Nushell => {
+                // Nushell parses each line into a record
+                Self::format_nushell(result)
+            }
         };
 
         // Add ActiveHelp messages if any (and context is provided)
@@ -64,6 +89,31 @@ impl CompletionFormat {
         output
     }
 
+    /// Moves the default value to the front and marks its description
+    fn default_first(result: &CompletionResult) -> Cow<'_, CompletionResult> {
+        let Some(index) = result
+            .default
+            .as_ref()
+            .and_then(|default| result.values.iter().position(|value| value == default))
+        else {
+            return Cow::Borrowed(result);
+        };
+
+        let mut result = result.clone();
+        let value = result.values.remove(index);
+        let desc = result.descriptions.remove(index);
+        result.values.insert(0, value);
+        result.descriptions.insert(
+            0,
+            if desc.is_empty() {
+                "(default)".to_string()
+            } else {
+                format!("{desc} (default)")
+            },
+        );
+        Cow::Owned(result)
+    }
+
     /// Formats for human-readable display (not shell consumption)
     fn format_display(result: &CompletionResult) -> Vec<String> {
         use crate::color;
@@ -96,6 +146,28 @@ impl CompletionFormat {
             .collect()
     }
 
+    /// Formats for Bash completion
+    ///
+    /// Bash has no native description support, so a described value is
+    /// sent as `value<TAB>description` and the completion script pads the
+    /// descriptions into the menu itself. Values without a description
+    /// stay bare, so a result with no descriptions is unchanged.
+    fn format_bash(result: &CompletionResult) -> Vec<String> {
+        result
+            .values
+            .iter()
+            .zip(&result.descriptions)
+            .map(|(value, desc)| {
+                if desc.is_empty() {
+                    value.clone()
+                } else {
+                    // Keep the description on the value's line
+                    format!("{value}\t{}", desc.replace('\n', " "))
+                }
+            })
+            .collect()
+    }
+
     /// Formats for Zsh completion
     fn format_zsh(result: &CompletionResult) -> Vec<String> {
         // Terminal width constraint
@@ -181,7 +253,32 @@ impl CompletionFormat {
             .collect()
     }
 
+    /// Formats for Nushell completion
+    ///
+    /// Each line is a JSON object with `value` and `description` fields, so
+    /// spaces, colons and tabs survive without any shell-specific escaping.
+    fn format_nushell(result: &CompletionResult) -> Vec<String> {
+        use crate::json::JsonValue;
+
+        result
+            .values
+            .iter()
+            .zip(&result.descriptions)
+            .map(|(value, desc)| {
+                JsonValue::Object(vec![
+                    ("value".to_string(), JsonValue::from(value.as_str())),
+                    ("description".to_string(), JsonValue::from(desc.as_str())),
+                ])
+                .to_string()
+            })
+            .collect()
+    }
+
     /// Formats `ActiveHelp` messages for the given shell
+    ///
+    /// Every line carries the marker the completion scripts filter on,
+    /// so each line of a multi-line message is marked on its own; an
+    /// unmarked line would be offered as a value.
     fn format_active_help(
         help_messages: &[ActiveHelp],
         ctx: &Context,
@@ -189,27 +286,31 @@ impl CompletionFormat {
     ) -> Vec<String> {
         let mut formatted = Vec::new();
 
-        for help in help_messages {
-            if help.should_display(ctx) {
-                match format {
-                    Self::Bash => {
-                        // Bash: ActiveHelp messages are prefixed with a special marker
-                        // that completion scripts can recognize and display differently
-                        formatted.push(format!("_activehelp_ {}", help.message));
-                    }
-                    Self::Zsh => {
-                        // Zsh: Use a special format that won't be selectable
-                        // The completion script should recognize this pattern
-                        formatted.push(format!("_activehelp_::{}", help.message));
-                    }
-                    Self::Fish => {
-                        // Fish: Similar to Zsh, use a special prefix
-                        formatted.push(format!("_activehelp_\t{}", help.message));
-                    }
-                    Self::Simple | Self::Display => {
-                        // For simple/display format, just show the message with a prefix
-                        formatted.push(format!("[HELP] {}", help.message));
-                    }
+        let lines = help_messages
+            .iter()
+            .filter(|help| help.should_display_in(ctx, format.shell()))
+            .flat_map(|help| help.message.lines())
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty());
+        for message in lines {
+            match format {
+                Self::Bash => {
+                    // Bash: ActiveHelp messages are prefixed with a special marker
+                    // that completion scripts can recognize and display differently
+                    formatted.push(format!("_activehelp_ {message}"));
+                }
+                Self::Zsh => {
+                    // Zsh: Use a special format that won't be selectable
+                    // The completion script should recognize this pattern
+                    formatted.push(format!("_activehelp_::{message}"));
+                }
+                Self::Fish | Self::Nushell => {
+                    // Fish and Nushell: Similar to Zsh, use a special prefix
+                    formatted.push(format!("_activehelp_\t{message}"));
+                }
+                Self::Simple | Self::Display => {
+                    // For simple/display format, just show the message with a prefix
+                    formatted.push(format!("[HELP] {message}"));
                 }
             }
         }
@@ -302,15 +403,75 @@ mod tests {
         assert!(formatted[1].contains('\t'));
     }
 
+    #[test]
+    fn test_nushell_format_records() {
+        let result = CompletionResult::new()
+            .add("plain")
+            .add_with_description("my file.txt", "Modified: 10:42")
+            .add_help_text("Pick a file");
+
+        let ctx = Context::new(vec![]);
+        let formatted = CompletionFormat::Nushell.format(&result, Some(&ctx));
+
+        assert_eq!(
+            formatted,
+            vec![
+                r#"{"value":"plain","description":""}"#,
+                r#"{"value":"my file.txt","description":"Modified: 10:42"}"#,
+                "_activehelp_\tPick a file",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_entry_listed_first() {
+        let result = CompletionResult::new()
+            .add_with_description("small", "1 CPU")
+            .add("medium")
+            .add_default("large", "4 CPUs");
+        assert_eq!(result.default.as_deref(), Some("large"));
+
+        let zsh = CompletionFormat::Zsh.format(&result, None);
+        assert!(zsh[0].starts_with("large:"));
+        assert!(zsh[0].ends_with("- 4 CPUs (default)"));
+
+        let fish = CompletionFormat::Fish.format(&result, None);
+        assert!(fish[0].starts_with("large\t"));
+        assert!(fish[0].ends_with("4 CPUs (default)"));
+        assert_eq!(fish[2], "medium");
+
+        let bash = CompletionFormat::Bash.format(&result, None);
+        assert_eq!(
+            bash,
+            vec!["large\t4 CPUs (default)", "small\t1 CPU", "medium"]
+        );
+
+        // The result itself keeps insertion order
+        assert_eq!(result.values, vec!["small", "medium", "large"]);
+    }
+
     #[test]
     fn test_bash_format() {
         let result = CompletionResult::new()
             .add("value1")
-            .add_with_description("value2", "Description ignored for bash");
+            .add_with_description("value2", "Second value\nover two lines");
+
+        let formatted = CompletionFormat::Bash.format(&result, None);
+
+        // Described values carry their description after a tab
+        assert_eq!(
+            formatted,
+            vec!["value1", "value2\tSecond value over two lines"]
+        );
+    }
+
+    #[test]
+    fn test_bash_format_without_descriptions() {
+        let result = CompletionResult::new().add("value1").add("value2");
 
         let formatted = CompletionFormat::Bash.format(&result, None);
 
-        // Bash format is just the values
+        // Without descriptions bash gets just the values
         assert_eq!(formatted, vec!["value1", "value2"]);
     }
 
@@ -375,4 +536,54 @@ mod tests {
         let no_ctx_formatted = CompletionFormat::Bash.format(&result, None);
         assert!(!no_ctx_formatted.iter().any(|s| s.contains("_activehelp_")));
     }
+
+    #[test]
+    fn test_active_help_lines_are_always_marked() {
+        let result = CompletionResult::new()
+            .add_with_description("prod", "Production")
+            .add("staging")
+            .add_help_text("Pick an environment\nor set APP_ENV\n");
+        let ctx = Context::new(vec![]);
+
+        for (format, marker) in [
+            (CompletionFormat::Bash, "_activehelp_ "),
+            (CompletionFormat::Zsh, "_activehelp_::"),
+            (CompletionFormat::Fish, "_activehelp_\t"),
+            (CompletionFormat::Nushell, "_activehelp_\t"),
+        ] {
+            let formatted = format.format(&result, Some(&ctx));
+            let (help, values): (Vec<_>, Vec<_>) =
+                formatted.iter().partition(|line| line.starts_with(marker));
+            assert_eq!(
+                help,
+                vec![
+                    &format!("{marker}Pick an environment"),
+                    &format!("{marker}or set APP_ENV"),
+                ],
+                "{format:?}"
+            );
+            assert_eq!(values.len(), 2, "{format:?}");
+            assert!(!values.iter().any(|value| value.contains("APP_ENV")));
+        }
+    }
+
+    #[test]
+    fn test_shell_specific_active_help() {
+        let result = CompletionResult::new()
+            .add("option1")
+            .add_shell_help("Tip: bind menu-complete to TAB", &[Shell::Bash])
+            .add_help_text("Shown everywhere");
+
+        let ctx = Context::new(vec![]);
+
+        let bash_formatted = CompletionFormat::Bash.format(&result, Some(&ctx));
+        assert!(
+            bash_formatted.contains(&"_activehelp_ Tip: bind menu-complete to TAB".to_string())
+        );
+        assert!(bash_formatted.contains(&"_activehelp_ Shown everywhere".to_string()));
+
+        let zsh_formatted = CompletionFormat::Zsh.format(&result, Some(&ctx));
+        assert!(!zsh_formatted.iter().any(|s| s.contains("menu-complete")));
+        assert!(zsh_formatted.contains(&"_activehelp_::Shown everywhere".to_string()));
+    }
 }
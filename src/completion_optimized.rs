@@ -7,6 +7,7 @@ use crate::active_help::ActiveHelp;
 use crate::completion_item::CompletionItem;
 use crate::context::Context;
 use crate::error::Result;
+use crate::shell::Shell;
 use std::borrow::Cow;
 
 /// Memory-optimized completion result
@@ -99,6 +100,14 @@ impl CompletionResultOptimized {
         self
     }
 
+    /// Adds an `ActiveHelp` message shown only in the given shells
+    #[must_use]
+    pub fn add_shell_help<S: Into<String>>(mut self, message: S, shells: &[Shell]) -> Self {
+        self.active_help
+            .push(ActiveHelp::new(message).for_shells(shells));
+        self
+    }
+
     /// Merges two completion results
     #[must_use]
     pub fn merge(mut self, other: Self) -> Self {
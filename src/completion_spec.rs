@@ -0,0 +1,176 @@
+//! Static completion spec for generic completers
+//!
+//! Shell scripts from [`Command::generate_completion`] run the program on
+//! every TAB. [`Command::generate_static_completion_spec`] instead writes
+//! out the parts of the CLI that never change, so a simple or generic
+//! completer can offer subcommands and flag names by itself and only run
+//! the program for values that are computed at runtime.
+//!
+//! # Format
+//!
+//! The spec is plain text, one record per line. Fields are separated by
+//! tabs and the first field names the record. `<path>` is the command's
+//! names from the root, separated by spaces.
+//!
+//! - `spec  1  <ENV>`: always the first line. `1` is the format version;
+//!   `<ENV>` is the environment variable that enables runtime completion.
+//! - `command  <path>  <description>`: one per command, parents first.
+//! - `alias  <path>  <alias>`: another name for the command at `<path>`.
+//! - `flag  <path>  <long>  <short>  <kind>  <description>`: a flag of the
+//!   command at `<path>`, which its subcommands accept too. `<short>` is
+//!   empty if there is none. `<kind>` is `switch` (takes no value),
+//!   `value` (requires one) or `optional` (may take one).
+//! - `values  <path>  <long>  <value>...`: the fixed set of values a flag
+//!   accepts.
+//! - `dynamic-flag  <path>  <long>`: the flag's values are computed at
+//!   runtime.
+//! - `dynamic-args  <path>`: the command's arguments are computed at
+//!   runtime.
+//!
+//! Tabs and newlines inside descriptions are replaced with spaces. Runtime
+//! completions are requested like the shell scripts do: run the program
+//! with `<ENV>` set and the arguments `__complete`, the words after the
+//! program name, and the word being completed.
+//!
+//! [`Command::generate_completion`]: crate::Command::generate_completion
+
+use crate::command::Command;
+use crate::doc_tree::{sorted_flags, sorted_subcommands};
+use crate::flag::FlagType;
+use std::fmt::Write;
+
+impl Command {
+    /// Describes this command tree for completers that don't run the program
+    ///
+    /// See the [module documentation](self) for the format. Commands are
+    /// listed with parents before children, and subcommands and flags in
+    /// name order, leaving out hidden ones. The root also lists the
+    /// built-in `--help` flag, and `--version` when it is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("tool")
+    ///     .flag(Flag::string("config").short('c').usage("Config file"))
+    ///     .build();
+    ///
+    /// let spec = cmd.generate_static_completion_spec();
+    /// assert!(spec.starts_with("spec\t1\tTOOL_COMPLETE\n"));
+    /// assert!(spec.contains("flag\ttool\tconfig\tc\tvalue\tConfig file\n"));
+    /// ```
+    #[must_use]
+    pub fn generate_static_completion_spec(&self) -> String {
+        let mut out = format!("spec\t1\t{}\n", self.completion_env_var());
+        self.write_spec(&mut out, self.program_name(), true);
+        out
+    }
+
+    fn write_spec(&self, out: &mut String, path: &str, root: bool) {
+        let _ = writeln!(out, "command\t{path}\t{}", field(self.short()));
+        for alias in self.aliases() {
+            let _ = writeln!(out, "alias\t{path}\t{alias}");
+        }
+
+        if root {
+            let _ = writeln!(out, "flag\t{path}\thelp\th\tswitch\tShow help");
+            if self.builtin_version(&[]).is_some() {
+                // `-V` is only built in when no flag claims it
+                let short = if self.flags().values().any(|flag| flag.short == Some('V')) {
+                    ""
+                } else {
+                    "V"
+                };
+                let _ = writeln!(out, "flag\t{path}\tversion\t{short}\tswitch\tShow version");
+            }
+        }
+
+        for flag in sorted_flags(self) {
+            let kind = if flag.is_switch() {
+                "switch"
+            } else if flag.optional_value.is_some() {
+                "optional"
+            } else {
+                "value"
+            };
+            let short = flag.short.map(String::from).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "flag\t{path}\t{}\t{short}\t{kind}\t{}",
+                flag.name,
+                field(&flag.usage)
+            );
+
+            if self.has_dynamic_flag_values(flag) {
+                let _ = writeln!(out, "dynamic-flag\t{path}\t{}", flag.name);
+            } else if let FlagType::Choice(choices) = &flag.value_type {
+                let values: Vec<String> = choices.iter().map(|value| field(value)).collect();
+                let _ = writeln!(out, "values\t{path}\t{}\t{}", flag.name, values.join("\t"));
+            }
+        }
+
+        if self.has_arg_completions() {
+            let _ = writeln!(out, "dynamic-args\t{path}");
+        }
+
+        for sub in sorted_subcommands(self) {
+            sub.write_spec(out, &format!("{path} {}", sub.name()), false);
+        }
+    }
+}
+
+/// Keeps a value within its field by replacing tabs and newlines
+fn field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CommandBuilder, CompletionResult, Flag};
+
+    #[test]
+    fn test_spec_lists_commands_and_flags() {
+        let cmd = CommandBuilder::new("tool")
+            .short("Manage\tremote things")
+            .flag(Flag::bool("verbose").short('v').usage("Verbose output"))
+            .flag(Flag::choice("format", &["json", "yaml"]))
+            .subcommand(
+                CommandBuilder::new("remote")
+                    .short("Manage remotes")
+                    .alias("rm")
+                    .flag(Flag::string("color").optional_value("auto"))
+                    .subcommand(
+                        CommandBuilder::new("add")
+                            .short("Add a remote")
+                            .flag(Flag::string("url").usage("Remote URL"))
+                            .flag_completion("url", |_ctx, _prefix| Ok(CompletionResult::new()))
+                            .arg_completion(|_ctx, _prefix| Ok(CompletionResult::new()))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let spec = cmd.generate_static_completion_spec();
+        let lines: Vec<&str> = spec.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "spec\t1\tTOOL_COMPLETE",
+                "command\ttool\tManage remote things",
+                "flag\ttool\thelp\th\tswitch\tShow help",
+                "flag\ttool\tformat\t\tvalue\t",
+                "values\ttool\tformat\tjson\tyaml",
+                "flag\ttool\tverbose\tv\tswitch\tVerbose output",
+                "command\ttool remote\tManage remotes",
+                "alias\ttool remote\trm",
+                "flag\ttool remote\tcolor\t\toptional\t",
+                "command\ttool remote add\tAdd a remote",
+                "flag\ttool remote add\turl\t\tvalue\tRemote URL",
+                "dynamic-flag\ttool remote add\turl",
+                "dynamic-args\ttool remote add",
+            ]
+        );
+    }
+}
@@ -13,12 +13,21 @@ use std::time::Duration;
 /// Default timeout for completion operations (2 seconds)
 pub const DEFAULT_COMPLETION_TIMEOUT: Duration = Duration::from_secs(2);
 
+/// The hint a timed-out completion carries
+pub(crate) const TIMED_OUT_HELP: &str =
+    "⚠️  Completion timed out - results may be incomplete. Try a more specific prefix.";
+
 /// Wraps a completion function with a timeout
 ///
 /// This function ensures that completion operations don't hang indefinitely
 /// by imposing a timeout. If the operation doesn't complete within the timeout,
 /// it returns a partial result with a help message indicating the timeout.
 ///
+/// `f` runs on its own thread with a copy of `ctx` holding its arguments,
+/// flags and command path. Values stored with [`Context::set`] and
+/// [`Context::root`] aren't carried over, so a function needing them
+/// should capture what it uses, such as an `Arc` of a shared client.
+///
 /// # Arguments
 ///
 /// * `f` - The completion function to wrap
@@ -43,8 +52,8 @@ where
     let result: Arc<Mutex<Option<Result<CompletionResult>>>> = Arc::new(Mutex::new(None));
     let result_clone = Arc::clone(&result);
 
-    // Clone necessary data for the thread
-    let ctx_clone = Context::new(ctx.args().to_vec());
+    // The thread may outlive `ctx`, so it gets a copy
+    let ctx_clone = ctx.detached();
     let prefix_clone = prefix.to_string();
 
     // Spawn the completion function in a separate thread
@@ -75,9 +84,7 @@ where
     } else {
         // Timeout occurred
         let mut partial_result = CompletionResult::new();
-        partial_result = partial_result.add_help_text(
-            "⚠️  Completion timed out - results may be incomplete. Try a more specific prefix.",
-        );
+        partial_result = partial_result.add_help_text(TIMED_OUT_HELP);
 
         // If we have any partial results from before the timeout, include them
         if let Ok(result_lock) = result.lock() {
@@ -146,6 +153,7 @@ impl CompletionResult {
         self.values.extend(other.values);
         self.descriptions.extend(other.descriptions);
         self.active_help.extend(other.active_help);
+        self.default = self.default.or(other.default);
         self
     }
 }
@@ -3,8 +3,16 @@
 //! The context module provides a way to pass data between parent and child
 //! commands, including parsed arguments, flags, and arbitrary typed values.
 
+use crate::cancel::CancellationToken;
+use crate::command::Command;
+use crate::error::{Error, Result};
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 /// Context passed to command handlers
 ///
@@ -39,13 +47,23 @@ use std::collections::HashMap;
 /// let config = ctx.get::<Config>().unwrap();
 /// assert_eq!(config.api_key, "secret");
 /// ```
-pub struct Context {
+pub struct Context<'a> {
     args: Vec<String>,
     flags: HashMap<String, String>,
+    changed: HashSet<String>,
     values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    input: Option<Box<dyn BufRead + Send + Sync>>,
+    command_path: Vec<String>,
+    forwarded_args: Vec<String>,
+    passthrough_args: Vec<String>,
+    cancellation: CancellationToken,
+    flags_os: HashMap<String, OsString>,
+    flag_lists: HashMap<String, Vec<String>>,
+    raw_args: Vec<Option<OsString>>,
+    root: Option<&'a Command>,
 }
 
-impl Context {
+impl<'a> Context<'a> {
     /// Creates a new context with the given arguments
     ///
     /// # Arguments
@@ -55,7 +73,17 @@ impl Context {
         Self {
             args,
             flags: HashMap::new(),
+            changed: HashSet::new(),
             values: HashMap::new(),
+            input: None,
+            command_path: Vec::new(),
+            forwarded_args: Vec::new(),
+            passthrough_args: Vec::new(),
+            cancellation: CancellationToken::new(),
+            flags_os: HashMap::new(),
+            flag_lists: HashMap::new(),
+            raw_args: Vec::new(),
+            root: None,
         }
     }
 
@@ -88,6 +116,178 @@ impl Context {
         &mut self.args
     }
 
+    /// Returns the arguments after the first one equal to `marker`
+    ///
+    /// Useful for commands that split their arguments into groups at a
+    /// keyword, like `tool run build with --release`. The slice is empty
+    /// when `marker` isn't among the arguments. `--` can't be used as a
+    /// marker: by the time a run function is called, `--` is gone and the
+    /// arguments after it are simply appended to [`args`](Self::args).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let ctx = Context::new(vec!["build".to_string(), "with".to_string(), "--release".to_string()]);
+    /// assert_eq!(ctx.args_after("with"), &["--release"]);
+    /// assert!(ctx.args_after("without").is_empty());
+    /// ```
+    pub fn args_after(&self, marker: &str) -> &[String] {
+        self.args
+            .iter()
+            .position(|arg| arg == marker)
+            .map_or(&[], |pos| &self.args[pos + 1..])
+    }
+
+    /// Returns the names of the command being run, from the root
+    ///
+    /// Set once the command is known, so run functions, hooks and
+    /// [`CommandBuilder::validate`](crate::CommandBuilder::validate) checks
+    /// see e.g. `["app", "remote", "add"]`. Empty before that.
+    pub fn command_path(&self) -> &[String] {
+        &self.command_path
+    }
+
+    pub(crate) fn set_command_path(&mut self, path: Vec<String>) {
+        self.command_path = path;
+    }
+
+    /// Copies this context for a thread that may outlive it
+    ///
+    /// Arguments, flags and the command path come along. Values stored
+    /// with [`set`](Self::set), the input and the root can't be copied
+    /// and are left out.
+    pub(crate) fn detached(&self) -> Context<'static> {
+        Context {
+            args: self.args.clone(),
+            flags: self.flags.clone(),
+            changed: self.changed.clone(),
+            values: HashMap::new(),
+            input: None,
+            command_path: self.command_path.clone(),
+            forwarded_args: self.forwarded_args.clone(),
+            passthrough_args: self.passthrough_args.clone(),
+            cancellation: self.cancellation.clone(),
+            flags_os: self.flags_os.clone(),
+            flag_lists: self.flag_lists.clone(),
+            raw_args: self.raw_args.clone(),
+            root: None,
+        }
+    }
+
+    /// Returns the words a
+    /// [`passthrough_unknown`](crate::CommandBuilder::passthrough_unknown)
+    /// command didn't recognize, in the order typed
+    ///
+    /// Empty for other commands.
+    pub fn forwarded_args(&self) -> &[String] {
+        &self.forwarded_args
+    }
+
+    pub(crate) fn set_forwarded_args(&mut self, args: Vec<String>) {
+        self.forwarded_args = args;
+    }
+
+    /// Returns the words after `--`, exactly as typed
+    ///
+    /// Flags before `--` are the command's own, so in
+    /// `app run --verbose -- prog --verbose` only the first `--verbose`
+    /// sets a flag and this returns `["prog", "--verbose"]`. The same words
+    /// still end [`args`](Self::args), or
+    /// [`forwarded_args`](Self::forwarded_args) for a passthrough command.
+    /// Empty without `--`, and for
+    /// [`trailing_var_arg`](crate::CommandBuilder::trailing_var_arg)
+    /// commands, whose arguments keep the `--`.
+    pub fn passthrough_args(&self) -> &[String] {
+        &self.passthrough_args
+    }
+
+    pub(crate) fn set_passthrough_args(&mut self, args: Vec<String>) {
+        self.passthrough_args = args;
+    }
+
+    /// Returns the command this execution started from
+    ///
+    /// Handlers can inspect the running command tree without rebuilding
+    /// it, for example a `completion` subcommand generating a script with
+    /// [`Command::generate_completion`]. Set while
+    /// [`Command::execute`](crate::Command::execute) or one of its variants
+    /// runs, and `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flag_rs::{CommandBuilder, Shell};
+    ///
+    /// let app = CommandBuilder::new("myapp")
+    ///     .subcommand(
+    ///         CommandBuilder::new("completion")
+    ///             .run(|ctx| {
+    ///                 let root = ctx.root().expect("set while executing");
+    ///                 print!("{}", root.generate_completion(Shell::Bash));
+    ///                 Ok(())
+    ///             })
+    ///             .build(),
+    ///     )
+    ///     .build();
+    ///
+    /// app.execute(vec!["completion".to_string()]).unwrap();
+    /// ```
+    ///
+    /// The context borrows the root, so it can't outlive the command:
+    ///
+    /// ```rust,compile_fail
+    /// use flag_rs::{CommandBuilder, Context};
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// {
+    ///     let app = CommandBuilder::new("app").run(|_| Ok(())).build();
+    ///     app.execute_with_context(&mut ctx).unwrap();
+    /// }
+    /// let _ = ctx.root();
+    /// ```
+    pub fn root(&self) -> Option<&'a Command> {
+        self.root
+    }
+
+    /// Sets the command returned by [`root`](Self::root), returning the
+    /// previous one
+    pub(crate) fn set_root(&mut self, root: Option<&'a Command>) -> Option<&'a Command> {
+        std::mem::replace(&mut self.root, root)
+    }
+
+    /// Puts back a root saved by [`set_root`](Self::set_root)
+    pub(crate) fn restore_root(&mut self, previous: Option<&'a Command>) {
+        self.root = previous;
+    }
+
+    /// Returns the token that tells this run to stop early
+    ///
+    /// Cancelled when the command's
+    /// [`execution_timeout`](crate::CommandBuilder::execution_timeout) deadline
+    /// passes. Clone it to check from threads the run function starts.
+    pub const fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Returns whether this run has been asked to stop
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let ctx = Context::new(vec![]);
+    /// assert!(!ctx.is_cancelled());
+    ///
+    /// ctx.cancellation().cancel();
+    /// assert!(ctx.is_cancelled());
+    /// ```
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
     /// Gets the value of a flag by name
     ///
     /// # Arguments
@@ -103,14 +303,104 @@ impl Context {
 
     /// Sets a flag value
     ///
+    /// The flag counts as explicitly set; see [`flag_changed`](Self::flag_changed).
+    ///
     /// # Arguments
     ///
     /// * `name` - The name of the flag
     /// * `value` - The value to set
     pub fn set_flag(&mut self, name: String, value: String) {
+        self.flags_os.remove(&name);
+        self.flag_lists.remove(&name);
+        self.changed.insert(name.clone());
+        self.flags.insert(name, value);
+    }
+
+    /// Sets a flag value that may not be valid UTF-8
+    ///
+    /// [`flag`](Self::flag) then returns the value converted lossily, with
+    /// invalid sequences replaced by `U+FFFD`, while
+    /// [`flag_os`](Self::flag_os) returns it unchanged.
+    pub fn set_flag_os(&mut self, name: String, value: OsString) {
+        let lossy = value.to_string_lossy().into_owned();
+        self.flag_lists.remove(&name);
+        self.changed.insert(name.clone());
+        self.flags.insert(name.clone(), lossy);
+        self.flags_os.insert(name, value);
+    }
+
+    /// Keeps the items a list flag was parsed into
+    ///
+    /// The flag's value stays what was typed, comma-joined; the slice
+    /// getters read these items instead of splitting it.
+    pub(crate) fn set_flag_items(&mut self, name: String, items: Vec<String>) {
+        self.flag_lists.insert(name, items);
+    }
+
+    /// Gets a flag's value exactly as it was given
+    ///
+    /// Arguments passed to [`Command::execute_os`](crate::Command::execute_os)
+    /// needn't be valid UTF-8, such as file names on Unix. For those,
+    /// [`flag`](Self::flag) has a lossy copy and this returns the original;
+    /// other values are the same either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    /// use std::ffi::OsStr;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("out".to_string(), "report.txt".to_string());
+    ///
+    /// assert_eq!(ctx.flag_os("out"), Some(OsStr::new("report.txt")));
+    /// assert_eq!(ctx.flag_os("missing"), None);
+    /// ```
+    pub fn flag_os(&self, name: &str) -> Option<&OsStr> {
+        self.flags_os
+            .get(name)
+            .map(OsString::as_os_str)
+            .or_else(|| self.flags.get(name).map(OsStr::new))
+    }
+
+    /// Remembers the original form of arguments that weren't valid UTF-8,
+    /// by their position in [`args`](Self::args)
+    pub(crate) fn set_raw_args(&mut self, raw_args: Vec<Option<OsString>>) {
+        self.raw_args = raw_args;
+    }
+
+    /// Takes the originals set by [`set_raw_args`](Self::set_raw_args)
+    pub(crate) fn take_raw_args(&mut self) -> Vec<Option<OsString>> {
+        std::mem::take(&mut self.raw_args)
+    }
+
+    /// Sets a flag to its default without marking it as changed
+    pub(crate) fn set_default_flag(&mut self, name: String, value: String) {
         self.flags.insert(name, value);
     }
 
+    /// Returns whether a flag was explicitly set rather than defaulted
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("serve")
+    ///     .flag(Flag::int("port").default_int(8080))
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.flag("port").map(String::as_str), Some("8080"));
+    ///         assert!(!ctx.flag_changed("port"));
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(vec![]).unwrap();
+    /// ```
+    pub fn flag_changed(&self, name: &str) -> bool {
+        self.changed.contains(name)
+    }
+
     /// Returns a reference to all flags
     pub fn flags(&self) -> &HashMap<String, String> {
         &self.flags
@@ -198,6 +488,114 @@ impl Context {
         self.flag(name).and_then(|v| v.parse().ok())
     }
 
+    /// Gets how many times a `Count` flag was given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the flag
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(u64)` if the flag exists and holds a count, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("app")
+    ///     .flag(Flag::count("verbose").short('v'))
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.flag_count("verbose"), Some(3));
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(vec!["-vv".to_string(), "-v".to_string()]).unwrap();
+    /// ```
+    pub fn flag_count(&self, name: &str) -> Option<u64> {
+        self.flag(name).and_then(|v| v.parse().ok())
+    }
+
+    /// Gets a flag value as a timestamp in seconds since the Unix epoch
+    ///
+    /// Accepts the same formats as `FlagType::Timestamp`: RFC3339, epoch
+    /// seconds, `now`, and relative offsets like `-1h`. Relative values are
+    /// resolved against the current time when this is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the flag
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(i64)` if the flag exists and is a valid timestamp, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("since".to_string(), "2024-01-15T10:30:00Z".to_string());
+    ///
+    /// assert_eq!(ctx.flag_timestamp("since"), Some(1_705_314_600));
+    /// assert_eq!(ctx.flag_timestamp("missing"), None);
+    /// ```
+    pub fn flag_timestamp(&self, name: &str) -> Option<i64> {
+        self.flag(name)
+            .and_then(|v| crate::flag::parse_timestamp(v))
+    }
+
+    /// Gets a flag value as a duration
+    ///
+    /// Accepts the same formats as `FlagType::Duration`, like `500ms`,
+    /// `30s` or `1h30m`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Duration)` if the flag exists and is a valid duration, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    /// use std::time::Duration;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("timeout".to_string(), "1m30s".to_string());
+    ///
+    /// assert_eq!(ctx.flag_duration("timeout"), Some(Duration::from_secs(90)));
+    /// assert_eq!(ctx.flag_duration("missing"), None);
+    /// ```
+    pub fn flag_duration(&self, name: &str) -> Option<Duration> {
+        self.flag(name).and_then(|v| crate::flag::parse_duration(v))
+    }
+
+    /// Gets a flag value as a number of bytes
+    ///
+    /// Accepts the same formats as `FlagType::Bytes`, like `4096`, `10MB`
+    /// or `512MiB`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(i64)` if the flag exists and is a valid size, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("max-size".to_string(), "512MiB".to_string());
+    ///
+    /// assert_eq!(ctx.flag_bytes("max-size"), Some(512 * 1024 * 1024));
+    /// assert_eq!(ctx.flag_bytes("missing"), None);
+    /// ```
+    pub fn flag_bytes(&self, name: &str) -> Option<i64> {
+        self.flag(name).and_then(|v| crate::flag::parse_bytes(v))
+    }
+
     /// Gets a flag value as a string, returning a default if not present
     ///
     /// # Arguments
@@ -220,7 +618,7 @@ impl Context {
     /// assert_eq!(ctx.flag_str_or("env", "development"), "production");
     /// assert_eq!(ctx.flag_str_or("missing", "development"), "development");
     /// ```
-    pub fn flag_str_or<'a>(&'a self, name: &str, default: &'a str) -> &'a str {
+    pub fn flag_str_or<'s>(&'s self, name: &str, default: &'s str) -> &'s str {
         self.flag(name).map_or(default, String::as_str)
     }
 
@@ -290,6 +688,213 @@ impl Context {
         self.flag_float(name).unwrap_or(default)
     }
 
+    /// Gets a flag's value as a path
+    ///
+    /// Paths that aren't valid UTF-8 are returned unchanged; see
+    /// [`flag_os`](Self::flag_os).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    /// use std::path::PathBuf;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("config".to_string(), "/etc/app.toml".to_string());
+    ///
+    /// assert_eq!(ctx.flag_path("config"), Some(PathBuf::from("/etc/app.toml")));
+    /// assert_eq!(ctx.flag_path("missing"), None);
+    /// ```
+    pub fn flag_path(&self, name: &str) -> Option<PathBuf> {
+        self.flag_os(name).map(PathBuf::from)
+    }
+
+    /// Gets a repeated flag's items as strings
+    ///
+    /// Each occurrence of a parsed flag is one item, or several on a
+    /// [`comma_separated`](crate::Flag::comma_separated) flag, so `--tag a
+    /// --tag b` reads the same as `--tag a,b` there. A value set with
+    /// [`set_flag`](Self::set_flag) is split on commas, with `\,` for a
+    /// comma inside an item. Items are trimmed and empty ones are dropped.
+    /// Returns `None` if the flag is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("tag".to_string(), "web, db".to_string());
+    ///
+    /// assert_eq!(ctx.flag_string_slice("tag"), Some(vec!["web".to_string(), "db".to_string()]));
+    /// assert_eq!(ctx.flag_string_slice("missing"), None);
+    /// ```
+    pub fn flag_string_slice(&self, name: &str) -> Option<Vec<String>> {
+        self.flag(name)?;
+        Some(self.flag_items(name))
+    }
+
+    /// Gets a repeated flag's `key=value` items as pairs
+    ///
+    /// Repeated slice flags are stored comma-joined, so `--label a=1
+    /// --label b=2` and `--label a=1,b=2` read the same. Returns an empty
+    /// list if the flag is missing or any item lacks an `=`; use
+    /// [`flag_pairs_checked`](Self::flag_pairs_checked) to see the problem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("label".to_string(), "env=prod,tier=web".to_string());
+    ///
+    /// assert_eq!(
+    ///     ctx.flag_pairs("label"),
+    ///     vec![
+    ///         ("env".to_string(), "prod".to_string()),
+    ///         ("tier".to_string(), "web".to_string()),
+    ///     ]
+    /// );
+    /// assert!(ctx.flag_pairs("missing").is_empty());
+    /// ```
+    pub fn flag_pairs(&self, name: &str) -> Vec<(String, String)> {
+        self.flag_pairs_checked(name).unwrap_or_default()
+    }
+
+    /// Gets a repeated flag's `key=value` items as pairs, reporting bad items
+    ///
+    /// Only the first `=` splits an item, so values may contain `=`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` for the first item without an `=` or
+    /// with an empty key.
+    pub fn flag_pairs_checked(&self, name: &str) -> Result<Vec<(String, String)>> {
+        self.flag_items(name)
+            .into_iter()
+            .map(|item| match item.split_once('=') {
+                Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+                _ => Err(Error::flag_parsing_with_suggestions(
+                    format!("Invalid key=value pair: '{item}'"),
+                    name,
+                    vec!["key=value (e.g., env=prod)".to_string()],
+                )),
+            })
+            .collect()
+    }
+
+    /// Gets a `FlagType::KeyValue` flag's pairs
+    ///
+    /// Reads the same as [`flag_pairs`](Self::flag_pairs), but returns
+    /// `None` if the flag is missing or any item lacks an `=`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("set".to_string(), "replicas=3,image=web:1.2".to_string());
+    ///
+    /// assert_eq!(
+    ///     ctx.flag_key_value("set"),
+    ///     Some(vec![
+    ///         ("replicas".to_string(), "3".to_string()),
+    ///         ("image".to_string(), "web:1.2".to_string()),
+    ///     ])
+    /// );
+    /// assert_eq!(ctx.flag_key_value("missing"), None);
+    /// ```
+    pub fn flag_key_value(&self, name: &str) -> Option<Vec<(String, String)>> {
+        self.flag(name)?;
+        self.flag_pairs_checked(name).ok()
+    }
+
+    /// Gets a repeated flag's items as integers
+    ///
+    /// Returns an empty list if the flag is missing or any item isn't an
+    /// integer; use [`flag_int_slice_checked`](Self::flag_int_slice_checked)
+    /// to see the problem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("port".to_string(), "80,443".to_string());
+    ///
+    /// assert_eq!(ctx.flag_int_slice("port"), vec![80, 443]);
+    /// ```
+    pub fn flag_int_slice(&self, name: &str) -> Vec<i64> {
+        self.flag_int_slice_checked(name).unwrap_or_default()
+    }
+
+    /// Gets a repeated flag's items as integers, reporting bad items
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` for the first item that isn't an integer.
+    pub fn flag_int_slice_checked(&self, name: &str) -> Result<Vec<i64>> {
+        self.parse_items(name, "integer", "a whole number (e.g., 42, -10, 0)")
+    }
+
+    /// Gets a repeated flag's items as floats
+    ///
+    /// Returns an empty list if the flag is missing or any item isn't a
+    /// number; use [`flag_float_slice_checked`](Self::flag_float_slice_checked)
+    /// to see the problem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_flag("weight".to_string(), "0.5,1.5".to_string());
+    ///
+    /// assert_eq!(ctx.flag_float_slice("weight"), vec![0.5, 1.5]);
+    /// ```
+    pub fn flag_float_slice(&self, name: &str) -> Vec<f64> {
+        self.flag_float_slice_checked(name).unwrap_or_default()
+    }
+
+    /// Gets a repeated flag's items as floats, reporting bad items
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` for the first item that isn't a number.
+    pub fn flag_float_slice_checked(&self, name: &str) -> Result<Vec<f64>> {
+        self.parse_items(name, "float", "a decimal number (e.g., 3.14, -0.5, 1e10)")
+    }
+
+    /// Returns a flag's items as parsed, or its comma-joined value split
+    fn flag_items(&self, name: &str) -> Vec<String> {
+        if let Some(items) = self.flag_lists.get(name) {
+            return items.clone();
+        }
+        self.flag(name)
+            .map(|value| crate::flag::split_list(value))
+            .unwrap_or_default()
+    }
+
+    /// Parses each item of a flag, failing on the first bad one
+    fn parse_items<T: FromStr>(&self, name: &str, kind: &str, expected: &str) -> Result<Vec<T>> {
+        self.flag_items(name)
+            .into_iter()
+            .map(|item| {
+                item.parse().map_err(|_| {
+                    Error::flag_parsing_with_suggestions(
+                        format!("Invalid {kind} value: '{item}'"),
+                        name,
+                        vec![expected.to_string()],
+                    )
+                })
+            })
+            .collect()
+    }
+
     /// Stores a typed value in the context
     ///
     /// Values are stored by their type, so only one value of each type
@@ -345,12 +950,173 @@ impl Context {
             .get_mut(&TypeId::of::<T>())
             .and_then(|v| (**v).downcast_mut())
     }
+
+    /// Replaces stdin as the source of answers to interactive prompts
+    ///
+    /// Input set this way is always treated as interactive, which makes
+    /// prompts such as autocorrect confirmations testable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::context::Context;
+    /// use std::io::Cursor;
+    ///
+    /// let mut ctx = Context::new(vec![]);
+    /// ctx.set_input(Cursor::new("y\n"));
+    /// ```
+    pub fn set_input<R: BufRead + Send + Sync + 'static>(&mut self, input: R) {
+        self.input = Some(Box::new(input));
+    }
+
+    /// Asks a yes/no question on stderr and reads the answer
+    ///
+    /// An empty answer picks `default`. Returns `None` when there is nobody
+    /// to ask: no input was set and stdin isn't a terminal, or the input
+    /// ended before an answer.
+    pub(crate) fn confirm(&mut self, question: &str, default: bool) -> Option<bool> {
+        let choices = if default { "[Y/n]" } else { "[y/N]" };
+        let answer = self.prompt(&format!("{question} {choices}"))?;
+
+        match answer.to_lowercase().as_str() {
+            "" => Some(default),
+            "y" | "yes" => Some(true),
+            _ => Some(false),
+        }
+    }
+
+    /// Asks a question on stderr and reads one trimmed line of answer
+    ///
+    /// Reads from the input set with [`set_input`](Self::set_input), or
+    /// from stdin when it is a terminal. Returns `None` when there is no one
+    /// to ask or the input has ended.
+    pub(crate) fn prompt(&mut self, question: &str) -> Option<String> {
+        use std::io::IsTerminal;
+
+        let mut answer = String::new();
+        let read = if let Some(input) = self.input.as_mut() {
+            eprint!("{question} ");
+            let _ = std::io::stderr().flush();
+            input.read_line(&mut answer)
+        } else if std::io::stdin().is_terminal() {
+            eprint!("{question} ");
+            let _ = std::io::stderr().flush();
+            std::io::stdin().lock().read_line(&mut answer)
+        } else {
+            return None;
+        };
+        if !matches!(read, Ok(n) if n > 0) {
+            return None;
+        }
+        Some(answer.trim().to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_flag_os_keeps_invalid_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let raw = OsString::from_vec(b"caf\xe9.txt".to_vec());
+        let mut ctx = Context::new(vec![]);
+        ctx.set_flag_os("out".to_string(), raw.clone());
+
+        assert_eq!(ctx.flag_os("out"), Some(raw.as_os_str()));
+        assert_eq!(ctx.flag("out").map(String::as_str), Some("caf\u{fffd}.txt"));
+        assert_eq!(ctx.flag_path("out"), Some(PathBuf::from(raw)));
+        assert!(ctx.flag_changed("out"));
+
+        // Setting a plain value again drops the raw one
+        ctx.set_flag("out".to_string(), "plain.txt".to_string());
+        assert_eq!(ctx.flag_os("out"), Some(OsStr::new("plain.txt")));
+    }
+
+    #[test]
+    fn test_confirm_with_input() {
+        let mut ctx = Context::new(vec![]);
+        ctx.set_input(std::io::Cursor::new("yes\nN\n\n"));
+        assert_eq!(ctx.confirm("Continue?", false), Some(true));
+        assert_eq!(ctx.confirm("Continue?", true), Some(false));
+        assert_eq!(ctx.confirm("Continue?", true), Some(true));
+        // Input exhausted
+        assert_eq!(ctx.confirm("Continue?", true), None);
+    }
+
+    #[test]
+    fn test_flag_pairs() {
+        let mut ctx = Context::new(vec![]);
+        ctx.set_flag("label".to_string(), "env=prod,query=a=b, tier=".to_string());
+        assert_eq!(
+            ctx.flag_pairs("label"),
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("query".to_string(), "a=b".to_string()),
+                ("tier".to_string(), String::new()),
+            ]
+        );
+
+        ctx.set_flag("label".to_string(), "env=prod,oops".to_string());
+        assert!(ctx.flag_pairs("label").is_empty());
+        let err = ctx.flag_pairs_checked("label").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::FlagParsing { ref message, flag: Some(ref flag), .. }
+                if message.contains("'oops'") && flag == "label"
+        ));
+    }
+
+    #[test]
+    fn test_flag_string_slice_and_path() {
+        let mut ctx = Context::new(vec![]);
+        ctx.set_flag("tag".to_string(), "web,,db ,cache".to_string());
+        ctx.set_flag("config".to_string(), "conf/app.toml".to_string());
+
+        assert_eq!(
+            ctx.flag_string_slice("tag"),
+            Some(["web", "db", "cache"].map(String::from).to_vec())
+        );
+        assert_eq!(ctx.flag_string_slice("missing"), None);
+
+        assert_eq!(
+            ctx.flag_path("config"),
+            Some(std::path::Path::new("conf").join("app.toml"))
+        );
+        assert_eq!(ctx.flag_path("missing"), None);
+    }
+
+    #[test]
+    fn test_flag_numeric_slices() {
+        let mut ctx = Context::new(vec![]);
+        ctx.set_flag("port".to_string(), "80,443,-1".to_string());
+        ctx.set_flag("weight".to_string(), "0.5,2".to_string());
+
+        assert_eq!(ctx.flag_int_slice("port"), vec![80, 443, -1]);
+        assert_eq!(ctx.flag_float_slice("weight"), vec![0.5, 2.0]);
+        assert!(ctx.flag_int_slice_checked("missing").unwrap().is_empty());
+
+        ctx.set_flag("port".to_string(), "80,http".to_string());
+        assert!(ctx.flag_int_slice("port").is_empty());
+        let err = ctx.flag_int_slice_checked("port").unwrap_err();
+        assert!(err.to_string().contains("Invalid integer value: 'http'"));
+        assert!(ctx.flag_float_slice_checked("weight").is_ok());
+    }
+
+    #[test]
+    fn test_args_after() {
+        let ctx = Context::new(
+            ["build", "with", "a", "with", "b"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert_eq!(ctx.args_after("with"), &["a", "with", "b"]);
+        assert_eq!(ctx.args_after("b"), &[] as &[String]);
+        assert!(ctx.args_after("missing").is_empty());
+    }
+
     #[test]
     fn test_context_args() {
         let args = vec!["arg1".to_string(), "arg2".to_string()];
@@ -0,0 +1,35 @@
+//! Walking a command tree for the documentation generators
+//!
+//! Man pages, Markdown and the static completion spec list the same
+//! commands and flags in the same order; these helpers keep them in step.
+
+use crate::command::Command;
+use crate::flag::Flag;
+
+/// Returns the command's names from the root, joined with `separator`
+pub(crate) fn command_path(ancestors: &[&Command], cmd: &Command, separator: &str) -> String {
+    ancestors
+        .iter()
+        .map(|cmd| cmd.name())
+        .chain(std::iter::once(cmd.name()))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Returns the subcommands that aren't hidden, sorted by name
+pub(crate) fn sorted_subcommands(cmd: &Command) -> Vec<&Command> {
+    let mut subs: Vec<&Command> = cmd
+        .subcommands()
+        .values()
+        .filter(|sub| !sub.is_hidden())
+        .collect();
+    subs.sort_by(|a, b| a.name().cmp(b.name()));
+    subs
+}
+
+/// Returns the flags that aren't hidden, sorted by name
+pub(crate) fn sorted_flags(cmd: &Command) -> Vec<&Flag> {
+    let mut flags: Vec<&Flag> = cmd.flags().values().filter(|flag| !flag.hidden).collect();
+    flags.sort_by(|a, b| a.name.cmp(&b.name));
+    flags
+}
@@ -8,8 +8,10 @@ use std::fmt;
 /// The main error type for the flag framework
 ///
 /// This enum represents all possible errors that can occur during command
-/// parsing, validation, and execution.
+/// parsing, validation, and execution. New variants may be added, so
+/// matches on it need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The specified command was not found
     ///
@@ -36,7 +38,10 @@ pub enum Error {
     /// An error occurred while parsing flag values
     ///
     /// This error occurs when a flag value cannot be parsed as the expected type
-    /// (e.g., "abc" for an integer flag).
+    /// (e.g., "abc" for an integer flag). Build it with
+    /// [`Error::flag_parsing`] and the other constructors; match it with `..`
+    /// so new fields can be added.
+    #[non_exhaustive]
     FlagParsing {
         /// Description of the error
         message: String,
@@ -44,6 +49,8 @@ pub enum Error {
         flag: Option<String>,
         /// Suggested valid values or format
         suggestions: Vec<String>,
+        /// Close matches for the rejected value, shown as "Did you mean"
+        did_you_mean: Vec<String>,
     },
 
     /// An error occurred while parsing command arguments
@@ -85,6 +92,16 @@ pub enum Error {
     ///
     /// Allows command handlers to return their own error types.
     Custom(Box<dyn std::error::Error + Send + Sync>),
+
+    /// Ends the program with a specific exit code
+    ///
+    /// Lets command handlers choose the process status, e.g. `130` after
+    /// being interrupted. It is meant to be silent: the handler has already
+    /// reported whatever went wrong, so [`Command::execute_or_exit`] exits
+    /// without printing it.
+    ///
+    /// [`Command::execute_or_exit`]: crate::Command::execute_or_exit
+    Exit(i32),
 }
 
 impl fmt::Display for Error {
@@ -140,12 +157,31 @@ impl fmt::Display for Error {
                 message,
                 flag,
                 suggestions,
+                did_you_mean,
             } => {
                 write!(f, "{}: {}", color::red("Error"), message)?;
-                if let Some(flag_name) = flag {
+                // Messages like "unknown flag --foo" name the flag already
+                if let Some(flag_name) = flag
+                    .as_ref()
+                    .filter(|name| !message.contains(&format!("--{name}")))
+                {
                     write!(f, " for flag '{}'", color::bold(flag_name))?;
                 }
 
+                if let [only] = did_you_mean.as_slice() {
+                    write!(
+                        f,
+                        "\n\n{} '{}'?",
+                        color::yellow("Did you mean"),
+                        color::green(only)
+                    )?;
+                } else if !did_you_mean.is_empty() {
+                    write!(f, "\n\n{}?", color::yellow("Did you mean one of these"))?;
+                    for suggestion in did_you_mean {
+                        write!(f, "\n    {}", color::green(suggestion))?;
+                    }
+                }
+
                 if !suggestions.is_empty() {
                     write!(f, "\n\n")?;
                     if suggestions.len() == 1 {
@@ -193,6 +229,9 @@ impl fmt::Display for Error {
             Self::Custom(err) => {
                 write!(f, "{}: {}", color::red("Error"), err)
             }
+            Self::Exit(code) => {
+                write!(f, "exit status {code}")
+            }
         }
     }
 }
@@ -213,6 +252,35 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Self::Custom(err)
+    }
+}
+
+/// Wraps common standard library errors in [`Error::Custom`], so handlers
+/// can use `?` on them directly
+macro_rules! impl_from_custom {
+    ($($err:ty),* $(,)?) => {
+        $(
+            impl From<$err> for Error {
+                fn from(err: $err) -> Self {
+                    Self::Custom(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_custom!(
+    std::fmt::Error,
+    std::num::ParseIntError,
+    std::num::ParseFloatError,
+    std::str::ParseBoolError,
+    std::str::Utf8Error,
+    std::string::FromUtf8Error,
+);
+
 /// Type alias for Results with the flag Error type
 ///
 /// This is a convenience type alias for `std::result::Result<T, flag::Error>`.
@@ -229,13 +297,225 @@ impl From<std::io::Error> for Error {
 /// ```
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A wiring problem in a command tree, found by [`Command::validate_tree`]
+///
+/// Each problem carries the path of the command it was found on, such as
+/// `"kubectl config view"`.
+///
+/// [`Command::validate_tree`]: crate::Command::validate_tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// Two subcommands share a name or alias
+    DuplicateCommandName {
+        /// Path of the parent command
+        path: String,
+        /// The name or alias used more than once
+        name: String,
+    },
+    /// Two flags on the same command share a short name
+    DuplicateShort {
+        /// Path of the command defining the flags
+        path: String,
+        /// The shared short name
+        short: char,
+        /// The flags using it, sorted
+        flags: Vec<String>,
+    },
+    /// A constraint names a flag that isn't defined on the command or its parents
+    DanglingConstraint {
+        /// Path of the command defining the flag
+        path: String,
+        /// The flag carrying the constraint
+        flag: String,
+        /// The missing flag the constraint refers to
+        target: String,
+    },
+    /// A range flag whose minimum is above its maximum
+    InvertedRange {
+        /// Path of the command defining the flag
+        path: String,
+        /// The range flag
+        flag: String,
+        /// The declared minimum
+        min: i64,
+        /// The declared maximum
+        max: i64,
+    },
+    /// A required flag that also has a default, so it can never be missing
+    RequiredWithDefault {
+        /// Path of the command defining the flag
+        path: String,
+        /// The flag
+        flag: String,
+    },
+    /// A completer reference to a name no command in scope registers
+    UnknownCompleter {
+        /// Path of the command holding the reference
+        path: String,
+        /// The completer name
+        name: String,
+    },
+    /// A command with profiles also defines its own `--profile` flag
+    ProfileFlagTaken {
+        /// Path of the command defining the profiles
+        path: String,
+    },
+    /// A profile sets a flag that isn't defined on the command, its
+    /// parents or its subcommands
+    UnknownProfileFlag {
+        /// Path of the command defining the profile
+        path: String,
+        /// The profile
+        profile: String,
+        /// The missing flag
+        flag: String,
+    },
+}
+
+impl BuildError {
+    /// Returns the path of the command the problem was found on
+    pub fn path(&self) -> &str {
+        match self {
+            Self::DuplicateCommandName { path, .. }
+            | Self::DuplicateShort { path, .. }
+            | Self::DanglingConstraint { path, .. }
+            | Self::InvertedRange { path, .. }
+            | Self::RequiredWithDefault { path, .. }
+            | Self::UnknownCompleter { path, .. }
+            | Self::ProfileFlagTaken { path }
+            | Self::UnknownProfileFlag { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path())?;
+        match self {
+            Self::DuplicateCommandName { name, .. } => {
+                write!(f, "subcommand name '{name}' is used more than once")
+            }
+            Self::DuplicateShort { short, flags, .. } => {
+                let flags: Vec<String> = flags.iter().map(|flag| format!("--{flag}")).collect();
+                write!(f, "short flag '-{short}' is used by {}", flags.join(", "))
+            }
+            Self::DanglingConstraint { flag, target, .. } => {
+                write!(
+                    f,
+                    "flag '--{flag}' has a constraint on unknown flag '--{target}'"
+                )
+            }
+            Self::InvertedRange { flag, min, max, .. } => {
+                write!(f, "flag '--{flag}' has an empty range {min}..={max}")
+            }
+            Self::RequiredWithDefault { flag, .. } => {
+                write!(f, "flag '--{flag}' is required but also has a default")
+            }
+            Self::UnknownCompleter { name, .. } => {
+                write!(f, "completer '{name}' is not registered")
+            }
+            Self::ProfileFlagTaken { .. } => {
+                write!(
+                    f,
+                    "flag '--profile' is defined, so profiles can't be chosen"
+                )
+            }
+            Self::UnknownProfileFlag { profile, flag, .. } => {
+                write!(f, "profile '{profile}' sets unknown flag '--{flag}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
 impl Error {
+    /// Returns the process exit code this error should end the program with
+    ///
+    /// Mistakes in how the program was invoked, such as unknown commands,
+    /// bad flags or wrong arguments, give `2`, following the shell
+    /// convention for misuse. Errors while running give `1`, and
+    /// [`Error::Exit`] gives the code it carries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::Error;
+    ///
+    /// assert_eq!(Error::Exit(130).exit_code(), 130);
+    /// assert_eq!(Error::Validation("bad input".to_string()).exit_code(), 1);
+    ///
+    /// // In main():
+    /// // if let Err(e) = app.execute(args) {
+    /// //     if !matches!(e, Error::Exit(_)) {
+    /// //         eprintln!("{e}");
+    /// //     }
+    /// //     std::process::exit(e.exit_code());
+    /// // }
+    /// ```
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::CommandNotFound { .. }
+            | Self::SubcommandRequired(_)
+            | Self::FlagParsing { .. }
+            | Self::ArgumentParsing(_)
+            | Self::ArgumentValidation { .. } => 2,
+            Self::NoRunFunction(_)
+            | Self::Validation(_)
+            | Self::Completion(_)
+            | Self::Io(_)
+            | Self::Custom(_) => 1,
+            Self::Exit(code) => *code,
+        }
+    }
+
+    /// Wraps any error, or an error message, in [`Error::Custom`]
+    ///
+    /// A blanket `From` impl for every error type would conflict with
+    /// `From<Error> for Error`, so errors of your own types go through
+    /// this instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Error};
+    ///
+    /// #[derive(Debug)]
+    /// struct NotLoggedIn;
+    ///
+    /// impl std::fmt::Display for NotLoggedIn {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    ///         write!(f, "not logged in")
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for NotLoggedIn {}
+    ///
+    /// fn session() -> Result<String, NotLoggedIn> {
+    ///     Err(NotLoggedIn)
+    /// }
+    ///
+    /// let cmd = CommandBuilder::new("whoami")
+    ///     .run(|_ctx| {
+    ///         println!("{}", session().map_err(Error::custom)?);
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// let err = cmd.execute(vec![]).unwrap_err();
+    /// assert!(matches!(err, Error::Custom(ref inner) if inner.to_string() == "not logged in"));
+    /// ```
+    pub fn custom(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Custom(err.into())
+    }
+
     /// Create a simple flag parsing error
     pub fn flag_parsing(message: impl Into<String>) -> Self {
         Self::FlagParsing {
             message: message.into(),
             flag: None,
             suggestions: vec![],
+            did_you_mean: vec![],
         }
     }
 
@@ -249,6 +529,38 @@ impl Error {
             message: message.into(),
             flag: Some(flag.into()),
             suggestions,
+            did_you_mean: vec![],
+        }
+    }
+
+    /// Create an error for a long flag no command in scope defines
+    ///
+    /// `did_you_mean` holds close flag names, spelled with their dashes.
+    pub fn unknown_flag(flag: impl Into<String>, did_you_mean: Vec<String>) -> Self {
+        let flag = flag.into();
+        Self::FlagParsing {
+            message: format!("unknown flag --{flag}"),
+            flag: Some(flag),
+            suggestions: vec![],
+            did_you_mean,
+        }
+    }
+
+    /// Create an error for a value that is not one of a flag's choices
+    ///
+    /// The choices are listed as suggestions, led by the closest matches
+    /// to `value`.
+    pub fn invalid_choice(
+        value: impl Into<String>,
+        flag: impl Into<String>,
+        choices: Vec<String>,
+    ) -> Self {
+        let value = value.into();
+        Self::FlagParsing {
+            did_you_mean: crate::suggestion::find_value_suggestions(&value, &choices),
+            message: format!("Invalid choice: '{value}'"),
+            flag: Some(flag.into()),
+            suggestions: choices,
         }
     }
 }
@@ -256,12 +568,13 @@ impl Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::no_color;
     use std::error::Error as StdError;
 
     #[test]
     fn test_error_display() {
         // Test without color for predictable test output
-        unsafe { std::env::set_var("NO_COLOR", "1") };
+        let _no_color = no_color();
 
         assert_eq!(
             Error::CommandNotFound {
@@ -280,12 +593,74 @@ mod tests {
                 message: "invalid flag".to_string(),
                 flag: Some("invalid".to_string()),
                 suggestions: vec![],
+                did_you_mean: vec![],
             }
             .to_string(),
             "Error: invalid flag for flag 'invalid'"
         );
+    }
+
+    #[test]
+    fn test_custom_error_conversions() {
+        fn parse_port(value: &str) -> Result<u16> {
+            Ok(value.parse::<u16>()?)
+        }
 
-        unsafe { std::env::remove_var("NO_COLOR") };
+        fn boxed() -> std::result::Result<(), Box<dyn StdError + Send + Sync>> {
+            Err("token expired".into())
+        }
+
+        fn run_boxed() -> Result<()> {
+            boxed()?;
+            Ok(())
+        }
+
+        assert_eq!(parse_port("8080").unwrap(), 8080);
+        let err = parse_port("http").unwrap_err();
+        assert!(matches!(&err, Error::Custom(inner) if inner.is::<std::num::ParseIntError>()));
+        assert!(err.source().is_some());
+
+        let err = run_boxed().unwrap_err();
+        assert!(matches!(&err, Error::Custom(inner) if inner.to_string() == "token expired"));
+
+        let err = Error::custom("disk full");
+        assert!(matches!(&err, Error::Custom(inner) if inner.to_string() == "disk full"));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let usage_errors = [
+            Error::CommandNotFound {
+                command: "strt".to_string(),
+                suggestions: vec![],
+            },
+            Error::SubcommandRequired("app".to_string()),
+            Error::flag_parsing("bad flag"),
+            Error::ArgumentParsing("bad argument".to_string()),
+            Error::ArgumentValidation {
+                message: "too many".to_string(),
+                expected: "1".to_string(),
+                received: 2,
+            },
+        ];
+        for err in usage_errors {
+            assert_eq!(err.exit_code(), 2, "{err:?}");
+        }
+
+        let runtime_errors = [
+            Error::NoRunFunction("app".to_string()),
+            Error::Validation("bad input".to_string()),
+            Error::Completion("failed".to_string()),
+            Error::Io(std::io::Error::other("disk full")),
+            Error::custom("boom"),
+        ];
+        for err in runtime_errors {
+            assert_eq!(err.exit_code(), 1, "{err:?}");
+        }
+
+        assert_eq!(Error::Exit(130).exit_code(), 130);
+        assert_eq!(Error::Exit(0).exit_code(), 0);
+        assert!(Error::Exit(3).source().is_none());
     }
 
     #[test]
@@ -304,7 +679,7 @@ mod tests {
     #[test]
     fn test_error_with_suggestions() {
         // Test without color for predictable test output
-        unsafe { std::env::set_var("NO_COLOR", "1") };
+        let _no_color = no_color();
 
         // Single suggestion
         let error = Error::CommandNotFound {
@@ -325,7 +700,42 @@ mod tests {
             error.to_string(),
             "Error: unknown command lst\n\nDid you mean one of these?\n    list\n    last\n"
         );
+    }
+
+    #[test]
+    fn test_invalid_choice_did_you_mean() {
+        let _no_color = no_color();
+
+        let choices = vec![
+            "development".to_string(),
+            "staging".to_string(),
+            "production".to_string(),
+        ];
+        let error = Error::invalid_choice("prodd", "env", choices.clone());
+        assert_eq!(
+            error.to_string(),
+            "Error: Invalid choice: 'prodd' for flag 'env'\n\nDid you mean 'production'?\n\nExpected one of:\n    development\n    staging\n    production\n"
+        );
 
-        unsafe { std::env::remove_var("NO_COLOR") };
+        // No close match: just the choices
+        let error = Error::invalid_choice("qa", "env", choices);
+        assert!(
+            error
+                .to_string()
+                .starts_with("Error: Invalid choice: 'qa' for flag 'env'\n\nExpected one of:")
+        );
+    }
+
+    #[test]
+    fn test_unknown_flag_names_flag() {
+        let _no_color = no_color();
+
+        let error = Error::unknown_flag("verbos", vec!["--verbose".to_string()]);
+        assert!(matches!(&error, Error::FlagParsing { flag: Some(flag), .. } if flag == "verbos"));
+        // The message names the flag, so it isn't repeated
+        assert_eq!(
+            error.to_string(),
+            "Error: unknown flag --verbos\n\nDid you mean '--verbose'?"
+        );
     }
 }
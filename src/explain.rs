@@ -0,0 +1,144 @@
+//! Explain mode: show how an invocation would be resolved
+//!
+//! With explain enabled (see [`CommandBuilder::explain`]), passing
+//! `--explain` prints the resolved plan — which command runs, the value and
+//! source of each flag, and the arguments it receives — instead of running
+//! it. `--explain=json` prints the same plan as JSON for scripting.
+//!
+//! [`CommandBuilder::explain`]: crate::CommandBuilder::explain
+
+use crate::json::JsonValue;
+use std::fmt::Write;
+
+/// Where a resolved flag value came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagSource {
+    /// Passed on the command line
+    CommandLine,
+    /// Read from the flag's environment variable
+    Environment,
+    /// Set by the `--profile` preset that was chosen
+    Profile,
+    /// Taken from the flag's (possibly overridden) default
+    Default,
+}
+
+impl FlagSource {
+    /// Returns the name used for this source in explain output
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::CommandLine => "command-line",
+            Self::Environment => "env",
+            Self::Profile => "profile",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// A flag value in an [`ExplainPlan`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedFlag {
+    /// The flag's long name
+    pub name: String,
+    /// The value as stored in the context
+    pub value: String,
+    /// Where the value came from
+    pub source: FlagSource,
+}
+
+/// The resolved plan for an invocation
+///
+/// Besides being printed, the plan is stored in the context, so it can be
+/// inspected with `ctx.get::<ExplainPlan>()` after execution.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExplainPlan {
+    /// Command names from the root to the command that would run
+    pub command_path: Vec<String>,
+    /// Resolved flags, sorted by name
+    pub flags: Vec<ExplainedFlag>,
+    /// Positional arguments before any `--`
+    pub args: Vec<String>,
+    /// Arguments after `--`, passed through untouched
+    pub passthrough: Vec<String>,
+}
+
+impl ExplainPlan {
+    /// Renders the plan for people
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::explain::{ExplainPlan, ExplainedFlag, FlagSource};
+    ///
+    /// let plan = ExplainPlan {
+    ///     command_path: vec!["app".to_string(), "deploy".to_string()],
+    ///     flags: vec![ExplainedFlag {
+    ///         name: "env".to_string(),
+    ///         value: "prod".to_string(),
+    ///         source: FlagSource::CommandLine,
+    ///     }],
+    ///     args: vec![],
+    ///     passthrough: vec![],
+    /// };
+    /// assert_eq!(
+    ///     plan.to_text(),
+    ///     "Command: app deploy\nFlags:\n  --env = prod (command-line)\nArgs: (none)\n"
+    /// );
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Command: {}", self.command_path.join(" "));
+        if self.flags.is_empty() {
+            let _ = writeln!(out, "Flags: (none)");
+        } else {
+            let _ = writeln!(out, "Flags:");
+            for flag in &self.flags {
+                let _ = writeln!(
+                    out,
+                    "  --{} = {} ({})",
+                    flag.name,
+                    flag.value,
+                    flag.source.as_str()
+                );
+            }
+        }
+        if self.args.is_empty() {
+            let _ = writeln!(out, "Args: (none)");
+        } else {
+            let _ = writeln!(out, "Args: {}", self.args.join(" "));
+        }
+        if !self.passthrough.is_empty() {
+            let _ = writeln!(out, "Passthrough: {}", self.passthrough.join(" "));
+        }
+        out
+    }
+
+    /// Renders the plan as a single line of JSON
+    ///
+    /// The object has `command` (the path as an array), `flags` (objects
+    /// with `name`, `value` and `source`), `args` and `passthrough`.
+    pub fn to_json(&self) -> String {
+        let strings = |items: &[String]| {
+            JsonValue::Array(items.iter().cloned().map(JsonValue::from).collect())
+        };
+        let flags = self
+            .flags
+            .iter()
+            .map(|flag| {
+                JsonValue::Object(vec![
+                    ("name".to_string(), JsonValue::from(flag.name.as_str())),
+                    ("value".to_string(), JsonValue::from(flag.value.as_str())),
+                    ("source".to_string(), JsonValue::from(flag.source.as_str())),
+                ])
+            })
+            .collect();
+
+        JsonValue::Object(vec![
+            ("command".to_string(), strings(&self.command_path)),
+            ("flags".to_string(), JsonValue::Array(flags)),
+            ("args".to_string(), strings(&self.args)),
+            ("passthrough".to_string(), strings(&self.passthrough)),
+        ])
+        .to_string()
+    }
+}
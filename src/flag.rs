@@ -1,7 +1,8 @@
 //! Flag system for command-line argument parsing
 //!
 //! This module provides a flexible flag parsing system that supports:
-//! - Multiple value types (string, bool, int, float, string slice)
+//! - Multiple value types (string, bool, int, float, string slice, timestamp, duration, byte size,
+//!   IP address, CIDR block, key/value pairs)
 //! - Short and long flag names
 //! - Required and optional flags
 //! - Default values
@@ -9,7 +10,9 @@
 
 use crate::completion::{CompletionFunc, CompletionResult};
 use crate::error::{Error, Result};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::Duration;
 
 /// Represents the value of a parsed flag
 ///
@@ -42,6 +45,12 @@ pub enum FlagValue {
     Float(f64),
     /// A slice of strings (for repeated flags)
     StringSlice(Vec<String>),
+    /// A point in time, in seconds since the Unix epoch
+    Timestamp(i64),
+    /// A length of time
+    Duration(Duration),
+    /// `key=value` pairs, in the order given
+    KeyValue(Vec<(String, String)>),
 }
 
 impl FlagValue {
@@ -116,6 +125,68 @@ impl FlagValue {
             _ => Err(Error::flag_parsing("Flag value is not a string slice")),
         }
     }
+
+    /// Returns the value as seconds since the Unix epoch
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` if the value is not a timestamp
+    pub fn as_timestamp(&self) -> Result<i64> {
+        match self {
+            Self::Timestamp(t) => Ok(*t),
+            _ => Err(Error::flag_parsing("Flag value is not a timestamp")),
+        }
+    }
+
+    /// Returns the value as a duration
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` if the value is not a duration
+    pub fn as_duration(&self) -> Result<Duration> {
+        match self {
+            Self::Duration(d) => Ok(*d),
+            _ => Err(Error::flag_parsing("Flag value is not a duration")),
+        }
+    }
+
+    /// Returns the value as `key=value` pairs
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FlagParsing` if the value is not a list of pairs
+    pub fn as_key_value(&self) -> Result<&Vec<(String, String)>> {
+        match self {
+            Self::KeyValue(pairs) => Ok(pairs),
+            _ => Err(Error::flag_parsing(
+                "Flag value is not a list of key=value pairs",
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for FlagValue {
+    /// Formats the value the way it is stored in a [`Context`](crate::Context)
+    ///
+    /// String slices and `key=value` pairs are joined with commas, and
+    /// durations are written with units, like `1h30m`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String(s) => f.write_str(s),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) | Self::Timestamp(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::StringSlice(v) => f.write_str(&v.join(",")),
+            Self::Duration(d) => f.write_str(&format_duration(*d)),
+            Self::KeyValue(pairs) => {
+                let items: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect();
+                f.write_str(&items.join(","))
+            }
+        }
+    }
 }
 
 /// Represents constraints that can be applied to flags
@@ -130,6 +201,13 @@ pub enum FlagConstraint {
     ConflictsWith(Vec<String>),
     /// This flag requires other flags to be set
     Requires(Vec<String>),
+    /// This flag requires at least one of these flags to be set
+    RequiresOneOf(Vec<String>),
+    /// This flag can't be used when running these subcommands
+    ///
+    /// Each entry is a path below the root command, such as `config` or
+    /// `cluster delete`, and covers that command's subcommands too.
+    InvalidForCommands(Vec<String>),
 }
 
 /// Represents a command-line flag
@@ -156,6 +234,7 @@ pub enum FlagConstraint {
 ///     .value_type(FlagType::String)
 ///     .required();
 /// ```
+#[allow(clippy::struct_excessive_bools)]
 pub struct Flag {
     /// The long name of the flag (e.g., "verbose" for --verbose)
     pub name: String,
@@ -173,6 +252,25 @@ pub struct Flag {
     pub constraints: Vec<FlagConstraint>,
     /// Optional completion function for this flag's values
     pub completion: Option<CompletionFunc>,
+    /// Name of a flag that must be provided for this flag to appear in help
+    pub visible_when: Option<String>,
+    /// Custom words accepted as true and false by a `Bool` flag
+    pub bool_words: Option<(Vec<String>, Vec<String>)>,
+    /// Value used when the flag is given without one
+    pub optional_value: Option<String>,
+    /// Environment variable read when the flag isn't given
+    pub env: Option<String>,
+    /// Whether the flag is left out of help and completion
+    pub hidden: bool,
+    /// Warning shown when the flag is used, if it is deprecated
+    pub deprecated: Option<String>,
+    /// Whether a `Bool` flag can be turned off with `--no-<name>`
+    pub negatable: bool,
+    /// Whether one occurrence of a list flag holds several comma-separated
+    /// items
+    pub comma_separated: bool,
+    /// Heading the flag is listed under in help, if any
+    pub group: Option<String>,
 }
 
 /// Represents the type of value a flag accepts
@@ -192,6 +290,13 @@ pub enum FlagType {
     StringSlice,
     /// Accepts multiple string values with accumulation (--tag=a --tag=b)
     StringArray,
+    /// Accepts `key=value` pairs, accumulated like a string slice
+    ///
+    /// Each occurrence is one pair, as in `--label app=web --label
+    /// tier=front`. Only the first `=` splits a pair, so values may contain
+    /// `=` and commas. With [`Flag::comma_separated`],
+    /// `--label app=web,tier=front` gives two pairs.
+    KeyValue,
     /// Must be one of a predefined set of values
     Choice(Vec<String>),
     /// Numeric value within a specific range
@@ -200,6 +305,43 @@ pub enum FlagType {
     File,
     /// Must be a valid directory path
     Directory,
+    /// A point in time: RFC3339, Unix epoch seconds, or relative to now
+    ///
+    /// Relative values are `now` or an offset such as `-1h`, `+30m` or `-7d`
+    /// (units `s`, `m`, `h`, `d`, `w`). Offsets starting with `-` must be
+    /// attached with `=` (`--since=-1h`) so they are not taken for a flag.
+    Timestamp,
+    /// A length of time such as `500ms`, `30s` or `1h30m`
+    ///
+    /// Amounts are whole numbers, each followed by a unit: `ns`, `us`,
+    /// `ms`, `s`, `m`, `h` or `d`. A bare number is rejected, since its
+    /// unit would be a guess.
+    Duration,
+    /// A size in bytes such as `512`, `10MB` or `512MiB`
+    ///
+    /// Decimal units (`KB`, `MB`, `GB`, `TB`) count in thousands and
+    /// binary ones (`KiB`, `MiB`, `GiB`, `TiB`) in 1024s; a bare number is
+    /// a byte count. Units are case-insensitive. The value is stored as
+    /// [`FlagValue::Int`] holding the number of bytes.
+    Bytes,
+    /// An IPv4 or IPv6 address such as `0.0.0.0` or `::1`
+    ///
+    /// Validation only; the value is stored as a string.
+    IpAddr,
+    /// An address block such as `10.0.0.0/8` or `fd00::/64`
+    ///
+    /// The prefix length can be at most 32 for IPv4 and 128 for IPv6.
+    /// Validation only; the value is stored as a string.
+    Cidr,
+    /// An email address, checked for an obvious `user@domain.tld` shape
+    ///
+    /// Validation only; the value is stored as a string.
+    Email,
+    /// Counts how many times the flag is given, as in `-vvv`
+    ///
+    /// Each occurrence adds one, whether bundled (`-vvv`) or separate
+    /// (`-v -v -v`); `--verbose=3` sets the count directly.
+    Count,
 }
 
 impl Flag {
@@ -224,6 +366,15 @@ impl Flag {
             value_type: FlagType::String,
             constraints: Vec::new(),
             completion: None,
+            visible_when: None,
+            bool_words: None,
+            optional_value: None,
+            env: None,
+            hidden: false,
+            deprecated: None,
+            negatable: false,
+            comma_separated: false,
+            group: None,
         }
     }
 
@@ -241,6 +392,21 @@ impl Flag {
         Self::new(name).value_type(FlagType::Bool)
     }
 
+    /// Creates a new counting flag, for `-vvv` style levels
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::{Flag, FlagType};
+    ///
+    /// let flag = Flag::count("verbose").short('v');
+    /// assert_eq!(flag.value_type, FlagType::Count);
+    /// ```
+    #[must_use]
+    pub fn count(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Count)
+    }
+
     /// Creates a new integer flag
     ///
     /// # Examples
@@ -354,6 +520,104 @@ impl Flag {
         Self::new(name).value_type(FlagType::Directory)
     }
 
+    /// Creates a new timestamp flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::timestamp("since");
+    /// ```
+    #[must_use]
+    pub fn timestamp(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Timestamp)
+    }
+
+    /// Creates a new duration flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::duration("timeout");
+    /// ```
+    #[must_use]
+    pub fn duration(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Duration)
+    }
+
+    /// Creates a new byte size flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::bytes("max-size");
+    /// ```
+    #[must_use]
+    pub fn bytes(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Bytes)
+    }
+
+    /// Creates a new `key=value` pairs flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::key_value("label");
+    /// ```
+    #[must_use]
+    pub fn key_value(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::KeyValue)
+    }
+
+    /// Creates a new IP address flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::ip_addr("bind");
+    /// ```
+    #[must_use]
+    pub fn ip_addr(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::IpAddr)
+    }
+
+    /// Creates a new CIDR block flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::cidr("allow");
+    /// ```
+    #[must_use]
+    pub fn cidr(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Cidr)
+    }
+
+    /// Creates a new email address flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::email("notify");
+    /// ```
+    #[must_use]
+    pub fn email(name: impl Into<String>) -> Self {
+        Self::new(name).value_type(FlagType::Email)
+    }
+
     /// Sets the short name for this flag
     ///
     /// # Examples
@@ -510,6 +774,37 @@ impl Flag {
         self
     }
 
+    /// Requires at least one of `flags` whenever this flag is given
+    ///
+    /// Shorthand for [`FlagConstraint::RequiresOneOf`]. Unlike
+    /// [`FlagConstraint::Requires`], which needs every listed flag, any one
+    /// of them is enough; when this flag is absent nothing is checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::{Flag, FlagConstraint};
+    ///
+    /// let tls = Flag::bool("tls").requires_one_of(["cert-file", "cert-inline"]);
+    /// assert_eq!(
+    ///     tls.constraints,
+    ///     vec![FlagConstraint::RequiresOneOf(vec![
+    ///         "cert-file".to_string(),
+    ///         "cert-inline".to_string(),
+    ///     ])]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn requires_one_of<I, S>(self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.constraint(FlagConstraint::RequiresOneOf(
+            flags.into_iter().map(Into::into).collect(),
+        ))
+    }
+
     /// Sets a completion function for this flag's values
     ///
     /// # Arguments
@@ -541,6 +836,260 @@ impl Flag {
         self.completion = Some(Box::new(completion));
         self
     }
+
+    /// Hides this flag from help unless another flag is provided
+    ///
+    /// This enables progressive disclosure: `myapp --help` hides the flag,
+    /// while `myapp --advanced --help` shows it. The flag is parsed
+    /// normally regardless of its visibility.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::int("gc-interval").visible_when("advanced");
+    /// assert_eq!(flag.visible_when.as_deref(), Some("advanced"));
+    /// ```
+    #[must_use]
+    pub fn visible_when(mut self, flag_name: impl Into<String>) -> Self {
+        self.visible_when = Some(flag_name.into());
+        self
+    }
+
+    /// Hides this flag from help, completion and generated docs
+    ///
+    /// Meant for internal or debugging flags. A hidden flag is still parsed
+    /// and validated like any other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::bool("debug-dump").hidden();
+    /// assert!(flag.hidden);
+    /// ```
+    #[must_use]
+    pub const fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Marks this flag as deprecated
+    ///
+    /// The flag keeps working, but using it prints
+    /// `warning: flag --name is deprecated: <message>` to stderr, once per
+    /// invocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::string("out").deprecated("use --output");
+    /// assert_eq!(flag.deprecated.as_deref(), Some("use --output"));
+    /// ```
+    #[must_use]
+    pub fn deprecated(mut self, message: impl Into<String>) -> Self {
+        self.deprecated = Some(message.into());
+        self
+    }
+
+    /// Lets a `Bool` flag be turned off with `--no-<name>`
+    ///
+    /// This is useful for flags that default to true. Help shows the flag
+    /// as `--[no-]name`, and completion offers the `--no-` form once the
+    /// word being completed starts with `n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("app")
+    ///     .flag(Flag::bool("color").default_bool(true).negatable())
+    ///     .run(|ctx| {
+    ///         assert_eq!(ctx.flag_bool("color"), Some(false));
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    ///
+    /// cmd.execute(vec!["--no-color".to_string()]).unwrap();
+    /// ```
+    #[must_use]
+    pub const fn negatable(mut self) -> Self {
+        self.negatable = true;
+        self
+    }
+
+    /// Splits each occurrence of a list flag on commas
+    ///
+    /// For `StringSlice` and `StringArray` flags, `--tag a,b` then gives two
+    /// items, and repeating the flag adds more, so `--tag a,b --tag c`
+    /// gives three; `KeyValue` flags likewise take `--set a=1,b=2`. A comma
+    /// written as `\,` stays part of its item. Without this, every
+    /// occurrence is a single item, commas included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::{Flag, FlagValue};
+    ///
+    /// let flag = Flag::string_slice("tag").comma_separated();
+    /// assert_eq!(
+    ///     flag.parse_value("web,cache\\,db").unwrap(),
+    ///     FlagValue::StringSlice(vec!["web".to_string(), "cache,db".to_string()])
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn comma_separated(mut self) -> Self {
+        self.comma_separated = true;
+        self
+    }
+
+    /// Lists the flag under its own heading in help
+    ///
+    /// Optional flags sharing a group are shown together under that
+    /// heading, after the ungrouped ones, the same way subcommands are
+    /// grouped by `group_id`. Required flags keep their own section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::new("output").group("Output");
+    /// assert_eq!(flag.group.as_deref(), Some("Output"));
+    /// ```
+    #[must_use]
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Replaces the words a `Bool` flag accepts as true and false
+    ///
+    /// Matching is case-insensitive. The default words (`true`/`false`,
+    /// `yes`/`no`, `1`/`0`, ...) are no longer accepted, and the custom words
+    /// are offered when completing the flag's value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::{Flag, FlagValue};
+    ///
+    /// let flag = Flag::bool("cache").bool_words(&["on", "enabled"], &["off", "disabled"]);
+    /// assert_eq!(flag.parse_value("on").unwrap(), FlagValue::Bool(true));
+    /// assert_eq!(flag.parse_value("Disabled").unwrap(), FlagValue::Bool(false));
+    /// assert!(flag.parse_value("yes").is_err());
+    /// ```
+    #[must_use]
+    pub fn bool_words(mut self, true_words: &[&str], false_words: &[&str]) -> Self {
+        let owned = |words: &[&str]| words.iter().map(|&w| w.to_string()).collect();
+        self.bool_words = Some((owned(true_words), owned(false_words)));
+        self
+    }
+
+    /// Makes the flag's value optional, using `value` when it is omitted
+    ///
+    /// A bare `--color` then means `--color=<value>`. A value can still be
+    /// given with `=` or as the next argument, unless that argument looks
+    /// like a flag or names a subcommand.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::new("color").optional_value("auto");
+    /// assert_eq!(flag.optional_value.as_deref(), Some("auto"));
+    /// ```
+    #[must_use]
+    pub fn optional_value(mut self, value: impl Into<String>) -> Self {
+        self.optional_value = Some(value.into());
+        self
+    }
+
+    /// Falls back to an environment variable when the flag isn't given
+    ///
+    /// Precedence is the command line, then the variable, then the default.
+    /// Values from the variable are validated like command-line values;
+    /// an empty variable counts as unset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::flag::Flag;
+    ///
+    /// let flag = Flag::string("namespace").env("KUBE_NAMESPACE");
+    /// assert_eq!(flag.env.as_deref(), Some("KUBE_NAMESPACE"));
+    /// ```
+    #[must_use]
+    pub fn env(mut self, var: impl Into<String>) -> Self {
+        self.env = Some(var.into());
+        self
+    }
+
+    /// Returns whether this flag must be followed by a value
+    pub(crate) fn requires_value(&self) -> bool {
+        !self.is_switch() && self.optional_value.is_none()
+    }
+
+    /// Returns whether this flag is given on its own, never with the next word
+    pub(crate) const fn is_switch(&self) -> bool {
+        matches!(self.value_type, FlagType::Bool | FlagType::Count)
+    }
+
+    /// Returns the words this flag accepts as true and false
+    fn accepted_bool_words(&self) -> (Vec<&str>, Vec<&str>) {
+        match &self.bool_words {
+            Some((true_words, false_words)) => (
+                true_words.iter().map(String::as_str).collect(),
+                false_words.iter().map(String::as_str).collect(),
+            ),
+            None => (
+                vec!["true", "t", "1", "yes", "y"],
+                vec!["false", "f", "0", "no", "n"],
+            ),
+        }
+    }
+
+    /// Completes the values this flag's type declares
+    ///
+    /// `Choice` flags offer their choices, `Range` flags their endpoints
+    /// and a few round numbers in between, and `Bool` flags `true` and
+    /// `false`, or their custom words. Other types declare no values.
+    /// Plain `true`/`false` are only offered for `--flag=`, since the
+    /// word after a bare bool flag is usually an argument.
+    pub(crate) fn complete_declared_values(&self, prefix: &str) -> Option<CompletionResult> {
+        let values: Vec<String> = match (&self.value_type, &self.bool_words) {
+            (FlagType::Bool, Some((true_words, false_words))) => {
+                true_words.iter().chain(false_words).cloned().collect()
+            }
+            (FlagType::Bool, None) => vec!["true".to_string(), "false".to_string()],
+            (FlagType::Choice(choices), _) => choices.clone(),
+            (FlagType::Range(min, max), _) => range_samples(*min, *max)
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            _ => return None,
+        };
+        Some(
+            CompletionResult::new()
+                .extend(values.into_iter().filter(|value| value.starts_with(prefix))),
+        )
+    }
+
+    /// Returns whether this flag should be shown in help given the provided flags
+    pub(crate) fn is_visible(&self, provided: &HashMap<String, String>) -> bool {
+        !self.hidden
+            && self.visible_when.as_ref().map_or(true, |gate| {
+                provided
+                    .get(gate)
+                    .is_some_and(|value| !value.eq_ignore_ascii_case("false"))
+            })
+    }
 }
 
 impl Clone for Flag {
@@ -554,6 +1103,15 @@ impl Clone for Flag {
             value_type: self.value_type.clone(),
             constraints: self.constraints.clone(),
             completion: None, // Don't clone the completion function
+            visible_when: self.visible_when.clone(),
+            bool_words: self.bool_words.clone(),
+            optional_value: self.optional_value.clone(),
+            env: self.env.clone(),
+            hidden: self.hidden,
+            deprecated: self.deprecated.clone(),
+            negatable: self.negatable,
+            comma_separated: self.comma_separated,
+            group: self.group.clone(),
         }
     }
 }
@@ -593,19 +1151,33 @@ impl Flag {
     pub fn parse_value(&self, input: &str) -> Result<FlagValue> {
         match &self.value_type {
             FlagType::String => Ok(FlagValue::String(input.to_string())),
-            FlagType::Bool => match input.to_lowercase().as_str() {
-                "true" | "t" | "1" | "yes" | "y" => Ok(FlagValue::Bool(true)),
-                "false" | "f" | "0" | "no" | "n" => Ok(FlagValue::Bool(false)),
-                _ => Err(Error::flag_parsing_with_suggestions(
-                    format!("Invalid boolean value: '{input}'"),
-                    self.name.clone(),
-                    vec![
-                        "true, false".to_string(),
-                        "yes, no".to_string(),
-                        "1, 0".to_string(),
-                    ],
-                )),
-            },
+            FlagType::Bool => {
+                let (true_words, false_words) = self.accepted_bool_words();
+                let matches = |word: &&str| word.eq_ignore_ascii_case(input);
+                if true_words.iter().any(matches) {
+                    Ok(FlagValue::Bool(true))
+                } else if false_words.iter().any(matches) {
+                    Ok(FlagValue::Bool(false))
+                } else {
+                    let suggestions = if self.bool_words.is_some() {
+                        vec![
+                            format!("true: {}", true_words.join(", ")),
+                            format!("false: {}", false_words.join(", ")),
+                        ]
+                    } else {
+                        vec![
+                            "true, false".to_string(),
+                            "yes, no".to_string(),
+                            "1, 0".to_string(),
+                        ]
+                    };
+                    Err(Error::flag_parsing_with_suggestions(
+                        format!("Invalid boolean value: '{input}'"),
+                        self.name.clone(),
+                        suggestions,
+                    ))
+                }
+            }
             FlagType::Int => input.parse::<i64>().map(FlagValue::Int).map_err(|_| {
                 Error::flag_parsing_with_suggestions(
                     format!("Invalid integer value: '{input}'"),
@@ -621,14 +1193,39 @@ impl Flag {
                 )
             }),
             FlagType::StringSlice | FlagType::StringArray => {
-                Ok(FlagValue::StringSlice(vec![input.to_string()]))
+                if self.comma_separated {
+                    Ok(FlagValue::StringSlice(split_list(input)))
+                } else {
+                    Ok(FlagValue::StringSlice(vec![input.to_string()]))
+                }
+            }
+            FlagType::KeyValue => {
+                let items = if self.comma_separated {
+                    split_list(input)
+                } else {
+                    vec![input.trim().to_string()]
+                };
+                items
+                    .iter()
+                    .map(|item| match item.split_once('=') {
+                        Some((key, value)) if !key.is_empty() => {
+                            Ok((key.to_string(), value.to_string()))
+                        }
+                        _ => Err(Error::flag_parsing_with_suggestions(
+                            format!("Invalid key=value pair: '{item}'"),
+                            self.name.clone(),
+                            vec![format!("key=value (e.g., --{} env=prod)", self.name)],
+                        )),
+                    })
+                    .collect::<Result<_>>()
+                    .map(FlagValue::KeyValue)
             }
             FlagType::Choice(choices) => {
                 if choices.contains(&input.to_string()) {
                     Ok(FlagValue::String(input.to_string()))
                 } else {
-                    Err(Error::flag_parsing_with_suggestions(
-                        format!("Invalid choice: '{input}'"),
+                    Err(Error::invalid_choice(
+                        input,
                         self.name.clone(),
                         choices.clone(),
                     ))
@@ -690,6 +1287,99 @@ impl Flag {
                     ))
                 }
             }
+            FlagType::Timestamp => {
+                parse_timestamp(input)
+                    .map(FlagValue::Timestamp)
+                    .ok_or_else(|| {
+                        Error::flag_parsing_with_suggestions(
+                            format!("Invalid timestamp: '{input}'"),
+                            self.name.clone(),
+                            vec![
+                                "RFC3339 (e.g., 2024-01-15T10:30:00Z)".to_string(),
+                                "Unix epoch seconds (e.g., 1705314600)".to_string(),
+                                "relative to now (e.g., now, -1h, -30m, -7d)".to_string(),
+                            ],
+                        )
+                    })
+            }
+            FlagType::Duration => parse_duration(input)
+                .map(FlagValue::Duration)
+                .ok_or_else(|| {
+                    Error::flag_parsing_with_suggestions(
+                        format!("Invalid duration: '{input}'"),
+                        self.name.clone(),
+                        vec![
+                            "a number with a unit: ns, us, ms, s, m, h or d".to_string(),
+                            "units combined (e.g., 500ms, 30s, 1h30m)".to_string(),
+                        ],
+                    )
+                }),
+            FlagType::Bytes => parse_byte_size(input)
+                .map(FlagValue::Int)
+                .map_err(|problem| {
+                    let (message, suggestions) = match problem {
+                        ByteSizeProblem::Overflow => (
+                            format!("Size too large: '{input}'"),
+                            vec![format!("at most {} bytes (about 8EiB)", i64::MAX)],
+                        ),
+                        ByteSizeProblem::Invalid => (
+                            format!("Invalid size: '{input}'"),
+                            vec![
+                                "a number of bytes (e.g., 4096)".to_string(),
+                                "a number with a unit: B, KB, MB, GB, TB".to_string(),
+                                "a number with a binary unit: KiB, MiB, GiB, TiB".to_string(),
+                            ],
+                        ),
+                    };
+                    Error::flag_parsing_with_suggestions(message, self.name.clone(), suggestions)
+                }),
+            FlagType::IpAddr => input.parse::<IpAddr>().map_or_else(
+                |_| {
+                    Err(Error::flag_parsing_with_suggestions(
+                        format!("Invalid IP address: '{input}'"),
+                        self.name.clone(),
+                        vec![
+                            "an IPv4 address like 192.168.1.10".to_string(),
+                            "an IPv6 address like ::1 or fe80::1".to_string(),
+                        ],
+                    ))
+                },
+                |_| Ok(FlagValue::String(input.to_string())),
+            ),
+            FlagType::Cidr => cidr_problem(input).map_or_else(
+                || Ok(FlagValue::String(input.to_string())),
+                |problem| {
+                    Err(Error::flag_parsing_with_suggestions(
+                        format!("Invalid CIDR block '{input}': {problem}"),
+                        self.name.clone(),
+                        vec![
+                            "an address and prefix length like 10.0.0.0/8 or fd00::/64".to_string(),
+                        ],
+                    ))
+                },
+            ),
+            FlagType::Email => email_problem(input).map_or_else(
+                || Ok(FlagValue::String(input.to_string())),
+                |problem| {
+                    Err(Error::flag_parsing_with_suggestions(
+                        format!("Invalid email address '{input}': {problem}"),
+                        self.name.clone(),
+                        vec!["an address like user@example.com".to_string()],
+                    ))
+                },
+            ),
+            FlagType::Count => input
+                .parse::<i64>()
+                .ok()
+                .filter(|count| *count >= 0)
+                .map(FlagValue::Int)
+                .ok_or_else(|| {
+                    Error::flag_parsing_with_suggestions(
+                        format!("Invalid count value: '{input}'"),
+                        self.name.clone(),
+                        vec!["a non-negative whole number (e.g., 0, 3)".to_string()],
+                    )
+                }),
         }
     }
 
@@ -740,6 +1430,8 @@ impl Flag {
                         }
                     }
                 }
+                // Checked by the running command, which knows its path
+                FlagConstraint::InvalidForCommands(_) => {}
                 FlagConstraint::Requires(required_flags) => {
                     if provided_flags.contains(flag_name) {
                         for required in required_flags {
@@ -755,18 +1447,444 @@ impl Flag {
                         }
                     }
                 }
+                FlagConstraint::RequiresOneOf(candidates) => {
+                    if provided_flags.contains(flag_name)
+                        && !candidates.is_empty()
+                        && !candidates.iter().any(|name| provided_flags.contains(name))
+                    {
+                        let names: Vec<String> = candidates
+                            .iter()
+                            .map(|name| format!("'--{name}'"))
+                            .collect();
+                        let listed = match names.split_last() {
+                            Some((last, rest)) if !rest.is_empty() => {
+                                format!("{} or {last}", rest.join(", "))
+                            }
+                            _ => names.concat(),
+                        };
+                        return Err(Error::flag_parsing_with_suggestions(
+                            format!("Flag '--{flag_name}' requires one of {listed} to be set"),
+                            flag_name.to_string(),
+                            vec![format!("add {listed}")],
+                        ));
+                    }
+                }
             }
         }
         Ok(())
     }
 }
 
+/// Picks values worth offering for a range: both ends and, for wider
+/// ranges, up to a few round numbers in between
+fn range_samples(min: i64, max: i64) -> Vec<i64> {
+    if min > max {
+        return Vec::new();
+    }
+    let span = max.abs_diff(min);
+    if span <= 10 {
+        return (min..=max).collect();
+    }
+
+    // The smallest 1, 2 or 5 times a power of ten leaving at most four
+    // steps between the ends
+    let mut magnitude: u64 = 1;
+    let step = loop {
+        if let Some(step) = [1, 2, 5]
+            .iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| span / step <= 4)
+        {
+            break i64::try_from(step).unwrap_or(i64::MAX);
+        }
+        magnitude *= 10;
+    };
+
+    let mut samples = vec![min];
+    let mut value = min.div_euclid(step).saturating_add(1).saturating_mul(step);
+    while value < max {
+        samples.push(value);
+        value = value.saturating_add(step);
+    }
+    samples.push(max);
+    samples
+}
+
+/// Describes what is wrong with an `addr/prefixlen` block, if anything
+fn cidr_problem(input: &str) -> Option<String> {
+    let Some((addr, prefix)) = input.split_once('/') else {
+        return Some("missing '/prefix-length'".to_string());
+    };
+    let Ok(addr) = addr.parse::<IpAddr>() else {
+        return Some(format!("'{addr}' is not an IP address"));
+    };
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(format!("'{prefix}' is not a prefix length"));
+    }
+    match prefix.parse::<u8>() {
+        Ok(len) if len <= max => None,
+        _ => Some(format!("prefix length {prefix} is above {max}")),
+    }
+}
+
+/// Describes what is obviously wrong with an email address, if anything
+///
+/// This is a pragmatic shape check, not full RFC 5322 validation.
+fn email_problem(input: &str) -> Option<&'static str> {
+    let Some((local, domain)) = input.split_once('@') else {
+        return Some("missing '@'");
+    };
+    if domain.contains('@') {
+        return Some("more than one '@'");
+    }
+    if local.is_empty() {
+        return Some("nothing before '@'");
+    }
+    if domain.is_empty() {
+        return Some("nothing after '@'");
+    }
+    if !domain.contains('.') {
+        return Some("domain has no '.'");
+    }
+    if domain.starts_with('.') || domain.ends_with('.') {
+        return Some("domain starts or ends with '.'");
+    }
+    None
+}
+
+/// Parses a timestamp into seconds since the Unix epoch
+///
+/// Accepts RFC3339, integer epoch seconds, `now`, and offsets from now
+/// such as `-2h` or `+15m`.
+pub(crate) fn parse_timestamp(input: &str) -> Option<i64> {
+    if let Ok(epoch) = input.parse::<i64>() {
+        return Some(epoch);
+    }
+    if input.eq_ignore_ascii_case("now") {
+        return Some(unix_now());
+    }
+    if input.starts_with(['-', '+']) {
+        return parse_relative_offset(input).and_then(|offset| unix_now().checked_add(offset));
+    }
+    parse_rfc3339(input)
+}
+
+fn unix_now() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// Parses `-1h`, `+30m`, ... into a signed number of seconds
+fn parse_relative_offset(input: &str) -> Option<i64> {
+    let (sign, rest) = input.split_at(1);
+    let unit = rest.chars().last()?;
+    let amount: i64 = rest[..rest.len() - unit.len_utf8()].parse().ok()?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 604_800,
+        _ => return None,
+    };
+    let seconds = amount.checked_mul(multiplier)?;
+    if sign == "-" {
+        seconds.checked_neg()
+    } else {
+        Some(seconds)
+    }
+}
+
+/// Units a byte size can be written in, matched case-insensitively
+const BYTE_UNITS: [(&str, i64); 9] = [
+    ("b", 1),
+    ("kb", 1_000),
+    ("mb", 1_000_000),
+    ("gb", 1_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("kib", 1 << 10),
+    ("mib", 1 << 20),
+    ("gib", 1 << 30),
+    ("tib", 1 << 40),
+];
+
+/// Why a byte size didn't parse
+enum ByteSizeProblem {
+    Invalid,
+    Overflow,
+}
+
+/// Parses `4096`, `10MB`, `512MiB`, ... into a number of bytes
+fn parse_byte_size(input: &str) -> std::result::Result<i64, ByteSizeProblem> {
+    let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return Err(ByteSizeProblem::Invalid);
+    }
+    let suffix = input[digits..].trim_start();
+    let unit = if suffix.is_empty() {
+        1
+    } else {
+        BYTE_UNITS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(suffix))
+            .ok_or(ByteSizeProblem::Invalid)?
+            .1
+    };
+    input[..digits]
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit))
+        .ok_or(ByteSizeProblem::Overflow)
+}
+
+/// Splits a stored list flag value into its items
+///
+/// Items are separated by commas; `\,` is a comma inside an item. Items
+/// are trimmed and empty ones dropped.
+pub(crate) fn split_list(input: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&',') => {
+                item.push(',');
+                chars.next();
+            }
+            ',' => items.push(std::mem::take(&mut item)),
+            _ => item.push(ch),
+        }
+    }
+    items.push(item);
+    items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parses a byte size the way `FlagType::Bytes` does
+pub(crate) fn parse_bytes(input: &str) -> Option<i64> {
+    parse_byte_size(input).ok()
+}
+
+/// Units a duration can be written in, largest first
+const DURATION_UNITS: [(&str, Duration); 7] = [
+    ("d", Duration::from_secs(86_400)),
+    ("h", Duration::from_secs(3_600)),
+    ("m", Duration::from_secs(60)),
+    ("s", Duration::from_secs(1)),
+    ("ms", Duration::from_millis(1)),
+    ("us", Duration::from_micros(1)),
+    ("ns", Duration::from_nanos(1)),
+];
+
+/// Parses `500ms`, `1h30m`, ... into a duration
+///
+/// Each amount needs a unit; the parts are added up.
+pub(crate) fn parse_duration(input: &str) -> Option<Duration> {
+    let mut rest = input;
+    let mut total = Duration::ZERO;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let amount: u32 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let letters = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+        let unit = DURATION_UNITS
+            .iter()
+            .find(|(name, _)| *name == &rest[..letters])?
+            .1;
+        total = total.checked_add(unit.checked_mul(amount)?)?;
+        rest = &rest[letters..];
+    }
+    Some(total)
+}
+
+/// Writes a duration the way [`parse_duration`] reads it, e.g. `1h30m`
+fn format_duration(duration: Duration) -> String {
+    use std::fmt::Write;
+
+    if duration.is_zero() {
+        return "0s".to_string();
+    }
+    let mut out = String::new();
+    let mut rest = duration.as_nanos();
+    for (name, unit) in DURATION_UNITS {
+        let unit = unit.as_nanos();
+        if rest >= unit {
+            let _ = write!(out, "{}{name}", rest / unit);
+            rest %= unit;
+        }
+    }
+    out
+}
+
+/// Parses `YYYY-MM-DDTHH:MM:SS[.frac](Z|+HH:MM|-HH:MM)`
+fn parse_rfc3339(input: &str) -> Option<i64> {
+    let bytes = input.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+
+    let field = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = input.get(range)?;
+        if digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // Fractional seconds are accepted but truncated
+    let mut rest = &input[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let digit = |b: &u8| b.is_ascii_digit().then(|| i64::from(b - b'0'));
+            let hours = digit(h1)? * 10 + digit(h2)?;
+            let minutes = digit(m1)? * 10 + digit(m2)?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3_600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second - offset)
+}
+
+const fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+const fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Declares a flag in one line
+///
+/// `flag!(name: kind)` calls the [`Flag`] constructor named `kind`, so
+/// `flag!(port: int)` is `Flag::int("port")`. Names that aren't Rust
+/// identifiers can be given as strings, like `flag!("dry-run": bool)`.
+/// Choices are listed inline with `choice[...]`; like any choice flag,
+/// its values are offered by TAB completion.
+///
+/// `key = value` pairs after the kind call the builder method of that
+/// name, so `usage = "..."` becomes `.usage("...")`.
+///
+/// # Examples
+///
+/// ```
+/// use flag_rs::flag;
+/// use flag_rs::flag::FlagType;
+///
+/// let env = flag!(env: choice["dev", "staging", "prod"], short = 'e', usage = "Target environment");
+/// assert_eq!(env.name, "env");
+/// assert_eq!(env.short, Some('e'));
+/// assert!(matches!(env.value_type, FlagType::Choice(ref values) if values.len() == 3));
+///
+/// let dry_run = flag!("dry-run": bool, usage = "Only show what would change");
+/// assert_eq!(dry_run.name, "dry-run");
+/// ```
+#[macro_export]
+macro_rules! flag {
+    ($name:ident : $($rest:tt)+) => {
+        $crate::flag!(@build stringify!($name), $($rest)+)
+    };
+    ($name:literal : $($rest:tt)+) => {
+        $crate::flag!(@build $name, $($rest)+)
+    };
+    (@build $name:expr, choice [$($choice:expr),* $(,)?] $(, $method:ident = $value:expr)* $(,)?) => {
+        $crate::Flag::choice($name, &[$($choice),*])$(.$method($value))*
+    };
+    (@build $name:expr, $kind:ident $(, $method:ident = $value:expr)* $(,)?) => {
+        $crate::Flag::$kind($name)$(.$method($value))*
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::complete;
     #[allow(clippy::approx_constant)]
     const PI: f64 = 3.14;
 
+    #[test]
+    fn test_flag_macro_choice() {
+        let flag =
+            crate::flag!(env: choice["dev", "staging", "prod"], usage = "Target environment");
+        assert_eq!(flag.name, "env");
+        assert_eq!(flag.usage, "Target environment");
+        assert!(matches!(
+            flag.value_type,
+            FlagType::Choice(ref values) if values == &["dev", "staging", "prod"]
+        ));
+        assert!(flag.parse_value("staging").is_ok());
+        assert!(flag.parse_value("test").is_err());
+
+        let cmd = crate::CommandBuilder::new("deploy").flag(flag).build();
+        assert_eq!(
+            complete(&cmd, &["--env", ""]),
+            vec!["dev", "staging", "prod"]
+        );
+        assert_eq!(complete(&cmd, &["--env", "s"]), vec!["staging"]);
+
+        let flag = crate::flag!("dry-run": bool, short = 'n');
+        assert_eq!(flag.name, "dry-run");
+        assert_eq!(flag.short, Some('n'));
+        assert_eq!(flag.value_type, FlagType::Bool);
+    }
+
     #[test]
     fn test_flag_value_conversions() {
         let string_val = FlagValue::String("hello".to_string());
@@ -935,6 +2053,339 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_comma_separated_splits_and_unescapes() {
+        let strings = |items: &[&str]| {
+            FlagValue::StringSlice(items.iter().map(|item| (*item).to_string()).collect())
+        };
+        let tags = Flag::string_slice("tag").comma_separated();
+        assert_eq!(
+            tags.parse_value("a,b,c").unwrap(),
+            strings(&["a", "b", "c"])
+        );
+        assert_eq!(tags.parse_value(" a , ,b").unwrap(), strings(&["a", "b"]));
+        assert_eq!(tags.parse_value(r"x\,y,z").unwrap(), strings(&["x,y", "z"]));
+        // Other backslashes are kept
+        assert_eq!(
+            tags.parse_value(r"C:\dir,D:\").unwrap(),
+            strings(&[r"C:\dir", r"D:\"])
+        );
+
+        let array = Flag::new("tag")
+            .value_type(FlagType::StringArray)
+            .comma_separated();
+        assert_eq!(array.parse_value("a,b").unwrap(), strings(&["a", "b"]));
+
+        // Without the modifier an occurrence is one item
+        assert_eq!(
+            Flag::string_slice("tag").parse_value("a,b").unwrap(),
+            strings(&["a,b"])
+        );
+    }
+
+    #[test]
+    fn test_key_value_flag() {
+        let set = Flag::key_value("set");
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+
+        assert_eq!(
+            set.parse_value("replicas=3").unwrap(),
+            FlagValue::KeyValue(vec![pair("replicas", "3")])
+        );
+        // Only the first `=` splits, and empty values are allowed
+        assert_eq!(
+            set.parse_value("url=http://host/?a=b").unwrap(),
+            FlagValue::KeyValue(vec![pair("url", "http://host/?a=b")])
+        );
+        // An occurrence is one pair, commas included
+        assert_eq!(
+            set.parse_value("tags=a,b").unwrap(),
+            FlagValue::KeyValue(vec![pair("tags", "a,b")])
+        );
+        for bad in ["replicas", "=3"] {
+            assert!(set.parse_value(bad).is_err(), "{bad:?}");
+        }
+
+        // Comma-separated, an occurrence holds several pairs
+        let set = Flag::key_value("set").comma_separated();
+        assert_eq!(
+            set.parse_value(r"app=web,tier=,tags=a\,b").unwrap(),
+            FlagValue::KeyValue(vec![
+                pair("app", "web"),
+                pair("tier", ""),
+                pair("tags", "a,b")
+            ])
+        );
+        assert_eq!(
+            set.parse_value("app=web,tier=front").unwrap().to_string(),
+            "app=web,tier=front"
+        );
+        assert!(set.parse_value("app=web,tier").is_err());
+        match set.parse_value("replicas") {
+            Err(Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            }) => {
+                assert_eq!(message, "Invalid key=value pair: 'replicas'");
+                assert_eq!(flag.as_deref(), Some("set"));
+                assert_eq!(suggestions, vec!["key=value (e.g., --set env=prod)"]);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_custom_bool_words() {
+        let flag = Flag::bool("cache").bool_words(&["on"], &["off"]);
+        assert_eq!(flag.parse_value("on").unwrap(), FlagValue::Bool(true));
+        assert_eq!(flag.parse_value("OFF").unwrap(), FlagValue::Bool(false));
+
+        // The default words are replaced, not extended
+        for word in ["true", "false", "yes", "1"] {
+            assert!(flag.parse_value(word).is_err(), "{word} should be rejected");
+        }
+
+        // Completion offers the custom words
+        let completions = flag.complete_declared_values("o").unwrap();
+        assert_eq!(completions.values, vec!["on", "off"]);
+        let completions = Flag::bool("verbose").complete_declared_values("").unwrap();
+        assert_eq!(completions.values, vec!["true", "false"]);
+    }
+
+    #[test]
+    fn test_range_samples() {
+        assert_eq!(range_samples(1, 5), vec![1, 2, 3, 4, 5]);
+        assert_eq!(range_samples(0, 100), vec![0, 50, 100]);
+        assert_eq!(range_samples(1, 65535), vec![1, 20000, 40000, 60000, 65535]);
+        assert_eq!(range_samples(-50, 50), vec![-50, 0, 50]);
+        assert!(range_samples(5, 1).is_empty());
+    }
+
+    #[test]
+    fn test_ip_addr_flag() {
+        let bind = Flag::ip_addr("bind");
+
+        for valid in ["0.0.0.0", "192.168.1.10", "::1", "2001:db8::8a2e:370:7334"] {
+            assert_eq!(
+                bind.parse_value(valid).unwrap(),
+                FlagValue::String(valid.to_string())
+            );
+        }
+
+        for bad in ["256.1.1.1", "10.0.0", "localhost", "::g", ""] {
+            assert!(bind.parse_value(bad).is_err(), "{bad:?}");
+        }
+        match bind.parse_value("10.0.0") {
+            Err(Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            }) => {
+                assert_eq!(message, "Invalid IP address: '10.0.0'");
+                assert_eq!(flag.as_deref(), Some("bind"));
+                assert!(suggestions[0].contains("192.168.1.10"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_cidr_flag() {
+        let allow = Flag::cidr("allow");
+
+        for valid in [
+            "10.0.0.0/8",
+            "0.0.0.0/0",
+            "192.168.1.1/32",
+            "fd00::/64",
+            "::/128",
+        ] {
+            assert_eq!(
+                allow.parse_value(valid).unwrap(),
+                FlagValue::String(valid.to_string())
+            );
+        }
+
+        for (bad, problem) in [
+            ("10.0.0.0/40", "prefix length 40 is above 32"),
+            ("fd00::/129", "prefix length 129 is above 128"),
+            ("10.0.0.0", "missing '/prefix-length'"),
+            ("10.0.0/8", "'10.0.0' is not an IP address"),
+            ("10.0.0.0/", "'' is not a prefix length"),
+            ("10.0.0.0/+8", "'+8' is not a prefix length"),
+        ] {
+            match allow.parse_value(bad) {
+                Err(Error::FlagParsing {
+                    message,
+                    suggestions,
+                    ..
+                }) => {
+                    assert_eq!(message, format!("Invalid CIDR block '{bad}': {problem}"));
+                    assert!(suggestions[0].contains("10.0.0.0/8"));
+                }
+                other => panic!("unexpected result for {bad:?}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_email_flag() {
+        let email = Flag::email("notify");
+
+        for valid in ["ops@example.com", "first.last+tag@mail.example.co.uk"] {
+            assert_eq!(
+                email.parse_value(valid).unwrap(),
+                FlagValue::String(valid.to_string())
+            );
+        }
+
+        match email.parse_value("ops.example.com") {
+            Err(Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            }) => {
+                assert!(message.contains("missing '@'"));
+                assert_eq!(flag.as_deref(), Some("notify"));
+                assert_eq!(suggestions, vec!["an address like user@example.com"]);
+            }
+            other => panic!("expected FlagParsing error, got {other:?}"),
+        }
+
+        match email.parse_value("ops@localhost") {
+            Err(Error::FlagParsing { message, .. }) => {
+                assert!(message.contains("domain has no '.'"));
+            }
+            other => panic!("expected FlagParsing error, got {other:?}"),
+        }
+
+        assert!(email.parse_value("@example.com").is_err());
+        assert!(email.parse_value("ops@").is_err());
+        assert!(email.parse_value("a@b@example.com").is_err());
+        assert!(email.parse_value("ops@example.").is_err());
+    }
+
+    #[test]
+    fn test_bytes_flag() {
+        let size = Flag::bytes("max-size");
+        let parse = |input: &str| size.parse_value(input).unwrap().as_int().unwrap();
+
+        assert_eq!(parse("1KiB"), 1024);
+        assert_eq!(parse("1KB"), 1000);
+        assert_eq!(parse("512MiB"), 512 * 1024 * 1024);
+        assert_eq!(parse("10mb"), 10_000_000);
+        assert_eq!(parse("2 GiB"), 2 << 30);
+        assert_eq!(parse("4096"), 4096);
+        assert_eq!(parse("0"), 0);
+        assert_eq!(parse("7B"), 7);
+
+        for bad in ["", "MB", "1.5GB", "-1KB", "10XB", "10 M B"] {
+            assert!(size.parse_value(bad).is_err(), "{bad:?}");
+        }
+        match size.parse_value("10XB") {
+            Err(Error::FlagParsing {
+                message,
+                suggestions,
+                ..
+            }) => {
+                assert_eq!(message, "Invalid size: '10XB'");
+                assert!(suggestions.iter().any(|s| s.contains("KiB, MiB")));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        // Past i64::MAX, whether through the unit or the digits alone
+        for huge in ["9000000TiB", "99999999999999999999"] {
+            match size.parse_value(huge) {
+                Err(Error::FlagParsing { message, .. }) => {
+                    assert_eq!(message, format!("Size too large: '{huge}'"));
+                }
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_duration_flag() {
+        let timeout = Flag::duration("timeout");
+        let parse = |input: &str| timeout.parse_value(input).unwrap().as_duration().unwrap();
+
+        assert_eq!(parse("500ms"), Duration::from_millis(500));
+        assert_eq!(parse("30s"), Duration::from_secs(30));
+        assert_eq!(parse("5m"), Duration::from_secs(300));
+        assert_eq!(parse("2h"), Duration::from_secs(7_200));
+        assert_eq!(parse("1h30m"), Duration::from_secs(5_400));
+        assert_eq!(parse("1d2h3m4s5ms"), Duration::new(93_784, 5_000_000));
+
+        // Written back the same way
+        assert_eq!(FlagValue::Duration(parse("90m")).to_string(), "1h30m");
+        assert_eq!(FlagValue::Duration(Duration::ZERO).to_string(), "0s");
+        assert_eq!(
+            FlagValue::Duration(Duration::from_micros(1_500)).to_string(),
+            "1ms500us"
+        );
+
+        for bad in ["30", "0", "", "1.5h", "10x", "h", "-5s", "1h 30m"] {
+            assert!(timeout.parse_value(bad).is_err(), "{bad:?}");
+        }
+        match timeout.parse_value("30") {
+            Err(Error::FlagParsing {
+                message,
+                flag,
+                suggestions,
+                ..
+            }) => {
+                assert_eq!(message, "Invalid duration: '30'");
+                assert_eq!(flag.as_deref(), Some("timeout"));
+                assert!(suggestions[0].contains("ns, us, ms, s, m, h or d"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_timestamp_flag() {
+        let since = Flag::timestamp("since");
+
+        assert_eq!(
+            since.parse_value("2024-01-15T10:30:00Z").unwrap(),
+            FlagValue::Timestamp(1_705_314_600)
+        );
+        assert_eq!(
+            since.parse_value("2024-01-15T12:30:00.250+02:00").unwrap(),
+            FlagValue::Timestamp(1_705_314_600)
+        );
+        assert_eq!(
+            since.parse_value("1705314600").unwrap(),
+            FlagValue::Timestamp(1_705_314_600)
+        );
+
+        let before = unix_now();
+        let now = since.parse_value("now").unwrap().as_timestamp().unwrap();
+        let two_hours_ago = since.parse_value("-2h").unwrap().as_timestamp().unwrap();
+        let after = unix_now();
+        assert!((before..=after).contains(&now));
+        assert!((before - 7_200..=after - 7_200).contains(&two_hours_ago));
+
+        assert!(since.parse_value("2024-02-30T00:00:00Z").is_err());
+        assert!(since.parse_value("-2x").is_err());
+        assert!(since.parse_value("--9223372036854775808s").is_err());
+        match since.parse_value("yesterday") {
+            Err(Error::FlagParsing {
+                flag, suggestions, ..
+            }) => {
+                assert_eq!(flag.as_deref(), Some("since"));
+                assert_eq!(suggestions.len(), 3);
+                assert!(suggestions[0].contains("RFC3339"));
+            }
+            other => panic!("expected FlagParsing error, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_flag_constraints() {
         let mut provided_flags = HashSet::new();
@@ -1004,4 +2455,41 @@ mod tests {
                 .is_ok()
         );
     }
+
+    #[test]
+    fn test_requires_one_of_constraint() {
+        let tls = Flag::bool("tls").requires_one_of(["cert-file", "cert-inline"]);
+        let mut provided_flags = HashSet::new();
+
+        // Absent flag: nothing to check
+        provided_flags.insert("cert-file".to_string());
+        assert!(tls.validate_constraints("tls", &provided_flags).is_ok());
+
+        // Unsatisfied
+        provided_flags.clear();
+        provided_flags.insert("tls".to_string());
+        match tls
+            .validate_constraints("tls", &provided_flags)
+            .unwrap_err()
+        {
+            Error::FlagParsing {
+                message,
+                suggestions,
+                ..
+            } => {
+                assert_eq!(
+                    message,
+                    "Flag '--tls' requires one of '--cert-file' or '--cert-inline' to be set"
+                );
+                assert_eq!(suggestions, vec!["add '--cert-file' or '--cert-inline'"]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        // Satisfied by either one
+        provided_flags.insert("cert-inline".to_string());
+        assert!(tls.validate_constraints("tls", &provided_flags).is_ok());
+        provided_flags.insert("cert-file".to_string());
+        assert!(tls.validate_constraints("tls", &provided_flags).is_ok());
+    }
 }
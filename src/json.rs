@@ -0,0 +1,103 @@
+//! Minimal JSON serialization
+//!
+//! flag-rs has no dependencies, so machine-readable output is produced by
+//! this small hand-rolled serializer. It only writes JSON; there is no parser.
+
+use std::fmt;
+
+/// A JSON value that can be written out with `Display`
+///
+/// Objects keep their keys in insertion order so output is stable.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    /// A string, escaped on output
+    String(String),
+    /// An ordered list of values
+    Array(Vec<Self>),
+    /// Key/value pairs in insertion order
+    Object(Vec<(String, Self)>),
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(s) => write_escaped(f, s),
+            Self::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(entries) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Writes `s` as a quoted JSON string
+fn write_escaped(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if u32::from(c) < 0x20 => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_output() {
+        let value = JsonValue::Object(vec![
+            (
+                "items".to_string(),
+                JsonValue::Array(vec![JsonValue::from("a"), JsonValue::Array(vec![])]),
+            ),
+            ("empty".to_string(), JsonValue::Object(vec![])),
+        ]);
+        assert_eq!(value.to_string(), r#"{"items":["a",[]],"empty":{}}"#);
+    }
+
+    #[test]
+    fn test_json_string_escaping() {
+        let value = JsonValue::from("quote \" slash \\ line\nbell \u{7}");
+        assert_eq!(
+            value.to_string(),
+            r#""quote \" slash \\ line\nbell \u0007""#
+        );
+    }
+}
@@ -16,7 +16,7 @@
 //! - **Colored Output**: Beautiful help messages with automatic TTY detection
 //! - **Flexible Architecture**: Use builder pattern or direct construction
 //! - **Advanced Flag Types**: Choice, Range, File, Directory validation
-//! - **Flag Constraints**: `RequiredIf`, `ConflictsWith`, `Requires` relationships
+//! - **Flag Constraints**: `RequiredIf`, `ConflictsWith`, `Requires`, `RequiresOneOf` relationships
 //! - **Completion Caching**: Cache expensive completion operations
 //! - **Timeout Protection**: Prevent slow completions from hanging
 //! - **Memory Optimization**: String interning and efficient data structures
@@ -67,9 +67,13 @@
 //! // In main():
 //! // let args: Vec<String> = std::env::args().skip(1).collect();
 //! // if let Err(e) = app.execute(args) {
-//! //     eprintln!("Error: {}", e);
-//! //     std::process::exit(1);
+//! //     if !matches!(e, flag_rs::Error::Exit(_)) {
+//! //         eprintln!("{e}");
+//! //     }
+//! //     std::process::exit(e.exit_code());
 //! // }
+//! //
+//! // or, doing the same: app.execute_or_exit(args);
 //! ```
 //!
 //! ## Dynamic Completions
@@ -119,14 +123,15 @@
 //!             let shell_name = ctx.args().first()
 //!                 .ok_or(flag_rs::Error::ArgumentParsing("shell name required".to_string()))?;
 //!
-//!             // In a real app, get the root command here
-//!             // let script = match shell_name.as_str() {
-//!             //     "bash" => root_cmd.generate_completion(Shell::Bash),
-//!             //     "zsh" => root_cmd.generate_completion(Shell::Zsh),
-//!             //     "fish" => root_cmd.generate_completion(Shell::Fish),
-//!             //     _ => return Err(flag_rs::Error::ArgumentParsing("unsupported shell".to_string())),
-//!             // };
-//!             // println!("{}", script);
+//!             // The running command tree, no need to build it again
+//!             let root = ctx.root().expect("root is set while executing");
+//!             let script = match shell_name.as_str() {
+//!                 "bash" => root.generate_completion(Shell::Bash),
+//!                 "zsh" => root.generate_completion(Shell::Zsh),
+//!                 "fish" => root.generate_completion(Shell::Fish),
+//!                 _ => return Err(flag_rs::Error::ArgumentParsing("unsupported shell".to_string())),
+//!             };
+//!             println!("{}", script);
 //!             Ok(())
 //!         })
 //!         .build()
@@ -202,6 +207,10 @@
 //! use std::time::Duration;
 //!
 //! let cache = Arc::new(CompletionCache::new(Duration::from_secs(5)));
+//! let cmd = CommandBuilder::new("get")
+//!     .completion_cache(cache)
+//!     .arg_completion(expensive_completion_fn)
+//!     .build();
 //! ```
 //!
 //! ### Timeout Protection
@@ -306,6 +315,12 @@ pub mod completion_cache;
 /// Completion timeout handling
 pub mod completion_timeout;
 
+/// Completion from an external command's output
+pub mod completion_command;
+
+/// Static completion spec for generic completers
+pub mod completion_spec;
+
 /// Terminal utilities for enhanced CLI output
 pub mod terminal;
 
@@ -330,12 +345,37 @@ pub mod string_pool;
 /// Memory-optimized flag parsing
 pub mod parse_optimized;
 
+/// Explain mode for showing how an invocation resolves
+pub mod explain;
+
+/// Man page generation
+pub mod man;
+
+/// Markdown documentation generation
+pub mod markdown;
+
+/// Cooperative cancellation for run functions
+pub mod cancel;
+
+/// Command tree helpers shared by the documentation generators
+mod doc_tree;
+
+/// Minimal JSON serialization for machine-readable output
+mod json;
+
+#[cfg(test)]
+mod test_support;
+
 // Re-export main types for convenience
-pub use command::{Command, CommandBuilder};
+pub use cancel::CancellationToken;
+pub use command::{
+    Command, CommandBuilder, DeprecationWarnings, ErrorHandler, FlagPrefixStyle, MergePolicy,
+    Positional, RequiredFlagsFunc, ValidateFunc,
+};
 pub use completion::{CompletionFunc, CompletionResult};
 pub use completion_cache::CompletionCache;
 pub use context::Context;
-pub use error::{Error, Result};
+pub use error::{BuildError, Error, Result};
 pub use flag::{Flag, FlagConstraint, FlagType, FlagValue};
 pub use shell::Shell;
 pub use validator::ArgValidator;
@@ -0,0 +1,317 @@
+//! Man page generation
+//!
+//! [`Command::generate_manpage`] renders a command as a section 1 man page
+//! in troff, ready to be installed by a package. Subcommands can get pages
+//! of their own with [`Command::generate_manpages`], named like
+//! `tool-subcommand` the way git names its pages.
+
+use crate::command::Command;
+use crate::doc_tree::{command_path, sorted_flags, sorted_subcommands};
+use crate::flag::{Flag, FlagValue};
+use std::fmt::Write;
+
+impl Command {
+    /// Renders this command as a section 1 man page in troff
+    ///
+    /// The page has `NAME`, `SYNOPSIS`, `DESCRIPTION` (from `long`, or
+    /// `short` if there is none), `OPTIONS` with each flag's usage and
+    /// default, `COMMANDS` listing subcommands, and `EXAMPLES`. Sections
+    /// with nothing to show are left out, and so are hidden commands and
+    /// flags.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("mytool")
+    ///     .short("Does things")
+    ///     .flag(Flag::bool("verbose").short('v').usage("Print more"))
+    ///     .build();
+    ///
+    /// let page = cmd.generate_manpage();
+    /// assert!(page.starts_with(".TH \"MYTOOL\" \"1\""));
+    /// assert!(page.contains("mytool \\- Does things"));
+    /// ```
+    #[must_use]
+    pub fn generate_manpage(&self) -> String {
+        self.render_manpage(&[])
+    }
+
+    /// Renders man pages for this command and all of its subcommands
+    ///
+    /// Returns `(page name, troff)` pairs, parents before children and
+    /// siblings sorted by name. Subcommand pages are named after their
+    /// path, e.g. `tool-remote-add`, and list inherited flags under
+    /// `GLOBAL OPTIONS`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::CommandBuilder;
+    ///
+    /// let cmd = CommandBuilder::new("tool")
+    ///     .subcommand(CommandBuilder::new("remote").build())
+    ///     .build();
+    ///
+    /// let names: Vec<String> = cmd.generate_manpages().into_iter().map(|(name, _)| name).collect();
+    /// assert_eq!(names, vec!["tool", "tool-remote"]);
+    /// ```
+    #[must_use]
+    pub fn generate_manpages(&self) -> Vec<(String, String)> {
+        let mut pages = Vec::new();
+        self.collect_manpages(&mut Vec::new(), &mut pages);
+        pages
+    }
+
+    fn collect_manpages<'a>(
+        &'a self,
+        ancestors: &mut Vec<&'a Self>,
+        pages: &mut Vec<(String, String)>,
+    ) {
+        pages.push((
+            command_path(ancestors, self, "-"),
+            self.render_manpage(ancestors),
+        ));
+
+        ancestors.push(self);
+        for sub in sorted_subcommands(self) {
+            sub.collect_manpages(ancestors, pages);
+        }
+        ancestors.pop();
+    }
+
+    fn render_manpage(&self, ancestors: &[&Self]) -> String {
+        let mut out = String::new();
+        let page = command_path(ancestors, self, "-");
+        let invocation: Vec<&str> = ancestors
+            .iter()
+            .map(|cmd| cmd.name())
+            .chain(std::iter::once(self.name()))
+            .collect();
+
+        let _ = writeln!(out, ".TH \"{}\" \"1\"", escape(&page.to_uppercase()));
+
+        let _ = writeln!(out, ".SH NAME");
+        if self.short().is_empty() {
+            let _ = writeln!(out, "{}", escape(&page));
+        } else {
+            let _ = writeln!(out, "{} \\- {}", escape(&page), escape(self.short()));
+        }
+
+        let _ = writeln!(out, ".SH SYNOPSIS");
+        let _ = writeln!(out, ".B {}", escape(&invocation.join(" ")));
+        if !sorted_subcommands(self).is_empty() {
+            let _ = writeln!(out, "[\\fIcommand\\fR]");
+        }
+        let _ = writeln!(out, "[\\fIflags\\fR]");
+
+        let description = if self.long().is_empty() {
+            self.short()
+        } else {
+            self.long()
+        };
+        if !description.is_empty() {
+            let _ = writeln!(out, ".SH DESCRIPTION");
+            write_paragraphs(&mut out, description);
+        }
+
+        let _ = writeln!(out, ".SH OPTIONS");
+        for flag in sorted_flags(self) {
+            write_flag(&mut out, flag);
+        }
+        let _ = writeln!(out, ".TP");
+        let _ = writeln!(out, "\\fB\\-h\\fR, \\fB\\-\\-help\\fR");
+        let _ = writeln!(out, "Show help for {}", escape(self.name()));
+        if self.builtin_version(ancestors).is_some() {
+            let _ = writeln!(out, ".TP");
+            let _ = writeln!(out, "\\fB\\-\\-version\\fR");
+            let _ = writeln!(out, "Print the version and exit");
+        }
+
+        if ancestors.iter().any(|cmd| !sorted_flags(cmd).is_empty()) {
+            let _ = writeln!(out, ".SH GLOBAL OPTIONS");
+            for cmd in ancestors.iter().rev() {
+                for flag in sorted_flags(cmd) {
+                    write_flag(&mut out, flag);
+                }
+            }
+        }
+
+        if !sorted_subcommands(self).is_empty() {
+            let _ = writeln!(out, ".SH COMMANDS");
+            for sub in sorted_subcommands(self) {
+                let _ = writeln!(out, ".TP");
+                let _ = writeln!(out, "\\fB{}\\fR", escape(sub.name()));
+                write_paragraphs(&mut out, sub.short());
+            }
+        }
+
+        if !self.examples().is_empty() {
+            let _ = writeln!(out, ".SH EXAMPLES");
+            let _ = writeln!(out, ".nf");
+            let _ = writeln!(out, ".RS");
+            for (example, description) in self.described_examples() {
+                if let Some(description) = description {
+                    write_paragraphs(&mut out, &format!("# {description}"));
+                }
+                write_paragraphs(&mut out, example);
+            }
+            let _ = writeln!(out, ".RE");
+            let _ = writeln!(out, ".fi");
+        }
+
+        let mut related = Vec::new();
+        if let Some((parent, rest)) = ancestors.split_last() {
+            related.push(command_path(rest, parent, "-"));
+        }
+        related.extend(
+            sorted_subcommands(self)
+                .into_iter()
+                .map(|sub| format!("{page}-{}", sub.name())),
+        );
+        if !related.is_empty() {
+            let _ = writeln!(out, ".SH SEE ALSO");
+            let links: Vec<String> = related
+                .iter()
+                .map(|name| format!("\\fB{}\\fR(1)", escape(name)))
+                .collect();
+            let _ = writeln!(out, "{}", links.join(", "));
+        }
+
+        out
+    }
+}
+
+/// Writes a flag as a tagged paragraph: its names, then usage and default
+fn write_flag(out: &mut String, flag: &Flag) {
+    let _ = writeln!(out, ".TP");
+    let short = flag
+        .short
+        .map(|short| format!("\\fB\\-{}\\fR, ", escape(&short.to_string())))
+        .unwrap_or_default();
+    let hint = Command::flag_value_hint(flag);
+    let hint = if hint.is_empty() {
+        String::new()
+    } else {
+        format!(" \\fI{}\\fR", escape(&hint))
+    };
+    let _ = writeln!(out, "{short}\\fB\\-\\-{}\\fR{hint}", escape(&flag.name));
+
+    let mut text = flag.usage.clone();
+    if let Some(default) = &flag.default {
+        let default = match default {
+            FlagValue::String(s) => format!("(default \"{s}\")"),
+            other => format!("(default {other})"),
+        };
+        text = format!("{text} {default}");
+    }
+    if flag.required {
+        text.push_str(" (required)");
+    }
+    if let Some(var) = &flag.env {
+        let _ = write!(text, " [env: {var}]");
+    }
+    write_paragraphs(out, text.trim_start());
+}
+
+/// Writes text line by line, turning blank lines into paragraph breaks
+fn write_paragraphs(out: &mut String, text: &str) {
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            let _ = writeln!(out, ".PP");
+        } else {
+            let _ = writeln!(out, "{}", escape(line));
+        }
+    }
+}
+
+/// Escapes text for troff
+///
+/// Backslashes become `\e` and hyphens `\-`, so they print as written
+/// instead of starting escapes or turning into typographic hyphens. A
+/// leading `.` or `'` would start a request, so it is guarded with `\&`.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandBuilder, Flag};
+
+    fn create_man_cli() -> Command {
+        CommandBuilder::new("tool")
+            .short("Manage remote things")
+            .long("Tool manages remote things.\n\nIt talks to the server.")
+            .example("tool --config ~/my-tool.conf remote add origin")
+            .flag(
+                Flag::string("config")
+                    .short('c')
+                    .usage("Path to the config file")
+                    .default_str("/etc/tool.conf"),
+            )
+            .subcommand(
+                CommandBuilder::new("remote")
+                    .short("Manage remotes")
+                    .subcommand(
+                        CommandBuilder::new("add")
+                            .short("Add a remote")
+                            .flag(Flag::bool("fetch").usage("Fetch after adding"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_manpage_options_list_flags() {
+        let page = create_man_cli().generate_manpage();
+
+        for section in [
+            ".SH NAME",
+            ".SH SYNOPSIS",
+            ".SH DESCRIPTION",
+            ".SH OPTIONS",
+            ".SH COMMANDS",
+            ".SH EXAMPLES",
+        ] {
+            assert!(page.contains(section), "missing {section}");
+        }
+
+        let options = &page[page.find(".SH OPTIONS").unwrap()..page.find(".SH COMMANDS").unwrap()];
+        assert!(options.contains(
+            ".TP\n\\fB\\-c\\fR, \\fB\\-\\-config\\fR \\fIstring\\fR\n\
+             Path to the config file (default \"/etc/tool.conf\")\n"
+        ));
+        assert!(page.contains("Tool manages remote things.\n.PP\nIt talks to the server.\n"));
+        assert!(page.contains("tool \\-\\-config ~/my\\-tool.conf remote add origin"));
+    }
+
+    #[test]
+    fn test_manpages_for_nested_subcommands() {
+        let pages = create_man_cli().generate_manpages();
+        let names: Vec<&str> = pages.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["tool", "tool-remote", "tool-remote-add"]);
+
+        let add = &pages[2].1;
+        assert!(add.starts_with(".TH \"TOOL\\-REMOTE\\-ADD\" \"1\""));
+        assert!(add.contains(".SH NAME\ntool\\-remote\\-add \\- Add a remote\n"));
+        assert!(add.contains(".B tool remote add\n"));
+        assert!(add.contains(".SH GLOBAL OPTIONS\n.TP\n\\fB\\-c\\fR, \\fB\\-\\-config\\fR"));
+        assert!(add.contains(".SH SEE ALSO\n\\fBtool\\-remote\\fR(1)\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a-b \\n"), "a\\-b \\en");
+        assert_eq!(escape(".hidden"), "\\&.hidden");
+        assert_eq!(escape("'quoted'"), "\\&'quoted'");
+    }
+}
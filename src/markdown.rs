@@ -0,0 +1,233 @@
+//! Markdown documentation generation
+//!
+//! [`Command::generate_markdown`] turns a command tree into a single
+//! Markdown document, so reference docs can be regenerated from the code
+//! instead of kept in sync by hand. Flag types use the same labels as help
+//! output.
+
+use crate::command::Command;
+use crate::doc_tree::{command_path, sorted_flags, sorted_subcommands};
+use crate::flag::{Flag, FlagType, FlagValue};
+use std::fmt::Write;
+
+impl Command {
+    /// Renders this command and all of its subcommands as Markdown
+    ///
+    /// Every command gets a heading named after its full path, nested one
+    /// level per subcommand, followed by its description, a usage line, a
+    /// table of its flags, its examples, and links to its subcommands'
+    /// sections. Subcommands are listed in name order; hidden commands and
+    /// flags are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Flag};
+    ///
+    /// let cmd = CommandBuilder::new("tool")
+    ///     .flag(Flag::int("port").short('p').usage("Port to listen on"))
+    ///     .subcommand(CommandBuilder::new("serve").short("Start the server").build())
+    ///     .build();
+    ///
+    /// let docs = cmd.generate_markdown();
+    /// assert!(docs.starts_with("# tool\n"));
+    /// assert!(docs.contains("| `--port` | `-p` | int |  | Port to listen on |"));
+    /// assert!(docs.contains("- [`tool serve`](#tool-serve) - Start the server"));
+    /// ```
+    #[must_use]
+    pub fn generate_markdown(&self) -> String {
+        let mut out = String::new();
+        self.write_markdown(&mut out, &mut Vec::new());
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+
+    fn write_markdown<'a>(&'a self, out: &mut String, ancestors: &mut Vec<&'a Self>) {
+        let path = command_path(ancestors, self, " ");
+        let level = "#".repeat((ancestors.len() + 1).min(6));
+        let _ = writeln!(out, "{level} {path}\n");
+
+        let description = if self.long().is_empty() {
+            self.short()
+        } else {
+            self.long()
+        };
+        if !description.is_empty() {
+            let _ = writeln!(out, "{description}\n");
+        }
+
+        let subcommands = sorted_subcommands(self);
+        let command = if subcommands.is_empty() {
+            ""
+        } else {
+            " [command]"
+        };
+        let _ = writeln!(out, "```\n{path}{command} [flags]\n```\n");
+
+        if !sorted_flags(self).is_empty() {
+            let _ = writeln!(out, "**Flags**\n");
+            write_flag_table(out, self);
+        }
+
+        let inherited: Vec<&Self> = ancestors
+            .iter()
+            .rev()
+            .copied()
+            .filter(|cmd| !sorted_flags(cmd).is_empty())
+            .collect();
+        if !inherited.is_empty() {
+            let _ = writeln!(out, "**Global Flags**\n");
+            for cmd in inherited {
+                write_flag_table(out, cmd);
+            }
+        }
+
+        if !self.examples().is_empty() {
+            let _ = writeln!(out, "**Examples**\n");
+            let _ = writeln!(out, "```");
+            for (example, description) in self.described_examples() {
+                if let Some(description) = description {
+                    let _ = writeln!(out, "# {description}");
+                }
+                let _ = writeln!(out, "{example}");
+            }
+            let _ = writeln!(out, "```\n");
+        }
+
+        if !subcommands.is_empty() {
+            let _ = writeln!(out, "**Commands**\n");
+            for sub in &subcommands {
+                let sub_path = format!("{path} {}", sub.name());
+                let _ = write!(out, "- [`{sub_path}`](#{})", anchor(&sub_path));
+                if sub.short().is_empty() {
+                    let _ = writeln!(out);
+                } else {
+                    let _ = writeln!(out, " - {}", sub.short());
+                }
+            }
+            let _ = writeln!(out);
+        }
+
+        ancestors.push(self);
+        for sub in subcommands {
+            sub.write_markdown(out, ancestors);
+        }
+        ancestors.pop();
+    }
+}
+
+/// Returns the anchor GitHub generates for a heading
+fn anchor(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|ch| match ch {
+            ' ' => Some('-'),
+            '-' | '_' => Some(ch),
+            ch if ch.is_alphanumeric() => Some(ch.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Writes a table with one row per visible flag of `cmd`
+fn write_flag_table(out: &mut String, cmd: &Command) {
+    let _ = writeln!(out, "| Flag | Short | Type | Default | Usage |");
+    let _ = writeln!(out, "|------|-------|------|---------|-------|");
+    for flag in sorted_flags(cmd) {
+        let short = flag
+            .short
+            .map(|short| format!("`-{short}`"))
+            .unwrap_or_default();
+        let default = cmd
+            .effective_flag_default(&[], &flag.name)
+            .map(|default| match default {
+                FlagValue::String(s) if s.is_empty() => String::new(),
+                other => format!("`{other}`"),
+            })
+            .unwrap_or_default();
+        let mut usage = flag.usage.clone();
+        if flag.required {
+            usage.push_str(" (required)");
+        }
+        let _ = writeln!(
+            out,
+            "| `--{}` | {} | {} | {} | {} |",
+            flag.name,
+            short,
+            table_cell(&type_label(flag)),
+            table_cell(&default),
+            table_cell(usage.trim_start())
+        );
+    }
+    let _ = writeln!(out);
+}
+
+/// Returns the flag's type as help shows it; switches are `bool` or `count`
+fn type_label(flag: &Flag) -> String {
+    match flag.value_type {
+        FlagType::Bool => "bool".to_string(),
+        FlagType::Count => "count".to_string(),
+        _ => Command::flag_value_hint(flag),
+    }
+}
+
+/// Escapes text for a table cell, where `|` ends the cell and newlines
+/// end the row
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandBuilder, Flag};
+
+    #[test]
+    fn test_markdown_flags_and_subcommands() {
+        let cmd = CommandBuilder::new("tool")
+            .short("Manage remote things")
+            .example("tool remote add origin")
+            .flag(
+                Flag::directory("root")
+                    .short('r')
+                    .usage("Working directory")
+                    .default_str("."),
+            )
+            .flag(Flag::choice("format", &["json", "yaml"]).usage("Output format"))
+            .subcommand(
+                CommandBuilder::new("remote")
+                    .short("Manage remotes")
+                    .subcommand(
+                        CommandBuilder::new("add")
+                            .short("Add a remote")
+                            .flag(Flag::bool("fetch").usage("Fetch after adding"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let docs = cmd.generate_markdown();
+
+        assert!(docs.contains("| `--root` | `-r` | dir | `.` | Working directory |"));
+        assert!(docs.contains("| `--format` |  | {json\\|yaml} |  | Output format |"));
+        assert!(docs.contains("```\ntool [command] [flags]\n```"));
+        assert!(docs.contains("```\ntool remote add origin\n```"));
+        assert!(docs.contains("- [`tool remote`](#tool-remote) - Manage remotes"));
+        assert!(docs.contains("\n## tool remote\n"));
+        assert!(docs.contains("\n### tool remote add\n\nAdd a remote\n"));
+        assert!(docs.contains("| `--fetch` |  | bool |  | Fetch after adding |"));
+
+        // Inherited flags are listed for subcommands too
+        let add = &docs[docs.find("### tool remote add").unwrap()..];
+        assert!(add.contains("**Global Flags**"));
+        assert!(add.contains("| `--root` |"));
+    }
+
+    #[test]
+    fn test_anchor() {
+        assert_eq!(anchor("tool remote add"), "tool-remote-add");
+        assert_eq!(anchor("My-Tool set_env"), "my-tool-set_env");
+    }
+}
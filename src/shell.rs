@@ -1,10 +1,12 @@
 //! Shell completion script generation
 //!
 //! This module provides functionality to generate shell completion scripts
-//! for Bash, Zsh, and Fish shells. The generated scripts integrate with the
+//! for Bash, Zsh, Fish, and Nushell. The generated scripts integrate with the
 //! dynamic completion system to provide TAB completions at runtime.
 
 use crate::command::Command;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
 use std::fmt::Write;
 
 /// Safe writeln macro that handles the rare case where writing to String fails
@@ -42,8 +44,11 @@ macro_rules! safe_writeln {
 ///
 /// // Generate Fish completion script
 /// let fish_script = cmd.generate_completion(Shell::Fish);
+///
+/// // Generate Nushell completion script
+/// let nu_script = cmd.generate_completion(Shell::Nushell);
 /// ```
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Shell {
     /// Bash shell (most common on Linux)
     Bash,
@@ -51,6 +56,99 @@ pub enum Shell {
     Zsh,
     /// Fish shell (modern alternative shell)
     Fish,
+    /// Nushell (structured data shell)
+    Nushell,
+}
+
+impl Shell {
+    /// Every shell completion scripts can be generated for
+    pub const ALL: [Self; 4] = [Self::Bash, Self::Zsh, Self::Fish, Self::Nushell];
+
+    /// Returns the name of the shell's executable
+    pub const fn program(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Nushell => "nu",
+        }
+    }
+
+    /// Returns whether this shell is installed and can be run
+    pub fn is_available(self) -> bool {
+        use std::process::{Command, Stdio};
+
+        Command::new(self.program())
+            .args(["-c", "exit 0"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Checks that a completion script parses in this shell without running it
+    ///
+    /// Uses `bash -n`, `zsh -n`, `fish --no-execute` or Nushell's `nu-check`. This guards against
+    /// script generation regressions such as unbalanced braces or quotes.
+    ///
+    /// Returns `None` when the shell is not installed, so tests can skip
+    /// instead of failing on machines without it.
+    ///
+    /// # Errors
+    ///
+    /// The inner result is `Error::Validation` with the shell's diagnostics
+    /// when the script does not parse, or `Error::Io` if the shell could not
+    /// be run
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::shell::Shell;
+    /// use flag_rs::Command;
+    ///
+    /// let script = Command::new("myapp").generate_completion(Shell::Bash);
+    /// if let Some(result) = Shell::Bash.check_syntax(&script) {
+    ///     assert!(result.is_ok());
+    /// }
+    /// ```
+    pub fn check_syntax(self, script: &str) -> Option<Result<()>> {
+        if !self.is_available() {
+            return None;
+        }
+        Some(self.run_syntax_check(script))
+    }
+
+    fn run_syntax_check(self, script: &str) -> Result<()> {
+        use std::io::Write as _;
+        use std::process::{Command, Stdio};
+
+        let no_exec_args: &[&str] = match self {
+            Self::Bash | Self::Zsh => &["-n"],
+            Self::Fish => &["--no-execute"],
+            Self::Nushell => &["--stdin", "-c", "$in | nu-check --debug | ignore"],
+        };
+        let mut child = Command::new(self.program())
+            .args(no_exec_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::Validation(format!(
+                "{} rejected the completion script: {}",
+                self.program(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
 }
 
 impl Command {
@@ -87,35 +185,303 @@ impl Command {
     /// ```bash
     /// myapp completion fish > ~/.config/fish/completions/myapp.fish
     /// ```
+    ///
+    /// ## Nushell
+    /// Save the script and source it from `config.nu`:
+    /// ```bash
+    /// myapp completion nushell > ~/.config/nushell/myapp-completion.nu
+    /// echo "source ~/.config/nushell/myapp-completion.nu" >> ~/.config/nushell/config.nu
+    /// ```
     pub fn generate_completion(&self, shell: Shell) -> String {
         match shell {
             Shell::Bash => self.generate_bash_completion(),
             Shell::Zsh => self.generate_zsh_completion(),
             Shell::Fish => self.generate_fish_completion(),
+            Shell::Nushell => self.generate_nushell_completion(),
         }
     }
 
+    /// Generates the completion script for every shell at once
+    ///
+    /// Handy in a build script or packaging step that ships all of them;
+    /// each entry is what [`generate_completion`](Self::generate_completion)
+    /// returns for that shell.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use flag_rs::{CommandBuilder, Shell};
+    ///
+    /// let cmd = CommandBuilder::new("myapp").build();
+    /// for (shell, script) in cmd.generate_all_completions() {
+    ///     std::fs::write(format!("completions/myapp.{}", shell.program()), script).unwrap();
+    /// }
+    /// ```
+    pub fn generate_all_completions(&self) -> HashMap<Shell, String> {
+        Shell::ALL
+            .into_iter()
+            .map(|shell| (shell, self.generate_completion(shell)))
+            .collect()
+    }
+
+    /// Generates a completion script with subcommands and flags built in
+    ///
+    /// Scripts from [`generate_completion`](Self::generate_completion) run
+    /// the program on every TAB, which adds up for large command trees.
+    /// For Bash and Zsh, this script instead carries the subcommand and
+    /// flag names from [`generate_static_completion_spec`] and completes
+    /// them itself; the program only runs to complete flag values and
+    /// arguments. Fish and Nushell get their usual scripts.
+    ///
+    /// Regenerate the script whenever commands or flags change.
+    ///
+    /// [`generate_static_completion_spec`]: Self::generate_static_completion_spec
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flag_rs::{CommandBuilder, Shell};
+    ///
+    /// let cmd = CommandBuilder::new("myapp")
+    ///     .subcommand(CommandBuilder::new("serve").build())
+    ///     .build();
+    ///
+    /// let script = cmd.generate_static_completion(Shell::Bash);
+    /// assert!(script.contains("subcommands=\"serve\""));
+    /// ```
+    pub fn generate_static_completion(&self, shell: Shell) -> String {
+        match shell {
+            Shell::Bash => self.generate_static_bash_completion(),
+            Shell::Zsh => self.generate_static_zsh_completion(),
+            Shell::Fish | Shell::Nushell => self.generate_completion(shell),
+        }
+    }
+
+    fn generate_static_bash_completion(&self) -> String {
+        let (commands, routes) = static_commands(&self.generate_static_completion_spec());
+        let mut script = String::new();
+
+        safe_writeln!(
+            &mut script,
+            "# Bash completion for {}, with subcommands and flags built in",
+            self.program_name()
+        );
+        script.push_str(&self.bash_complete_function());
+        safe_writeln!(&mut script);
+        safe_writeln!(&mut script, "_{}_static_complete() {{", self.program_name());
+        safe_writeln!(&mut script, "    local cur prev words cword");
+        safe_writeln!(
+            &mut script,
+            "    _get_comp_words_by_ref -n =: cur prev words cword"
+        );
+        safe_writeln!(&mut script);
+        write_static_path_walk(&mut script, self.program_name(), &routes, 1, "cword");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "    local subcommands=\"\" flags=\"\" value_flags=\"\" dynamic_args=\"\""
+        );
+        safe_writeln!(&mut script, "    case \"$cmd_path\" in");
+        for (path, cmd) in &commands {
+            let names = |entries: &[(String, String)]| {
+                entries
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            safe_writeln!(&mut script, "        \"{path}\")");
+            safe_writeln!(
+                &mut script,
+                "            subcommands=\"{}\"",
+                names(&cmd.subcommands)
+            );
+            safe_writeln!(&mut script, "            flags=\"{}\"", names(&cmd.flags));
+            write_static_value_flags(&mut script, cmd);
+            safe_writeln!(&mut script, "            ;;");
+        }
+        safe_writeln!(&mut script, "    esac");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "    # Only values and arguments are completed by running the binary"
+        );
+        safe_writeln!(
+            &mut script,
+            "    if [[ \"$cur\" == -*=* || ( \"$cur\" != -* && ( \"$value_flags\" == *\" $prev \"* || -n \"$dynamic_args\" || -z \"$subcommands\" ) ) ]]; then"
+        );
+        safe_writeln!(&mut script, "        _{}_complete", self.program_name());
+        safe_writeln!(&mut script, "        return");
+        safe_writeln!(&mut script, "    fi");
+        safe_writeln!(&mut script);
+        safe_writeln!(&mut script, "    local IFS=$'\\n'");
+        safe_writeln!(&mut script, "    if [[ \"$cur\" == -* ]]; then");
+        safe_writeln!(
+            &mut script,
+            "        COMPREPLY=( $(IFS=' ' compgen -W \"$flags\" -- \"$cur\") )"
+        );
+        safe_writeln!(&mut script, "    else");
+        safe_writeln!(
+            &mut script,
+            "        COMPREPLY=( $(IFS=' ' compgen -W \"$subcommands\" -- \"$cur\") )"
+        );
+        safe_writeln!(&mut script, "    fi");
+        if !self.file_completion_fallback() {
+            safe_writeln!(&mut script);
+            safe_writeln!(&mut script, "    if [[ ${{#COMPREPLY[@]}} -eq 0 ]]; then");
+            safe_writeln!(
+                &mut script,
+                "        # No matches: don't fall back to completing filenames"
+            );
+            safe_writeln!(
+                &mut script,
+                "        compopt +o default +o bashdefault 2>/dev/null"
+            );
+            safe_writeln!(&mut script, "    fi");
+        }
+        safe_writeln!(&mut script, "}}");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "complete -F _{}_static_complete {}",
+            self.program_name(),
+            self.program_name()
+        );
+
+        script
+    }
+
+    fn generate_static_zsh_completion(&self) -> String {
+        let (commands, routes) = static_commands(&self.generate_static_completion_spec());
+        let mut script = String::new();
+
+        safe_writeln!(&mut script, "#compdef -P {}", self.program_name());
+        safe_writeln!(
+            &mut script,
+            "# Zsh completion for {}, with subcommands and flags built in",
+            self.program_name()
+        );
+        safe_writeln!(&mut script);
+        script.push_str(&self.zsh_complete_function());
+        safe_writeln!(&mut script);
+        safe_writeln!(&mut script, "_{}_static_complete() {{", self.program_name());
+        write_static_path_walk(&mut script, self.program_name(), &routes, 2, "CURRENT");
+        safe_writeln!(&mut script);
+        safe_writeln!(&mut script, "    local -a subcommands flags");
+        safe_writeln!(&mut script, "    local value_flags=\"\" dynamic_args=\"\"");
+        safe_writeln!(&mut script, "    case \"$cmd_path\" in");
+        for (path, cmd) in &commands {
+            let described = |entries: &[(String, String)]| {
+                entries
+                    .iter()
+                    .map(|(name, description)| {
+                        zsh_quote(&format!("{}:{description}", name.replace(':', "\\:")))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            safe_writeln!(&mut script, "        \"{path}\")");
+            safe_writeln!(
+                &mut script,
+                "            subcommands=({})",
+                described(&cmd.subcommands)
+            );
+            safe_writeln!(&mut script, "            flags=({})", described(&cmd.flags));
+            write_static_value_flags(&mut script, cmd);
+            safe_writeln!(&mut script, "            ;;");
+        }
+        safe_writeln!(&mut script, "    esac");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "    local cur=\"${{words[CURRENT]}}\" prev=\"${{words[CURRENT-1]}}\""
+        );
+        safe_writeln!(
+            &mut script,
+            "    # Only values and arguments are completed by running the binary"
+        );
+        safe_writeln!(
+            &mut script,
+            "    if [[ \"$cur\" == -*=* || ( \"$cur\" != -* && ( \"$value_flags\" == *\" $prev \"* || -n \"$dynamic_args\" || ${{#subcommands}} -eq 0 ) ) ]]; then"
+        );
+        safe_writeln!(&mut script, "        _{}_complete", self.program_name());
+        safe_writeln!(&mut script, "        return");
+        safe_writeln!(&mut script, "    fi");
+        safe_writeln!(&mut script);
+        safe_writeln!(&mut script, "    if [[ \"$cur\" == -* ]]; then");
+        safe_writeln!(&mut script, "        _describe -t flags 'flag' flags");
+        safe_writeln!(&mut script, "    else");
+        safe_writeln!(
+            &mut script,
+            "        _describe -t commands 'command' subcommands"
+        );
+        safe_writeln!(&mut script, "    fi");
+        if !self.file_completion_fallback() {
+            safe_writeln!(&mut script);
+            safe_writeln!(
+                &mut script,
+                "    # Succeed even without matches, so zsh doesn't fall back to files"
+            );
+            safe_writeln!(&mut script, "    return 0");
+        }
+        safe_writeln!(&mut script, "}}");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "compdef _{}_static_complete {}",
+            self.program_name(),
+            self.program_name()
+        );
+
+        script
+    }
+
     fn generate_bash_completion(&self) -> String {
         let mut script = String::new();
 
-        safe_writeln!(&mut script, "# Bash completion for {}", self.name());
-        safe_writeln!(&mut script, "_{}_complete() {{", self.name());
+        safe_writeln!(&mut script, "# Bash completion for {}", self.program_name());
+        script.push_str(&self.bash_complete_function());
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "complete -F _{}_complete {}",
+            self.program_name(),
+            self.program_name()
+        );
+
+        script
+    }
+
+    /// Returns the Bash function that asks the binary for completions
+    fn bash_complete_function(&self) -> String {
+        let mut script = String::new();
+
+        safe_writeln!(&mut script, "_{}_complete() {{", self.program_name());
         safe_writeln!(&mut script, "    local cur prev words cword");
         safe_writeln!(
             &mut script,
-            "    _get_comp_words_by_ref -n : cur prev words cword"
+            "    _get_comp_words_by_ref -n =: cur prev words cword"
         );
         safe_writeln!(&mut script);
         safe_writeln!(
             &mut script,
             "    # Call our binary with special completion env var"
         );
+        safe_writeln!(
+            &mut script,
+            "    # The program may be typed as a path; quoting keeps '~' from expanding"
+        );
+        safe_writeln!(&mut script, "    local cmd=\"${{words[0]}}\"");
+        safe_writeln!(
+            &mut script,
+            "    [[ \"$cmd\" == \"~/\"* ]] && cmd=\"$HOME/${{cmd#\"~/\"}}\""
+        );
         safe_writeln!(&mut script, "    local IFS=$'\\n'");
         safe_writeln!(&mut script, "    local response");
         safe_writeln!(
             &mut script,
-            "    response=$({}_COMPLETE=bash \"${{words[0]}}\" __complete \"${{words[@]:1:$((cword-1))}}\" \"$cur\" 2>/dev/null)",
-            self.name().to_uppercase()
+            "    response=$({env}=bash {env}_SESSION=\"${{{env}_SESSION:-$$}}\" \"$cmd\" __complete \"${{words[@]:1:$((cword-1))}}\" \"$cur\" 2>/dev/null)",
+            env = self.completion_env_var()
         );
         safe_writeln!(&mut script);
         safe_writeln!(&mut script, "    if [[ -n \"$response\" ]]; then");
@@ -124,7 +490,9 @@ impl Command {
             "        # Use printf to handle each line separately"
         );
         safe_writeln!(&mut script, "        local lines=()");
+        safe_writeln!(&mut script, "        local descriptions=()");
         safe_writeln!(&mut script, "        local help_messages=()");
+        safe_writeln!(&mut script, "        local has_descriptions=0");
         safe_writeln!(&mut script, "        while IFS= read -r line; do");
         safe_writeln!(
             &mut script,
@@ -135,12 +503,91 @@ impl Command {
             &mut script,
             "                help_messages+=(\"${{line#_activehelp_ }}\")"
         );
+        safe_writeln!(
+            &mut script,
+            "            elif [[ \"$line\" == *$'\\t'* ]]; then"
+        );
+        safe_writeln!(&mut script, "                # value<TAB>description");
+        safe_writeln!(
+            &mut script,
+            "                lines+=(\"${{line%%$'\\t'*}}\")"
+        );
+        safe_writeln!(
+            &mut script,
+            "                descriptions+=(\"${{line#*$'\\t'}}\")"
+        );
+        safe_writeln!(&mut script, "                has_descriptions=1");
         safe_writeln!(&mut script, "            else");
         safe_writeln!(&mut script, "                lines+=(\"$line\")");
+        safe_writeln!(&mut script, "                descriptions+=(\"\")");
         safe_writeln!(&mut script, "            fi");
         safe_writeln!(&mut script, "        done <<< \"$response\"");
         safe_writeln!(&mut script, "        COMPREPLY=( \"${{lines[@]}}\" )");
         safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "        # Readline only replaces the text after the last '=', so drop"
+        );
+        safe_writeln!(
+            &mut script,
+            "        # the --flag= prefix the binary echoes back for --flag=value"
+        );
+        safe_writeln!(
+            &mut script,
+            "        if [[ \"$cur\" == *=* && \"$COMP_WORDBREAKS\" == *=* ]]; then"
+        );
+        safe_writeln!(&mut script, "            local eq_prefix=\"${{cur%=*}}=\"");
+        safe_writeln!(
+            &mut script,
+            "            COMPREPLY=( \"${{COMPREPLY[@]#\"$eq_prefix\"}}\" )"
+        );
+        safe_writeln!(&mut script, "        fi");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "        # A lone directory is completed into, not ended with a space"
+        );
+        safe_writeln!(
+            &mut script,
+            "        if [[ ${{#COMPREPLY[@]}} -eq 1 && \"${{COMPREPLY[0]}}\" == */ ]]; then"
+        );
+        safe_writeln!(&mut script, "            compopt -o nospace 2>/dev/null");
+        safe_writeln!(&mut script, "        fi");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "        # Show descriptions in the menu; a lone match is inserted bare"
+        );
+        safe_writeln!(
+            &mut script,
+            "        if [[ $has_descriptions -eq 1 && ${{#COMPREPLY[@]}} -gt 1 ]]; then"
+        );
+        safe_writeln!(&mut script, "            local i width=0");
+        safe_writeln!(
+            &mut script,
+            "            for i in \"${{!COMPREPLY[@]}}\"; do"
+        );
+        safe_writeln!(
+            &mut script,
+            "                (( ${{#COMPREPLY[i]}} > width )) && width=${{#COMPREPLY[i]}}"
+        );
+        safe_writeln!(&mut script, "            done");
+        safe_writeln!(
+            &mut script,
+            "            for i in \"${{!COMPREPLY[@]}}\"; do"
+        );
+        safe_writeln!(
+            &mut script,
+            "                if [[ -n \"${{descriptions[i]}}\" ]]; then"
+        );
+        safe_writeln!(
+            &mut script,
+            "                    printf -v \"COMPREPLY[$i]\" '%-*s  (%s)' \"$width\" \"${{COMPREPLY[i]}}\" \"${{descriptions[i]}}\""
+        );
+        safe_writeln!(&mut script, "                fi");
+        safe_writeln!(&mut script, "            done");
+        safe_writeln!(&mut script, "        fi");
+        safe_writeln!(&mut script);
         safe_writeln!(&mut script, "        # Display help messages if any");
         safe_writeln!(
             &mut script,
@@ -156,25 +603,47 @@ impl Command {
         safe_writeln!(&mut script, "            printf '\\n'");
         safe_writeln!(&mut script, "        fi");
         safe_writeln!(&mut script, "    fi");
+        if !self.file_completion_fallback() {
+            safe_writeln!(&mut script);
+            safe_writeln!(&mut script, "    if [[ ${{#COMPREPLY[@]}} -eq 0 ]]; then");
+            safe_writeln!(
+                &mut script,
+                "        # No matches: don't fall back to completing filenames"
+            );
+            safe_writeln!(
+                &mut script,
+                "        compopt +o default +o bashdefault 2>/dev/null"
+            );
+            safe_writeln!(&mut script, "    fi");
+        }
         safe_writeln!(&mut script, "}}");
+
+        script
+    }
+
+    fn generate_zsh_completion(&self) -> String {
+        let mut script = String::new();
+
+        safe_writeln!(&mut script, "#compdef -P {}", self.program_name());
+        safe_writeln!(&mut script, "# Zsh completion for {}", self.program_name());
+        safe_writeln!(&mut script);
+        script.push_str(&self.zsh_complete_function());
         safe_writeln!(&mut script);
         safe_writeln!(
             &mut script,
-            "complete -F _{}_complete {}",
-            self.name(),
-            self.name()
+            "compdef _{}_complete {}",
+            self.program_name(),
+            self.program_name()
         );
 
         script
     }
 
-    fn generate_zsh_completion(&self) -> String {
+    /// Returns the Zsh function that asks the binary for completions
+    fn zsh_complete_function(&self) -> String {
         let mut script = String::new();
 
-        safe_writeln!(&mut script, "#compdef -P {}", self.name());
-        safe_writeln!(&mut script, "# Zsh completion for {}", self.name());
-        safe_writeln!(&mut script);
-        safe_writeln!(&mut script, "_{}_complete() {{", self.name());
+        safe_writeln!(&mut script, "_{}_complete() {{", self.program_name());
         safe_writeln!(&mut script, "    local -a completions");
         safe_writeln!(&mut script, "    local IFS=$'\\n'");
         safe_writeln!(&mut script);
@@ -183,6 +652,10 @@ impl Command {
             "    # Get the actual command from the command line"
         );
         safe_writeln!(&mut script, "    local cmd=\"${{words[1]}}\"");
+        safe_writeln!(
+            &mut script,
+            "    [[ \"$cmd\" == \"~/\"* ]] && cmd=\"$HOME/${{cmd#\"~/\"}}\""
+        );
         safe_writeln!(
             &mut script,
             "    if [[ \"$cmd\" != /* ]] && ! command -v \"$cmd\" &>/dev/null; then"
@@ -216,8 +689,8 @@ impl Command {
         safe_writeln!(&mut script, "    local response");
         safe_writeln!(
             &mut script,
-            "    response=$({}_COMPLETE=zsh \"$cmd\" \"${{comp_line[@]}}\" 2>/dev/null)",
-            self.name().to_uppercase()
+            "    response=$({env}=zsh {env}_SESSION=\"${{{env}_SESSION:-$$}}\" \"$cmd\" \"${{comp_line[@]}}\" 2>/dev/null)",
+            env = self.completion_env_var()
         );
         safe_writeln!(&mut script);
         safe_writeln!(&mut script, "    if [[ -n \"$response\" ]]; then");
@@ -272,27 +745,50 @@ impl Command {
         );
         safe_writeln!(&mut script, "        fi");
         safe_writeln!(&mut script);
-        safe_writeln!(&mut script, "        # Add completions with descriptions");
         safe_writeln!(
             &mut script,
-            "        if [[ ${{#descriptions[@]}} -gt 0 ]] && [[ -n \"${{descriptions[*]// }}\" ]]; then"
+            "        # Complete only the value part of --flag=value"
         );
         safe_writeln!(
             &mut script,
-            "            compadd -Q -d descriptions -a values"
+            "        if [[ \"${{words[CURRENT]}}\" == --*=* ]]; then"
+        );
+        safe_writeln!(
+            &mut script,
+            "            local eq_prefix=\"${{words[CURRENT]%%=*}}=\""
+        );
+        safe_writeln!(&mut script, "            compset -P 1 '*='");
+        safe_writeln!(
+            &mut script,
+            "            values=(\"${{(@)values#$eq_prefix}}\")"
         );
-        safe_writeln!(&mut script, "        else");
-        safe_writeln!(&mut script, "            compadd -Q -a values");
         safe_writeln!(&mut script, "        fi");
-        safe_writeln!(&mut script, "    fi");
-        safe_writeln!(&mut script, "}}");
         safe_writeln!(&mut script);
         safe_writeln!(
             &mut script,
-            "compdef _{}_complete {}",
-            self.name(),
-            self.name()
+            "        # Add completions with descriptions, in our order so a default stays first"
+        );
+        safe_writeln!(
+            &mut script,
+            "        if [[ ${{#descriptions[@]}} -gt 0 ]] && [[ -n \"${{descriptions[*]// }}\" ]]; then"
+        );
+        safe_writeln!(
+            &mut script,
+            "            compadd -V values -Q -d descriptions -a values"
         );
+        safe_writeln!(&mut script, "        else");
+        safe_writeln!(&mut script, "            compadd -V values -Q -a values");
+        safe_writeln!(&mut script, "        fi");
+        safe_writeln!(&mut script, "    fi");
+        if !self.file_completion_fallback() {
+            safe_writeln!(&mut script);
+            safe_writeln!(
+                &mut script,
+                "    # Succeed even without matches, so zsh doesn't fall back to files"
+            );
+            safe_writeln!(&mut script, "    return 0");
+        }
+        safe_writeln!(&mut script, "}}");
 
         script
     }
@@ -300,11 +796,15 @@ impl Command {
     fn generate_fish_completion(&self) -> String {
         let mut script = String::new();
 
-        safe_writeln!(&mut script, "# Fish completion for {}", self.name());
-        safe_writeln!(&mut script, "function __{}_complete", self.name());
+        safe_writeln!(&mut script, "# Fish completion for {}", self.program_name());
+        safe_writeln!(&mut script, "function __{}_complete", self.program_name());
         safe_writeln!(&mut script, "    set -l cmd (commandline -opc)");
         safe_writeln!(&mut script, "    set -l cursor (commandline -C)");
         safe_writeln!(&mut script, "    set -l current (commandline -ct)");
+        safe_writeln!(
+            &mut script,
+            "    set cmd[1] (string replace -r '^~/' \"$HOME/\" -- $cmd[1])"
+        );
         safe_writeln!(&mut script);
         safe_writeln!(
             &mut script,
@@ -312,8 +812,13 @@ impl Command {
         );
         safe_writeln!(
             &mut script,
-            "    set -l response (env {}_COMPLETE=fish $cmd[1] __complete $cmd[2..-1] $current 2>/dev/null)",
-            self.name().to_uppercase()
+            "    set -q {env}_SESSION; or set -l {env}_SESSION $fish_pid",
+            env = self.completion_env_var()
+        );
+        safe_writeln!(
+            &mut script,
+            "    set -l response (env {env}=fish {env}_SESSION=${env}_SESSION $cmd[1] __complete $cmd[2..-1] $current 2>/dev/null)",
+            env = self.completion_env_var()
         );
         safe_writeln!(&mut script);
         safe_writeln!(&mut script, "    # Process response and handle ActiveHelp");
@@ -344,10 +849,211 @@ impl Command {
         safe_writeln!(
             &mut script,
             "complete -c {} -f -a '(__{}_complete)'",
-            self.name(),
-            self.name()
+            self.program_name(),
+            self.program_name()
+        );
+
+        script
+    }
+
+    fn generate_nushell_completion(&self) -> String {
+        let mut script = String::new();
+        let name = self.program_name();
+        // Nushell variable names can't contain '-'
+        let ident = name.replace('-', "_");
+
+        safe_writeln!(&mut script, "# Nushell completion for {name}");
+        safe_writeln!(
+            &mut script,
+            "# Each line of output is a JSON record with value and description fields"
+        );
+        safe_writeln!(&mut script, "let __{ident}_completer = {{|spans|");
+        safe_writeln!(
+            &mut script,
+            "    let session = ($env.{env}_SESSION? | default ($nu.pid | into string))",
+            env = self.completion_env_var()
+        );
+        safe_writeln!(
+            &mut script,
+            "    with-env {{ \"{env}\": \"nushell\", \"{env}_SESSION\": $session }} {{",
+            env = self.completion_env_var()
+        );
+        safe_writeln!(
+            &mut script,
+            "        ^($spans.0) __complete ...($spans | skip 1) | complete | get stdout"
+        );
+        safe_writeln!(&mut script, "    }}");
+        safe_writeln!(&mut script, "    | lines");
+        safe_writeln!(
+            &mut script,
+            "    | where {{|line| not ($line | str starts-with \"_activehelp_\") }}"
+        );
+        safe_writeln!(&mut script, "    | each {{|line| $line | from json }}");
+        safe_writeln!(&mut script, "}}");
+        safe_writeln!(&mut script);
+        safe_writeln!(
+            &mut script,
+            "# Chain to any completer that was already configured"
+        );
+        safe_writeln!(
+            &mut script,
+            "let __{ident}_previous = $env.config?.completions?.external?.completer?"
+        );
+        safe_writeln!(
+            &mut script,
+            "$env.config.completions.external.enable = true"
+        );
+        safe_writeln!(
+            &mut script,
+            "$env.config.completions.external.completer = {{|spans|"
+        );
+        safe_writeln!(
+            &mut script,
+            "    if (($spans.0 | path basename) == \"{name}\") {{"
         );
+        safe_writeln!(&mut script, "        do $__{ident}_completer $spans");
+        safe_writeln!(
+            &mut script,
+            "    }} else if ($__{ident}_previous != null) {{"
+        );
+        safe_writeln!(&mut script, "        do $__{ident}_previous $spans");
+        safe_writeln!(&mut script, "    }}");
+        safe_writeln!(&mut script, "}}");
 
         script
     }
 }
+
+/// A command as the static completion scripts see it
+#[derive(Default)]
+struct StaticCommand {
+    /// Subcommand names with their descriptions
+    subcommands: Vec<(String, String)>,
+    /// Flag names, like `--verbose` and `-v`, with their descriptions;
+    /// inherited flags included
+    flags: Vec<(String, String)>,
+    /// Names of flags whose value is the next word
+    value_flags: Vec<String>,
+    /// Whether arguments are completed at runtime
+    dynamic_args: bool,
+}
+
+/// A `(parent, word, child)` step from a command path to a subcommand's
+type Route = (String, String, String);
+
+/// Reads a static completion spec into its commands, parents first, and
+/// the routes from each command to its subcommands
+fn static_commands(spec: &str) -> (Vec<(String, StaticCommand)>, Vec<Route>) {
+    let mut commands: Vec<(String, StaticCommand)> = Vec::new();
+    let mut routes = Vec::new();
+    let mut flags = Vec::new();
+
+    for line in spec.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields.as_slice() {
+            ["command", path, description] => {
+                if let Some((parent, name)) = path.rsplit_once(' ') {
+                    routes.push((
+                        (*parent).to_string(),
+                        (*name).to_string(),
+                        (*path).to_string(),
+                    ));
+                    if let Some((_, cmd)) = commands.iter_mut().find(|(p, _)| p == parent) {
+                        cmd.subcommands
+                            .push(((*name).to_string(), (*description).to_string()));
+                    }
+                }
+                commands.push(((*path).to_string(), StaticCommand::default()));
+            }
+            ["alias", path, alias] => {
+                if let Some((parent, _)) = path.rsplit_once(' ') {
+                    routes.push((
+                        (*parent).to_string(),
+                        (*alias).to_string(),
+                        (*path).to_string(),
+                    ));
+                }
+            }
+            ["flag", path, long, short, kind, description] => {
+                flags.push((*path, *long, *short, *kind, *description));
+            }
+            ["dynamic-args", path] => {
+                if let Some((_, cmd)) = commands.iter_mut().find(|(p, _)| p == path) {
+                    cmd.dynamic_args = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Flags are accepted by the subcommands of the command declaring them
+    for (path, cmd) in &mut commands {
+        for (owner, long, short, kind, description) in &flags {
+            let inherited = path == owner
+                || path
+                    .strip_prefix(owner)
+                    .is_some_and(|rest| rest.starts_with(' '));
+            if !inherited {
+                continue;
+            }
+            let mut names = vec![format!("--{long}")];
+            if !short.is_empty() {
+                names.push(format!("-{short}"));
+            }
+            for name in names {
+                if *kind == "value" {
+                    cmd.value_flags.push(name.clone());
+                }
+                cmd.flags.push((name, (*description).to_string()));
+            }
+        }
+    }
+
+    (commands, routes)
+}
+
+/// Writes the loop that follows subcommand names in `words`, from index
+/// `first` up to the word at `current`, leaving the command in `cmd_path`
+fn write_static_path_walk(
+    script: &mut String,
+    root: &str,
+    routes: &[Route],
+    first: usize,
+    current: &str,
+) {
+    safe_writeln!(
+        script,
+        "    # Follow subcommand names to the command being completed"
+    );
+    safe_writeln!(script, "    local cmd_path=\"{root}\" i");
+    safe_writeln!(script, "    for (( i = {first}; i < {current}; i++ )); do");
+    safe_writeln!(script, "        case \"$cmd_path ${{words[i]}}\" in");
+    for (parent, word, child) in routes {
+        safe_writeln!(
+            script,
+            "            \"{parent} {word}\") cmd_path=\"{child}\" ;;"
+        );
+    }
+    safe_writeln!(script, "        esac");
+    safe_writeln!(script, "    done");
+}
+
+/// Writes the assignments of `value_flags` and `dynamic_args` for one
+/// command of a static script's `case`
+fn write_static_value_flags(script: &mut String, cmd: &StaticCommand) {
+    if !cmd.value_flags.is_empty() {
+        safe_writeln!(
+            script,
+            "            value_flags=\" {} \"",
+            cmd.value_flags.join(" ")
+        );
+    }
+    if cmd.dynamic_args {
+        safe_writeln!(script, "            dynamic_args=1");
+    }
+}
+
+/// Quotes a word for zsh with single quotes
+fn zsh_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
@@ -26,8 +26,8 @@ pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     for (i, row) in matrix.iter_mut().enumerate().take(len1 + 1) {
         row[0] = i;
     }
-    for j in 0..=len2 {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     // Fill the matrix
@@ -51,6 +51,86 @@ pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     matrix[len1][len2]
 }
 
+/// Calculates the Jaro-Winkler similarity between two strings
+///
+/// The score runs from 0.0 (nothing in common) to 1.0 (identical). Unlike
+/// Levenshtein distance it counts swapped neighbours as one transposition
+/// and rewards a shared prefix, so `pdos` scores close to `pods`.
+pub fn jaro_winkler_similarity(s1: &str, s2: &str) -> f64 {
+    // Weight of each shared prefix character, and the most that count
+    const PREFIX_SCALE: f64 = 0.1;
+    const MAX_PREFIX: usize = 4;
+
+    let chars1: Vec<char> = s1.chars().collect();
+    let chars2: Vec<char> = s2.chars().collect();
+    if chars1.is_empty() && chars2.is_empty() {
+        return 1.0;
+    }
+    if chars1.is_empty() || chars2.is_empty() {
+        return 0.0;
+    }
+
+    // Characters match when equal and no further apart than this
+    let window = (chars1.len().max(chars2.len()) / 2).saturating_sub(1);
+    let mut matched1 = vec![false; chars1.len()];
+    let mut matched2 = vec![false; chars2.len()];
+    let mut matches = 0;
+    for (i, c) in chars1.iter().enumerate() {
+        let end = (i + window + 1).min(chars2.len());
+        for j in i.saturating_sub(window)..end {
+            if !matched2[j] && chars2[j] == *c {
+                matched1[i] = true;
+                matched2[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+
+    // Matched characters that appear in a different order
+    let in_order = |chars: &[char], matched: &[bool]| -> Vec<char> {
+        chars
+            .iter()
+            .zip(matched)
+            .filter(|(_, m)| **m)
+            .map(|(c, _)| *c)
+            .collect()
+    };
+    let out_of_order = in_order(&chars1, &matched1)
+        .iter()
+        .zip(&in_order(&chars2, &matched2))
+        .filter(|(a, b)| a != b)
+        .count();
+
+    let to_f64 = |n: usize| f64::from(u32::try_from(n).unwrap_or(u32::MAX));
+    let m = to_f64(matches);
+    let transpositions = to_f64(out_of_order) / 2.0;
+    let jaro =
+        (m / to_f64(chars1.len()) + m / to_f64(chars2.len()) + (m - transpositions) / m) / 3.0;
+
+    let prefix = chars1
+        .iter()
+        .zip(&chars2)
+        .take(MAX_PREFIX)
+        .take_while(|(a, b)| a == b)
+        .count();
+    to_f64(prefix).mul_add(PREFIX_SCALE * (1.0 - jaro), jaro)
+}
+
+/// How a mistyped name is compared with the names that exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuggestionAlgorithm {
+    /// Edit distance: names at most a number of edits away are suggested
+    #[default]
+    Levenshtein,
+    /// Jaro-Winkler similarity: names scoring at least a threshold are
+    /// suggested, which forgives swapped letters
+    JaroWinkler,
+}
+
 /// Finds suggestions from a list of candidates based on similarity to input
 ///
 /// Returns candidates that have a Levenshtein distance less than or equal
@@ -71,9 +151,67 @@ pub fn find_suggestions(input: &str, candidates: &[String], max_distance: usize)
     suggestions.into_iter().map(|(name, _)| name).collect()
 }
 
+/// Finds suggestions from a list of candidates by Jaro-Winkler similarity
+///
+/// Returns candidates scoring at least `min_similarity`, sorted by score
+/// (closest first).
+pub fn find_similar_suggestions(
+    input: &str,
+    candidates: &[String],
+    min_similarity: f64,
+) -> Vec<String> {
+    let mut suggestions: Vec<(String, f64)> = candidates
+        .iter()
+        .map(|candidate| (candidate.clone(), jaro_winkler_similarity(input, candidate)))
+        .filter(|(_, score)| *score >= min_similarity)
+        .collect();
+
+    // Sort by score, then alphabetically
+    suggestions.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    suggestions.into_iter().map(|(name, _)| name).collect()
+}
+
+/// Finds likely intended values for a mistyped flag value
+///
+/// Uses [`find_suggestions`] with the default distance first. Values are
+/// often long compared to their typos (`prodd` for `production`), so when
+/// nothing is that close this falls back to the candidates sharing the
+/// longest common prefix with `input`, as long as it is at least three
+/// characters.
+pub fn find_value_suggestions(input: &str, candidates: &[String]) -> Vec<String> {
+    const MIN_COMMON_PREFIX: usize = 3;
+
+    let suggestions = find_suggestions(input, candidates, DEFAULT_SUGGESTION_DISTANCE);
+    if !suggestions.is_empty() {
+        return suggestions;
+    }
+
+    let common_prefix = |candidate: &String| {
+        input
+            .chars()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let longest = candidates.iter().map(common_prefix).max().unwrap_or(0);
+    if longest < MIN_COMMON_PREFIX {
+        return Vec::new();
+    }
+
+    candidates
+        .iter()
+        .filter(|candidate| common_prefix(candidate) == longest)
+        .cloned()
+        .collect()
+}
+
 /// Default maximum Levenshtein distance for suggestions
 pub const DEFAULT_SUGGESTION_DISTANCE: usize = 2;
 
+/// Default minimum Jaro-Winkler similarity for suggestions
+pub const DEFAULT_SUGGESTION_SIMILARITY: f64 = 0.85;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +263,68 @@ mod tests {
         assert_eq!(suggestions, vec!["start"]); // distance 1
     }
 
+    #[test]
+    fn test_find_value_suggestions() {
+        let candidates = vec![
+            "development".to_string(),
+            "staging".to_string(),
+            "production".to_string(),
+        ];
+
+        // Close enough for the edit distance
+        assert_eq!(
+            find_value_suggestions("stagin", &candidates),
+            vec!["staging"]
+        );
+
+        // Too far for the edit distance, but shares a prefix
+        assert_eq!(
+            find_value_suggestions("prodd", &candidates),
+            vec!["production"]
+        );
+
+        // Nothing similar
+        assert!(find_value_suggestions("qa", &candidates).is_empty());
+        assert!(find_value_suggestions("prx", &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_jaro_winkler_similarity() {
+        assert!((jaro_winkler_similarity("pods", "pods") - 1.0).abs() < f64::EPSILON);
+        assert!(jaro_winkler_similarity("abc", "xyz").abs() < f64::EPSILON);
+        assert!(jaro_winkler_similarity("", "pods").abs() < f64::EPSILON);
+
+        // The textbook example
+        let score = jaro_winkler_similarity("martha", "marhta");
+        assert!((score - 0.961).abs() < 0.001, "{score}");
+
+        // A transposition scores high
+        assert!(jaro_winkler_similarity("pdos", "pods") > DEFAULT_SUGGESTION_SIMILARITY);
+    }
+
+    #[test]
+    fn test_find_similar_suggestions() {
+        let candidates = vec![
+            "pods".to_string(),
+            "services".to_string(),
+            "deployments".to_string(),
+        ];
+
+        // Two edits apart, missed with a distance of one
+        assert!(find_suggestions("pdos", &candidates, 1).is_empty());
+        assert_eq!(
+            find_similar_suggestions("pdos", &candidates, DEFAULT_SUGGESTION_SIMILARITY),
+            vec!["pods"]
+        );
+        assert_eq!(
+            find_similar_suggestions("deploymnets", &candidates, DEFAULT_SUGGESTION_SIMILARITY),
+            vec!["deployments"]
+        );
+        assert!(
+            find_similar_suggestions("xyz", &candidates, DEFAULT_SUGGESTION_SIMILARITY).is_empty()
+        );
+    }
+
     #[test]
     fn test_case_sensitivity() {
         // Currently case-sensitive
@@ -3,7 +3,9 @@
 //! This module provides utilities for working with terminal capabilities,
 //! including width detection, text wrapping, and improved formatting.
 
+use crate::color::Style;
 use std::env;
+use std::io::{self, Write};
 
 /// Default terminal width to use when detection fails
 const DEFAULT_TERMINAL_WIDTH: usize = 80;
@@ -14,6 +16,18 @@ const MINIMUM_TERMINAL_WIDTH: usize = 40;
 /// Maximum terminal width to prevent lines from being too long
 const MAXIMUM_TERMINAL_WIDTH: usize = 120;
 
+/// Default terminal height to use when detection fails
+const DEFAULT_TERMINAL_HEIGHT: usize = 24;
+
+/// Spaces between table columns
+const TABLE_COLUMN_GAP: usize = 3;
+
+/// Narrowest a table column is shrunk to when the table is too wide
+const MINIMUM_TABLE_COLUMN_WIDTH: usize = 3;
+
+/// Pager used when `PAGER` is unset; `-R` keeps colored help readable
+const DEFAULT_PAGER: &str = "less -R";
+
 /// Detects the current terminal width
 ///
 /// This function attempts to determine the terminal width using multiple methods:
@@ -34,15 +48,21 @@ const MAXIMUM_TERMINAL_WIDTH: usize = 120;
 /// ```
 #[must_use]
 pub fn get_terminal_width() -> usize {
+    terminal_width(
+        env::var("COLUMNS").ok().as_deref(),
+        detect_terminal_size_platform(),
+    )
+}
+
+/// Picks the width from a `COLUMNS` value, then the detected terminal size
+fn terminal_width(columns: Option<&str>, tty_size: Option<(usize, usize)>) -> usize {
     // First, try the COLUMNS environment variable
-    if let Ok(columns_str) = env::var("COLUMNS") {
-        if let Ok(columns) = columns_str.parse::<usize>() {
-            return clamp_width(columns);
-        }
+    if let Some(columns) = columns.and_then(|columns| columns.parse::<usize>().ok()) {
+        return clamp_width(columns);
     }
 
-    // Try platform-specific detection
-    if let Some(width) = detect_terminal_width_platform() {
+    // Then the size the terminal reports
+    if let Some((width, _)) = tty_size {
         return clamp_width(width);
     }
 
@@ -50,16 +70,16 @@ pub fn get_terminal_width() -> usize {
     DEFAULT_TERMINAL_WIDTH
 }
 
-/// Platform-specific terminal width detection
-fn detect_terminal_width_platform() -> Option<usize> {
+/// Platform-specific terminal size detection, as `(width, height)`
+fn detect_terminal_size_platform() -> Option<(usize, usize)> {
     #[cfg(unix)]
     {
-        detect_terminal_width_unix()
+        detect_terminal_size_unix()
     }
 
     #[cfg(windows)]
     {
-        detect_terminal_width_windows()
+        detect_terminal_size_windows()
     }
 
     #[cfg(not(any(unix, windows)))]
@@ -69,27 +89,178 @@ fn detect_terminal_width_platform() -> Option<usize> {
 }
 
 #[cfg(unix)]
-fn detect_terminal_width_unix() -> Option<usize> {
+fn detect_terminal_size_unix() -> Option<(usize, usize)> {
     use std::io::IsTerminal;
+    use std::os::raw::{c_int, c_ulong};
+
+    /// `struct winsize` from `<sys/ioctl.h>`
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        rows: u16,
+        cols: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
 
-    // Only try to detect width if we're actually connected to a terminal
+    // The request number differs between platforms; elsewhere only the
+    // environment variables are used
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "sparc64"
+        ))
+    ))]
+    const TIOCGWINSZ: Option<c_ulong> = Some(0x5413);
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        all(
+            any(target_os = "linux", target_os = "android"),
+            any(
+                target_arch = "mips",
+                target_arch = "mips64",
+                target_arch = "powerpc",
+                target_arch = "powerpc64",
+                target_arch = "sparc64"
+            )
+        )
+    ))]
+    const TIOCGWINSZ: Option<c_ulong> = Some(0x4008_7468);
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    )))]
+    const TIOCGWINSZ: Option<c_ulong> = None;
+
+    unsafe extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    // Only try to detect the size if we're actually connected to a terminal
     if !std::io::stdout().is_terminal() {
         return None;
     }
 
-    // Try to get terminal size using TIOCGWINSZ ioctl
-    // This is a simplified implementation - a full implementation would use libc
-    // For now, we'll rely on COLUMNS env var and fall back to default
-    None
+    let mut size = WinSize::default();
+    // SAFETY: TIOCGWINSZ only writes a `struct winsize` through the pointer
+    let result = unsafe { ioctl(1, TIOCGWINSZ?, &raw mut size) };
+    (result == 0 && size.cols > 0 && size.rows > 0)
+        .then(|| (usize::from(size.cols), usize::from(size.rows)))
 }
 
 #[cfg(windows)]
-fn detect_terminal_width_windows() -> Option<usize> {
+fn detect_terminal_size_windows() -> Option<(usize, usize)> {
     // On Windows, we could use GetConsoleScreenBufferInfo
-    // For now, we'll rely on COLUMNS env var and fall back to default
+    // For now, we'll rely on COLUMNS/LINES env vars and fall back to defaults
     None
 }
 
+/// Detects the current terminal height
+///
+/// Tries the same methods as [`get_terminal_width`]: the `LINES`
+/// environment variable, then platform-specific detection, then a default
+/// of 24 lines.
+///
+/// # Examples
+///
+/// ```rust
+/// use flag_rs::terminal::get_terminal_height;
+///
+/// let height = get_terminal_height();
+/// println!("Terminal height: {}", height);
+/// ```
+#[must_use]
+pub fn get_terminal_height() -> usize {
+    terminal_height(
+        env::var("LINES").ok().as_deref(),
+        detect_terminal_size_platform(),
+    )
+}
+
+/// Picks the height from a `LINES` value, then the detected terminal size
+fn terminal_height(lines: Option<&str>, tty_size: Option<(usize, usize)>) -> usize {
+    lines
+        .and_then(|lines| lines.parse::<usize>().ok())
+        .or_else(|| tty_size.map(|(_, height)| height))
+        .filter(|&lines| lines > 0)
+        .unwrap_or(DEFAULT_TERMINAL_HEIGHT)
+}
+
+/// Returns whether `text` should be shown through a pager
+///
+/// Paging only makes sense for interactive output that doesn't fit on one
+/// screen, so this is `false` whenever output is not a TTY.
+#[must_use]
+pub fn should_page(text: &str, is_tty: bool, height: usize) -> bool {
+    is_tty && text.lines().count() > height
+}
+
+/// Writes text through a pager when it doesn't fit the terminal
+///
+/// The pager is taken from `PAGER`, falling back to `less -R`. When the
+/// output is not a TTY, the text fits on screen, or the pager cannot be
+/// started, the text is written to `out` directly.
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails
+///
+/// # Examples
+///
+/// ```rust
+/// use flag_rs::terminal::write_paged;
+///
+/// let mut out = Vec::new();
+/// write_paged("short text\n", &mut out, false).unwrap();
+/// assert_eq!(out, b"short text\n");
+/// ```
+pub fn write_paged<W: Write>(text: &str, out: &mut W, is_tty: bool) -> io::Result<()> {
+    if should_page(text, is_tty, get_terminal_height()) && run_pager(text).is_ok() {
+        return Ok(());
+    }
+    out.write_all(text.as_bytes())
+}
+
+/// Pipes text into the configured pager and waits for it to exit
+fn run_pager(text: &str) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "empty PAGER"))?;
+
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
 /// Clamps the terminal width to reasonable bounds
 fn clamp_width(width: usize) -> usize {
     width.clamp(MINIMUM_TERMINAL_WIDTH, MAXIMUM_TERMINAL_WIDTH)
@@ -189,6 +360,39 @@ pub fn wrap_text_to_terminal(text: &str, indent: Option<usize>) -> String {
     wrap_text(text, width, indent)
 }
 
+/// Returns how many columns `text` takes on screen
+///
+/// ANSI escape sequences such as colors take no space, so colored and plain
+/// text of the same characters have the same width.
+///
+/// # Examples
+///
+/// ```rust
+/// use flag_rs::terminal::visible_width;
+///
+/// assert_eq!(visible_width("\x1b[36m--verbose\x1b[0m"), 9);
+/// assert_eq!(visible_width("--verbose"), 9);
+/// ```
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Skip to the end of the sequence: `ESC [ params final-byte`
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
 /// Formats help text with proper alignment and wrapping
 ///
 /// This function formats help text entries (like flag descriptions) with
@@ -225,10 +429,11 @@ pub fn format_help_entry(
     }
 
     let right_width = total_width.saturating_sub(left_width + 2); // 2 for spacing
+    let left_len = visible_width(left_column);
 
-    if left_column.len() <= left_width {
+    if left_len <= left_width {
         // Left column fits, format normally
-        let padding = " ".repeat(left_width - left_column.len());
+        let padding = " ".repeat(left_width - left_len);
         let wrapped_right = wrap_text(right_column, right_width, Some(left_width + 2));
 
         format!("{left_column}{padding}  {wrapped_right}")
@@ -241,9 +446,189 @@ pub fn format_help_entry(
     }
 }
 
+/// A table of command output with aligned columns
+///
+/// Meant for what run functions print, like a list of resources. Columns
+/// are as wide as their widest cell; when the table is wider than the
+/// terminal, the widest columns are narrowed and cells that no longer fit
+/// end in `…`. The last column is never padded.
+///
+/// # Examples
+///
+/// ```rust
+/// use flag_rs::terminal::Table;
+///
+/// let table = Table::new()
+///     .header(&["NAME", "STATUS"])
+///     .row(&["web-1".to_string(), "Running".to_string()])
+///     .row(&["database-0".to_string(), "Pending".to_string()])
+///     .width(80);
+///
+/// assert_eq!(
+///     table.render(),
+///     "NAME         STATUS\nweb-1        Running\ndatabase-0   Pending\n"
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    header_style: Option<Style>,
+    width: Option<usize>,
+}
+
+impl Table {
+    /// Creates an empty table
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the column headers
+    #[must_use]
+    pub fn header(mut self, headers: &[&str]) -> Self {
+        self.headers = headers.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Adds a row; missing cells are left blank
+    #[must_use]
+    pub fn row(mut self, cells: &[String]) -> Self {
+        self.rows.push(cells.to_vec());
+        self
+    }
+
+    /// Styles the headers, such as [`Style::BOLD`]
+    ///
+    /// Like all colors, the style is dropped when output is not a TTY or
+    /// `NO_COLOR` is set.
+    #[must_use]
+    pub const fn header_style(mut self, style: Style) -> Self {
+        self.header_style = Some(style);
+        self
+    }
+
+    /// Sets the width to fit the table in, instead of the terminal width
+    #[must_use]
+    pub const fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Renders the table, one line per row after the header line
+    ///
+    /// A table without headers and rows renders as an empty string.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let widths = self.column_widths(self.width.unwrap_or_else(get_terminal_width));
+        let mut out = String::new();
+        if !self.headers.is_empty() {
+            let line = Self::render_line(&self.headers, &widths);
+            match self.header_style {
+                Some(style) => out.push_str(&style.paint(&line)),
+                None => out.push_str(&line),
+            }
+            out.push('\n');
+        }
+        for row in &self.rows {
+            out.push_str(&Self::render_line(row, &widths));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Width of every column, narrowed to fit in `total` where possible
+    fn column_widths(&self, total: usize) -> Vec<usize> {
+        let columns = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.headers.len()))
+            .max()
+            .unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for cells in std::iter::once(&self.headers).chain(&self.rows) {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(visible_width(cell));
+            }
+        }
+
+        // Narrow the widest column one step at a time, so columns that
+        // already fit keep their width
+        let gaps = TABLE_COLUMN_GAP * columns.saturating_sub(1);
+        while widths.iter().sum::<usize>() + gaps > total {
+            match widths.iter_mut().max() {
+                Some(widest) if *widest > MINIMUM_TABLE_COLUMN_WIDTH => *widest -= 1,
+                _ => break,
+            }
+        }
+        widths
+    }
+
+    /// Renders one line of cells padded or truncated to `widths`
+    fn render_line(cells: &[String], widths: &[usize]) -> String {
+        let gap = " ".repeat(TABLE_COLUMN_GAP);
+        let mut line = String::new();
+        for (i, &width) in widths.iter().enumerate() {
+            if i > 0 {
+                line.push_str(&gap);
+            }
+            let cell = truncate_cell(cells.get(i).map_or("", String::as_str), width);
+            line.push_str(&cell);
+            if i + 1 < widths.len() {
+                line.push_str(&" ".repeat(width - visible_width(&cell)));
+            }
+        }
+        line.trim_end().to_string()
+    }
+}
+
+/// Shortens `cell` to `width` columns, ending it in `…` when cut
+///
+/// Escape sequences are copied whole and don't count toward the width,
+/// and a cut styled cell ends in a reset so its style doesn't leak into
+/// the next column.
+fn truncate_cell(cell: &str, width: usize) -> String {
+    if visible_width(cell) <= width {
+        return cell.to_string();
+    }
+    let mut truncated = String::new();
+    let mut styled = false;
+    let mut remaining = width.saturating_sub(1);
+    let mut chars = cell.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            styled = true;
+            truncated.push(ch);
+            if let Some(next) = chars.next() {
+                truncated.push(next);
+                if next == '[' {
+                    for c in chars.by_ref() {
+                        truncated.push(c);
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+            }
+        } else if remaining > 0 {
+            truncated.push(ch);
+            remaining -= 1;
+        } else {
+            break;
+        }
+    }
+    truncated.push('…');
+    if styled {
+        truncated.push_str(Style::RESET);
+    }
+    truncated
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{no_color, set_env};
 
     #[test]
     fn test_get_terminal_width_default() {
@@ -253,6 +638,38 @@ mod tests {
         assert!(width <= MAXIMUM_TERMINAL_WIDTH);
     }
 
+    #[test]
+    fn test_terminal_width() {
+        assert_eq!(terminal_width(Some("100"), Some((60, 40))), 100);
+        assert_eq!(terminal_width(Some("30"), None), MINIMUM_TERMINAL_WIDTH);
+        // An unusable COLUMNS falls back to the terminal, then the default
+        assert_eq!(terminal_width(Some("wide"), Some((60, 40))), 60);
+        assert_eq!(terminal_width(Some("wide"), None), DEFAULT_TERMINAL_WIDTH);
+        assert_eq!(terminal_width(None, None), DEFAULT_TERMINAL_WIDTH);
+    }
+
+    #[test]
+    fn test_get_terminal_height() {
+        assert_eq!(terminal_height(Some("50"), Some((100, 40))), 50);
+        assert_eq!(terminal_height(None, Some((100, 40))), 40);
+        // Without a usable LINES, the default is used when no size is known
+        assert_eq!(terminal_height(None, None), DEFAULT_TERMINAL_HEIGHT);
+        assert_eq!(terminal_height(Some("tall"), None), DEFAULT_TERMINAL_HEIGHT);
+        assert_eq!(terminal_height(Some("0"), None), DEFAULT_TERMINAL_HEIGHT);
+
+        // LINES wins over whatever the terminal reports
+        let _lines = set_env("LINES", Some("50"));
+        assert_eq!(get_terminal_height(), 50);
+    }
+
+    #[test]
+    fn test_should_page() {
+        let long_text = "line\n".repeat(50);
+        assert!(should_page(&long_text, true, 24));
+        assert!(!should_page(&long_text, false, 24));
+        assert!(!should_page("one line\n", true, 24));
+    }
+
     #[test]
     fn test_clamp_width() {
         assert_eq!(clamp_width(10), MINIMUM_TERMINAL_WIDTH);
@@ -296,6 +713,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_help_entry_ignores_color_codes() {
+        let plain = format_help_entry("      -v, --verbose", "Verbose", 24, 80);
+        let colored = format_help_entry(
+            "      \x1b[36m-v, \x1b[0m--\x1b[36mverbose\x1b[0m",
+            "Verbose",
+            24,
+            80,
+        );
+        let no_short = format_help_entry("          --\x1b[36mquiet\x1b[0m", "Quiet", 24, 80);
+
+        let column = |line: &str, needle: &str| visible_width(&line[..line.find(needle).unwrap()]);
+        assert_eq!(column(&plain, "Verbose"), 26);
+        assert_eq!(column(&colored, "Verbose"), 26);
+        assert_eq!(column(&no_short, "Quiet"), 26);
+        assert_eq!(column(&colored, "verbose"), column(&no_short, "quiet"));
+    }
+
     #[test]
     fn test_format_help_entry_normal() {
         let result = format_help_entry("  -v, --verbose", "Enable verbose output", 20, 60);
@@ -316,4 +751,71 @@ mod tests {
         let wrapped = wrap_text(text, 50, None);
         assert_eq!(wrapped, "First paragraph\n\nSecond paragraph");
     }
+
+    #[test]
+    fn test_table_column_widths() {
+        let table = Table::new()
+            .header(&["NAME", "READY", "AGE"])
+            .row(&[
+                "nginx-abc123".to_string(),
+                "1/1".to_string(),
+                "5d".to_string(),
+            ])
+            .row(&["redis".to_string(), "0/1".to_string()])
+            .width(80);
+
+        assert_eq!(
+            table.render(),
+            "NAME           READY   AGE\n\
+             nginx-abc123   1/1     5d\n\
+             redis          0/1\n"
+        );
+    }
+
+    #[test]
+    fn test_table_truncates_to_width() {
+        let table = Table::new()
+            .header(&["NAME", "MESSAGE"])
+            .row(&[
+                "web".to_string(),
+                "Back-off restarting failed container".to_string(),
+            ])
+            .width(20);
+
+        let rendered = table.render();
+        assert_eq!(rendered, "NAME   MESSAGE\nweb    Back-off res…\n");
+        for line in rendered.lines() {
+            assert!(visible_width(line) <= 20);
+        }
+    }
+
+    #[test]
+    fn test_truncate_styled_cell() {
+        let cell = format!("\x1b[31m{}\x1b[0m", "Back-off restarting");
+        let truncated = truncate_cell(&cell, 8);
+        assert_eq!(truncated, "\x1b[31mBack-of…\x1b[0m");
+        assert_eq!(visible_width(&truncated), 8);
+
+        // Plain cells are cut without a reset
+        assert_eq!(truncate_cell("Back-off restarting", 8), "Back-of…");
+    }
+
+    #[test]
+    fn test_table_header_style_respects_no_color() {
+        let _no_color = no_color();
+        let table = Table::new()
+            .header(&["NAME"])
+            .header_style(Style::BOLD)
+            .width(80);
+        assert_eq!(table.render(), "NAME\n");
+    }
+
+    #[test]
+    fn test_empty_table() {
+        assert_eq!(Table::new().render(), "");
+        assert_eq!(
+            Table::new().header(&["NAME", "AGE"]).render(),
+            "NAME   AGE\n"
+        );
+    }
 }
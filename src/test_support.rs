@@ -0,0 +1,55 @@
+//! Helpers shared by the unit tests
+
+use crate::command::Command;
+use std::ffi::OsString;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Serializes the tests that change environment variables
+static TEST_ENV: Mutex<()> = Mutex::new(());
+
+/// Holds an environment variable until dropped, then puts it back as it was
+///
+/// Only one guard can be alive at a time, so a test takes at most one.
+pub(crate) struct EnvGuard {
+    name: &'static str,
+    previous: Option<OsString>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(value) => unsafe { std::env::set_var(self.name, value) },
+            None => unsafe { std::env::remove_var(self.name) },
+        }
+    }
+}
+
+/// Sets `name` to `value`, or removes it for `None`, while the guard lives
+pub(crate) fn set_env(name: &'static str, value: Option<&str>) -> EnvGuard {
+    let lock = TEST_ENV.lock().unwrap_or_else(PoisonError::into_inner);
+    let previous = std::env::var_os(name);
+    match value {
+        Some(value) => unsafe { std::env::set_var(name, value) },
+        None => unsafe { std::env::remove_var(name) },
+    }
+    EnvGuard {
+        name,
+        previous,
+        _lock: lock,
+    }
+}
+
+/// Turns color off for as long as the returned guard lives
+pub(crate) fn no_color() -> EnvGuard {
+    set_env("NO_COLOR", Some("1"))
+}
+
+/// Returns what TAB offers after `words` are typed after the program name
+pub(crate) fn complete(cmd: &Command, words: &[&str]) -> Vec<String> {
+    let args: Vec<String> = std::iter::once("__complete")
+        .chain(words.iter().copied())
+        .map(String::from)
+        .collect();
+    cmd.handle_completion_request(&args).unwrap()
+}
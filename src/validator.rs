@@ -21,6 +21,9 @@ pub enum ArgValidator {
     /// Between min and max arguments (inclusive)
     RangeArgs(usize, usize),
     /// Arguments must be in the valid args list
+    ///
+    /// Unless the command has its own arg completion, these values are
+    /// also what completing an argument offers.
     OnlyValidArgs(Vec<String>),
     /// Custom validation function
     Custom(std::sync::Arc<ValidatorFn>),
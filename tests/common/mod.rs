@@ -0,0 +1,12 @@
+//! Helpers shared by the integration tests
+
+use flag_rs::Command;
+
+/// Returns what TAB offers after `words` are typed after the program name
+pub fn complete(cmd: &Command, words: &[&str]) -> Vec<String> {
+    let args: Vec<String> = std::iter::once("__complete")
+        .chain(words.iter().copied())
+        .map(String::from)
+        .collect();
+    cmd.handle_completion_request(&args).unwrap()
+}
@@ -331,3 +331,270 @@ fn test_completion_state_isolation() {
     assert_eq!(result3.values, vec!["call2"]);
     assert_eq!(call_count.load(Ordering::SeqCst), 3);
 }
+
+/// Builds a CLI whose `--format` flag completes values, for `--flag=prefix` tests
+fn create_equals_cli(name: &str) -> flag_rs::Command {
+    CommandBuilder::new(name)
+        .flag(
+            Flag::new("format")
+                .usage("Output format")
+                .value_type(FlagType::String),
+        )
+        .flag_completion("format", |_ctx, prefix| {
+            Ok(CompletionResult::new().extend(
+                ["json", "jsonl", "yaml"]
+                    .into_iter()
+                    .filter(|f| f.starts_with(prefix))
+                    .map(String::from),
+            ))
+        })
+        .build()
+}
+
+#[test]
+fn test_equals_flag_value_completion_bash() {
+    let app = create_equals_cli("eqbash");
+    unsafe { std::env::set_var("EQBASH_COMPLETE", "bash") };
+
+    let args = vec!["__complete".to_string(), "--format=js".to_string()];
+    let result = app.handle_completion_request(&args).unwrap();
+
+    unsafe { std::env::remove_var("EQBASH_COMPLETE") };
+
+    assert_eq!(result, vec!["--format=json", "--format=jsonl"]);
+
+    // The script strips the echoed --flag= prefix because readline splits on '='
+    let script = app.generate_completion(flag_rs::Shell::Bash);
+    assert!(script.contains("_get_comp_words_by_ref -n =:"));
+    assert!(script.contains("COMPREPLY=( \"${COMPREPLY[@]#\"$eq_prefix\"}\" )"));
+}
+
+#[test]
+fn test_equals_flag_value_completion_zsh() {
+    let app = create_equals_cli("eqzsh");
+    unsafe { std::env::set_var("EQZSH_COMPLETE", "zsh") };
+
+    let args = vec![
+        "__complete".to_string(),
+        "--format=json".to_string(),
+        "--format=y".to_string(),
+    ];
+    let result = app.handle_completion_request(&args).unwrap();
+
+    unsafe { std::env::remove_var("EQZSH_COMPLETE") };
+
+    assert_eq!(result, vec!["--format=yaml:--format=yaml    - "]);
+
+    // The script moves the --flag= prefix out of the matched word
+    let script = app.generate_completion(flag_rs::Shell::Zsh);
+    assert!(script.contains("compset -P 1 '*='"));
+    assert!(script.contains("values=(\"${(@)values#$eq_prefix}\")"));
+}
+
+#[test]
+fn test_equals_flag_value_without_completion() {
+    let app = CommandBuilder::new("eqnone")
+        .flag(Flag::new("name").value_type(FlagType::String))
+        .build();
+
+    let args = vec!["__complete".to_string(), "--name=".to_string()];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert!(result.is_empty());
+}
+
+/// A CLI whose argument completion reports what it saw in the context
+fn create_dash_cli() -> flag_rs::Command {
+    CommandBuilder::new("dashcat")
+        .flag(
+            Flag::new("input")
+                .usage("Input file")
+                .value_type(FlagType::String),
+        )
+        .flag(Flag::new("verbose").short('v').value_type(FlagType::Bool))
+        .arg_completion(|ctx, _prefix| {
+            let input = ctx.flag("input").map_or("none", String::as_str);
+            Ok(CompletionResult::new().add(format!("args={}:input={input}", ctx.args().join(","))))
+        })
+        .build()
+}
+
+#[test]
+fn test_completion_after_literal_dash() {
+    let app = create_dash_cli();
+
+    // A lone '-' is a positional argument, not a flag
+    let args = vec!["__complete".to_string(), "-".to_string(), String::new()];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert!(result.contains(&"args=-:input=none".to_string()));
+
+    // ...and can be the value of a flag
+    let args = vec![
+        "__complete".to_string(),
+        "--input".to_string(),
+        "-".to_string(),
+        String::new(),
+    ];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert!(result.contains(&"args=:input=-".to_string()));
+
+    // After '--' flag-looking words are positional too
+    let args = vec![
+        "__complete".to_string(),
+        "--".to_string(),
+        "--verbose".to_string(),
+        "--".to_string(),
+    ];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert_eq!(result, vec!["args=--verbose:input=none"]);
+}
+
+fn create_flag_value_cli() -> flag_rs::Command {
+    let colors = |_ctx: &flag_rs::Context, prefix: &str| {
+        Ok(CompletionResult::new().extend(
+            ["always", "auto", "never"]
+                .into_iter()
+                .filter(|c| c.starts_with(prefix))
+                .map(String::from),
+        ))
+    };
+
+    let mut app = CommandBuilder::new("flagvalue")
+        .flag(
+            Flag::new("output")
+                .short('o')
+                .value_type(FlagType::String)
+                .completion(|_ctx, prefix| {
+                    Ok(CompletionResult::new().extend(
+                        ["json", "yaml"]
+                            .into_iter()
+                            .filter(|f| f.starts_with(prefix))
+                            .map(String::from),
+                    ))
+                }),
+        )
+        .flag(Flag::new("name").value_type(FlagType::String))
+        .flag(Flag::new("color").optional_value("auto").completion(colors))
+        .build();
+    app.add_command(
+        CommandBuilder::new("apply")
+            .flag(Flag::bool("force"))
+            .build(),
+    );
+    app.add_command(CommandBuilder::new("attach").build());
+    app
+}
+
+fn complete(app: &flag_rs::Command, words: &[&str]) -> Vec<String> {
+    let mut args = vec!["__complete".to_string()];
+    args.extend(words.iter().map(|w| (*w).to_string()));
+    app.handle_completion_request(&args).unwrap()
+}
+
+#[test]
+fn test_required_value_flag_completes_only_its_value() {
+    let app = create_flag_value_cli();
+
+    assert_eq!(complete(&app, &["--output", ""]), vec!["json", "yaml"]);
+    assert_eq!(complete(&app, &["-o", "a"]), Vec::<String>::new());
+
+    // No completer: nothing is offered rather than subcommands
+    assert!(complete(&app, &["--name", "a"]).is_empty());
+
+    // Once the value is given, subcommands are completed again
+    assert_eq!(
+        complete(&app, &["--output", "json", "a"]),
+        vec!["apply", "attach"]
+    );
+}
+
+#[test]
+fn test_optional_value_flag_completes_value_and_subcommands() {
+    let app = create_flag_value_cli();
+
+    let result = complete(&app, &["--color", "a"]);
+    assert_eq!(result, vec!["always", "auto", "apply", "attach"]);
+
+    // A subcommand after the flag isn't taken as its value
+    assert_eq!(
+        complete(&app, &["--color", "apply", "--f"]),
+        vec!["--force"]
+    );
+}
+
+#[test]
+fn test_optional_value_flag_parsing() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_root = Arc::clone(&seen);
+    let seen_apply = Arc::clone(&seen);
+    let mut app = CommandBuilder::new("flagvalue")
+        .flag(Flag::new("color").optional_value("auto"))
+        .run(move |ctx| {
+            seen_root.lock().unwrap().push(format!(
+                "root:{}:{}",
+                ctx.flag("color").map_or("-", String::as_str),
+                ctx.args().join(",")
+            ));
+            Ok(())
+        })
+        .build();
+    app.add_command(
+        CommandBuilder::new("apply")
+            .run(move |ctx| {
+                seen_apply
+                    .lock()
+                    .unwrap()
+                    .push(format!("apply:{}", ctx.flag("color").unwrap()));
+                Ok(())
+            })
+            .build(),
+    );
+
+    for args in [
+        vec!["--color"],
+        vec!["--color", "never"],
+        vec!["--color=always"],
+        vec!["--color", "apply"],
+    ] {
+        app.execute(args.into_iter().map(String::from).collect())
+            .unwrap();
+    }
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["root:auto:", "root:never:", "root:always:", "apply:auto"]
+    );
+}
+
+#[test]
+fn test_flag_completion_sees_preceding_flag_values() {
+    let app = CommandBuilder::new("pods")
+        .flag(Flag::new("namespace").short('n'))
+        .flag(Flag::bool("all").short('a'))
+        .flag(Flag::new("name").completion(|ctx, prefix| {
+            let pods: &[&str] = match ctx.flag("namespace").map(String::as_str) {
+                Some("kube-system") => &["coredns", "etcd"],
+                _ => &["web", "worker"],
+            };
+            let all = ctx.flag_bool("all").unwrap_or(false);
+            Ok(CompletionResult::new().extend(
+                pods.iter()
+                    .filter(|p| p.starts_with(prefix))
+                    .map(|p| format!("{p}:all={all}")),
+            ))
+        }))
+        .build();
+
+    assert_eq!(
+        complete(&app, &["--namespace", "kube-system", "--name", ""]),
+        vec!["coredns:all=false", "etcd:all=false"]
+    );
+    assert_eq!(
+        complete(&app, &["-n", "kube-system", "-a", "--name", "e"]),
+        vec!["etcd:all=true"]
+    );
+    assert_eq!(
+        complete(&app, &["--namespace=default", "--all", "--name", "w"]),
+        vec!["web:all=true", "worker:all=true"]
+    );
+}
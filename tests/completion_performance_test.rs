@@ -2,9 +2,13 @@
 //!
 //! These tests verify that completion caching and timeout features work correctly.
 
+mod common;
+
+use common::complete;
 use flag_rs::completion_cache::CompletionCache;
 use flag_rs::completion_timeout::{make_timeout_completion, with_timeout};
-use flag_rs::{CommandBuilder, CompletionResult, Context};
+use flag_rs::{CommandBuilder, CompletionResult, Context, Flag, FlagType};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
@@ -316,3 +320,228 @@ fn test_completion_thread_safety() {
     // Verify all completions executed
     assert_eq!(results.lock().unwrap().len(), 50); // 5 threads * 10 completions each
 }
+
+#[test]
+fn test_completion_cache_shared_within_session() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let token = format!("session-test-{}", std::process::id());
+    let dir = std::env::temp_dir().join(format!("flag-rs-sessions-{}", std::process::id()));
+
+    // Each call builds its own cache, like separate invocations of the binary
+    let complete = |token: &str| {
+        let cache = CompletionCache::new(Duration::from_secs(5))
+            .with_cache_dir(&dir)
+            .with_session(token);
+        let key = CompletionCache::make_key(
+            &["app".to_string(), "get".to_string()],
+            "po",
+            &std::collections::HashMap::new(),
+        );
+        if let Some(result) = cache.get(&key) {
+            return (cache, result);
+        }
+        calls.fetch_add(1, Ordering::SeqCst);
+        let result = CompletionResult::new()
+            .add_with_description("pods", "List pods")
+            .add("policies");
+        cache.put(key, result.clone());
+        (cache, result)
+    };
+
+    let (_, first) = complete(&token);
+    let (session_cache, second) = complete(&token);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(first.values, second.values);
+    assert_eq!(first.descriptions, second.descriptions);
+
+    // A new TAB session recomputes
+    let other_token = format!("{token}-next");
+    let (other_cache, _) = complete(&other_token);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    session_cache.clear();
+    other_cache.clear();
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_completion_cache_attached_to_command() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let arg_calls = Arc::clone(&calls);
+    let flag_calls = Arc::clone(&calls);
+
+    let app = CommandBuilder::new("kubectl")
+        .completion_cache(Arc::new(CompletionCache::new(Duration::from_secs(60))))
+        .subcommand(
+            CommandBuilder::new("get")
+                .flag(Flag::new("namespace").value_type(FlagType::String))
+                .flag_completion("namespace", move |_ctx, prefix| {
+                    flag_calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(CompletionResult::new().add(format!("ns-{prefix}")))
+                })
+                .arg_completion(move |_ctx, prefix| {
+                    arg_calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(CompletionResult::new().add(format!("pod-{prefix}")))
+                })
+                .build(),
+        )
+        .build();
+
+    assert!(complete(&app, &["get", "web"]).contains(&"pod-web".to_string()));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // The same completion within the TTL comes from the cache
+    assert!(complete(&app, &["get", "web"]).contains(&"pod-web".to_string()));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Another prefix, a flag value, or other flags given are separate entries
+    complete(&app, &["get", "db"]);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        complete(&app, &["get", "--namespace", "web"]),
+        vec!["ns-web"]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        complete(&app, &["get", "--namespace", "web"]),
+        vec!["ns-web"]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    complete(&app, &["get", "--namespace=prod", "web"]);
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn test_warmed_cache_skips_completion_function() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let arg_calls = Arc::clone(&calls);
+    let flag_calls = Arc::clone(&calls);
+    let cache = Arc::new(CompletionCache::new(Duration::from_secs(60)));
+
+    let app = CommandBuilder::new("kubectl")
+        .completion_cache(Arc::clone(&cache))
+        .subcommand(
+            CommandBuilder::new("get")
+                .flag(Flag::new("namespace").value_type(FlagType::String))
+                .flag_completion("namespace", move |_ctx, _prefix| {
+                    flag_calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(CompletionResult::new().add("generated"))
+                })
+                .arg_completion(move |_ctx, _prefix| {
+                    arg_calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(CompletionResult::new().add("generated"))
+                })
+                .build(),
+        )
+        .build();
+
+    // An earlier command already listed the pods and namespaces
+    let no_flags = HashMap::new();
+    cache.warm(
+        CompletionCache::completion_key(&["kubectl", "get"], None, "", &no_flags),
+        CompletionResult::new()
+            .add("nginx-abc123")
+            .add("redis-def456"),
+    );
+    cache.warm(
+        CompletionCache::completion_key(&["kubectl", "get"], Some("namespace"), "", &no_flags),
+        CompletionResult::new().add("kube-system"),
+    );
+
+    let pods = complete(&app, &["get", ""]);
+    assert_eq!(pods[..2], ["nginx-abc123", "redis-def456"]);
+    assert!(!pods.contains(&"generated".to_string()));
+    assert_eq!(
+        complete(&app, &["get", "--namespace", ""]),
+        vec!["kube-system"]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    // Keys that weren't warmed still run the function
+    assert!(complete(&app, &["get", "ng"]).contains(&"generated".to_string()));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_completion_cache_skips_timed_out_results() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_clone = Arc::clone(&calls);
+
+    let app = CommandBuilder::new("slowcli")
+        .completion_cache(Arc::new(CompletionCache::new(Duration::from_secs(60))))
+        .arg_completion(make_timeout_completion(
+            Duration::from_millis(20),
+            move |_ctx, _prefix| {
+                calls_clone.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(200));
+                Ok(CompletionResult::new().add("late"))
+            },
+        ))
+        .build();
+
+    let ctx = Context::new(vec![]);
+    for expected_calls in 1..=2 {
+        let result = app.get_completions(&ctx, "", None).unwrap();
+        assert!(result.values.is_empty());
+        assert!(
+            result
+                .active_help
+                .iter()
+                .any(|h| h.message.contains("timed out"))
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+    }
+}
+
+#[test]
+fn test_completion_timeout_on_builder() {
+    let app = CommandBuilder::new("hungcli")
+        .completion_timeout(Duration::from_millis(50))
+        .flag(Flag::new("region").value_type(FlagType::String))
+        .flag_completion("region", |_ctx, _prefix| {
+            thread::sleep(Duration::from_secs(2));
+            Ok(CompletionResult::new().add("us-east-1"))
+        })
+        .subcommand(
+            CommandBuilder::new("get")
+                .arg_completion(|_ctx, _prefix| {
+                    thread::sleep(Duration::from_secs(2));
+                    Ok(CompletionResult::new().add("pod"))
+                })
+                .build(),
+        )
+        .subcommand(
+            CommandBuilder::new("fast")
+                .arg_completion(|ctx, _prefix| {
+                    // Flags given so far still reach a bounded function
+                    let region = ctx.flag("region").cloned().unwrap_or_default();
+                    Ok(CompletionResult::new().add(format!("in-{region}")))
+                })
+                .build(),
+        )
+        .build();
+
+    // Functions added after building are bounded too
+    let mut app = app;
+    app.set_arg_completion(|_ctx, _prefix| {
+        thread::sleep(Duration::from_secs(2));
+        Ok(CompletionResult::new().add("late"))
+    });
+
+    let ctx = Context::new(vec![]);
+    let start = Instant::now();
+    let result = app.get_completions(&ctx, "", Some("region")).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(result.values.is_empty());
+    assert!(result.active_help[0].message.contains("timed out"));
+
+    let start = Instant::now();
+    assert!(!complete(&app, &["get", ""]).contains(&"pod".to_string()));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(complete(&app, &["--region", "eu", "fast", ""]).contains(&"in-eu".to_string()));
+
+    let start = Instant::now();
+    let result = app.get_completions(&ctx, "", None).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(result.values.is_empty());
+}
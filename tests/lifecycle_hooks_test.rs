@@ -411,3 +411,81 @@ fn test_multiple_persistent_hooks_ordering() {
     ];
     assert_eq!(*execution_log.lock().unwrap(), expected);
 }
+
+/// Builds `app` with persistent hooks on the root and `child`, logging
+/// each hook that runs
+fn hooked_app(log: &Arc<Mutex<Vec<String>>>, hooks_on_help: bool) -> flag_rs::Command {
+    let hook = |name: &'static str| {
+        let log = log.clone();
+        move |_ctx: &mut flag_rs::Context| {
+            log.lock().unwrap().push(name.to_string());
+            Ok(())
+        }
+    };
+    CommandBuilder::new("app")
+        .persistent_pre_run(hook("root_pre"))
+        .persistent_post_run(hook("root_post"))
+        .run_hooks_on_help(hooks_on_help)
+        .with_help_command()
+        .subcommand(
+            CommandBuilder::new("child")
+                .persistent_pre_run(hook("child_pre"))
+                .persistent_post_run(hook("child_post"))
+                .arg_completion(|_ctx, _prefix| Ok(flag_rs::CompletionResult::new().add("value")))
+                .run(|_ctx| Ok(()))
+                .build(),
+        )
+        .build()
+}
+
+#[test]
+fn test_hooks_skipped_for_help_and_completion() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let app = hooked_app(&log, false);
+
+    for args in [
+        vec!["--help"],
+        vec!["child", "--help"],
+        vec!["-h", "child"],
+        vec!["help", "child"],
+    ] {
+        app.execute(args.iter().map(ToString::to_string).collect())
+            .unwrap();
+        assert!(log.lock().unwrap().is_empty(), "{args:?}");
+    }
+
+    let completions = app
+        .handle_completion_request(&["__complete", "child", ""].map(String::from))
+        .unwrap();
+    assert!(completions.contains(&"value".to_string()));
+    assert!(log.lock().unwrap().is_empty());
+
+    // Running the command still runs them
+    app.execute(vec!["child".to_string()]).unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["root_pre", "child_pre", "child_post", "root_post"]
+    );
+}
+
+#[test]
+fn test_run_hooks_on_help() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let app = hooked_app(&log, true);
+
+    app.execute(vec!["child".to_string(), "--help".to_string()])
+        .unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["root_pre", "child_pre", "child_post", "root_post"]
+    );
+
+    log.lock().unwrap().clear();
+    app.execute(vec!["--help".to_string()]).unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["root_pre", "root_post"]);
+
+    log.lock().unwrap().clear();
+    app.execute(vec!["help".to_string(), "child".to_string()])
+        .unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["root_pre", "root_post"]);
+}
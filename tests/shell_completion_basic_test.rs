@@ -2,6 +2,9 @@
 //!
 //! These tests verify the basic completion functionality that is implemented.
 
+mod common;
+
+use common::complete;
 use flag_rs::{CommandBuilder, CompletionResult, Flag, FlagType};
 
 /// Creates a test CLI with various command structures
@@ -298,3 +301,266 @@ fn test_completion_with_descriptions() {
     assert_eq!(result.descriptions[1], "Second option");
     assert_eq!(result.descriptions[2], "");
 }
+
+#[test]
+fn test_leaf_without_completer_offers_flags() {
+    let app = CommandBuilder::new("leaftest")
+        .flag(
+            Flag::new("force")
+                .usage("Skip confirmation")
+                .value_type(FlagType::Bool),
+        )
+        .flag(
+            Flag::new("output")
+                .usage("Output file")
+                .value_type(FlagType::String),
+        )
+        .run(|_| Ok(()))
+        .build();
+
+    let args = vec!["__complete".to_string(), String::new()];
+    let result = app.handle_completion_request(&args).unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            "--force",
+            "--output",
+            "[HELP] 'leaftest' has no argument completions; use --help for usage",
+        ]
+    );
+}
+
+#[test]
+fn test_single_dash_completes_short_flags() {
+    let app = create_test_cli();
+
+    // Short flags from the command and its ancestors, sorted
+    let args = vec![
+        "__complete".to_string(),
+        "server".to_string(),
+        "start".to_string(),
+        "-".to_string(),
+    ];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert_eq!(result, vec!["-c", "-d", "-p", "-v"]);
+
+    // A double dash still completes long flags
+    let args = vec![
+        "__complete".to_string(),
+        "server".to_string(),
+        "--".to_string(),
+    ];
+    let result = app.handle_completion_request(&args).unwrap();
+    assert!(result.contains(&"--port".to_string()));
+    assert!(result.contains(&"--verbose".to_string()));
+    assert!(!result.iter().any(|r| r == "-p"));
+}
+
+#[test]
+fn test_single_dash_describes_short_flags_with_long_names() {
+    let app = CommandBuilder::new("shortdash")
+        .flag(
+            Flag::new("verbose")
+                .short('v')
+                .usage("Enable verbose output")
+                .value_type(FlagType::Bool),
+        )
+        .flag(Flag::new("quiet").short('q').value_type(FlagType::Bool))
+        .flag(Flag::new("output").value_type(FlagType::String))
+        .build();
+
+    unsafe { std::env::set_var("SHORTDASH_COMPLETE", "fish") };
+    let args = vec!["__complete".to_string(), "-".to_string()];
+    let result = app.handle_completion_request(&args).unwrap();
+    unsafe { std::env::remove_var("SHORTDASH_COMPLETE") };
+
+    assert_eq!(result.len(), 2);
+    assert!(result[0].starts_with("-q\t"));
+    assert!(result[0].ends_with("- --quiet"));
+    assert!(result[1].starts_with("-v\t"));
+    assert!(result[1].ends_with("- --verbose: Enable verbose output"));
+}
+
+#[test]
+fn test_short_flag_value_completion() {
+    let app = CommandBuilder::new("kubectl")
+        .flag(
+            Flag::new("namespace")
+                .short('n')
+                .usage("Namespace to use")
+                .value_type(FlagType::String)
+                .completion(|_ctx, prefix| {
+                    Ok(CompletionResult::new().extend(
+                        ["default", "dev", "kube-system"]
+                            .into_iter()
+                            .filter(|ns| ns.starts_with(prefix))
+                            .map(String::from),
+                    ))
+                }),
+        )
+        .flag(Flag::new("all").short('a').value_type(FlagType::Bool))
+        .subcommand(CommandBuilder::new("get").run(|_| Ok(())).build())
+        .build();
+
+    assert_eq!(
+        complete(&app, &["-n", ""]),
+        vec!["default", "dev", "kube-system"]
+    );
+    assert_eq!(complete(&app, &["-n", "d"]), vec!["default", "dev"]);
+    // The same as the long form, also from a subcommand and in a group
+    assert_eq!(
+        complete(&app, &["--namespace", "d"]),
+        complete(&app, &["-n", "d"])
+    );
+    assert_eq!(complete(&app, &["get", "-n", "k"]), vec!["kube-system"]);
+    assert_eq!(complete(&app, &["get", "-an", "k"]), vec!["kube-system"]);
+
+    // A value already attached with `=` leaves nothing pending, and
+    // neither does an unknown short flag that happens to end in `n`
+    assert!(!complete(&app, &["get", "-n=dev", "k"]).contains(&"kube-system".to_string()));
+    assert!(!complete(&app, &["get", "-xn", "k"]).contains(&"kube-system".to_string()));
+}
+
+#[test]
+fn test_subcommand_prefix_completes_its_flags() {
+    let app = CommandBuilder::new("myapp")
+        .match_prefix(true)
+        .subcommand(
+            CommandBuilder::new("server")
+                .flag(Flag::new("port").value_type(FlagType::Int))
+                .run(|_| Ok(()))
+                .build(),
+        )
+        .subcommand(CommandBuilder::new("status").build())
+        .subcommand(CommandBuilder::new("client").build())
+        .build();
+
+    // `ser` can only be server, so its flags are offered
+    assert!(
+        complete(&app, &["ser", "--po"])
+            .iter()
+            .any(|c| c.starts_with("--port"))
+    );
+    // `s` could also be status, so nothing is resolved
+    assert!(
+        !complete(&app, &["s", "--po"])
+            .iter()
+            .any(|c| c.starts_with("--port"))
+    );
+    assert!(
+        app.execute(vec![
+            "ser".to_string(),
+            "--port".to_string(),
+            "80".to_string()
+        ])
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_named_completer_shared_by_commands() {
+    let app = CommandBuilder::new("kubectl")
+        .register_completer("resource", |_ctx, prefix| {
+            Ok(CompletionResult::new().extend(
+                ["pods", "services", "deployments"]
+                    .into_iter()
+                    .filter(|resource| resource.starts_with(prefix))
+                    .map(String::from),
+            ))
+        })
+        .subcommand(
+            CommandBuilder::new("get")
+                .arg_completer_ref("resource")
+                .build(),
+        )
+        .subcommand(
+            CommandBuilder::new("delete")
+                .flag(Flag::new("kind").value_type(FlagType::String))
+                .flag_completer_ref("kind", "resource")
+                .arg_completer_ref("resource")
+                .build(),
+        )
+        .build();
+    assert!(app.validate_tree().is_ok());
+
+    assert_eq!(complete(&app, &["get", "s"]), vec!["services"]);
+    assert!(complete(&app, &["delete", "p"]).contains(&"pods".to_string()));
+    assert_eq!(
+        complete(&app, &["delete", "--kind", "d"]),
+        vec!["deployments"]
+    );
+
+    // A reference nothing registers is a wiring mistake
+    let broken = CommandBuilder::new("app")
+        .subcommand(
+            CommandBuilder::new("get")
+                .arg_completer_ref("resource")
+                .build(),
+        )
+        .build();
+    let problems = broken.validate_tree().unwrap_err();
+    assert_eq!(
+        problems[0].to_string(),
+        "app get: completer 'resource' is not registered"
+    );
+}
+
+#[test]
+fn test_key_value_flag_completes_keys_then_values() {
+    let app = CommandBuilder::new("kubectl")
+        .flag(Flag::key_value("label").usage("Labels to filter by"))
+        .flag_completion(
+            "label",
+            CompletionResult::key_value(
+                |_ctx, prefix| {
+                    Ok(CompletionResult::new().extend(
+                        ["app", "env", "tier"]
+                            .into_iter()
+                            .filter(|key| key.starts_with(prefix))
+                            .map(String::from),
+                    ))
+                },
+                |_ctx, key, prefix| {
+                    let values: &[&str] = match key {
+                        "env" => &["prod", "staging"],
+                        "tier" => &["web", "db"],
+                        _ => &[],
+                    };
+                    Ok(CompletionResult::new().extend(
+                        values
+                            .iter()
+                            .filter(|value| value.starts_with(prefix))
+                            .map(|value| (*value).to_string()),
+                    ))
+                },
+            ),
+        )
+        .run(|_| Ok(()))
+        .build();
+
+    // Key phase: no `=` yet
+    assert_eq!(complete(&app, &["--label", "a"]), vec!["app="]);
+    assert_eq!(
+        complete(&app, &["--label", ""]),
+        vec!["app=", "env=", "tier="]
+    );
+
+    // Value phase: the key picks the values
+    assert_eq!(
+        complete(&app, &["--label", "env="]),
+        vec!["env=prod", "env=staging"]
+    );
+    assert_eq!(complete(&app, &["--label", "tier=w"]), vec!["tier=web"]);
+    assert_eq!(complete(&app, &["--label=env=p"]), vec!["--label=env=prod"]);
+
+    // Earlier items of a list are kept
+    assert_eq!(
+        complete(&app, &["--label", "env=prod,t"]),
+        vec!["env=prod,tier="]
+    );
+    assert_eq!(
+        complete(&app, &["--label", "env=prod,tier="]),
+        vec!["env=prod,tier=web", "env=prod,tier=db"]
+    );
+}
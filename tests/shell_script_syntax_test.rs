@@ -0,0 +1,372 @@
+//! Syntax checks for generated completion scripts
+//!
+//! Each test runs the generated script through its shell's no-execute mode
+//! and is skipped when that shell is not installed.
+
+use flag_rs::{CommandBuilder, CompletionResult, Flag, FlagType, Shell};
+
+/// Creates a CLI with nested subcommands, aliases and flags
+fn create_test_cli() -> flag_rs::Command {
+    CommandBuilder::new("syntaxcli")
+        .short("A CLI whose scripts are syntax checked")
+        .flag(
+            Flag::new("verbose")
+                .short('v')
+                .usage("Enable verbose output")
+                .value_type(FlagType::Bool),
+        )
+        .subcommand(
+            CommandBuilder::new("server")
+                .short("Server management commands")
+                .aliases(vec!["srv"])
+                .subcommand(
+                    CommandBuilder::new("start")
+                        .short("Start the server")
+                        .flag(Flag::new("port").short('p').value_type(FlagType::Int))
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+}
+
+fn assert_script_parses(shell: Shell) {
+    let script = create_test_cli().generate_completion(shell);
+    match shell.check_syntax(&script) {
+        Some(result) => result.unwrap(),
+        None => eprintln!("skipping: {} is not installed", shell.program()),
+    }
+}
+
+#[test]
+fn test_bash_script_syntax() {
+    assert_script_parses(Shell::Bash);
+}
+
+#[test]
+fn test_zsh_script_syntax() {
+    assert_script_parses(Shell::Zsh);
+}
+
+#[test]
+fn test_fish_script_syntax() {
+    assert_script_parses(Shell::Fish);
+}
+
+#[test]
+fn test_nushell_script_syntax() {
+    assert_script_parses(Shell::Nushell);
+}
+
+#[test]
+fn test_generate_all_completions() {
+    let cli = create_test_cli();
+    let scripts = cli.generate_all_completions();
+
+    assert_eq!(scripts.len(), Shell::ALL.len());
+    for shell in Shell::ALL {
+        let script = &scripts[&shell];
+        assert_eq!(*script, cli.generate_completion(shell), "{shell:?}");
+        assert!(script.contains("syntaxcli"), "{shell:?}");
+    }
+}
+
+#[test]
+fn test_nushell_script_registers_completer() {
+    let script = CommandBuilder::new("my-app")
+        .build()
+        .generate_completion(Shell::Nushell);
+
+    assert!(script.contains("let __my_app_completer = {|spans|"));
+    assert!(script.contains("\"MY-APP_COMPLETE\": \"nushell\""));
+    assert!(script.contains("if (($spans.0 | path basename) == \"my-app\")"));
+    assert!(script.contains("$env.config.completions.external.completer = {|spans|"));
+}
+
+#[test]
+fn test_no_file_completion_fallback_directives() {
+    let default_cli = create_test_cli();
+    let cli = CommandBuilder::new("syntaxcli")
+        .no_file_completion_fallback()
+        .subcommand(CommandBuilder::new("start").build())
+        .build();
+
+    let bash = cli.generate_completion(Shell::Bash);
+    assert!(bash.contains(
+        "    if [[ ${#COMPREPLY[@]} -eq 0 ]]; then\n\
+         \x20       # No matches: don't fall back to completing filenames\n\
+         \x20       compopt +o default +o bashdefault 2>/dev/null\n"
+    ));
+    assert!(
+        !default_cli
+            .generate_completion(Shell::Bash)
+            .contains("compopt +o default")
+    );
+
+    let zsh = cli.generate_completion(Shell::Zsh);
+    assert!(zsh.contains("    return 0\n}\n"));
+    assert!(
+        !default_cli
+            .generate_completion(Shell::Zsh)
+            .contains("return 0")
+    );
+
+    for shell in [Shell::Bash, Shell::Zsh] {
+        if let Some(result) = shell.check_syntax(&cli.generate_completion(shell)) {
+            result.unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_syntax_check_rejects_broken_script() {
+    if let Some(result) = Shell::Bash.check_syntax("_broken() {\n    echo 'unterminated\n") {
+        assert!(matches!(result, Err(flag_rs::Error::Validation(_))));
+    }
+}
+
+#[test]
+fn test_static_completion_inlines_names() {
+    let cli = create_test_cli();
+
+    let bash = cli.generate_static_completion(Shell::Bash);
+    assert!(bash.contains("            \"syntaxcli server\") cmd_path=\"syntaxcli server\" ;;\n"));
+    assert!(bash.contains("            \"syntaxcli srv\") cmd_path=\"syntaxcli server\" ;;\n"));
+    assert!(bash.contains(
+        "        \"syntaxcli\")\n\
+         \x20           subcommands=\"server\"\n\
+         \x20           flags=\"--help -h --verbose -v\"\n"
+    ));
+    assert!(bash.contains("            value_flags=\" --port -p \"\n"));
+    assert!(bash.ends_with("complete -F _syntaxcli_static_complete syntaxcli\n"));
+
+    let zsh = cli.generate_static_completion(Shell::Zsh);
+    assert!(zsh.contains("            subcommands=('start:Start the server')\n"));
+    assert!(zsh.contains("'--verbose:Enable verbose output' '-v:Enable verbose output'"));
+    assert!(zsh.ends_with("compdef _syntaxcli_static_complete syntaxcli\n"));
+
+    for script in [&bash, &zsh] {
+        // The binary is only run from the runtime function, which the
+        // static one calls for values and arguments
+        assert_eq!(script.matches("__complete").count(), 1);
+        assert_eq!(script.matches("        _syntaxcli_complete\n").count(), 1);
+    }
+
+    for shell in [Shell::Bash, Shell::Zsh] {
+        if let Some(result) = shell.check_syntax(&cli.generate_static_completion(shell)) {
+            result.unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_bash_script_keeps_active_help_out_of_compreply() {
+    if !Shell::Bash.is_available() {
+        eprintln!("skipping: bash is not installed");
+        return;
+    }
+
+    let cli = CommandBuilder::new("hybridcli")
+        .arg_completion(|_ctx, _prefix| {
+            Ok(CompletionResult::new()
+                .add("prod")
+                .add("staging")
+                .add_help_text("Pick an environment\nor set APP_ENV"))
+        })
+        .build();
+
+    unsafe { std::env::set_var("HYBRIDCLI_COMPLETE", "bash") };
+    let response = cli
+        .handle_completion_request(&["__complete".to_string(), String::new()])
+        .unwrap();
+    unsafe { std::env::remove_var("HYBRIDCLI_COMPLETE") };
+
+    // The script runs `${words[0]}`, so a function can stand in for it
+    let driver = format!(
+        "hybridcli() {{ cat <<'EOF'\n{}\nEOF\n}}\n\
+         _get_comp_words_by_ref() {{ words=(\"${{COMP_WORDS[@]}}\"); cword=$COMP_CWORD; cur=\"\"; }}\n\
+         {}\n\
+         COMP_WORDS=(hybridcli ''); COMP_CWORD=1\n\
+         _hybridcli_complete\n\
+         printf 'reply:%s\\n' \"${{COMPREPLY[@]}}\"\n",
+        response.join("\n"),
+        cli.generate_completion(Shell::Bash)
+    );
+    let output = std::process::Command::new("bash")
+        .arg("-c")
+        .arg(&driver)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let replies: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("reply:"))
+        .collect();
+    assert_eq!(replies, vec!["prod", "staging"]);
+    // The help is printed for the user instead
+    assert!(stdout.contains("\nPick an environment\nor set APP_ENV\n"));
+}
+
+#[test]
+fn test_zsh_script_keeps_completion_order() {
+    let zsh = create_test_cli().generate_completion(Shell::Zsh);
+
+    // An unsorted group, so a default listed first isn't sorted away
+    assert!(zsh.contains("compadd -V values -Q -d descriptions -a values"));
+    assert!(zsh.contains("compadd -V values -Q -a values"));
+}
+
+#[test]
+fn test_scripts_route_active_help_to_display() {
+    let cli = create_test_cli();
+
+    // Marked lines are taken out before anything is added as a value, and
+    // shown with zsh's message display or on fish's stderr
+    let zsh = cli.generate_completion(Shell::Zsh);
+    let marker = zsh.find("if [[ \"$line\" == _activehelp_::* ]]").unwrap();
+    assert!(marker < zsh.find("values+=").unwrap());
+    assert!(zsh.contains("compadd -x \"${(j: :)formatted_help}\""));
+
+    let fish = cli.generate_completion(Shell::Fish);
+    let marker = fish
+        .find("if string match -q '_activehelp_*' -- $line")
+        .unwrap();
+    assert!(marker < fish.find("echo $line").unwrap());
+    assert!(fish.contains("echo \"» $msg\" >&2"));
+}
+
+#[test]
+fn test_bash_script_shows_descriptions_in_menu() {
+    if !Shell::Bash.is_available() {
+        eprintln!("skipping: bash is not installed");
+        return;
+    }
+
+    let cli = CommandBuilder::new("desccli")
+        .arg_completion(|_ctx, prefix| {
+            let mut result = CompletionResult::new();
+            for (value, desc) in [
+                ("prod", "Production"),
+                ("preview", ""),
+                ("staging", "Pre-production"),
+            ] {
+                if value.starts_with(prefix) {
+                    result = result.add_with_description(value, desc);
+                }
+            }
+            Ok(result)
+        })
+        .build();
+
+    let run = |cur: &str| {
+        unsafe { std::env::set_var("DESCCLI_COMPLETE", "bash") };
+        let response = cli
+            .handle_completion_request(&["__complete".to_string(), cur.to_string()])
+            .unwrap();
+        unsafe { std::env::remove_var("DESCCLI_COMPLETE") };
+
+        let driver = format!(
+            "desccli() {{ cat <<'EOF'\n{}\nEOF\n}}\n\
+             _get_comp_words_by_ref() {{ words=(\"${{COMP_WORDS[@]}}\"); cword=$COMP_CWORD; cur=\"{cur}\"; }}\n\
+             {}\n\
+             COMP_WORDS=(desccli '{cur}'); COMP_CWORD=1\n\
+             _desccli_complete\n\
+             printf 'reply:%s\\n' \"${{COMPREPLY[@]}}\"\n",
+            response.join("\n"),
+            cli.generate_completion(Shell::Bash)
+        );
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(&driver)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_prefix("reply:"))
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    // Several matches are listed with their descriptions aligned
+    assert_eq!(
+        run(""),
+        vec![
+            "prod     (Production)",
+            "preview",
+            "staging  (Pre-production)"
+        ]
+    );
+    // A single match is inserted as the bare value
+    assert_eq!(run("st"), vec!["staging"]);
+}
+
+#[test]
+fn test_completion_with_path_qualified_program() {
+    // A root command named from argv[0] completes like one named plainly
+    let cli = CommandBuilder::new("/usr/local/bin/pathcli")
+        .subcommand(CommandBuilder::new("serve").build())
+        .subcommand(CommandBuilder::new("status").build())
+        .build();
+
+    unsafe { std::env::set_var("PATHCLI_COMPLETE", "bash") };
+    let response = cli
+        .handle_completion_request(&["__complete".to_string(), "s".to_string()])
+        .unwrap();
+    unsafe { std::env::remove_var("PATHCLI_COMPLETE") };
+    assert_eq!(response, vec!["serve", "status"]);
+
+    let script = cli.generate_completion(Shell::Bash);
+    assert!(script.contains("complete -F _pathcli_complete pathcli"));
+    assert!(script.contains(
+        "PATHCLI_COMPLETE=bash PATHCLI_COMPLETE_SESSION=\"${PATHCLI_COMPLETE_SESSION:-$$}\" \"$cmd\""
+    ));
+
+    if !Shell::Bash.is_available() {
+        eprintln!("skipping: bash is not installed");
+        return;
+    }
+
+    // The script runs whatever path was typed, with '~' expanded
+    let dir = std::env::temp_dir().join(format!("flag-rs-pathcli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let program = dir.join("pathcli");
+    std::fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\n[ \"$PATHCLI_COMPLETE\" = bash ] && printf '%s\\n' {}\n",
+            response.join(" ")
+        ),
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    for typed in [program.to_str().unwrap(), "./pathcli", "~/pathcli"] {
+        let driver = format!(
+            "cd '{dir}' && HOME='{dir}'\n\
+             _get_comp_words_by_ref() {{ words=(\"${{COMP_WORDS[@]}}\"); cword=$COMP_CWORD; cur=\"s\"; }}\n\
+             {script}\n\
+             COMP_WORDS=('{typed}' s); COMP_CWORD=1\n\
+             _pathcli_complete\n\
+             printf 'reply:%s\\n' \"${{COMPREPLY[@]}}\"\n",
+            dir = dir.display(),
+        );
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(&driver)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let replies: Vec<&str> = stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix("reply:"))
+            .collect();
+        assert_eq!(replies, vec!["serve", "status"], "{typed}");
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -29,12 +29,14 @@ fn test_terminal_width_respects_columns_env() {
         );
     }
 
-    // Test with invalid COLUMNS
+    // Invalid COLUMNS is ignored, falling back to the terminal size or 80
+    unsafe { env::remove_var("COLUMNS") };
+    let fallback = get_terminal_width();
     unsafe { env::set_var("COLUMNS", "invalid") };
     assert_eq!(
         get_terminal_width(),
-        80,
-        "Invalid COLUMNS should fall back to 80"
+        fallback,
+        "Invalid COLUMNS should be ignored"
     );
 
     // Restore original
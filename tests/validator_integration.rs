@@ -1,5 +1,8 @@
 //! Integration tests for argument validators
-use flag_rs::{ArgValidator, CommandBuilder, Error};
+mod common;
+
+use common::complete;
+use flag_rs::{ArgValidator, CommandBuilder, CompletionResult, Error};
 
 #[test]
 fn test_exact_args_validator() {
@@ -145,6 +148,48 @@ fn test_only_valid_args_validator() {
     }
 }
 
+#[test]
+fn test_only_valid_args_on_subcommand() {
+    let app = |completion: bool| {
+        let mut get = CommandBuilder::new("get")
+            .args(ArgValidator::OnlyValidArgs(vec![
+                "pods".to_string(),
+                "services".to_string(),
+                "deployments".to_string(),
+            ]))
+            .run(|_| Ok(()));
+        if completion {
+            get = get.arg_completion(|_ctx, _prefix| Ok(CompletionResult::new().add("custom")));
+        }
+        CommandBuilder::new("kubectl")
+            .subcommand(get.build())
+            .build()
+    };
+    let words = |words: &[&str]| words.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    let cmd = app(false);
+    assert!(cmd.execute(words(&["get", "pods"])).is_ok());
+    match cmd.execute(words(&["get", "secrets"])).unwrap_err() {
+        Error::ArgumentValidation {
+            message, expected, ..
+        } => {
+            assert_eq!(message, "invalid argument \"secrets\"");
+            assert_eq!(expected, "one of: pods, services, deployments");
+        }
+        other => panic!("Expected ArgumentValidation error, got {other:?}"),
+    }
+
+    // Completion offers the valid values
+    assert_eq!(
+        complete(&cmd, &["get", ""]),
+        vec!["pods", "services", "deployments"]
+    );
+    assert_eq!(complete(&cmd, &["get", "s"]), vec!["services"]);
+
+    // An explicit arg completion takes precedence
+    assert_eq!(complete(&app(true), &["get", ""]), vec!["custom"]);
+}
+
 #[test]
 fn test_custom_validator() {
     let cmd = CommandBuilder::new("test")